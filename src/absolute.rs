@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Absolute URLs for links read away from the site, like in feed readers and email clients.
+
+use std::path::Path;
+
+use fancy_regex::{Captures, Regex};
+use globset::{Glob, GlobSetBuilder};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref URL_ATTRIBUTE: Regex = Regex::new(
+        r#"(?i)(?<=\s)(?<name>src|href|poster)(?<equals>\s*=\s*)(?<quote>["'])(?<url>.*?)\k<quote>"#
+    )
+    .unwrap();
+    static ref SCHEME: Regex = Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").unwrap();
+}
+
+/// The absolute URL of `link` on the page at `page`, relative to the site. Links starting with
+/// `/` are relative to `site_url`, fragments and queries to the page, and others to the page's
+/// directory. Links with a scheme, like `https:` or `mailto:`, are kept as they are.
+pub fn url(site_url: &str, page: &Path, link: &str) -> String {
+    if SCHEME.is_match(link).unwrap_or(false) {
+        return link.to_owned();
+    }
+
+    if let Some(rest) = link.strip_prefix("//") {
+        let scheme = site_url
+            .split_once("://")
+            .map_or("https", |(scheme, _)| scheme);
+
+        return format!("{}://{}", scheme, rest);
+    }
+
+    if link.starts_with(['#', '?']) {
+        return format!("{}/{}{}", site_url, page.display(), link);
+    }
+
+    if let Some(rooted) = link.strip_prefix('/') {
+        return format!("{}/{}", site_url, rooted);
+    }
+
+    let dir = page.parent().unwrap_or(Path::new(""));
+    let mut parts: Vec<String> = dir
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let segments: Vec<&str> = link.split('/').collect();
+
+    for (i, part) in segments.iter().enumerate() {
+        let last = i == segments.len() - 1;
+
+        match *part {
+            "." | "" if !last => {}
+            ".." => {
+                parts.pop();
+
+                if last {
+                    parts.push(String::new());
+                }
+            }
+            "." => parts.push(String::new()),
+            part => parts.push(part.to_owned()),
+        }
+    }
+
+    format!("{}/{}", site_url, parts.join("/"))
+}
+
+/// The HTML with the `src`, `href` and `poster` attributes of the page at `page` made absolute.
+pub fn absolutize(html: &str, site_url: &str, page: &Path) -> String {
+    URL_ATTRIBUTE
+        .replace_all(html, |caps: &Captures| {
+            format!(
+                "{}{}{}{}{}",
+                &caps["name"],
+                &caps["equals"],
+                &caps["quote"],
+                url(site_url, page, &caps["url"]),
+                &caps["quote"]
+            )
+        })
+        .into_owned()
+}
+
+/// Makes the links of the text files in `dir` matching `globs`, relative to it, absolute.
+/// Returns how many were rewritten.
+pub fn rewrite_outputs(dir: &Path, site_url: &str, globs: &[String]) -> anyhow::Result<usize> {
+    let mut builder = GlobSetBuilder::new();
+
+    for glob in globs {
+        builder.add(Glob::new(glob)?);
+    }
+
+    let globs = builder.build()?;
+    let mut rewritten = 0;
+
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(dir)?;
+
+        if !entry.file_type().is_file() || !globs.is_match(relative) {
+            continue;
+        }
+
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let absolute = absolutize(&contents, site_url, relative);
+
+        if absolute != contents {
+            std::fs::write(entry.path(), absolute)?;
+            rewritten += 1;
+        }
+    }
+
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::absolute::{absolutize, rewrite_outputs, url};
+
+    #[test]
+    fn urls() {
+        let page = Path::new("blog/post.html");
+        let url = |link: &str| url("https://x.y/site", page, link);
+
+        assert_eq!(url("cat.png"), "https://x.y/site/blog/cat.png");
+        assert_eq!(url("./img/../cat.png"), "https://x.y/site/blog/cat.png");
+        assert_eq!(url("../about.html"), "https://x.y/site/about.html");
+        assert_eq!(url(".."), "https://x.y/site/");
+        assert_eq!(url("./"), "https://x.y/site/blog/");
+        assert_eq!(url("/feed"), "https://x.y/site/feed");
+        assert_eq!(url("#setup"), "https://x.y/site/blog/post.html#setup");
+        assert_eq!(url("//cdn.x.y/a.js"), "https://cdn.x.y/a.js");
+        assert_eq!(url("mailto:a@x.y"), "mailto:a@x.y");
+        assert_eq!(url("https://z.y/"), "https://z.y/");
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            absolutize(
+                "<p><a href=\"../a.html#b\">A</a> <img data-src=\"x.png\" src='cat.png' alt=\"href=x\" /><video poster = \"p.jpg\"></video></p>",
+                "https://x.y",
+                Path::new("blog/post.html")
+            ),
+            "<p><a href=\"https://x.y/a.html#b\">A</a> <img data-src=\"x.png\" src='https://x.y/blog/cat.png' alt=\"href=x\" /><video poster = \"https://x.y/blog/p.jpg\"></video></p>"
+        );
+    }
+
+    #[test]
+    fn outputs() {
+        let dir = std::env::temp_dir().join(format!("impertio-absolute-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("mail")).unwrap();
+        std::fs::write(dir.join("mail/a.html"), "<a href=\"b.html\">B</a>").unwrap();
+        std::fs::write(dir.join("c.html"), "<a href=\"b.html\">B</a>").unwrap();
+
+        let rewritten = rewrite_outputs(&dir, "https://x.y", &["mail/*.html".into()]).unwrap();
+        let mail = std::fs::read_to_string(dir.join("mail/a.html")).unwrap();
+        let other = std::fs::read_to_string(dir.join("c.html")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rewritten, 1);
+        assert_eq!(mail, "<a href=\"https://x.y/mail/b.html\">B</a>");
+        assert_eq!(other, "<a href=\"b.html\">B</a>");
+    }
+}
@@ -0,0 +1,126 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! New Org articles started from an archetype: a Tera template of their header, picked by the
+//! section they're created in.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDate;
+
+/// Where archetypes are kept, relative to the source directory, as `<section>.org` or
+/// `default.org`. It isn't built.
+pub const ARCHETYPE_DIR: &str = "archetypes";
+
+/// Used when the source directory has no archetype for the section, nor a default one.
+const DEFAULT_ARCHETYPE: &str = "#+TITLE: {{ title }}
+#+DATE: <{{ date }}>
+{% if tags %}#+TAGS: {{ tags | join(sep=\", \") }}
+{% endif %}
+";
+
+/// The archetype of `section` in `root`: `archetypes/<section>.org`, else
+/// `archetypes/default.org`, else the built-in one.
+fn archetype(root: &Path, section: Option<&str>) -> anyhow::Result<String> {
+    let dir = root.join(ARCHETYPE_DIR);
+    let candidates = section
+        .map(|section| dir.join(section).with_extension("org"))
+        .into_iter()
+        .chain([dir.join("default.org")]);
+
+    for candidate in candidates {
+        if candidate.is_file() {
+            return Ok(std::fs::read_to_string(candidate)?);
+        }
+    }
+
+    Ok(DEFAULT_ARCHETYPE.into())
+}
+
+/// Creates the article titled `title` in the `section` directory of `root`, named after its
+/// title, from the section's archetype. Its template gets `title`, `date` (like
+/// `2024-05-01 Wed`), `tags`, `section` and `slug`. Fails instead of overwriting an existing
+/// file.
+pub fn create(
+    root: &Path,
+    title: &str,
+    section: Option<&str>,
+    tags: &[String],
+    date: NaiveDate,
+) -> anyhow::Result<PathBuf> {
+    let slug = crate::org::slugify(title);
+    let section = section.map(|section| section.trim_matches('/'));
+    let path = root
+        .join(section.unwrap_or_default())
+        .join(&slug)
+        .with_extension("org");
+
+    if path.exists() {
+        anyhow::bail!("{:?} already exists", path);
+    }
+
+    let mut context = tera::Context::new();
+
+    context.insert("title", title);
+    context.insert("date", &date.format("%Y-%m-%d %a").to_string());
+    context.insert("tags", tags);
+    context.insert("section", &section.unwrap_or_default());
+    context.insert("slug", &slug);
+
+    let content = tera::Tera::one_off(&archetype(root, section)?, &context, false)?;
+
+    crate::handler::writeable(&path)?.write_all(content.as_bytes())?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::archetype::create;
+
+    #[test]
+    fn archetypes() {
+        let dir = std::env::temp_dir().join(format!("impertio-archetype-{}", std::process::id()));
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+
+        std::fs::create_dir_all(dir.join("archetypes")).unwrap();
+        std::fs::write(
+            dir.join("archetypes/blog.org"),
+            "#+TITLE: {{ title }}\n#+AUTHOR: Kazani\n#+FILETAGS: {{ section }}\n",
+        )
+        .unwrap();
+
+        let post = create(&dir, "Hello, World!", Some("blog"), &[], date).unwrap();
+        let page = create(&dir, "About", None, &["me".into(), "site".into()], date).unwrap();
+        let again = create(&dir, "About", None, &[], date);
+        let (post, page) = (
+            (
+                post.strip_prefix(&dir).unwrap().to_owned(),
+                std::fs::read_to_string(&post).unwrap(),
+            ),
+            (
+                page.strip_prefix(&dir).unwrap().to_owned(),
+                std::fs::read_to_string(&page).unwrap(),
+            ),
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(post.0.to_str(), Some("blog/hello-world.org"));
+        assert_eq!(
+            post.1,
+            "#+TITLE: Hello, World!\n#+AUTHOR: Kazani\n#+FILETAGS: blog\n"
+        );
+        assert_eq!(page.0.to_str(), Some("about.org"));
+        assert_eq!(
+            page.1,
+            "#+TITLE: About\n#+DATE: <2024-05-01 Wed>\n#+TAGS: me, site\n\n"
+        );
+        assert!(again.is_err());
+    }
+}
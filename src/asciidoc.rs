@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+/// Reads the document header (`= Title`, an author line, and `:name: value` attributes)
+/// into the same keys Org keywords use: `title`, `author`, `desc` and `tags`.
+pub fn parse_header(content: &str) -> HashMap<String, String> {
+    let mut metadata: HashMap<String, String> = HashMap::new();
+    let mut lines = content
+        .lines()
+        .skip_while(|line| line.trim().is_empty() || line.starts_with("//"))
+        .peekable();
+
+    if let Some(title) = lines.next_if(|line| line.starts_with("= ")) {
+        metadata.insert("title".into(), title[2..].trim().to_owned());
+
+        if let Some(author) =
+            lines.next_if(|line| !line.trim().is_empty() && !line.starts_with(':'))
+        {
+            // `Name <email>; Other Name`
+            metadata.insert(
+                "author".into(),
+                author
+                    .split(['<', ';'])
+                    .next()
+                    .unwrap_or("")
+                    .trim()
+                    .to_owned(),
+            );
+        }
+    }
+
+    for line in lines.take_while(|line| !line.trim().is_empty()) {
+        let Some((name, value)) = line.strip_prefix(':').and_then(|line| line.split_once(':'))
+        else {
+            continue;
+        };
+
+        let key = match name.trim() {
+            "author" => "author",
+            "description" => "desc",
+            "keywords" | "tags" => "tags",
+            _ => continue,
+        };
+
+        metadata.insert(key.into(), value.trim().to_owned());
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::asciidoc::parse_header;
+
+    #[test]
+    fn header() {
+        assert_eq!(
+            parse_header(
+                r#"// a comment
+= Migrating to Impertio
+Jane Doe <jane@example.com>
+:description: How we moved.
+:keywords: docs, migration
+:toc:
+
+:author: Not Jane
+
+Body text."#
+            ),
+            HashMap::from_iter(vec![
+                ("title".into(), "Migrating to Impertio".into()),
+                ("author".into(), "Jane Doe".into()),
+                ("desc".into(), "How we moved.".into()),
+                ("tags".into(), "docs, migration".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn no_header() {
+        assert_eq!(parse_header("Just a paragraph."), HashMap::new());
+    }
+}
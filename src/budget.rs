@@ -0,0 +1,108 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Size budgets for built files, so pages stay fast without an external audit.
+
+use std::{collections::HashMap, path::Path};
+
+use globset::Glob;
+
+use crate::files::glob_precedence;
+
+/// Built files in `dir` larger than the budget of the first glob, in the order of
+/// [`glob_precedence`], they match, as `path (size > budget bytes)`.
+pub fn over_budget(dir: &Path, budgets: &HashMap<String, u64>) -> anyhow::Result<Vec<String>> {
+    let mut globs = vec![];
+
+    for (glob, budget) in budgets {
+        globs.push((glob.as_str(), Glob::new(glob)?.compile_matcher(), *budget));
+    }
+
+    // The most specific glob, like `images/hero.*` over `*.png`, wins.
+    globs.sort_by(|(a, _, _), (b, _, _)| glob_precedence(a, b));
+
+    let mut over = vec![];
+
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(dir)?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let Some((_, _, budget)) = globs.iter().find(|(_, glob, _)| glob.is_match(relative)) else {
+            continue;
+        };
+        let size = entry.metadata()?.len();
+
+        if size > *budget {
+            over.push(format!(
+                "{} ({} > {} bytes)",
+                relative.display(),
+                size,
+                budget
+            ));
+        }
+    }
+
+    Ok(over)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::budget::over_budget;
+
+    #[test]
+    fn budgets() {
+        let dir = std::env::temp_dir().join(format!("impertio-budget-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("index.html"), "a".repeat(20)).unwrap();
+        std::fs::write(dir.join("small.html"), "a").unwrap();
+        std::fs::write(dir.join("img/cat.png"), "a".repeat(20)).unwrap();
+        std::fs::write(dir.join("img/hero.png"), "a".repeat(20)).unwrap();
+
+        let over = over_budget(
+            &dir,
+            &HashMap::from_iter([
+                ("*.html".into(), 10),
+                ("*.png".into(), 10),
+                ("img/hero.png".into(), 100),
+            ]),
+        )
+        .unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            over,
+            vec!["img/cat.png (20 > 10 bytes)", "index.html (20 > 10 bytes)"]
+        );
+    }
+
+    #[test]
+    fn tied_budgets() {
+        let dir = std::env::temp_dir().join(format!("impertio-tied-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.html"), "a".repeat(20)).unwrap();
+
+        // Both globs are as long; whichever is first alphabetically applies, every time.
+        let over = (0..8)
+            .map(|_| {
+                over_budget(
+                    &dir,
+                    &HashMap::from_iter([("*.html".into(), 100), ("a.htm*".into(), 10)]),
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(over.iter().all(|over| over.is_empty()));
+    }
+}
@@ -0,0 +1,254 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Hashes of what each page was built from, so pages are rebuilt when their templates, the
+//! config or the pages they list change, and not just when their source is newer.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{config::Config, metadata::Metadata};
+
+/// The hashes of the last build's inputs, kept in the destination.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct BuildCache {
+    /// Hashes of each source file's inputs, by its path relative to the source directory.
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl BuildCache {
+    pub const FILE: &'static str = ".impertio-build-cache.json";
+
+    /// The previous build's cache, or an empty one if there is none.
+    pub fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(Self::FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(dir.join(Self::FILE), serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Whether `file`'s inputs hash to something else than last time, or `None` if the last
+    /// build didn't record it.
+    pub fn stale(&self, file: &Path, hash: &str) -> Option<bool> {
+        self.inputs
+            .get(&file.to_string_lossy().into_owned())
+            .map(|previous| previous != hash)
+    }
+}
+
+fn hex(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Hashes `paths` and their contents, skipping those that can't be read.
+fn hash_files<'a>(hasher: &mut Sha256, paths: impl IntoIterator<Item = &'a PathBuf>) {
+    for path in paths {
+        if let Ok(contents) = std::fs::read(path) {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(Sha256::digest(contents));
+        }
+    }
+}
+
+/// A hash of what every page is built with: Impertio's version, the config and profile, the
+/// data files, and the bibliographies and plugins it names, under `root`.
+pub fn site_hash(config: &Config, root: &Path, plugin_dir: &Path) -> String {
+    let mut hasher = Sha256::new();
+
+    hasher.update(env!("CARGO_PKG_VERSION"));
+    hasher.update(&config.profile);
+    // Through a `Value`, whose maps are sorted, so `HashMap`s hash the same every time.
+    hasher.update(
+        serde_json::to_value(config)
+            .map(|config| config.to_string())
+            .unwrap_or_default(),
+    );
+
+    let mut plugins: Vec<PathBuf> = std::fs::read_dir(plugin_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+
+    plugins.sort();
+    plugins.extend(
+        config
+            .plugins
+            .iter()
+            .flatten()
+            .map(|plugin| root.join(plugin)),
+    );
+    let data: Vec<PathBuf> = walkdir::WalkDir::new(root.join(crate::data::DATA_DIR))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && crate::data::is_data_file(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect();
+
+    hash_files(
+        &mut hasher,
+        config
+            .bibliography
+            .iter()
+            .flatten()
+            .map(|bib| root.join(bib))
+            .collect::<Vec<_>>()
+            .iter()
+            .chain(&plugins)
+            .chain(&data),
+    );
+
+    hex(hasher)
+}
+
+/// A hash of the site's pages, for pages that list or number others.
+pub fn metadata_hash(metadata: &[Metadata]) -> String {
+    let mut pages: Vec<String> = metadata.iter().map(|meta| format!("{:?}", meta)).collect();
+    let mut hasher = Sha256::new();
+
+    pages.sort();
+
+    for page in pages {
+        hasher.update(page);
+    }
+
+    hex(hasher)
+}
+
+/// What a source file is built from besides itself.
+pub enum Dependencies {
+    /// Only the site's settings.
+    Site,
+    /// Other pages, too, like Org files calling macros or numbering figures across the site.
+    Pages,
+    /// Files that can't be known without parsing it, like `#+INCLUDE`s, so it's always
+    /// rebuilt.
+    Unknown,
+}
+
+/// What the source file with `ext` and `contents` depends on.
+pub fn dependencies(ext: &str, contents: &[u8], config: &Config) -> Dependencies {
+    if ext != "org" {
+        return Dependencies::Site;
+    }
+
+    let contents = String::from_utf8_lossy(contents).to_ascii_lowercase();
+
+    if contents.contains("#+include:") || contents.contains("#+bibliography:") {
+        Dependencies::Unknown
+    } else if contents.contains("{{{")
+        || contents.contains("[cite")
+        || config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref())
+            .and_then(|org| org.numbering)
+            == Some(crate::config::NumberingScope::Site)
+    {
+        Dependencies::Pages
+    } else {
+        Dependencies::Site
+    }
+}
+
+/// A hash of everything the source file at `source` is built from: its contents, the
+/// `templates` it's rendered with, `site`, and if it depends on them, `pages`. `None` if it
+/// has to be rebuilt regardless.
+pub fn inputs_hash(
+    source: &Path,
+    ext: &str,
+    templates: &[PathBuf],
+    config: &Config,
+    site: &str,
+    pages: &str,
+) -> Option<String> {
+    let contents = std::fs::read(source).ok()?;
+    let mut hasher = Sha256::new();
+
+    hasher.update(site);
+    hash_files(&mut hasher, templates);
+
+    match dependencies(ext, &contents, config) {
+        Dependencies::Site => {}
+        Dependencies::Pages => hasher.update(pages),
+        Dependencies::Unknown => return None,
+    }
+
+    hasher.update(Sha256::digest(&contents));
+
+    Some(hex(hasher))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        cache::{inputs_hash, BuildCache},
+        config::Config,
+    };
+
+    #[test]
+    fn inputs() {
+        let dir = std::env::temp_dir().join(format!("impertio-cache-{}", std::process::id()));
+        let (page, listing, included, template) = (
+            dir.join("a.org"),
+            dir.join("b.org"),
+            dir.join("c.org"),
+            dir.join("root.html"),
+        );
+        let config = Config::default();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&page, "* A").unwrap();
+        std::fs::write(&listing, "{{{index}}}").unwrap();
+        std::fs::write(&included, "#+INCLUDE: \"a.org\"").unwrap();
+        std::fs::write(&template, "{{ content }}").unwrap();
+
+        let templates = [template.clone()];
+        let hash = |path: &Path, site: &str, pages: &str| {
+            inputs_hash(path, "org", &templates, &config, site, pages)
+        };
+        let before = hash(&page, "site", "pages").unwrap();
+        let (other_site, other_pages) = (hash(&page, "x", "pages"), hash(&page, "site", "x"));
+        let listing_before = hash(&listing, "site", "pages");
+        let listing_after = hash(&listing, "site", "x");
+        let unknown = hash(&included, "site", "pages");
+
+        std::fs::write(&template, "<main>{{ content }}</main>").unwrap();
+
+        let after = hash(&page, "site", "pages").unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_ne!(other_site.as_ref(), Some(&before));
+        assert_eq!(other_pages.as_ref(), Some(&before));
+        assert_ne!(listing_before, listing_after);
+        assert_eq!(unknown, None);
+        assert_ne!(before, after);
+
+        let cache = BuildCache {
+            inputs: [("a.org".into(), before.clone())].into(),
+        };
+
+        assert_eq!(cache.stale(Path::new("a.org"), &before), Some(false));
+        assert_eq!(cache.stale(Path::new("a.org"), &after), Some(true));
+        assert_eq!(cache.stale(Path::new("b.org"), &after), None);
+    }
+}
@@ -0,0 +1,181 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Feeds whose items are the top-level headings of one file, like the releases of a changelog,
+//! dated by the heading's timestamp.
+
+use std::path::{Path, PathBuf};
+
+use crate::{config::ChangelogFeedConfig, handler::FileContext, org::Document};
+
+/// Where the feed of `config` is written, relative to the destination.
+pub fn feed_path(config: &ChangelogFeedConfig) -> PathBuf {
+    config
+        .feed
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(&config.file).with_extension("xml"))
+}
+
+/// The feed of the Org file `ctx` is for.
+pub fn channel(config: &ChangelogFeedConfig, ctx: &FileContext) -> anyhow::Result<rss::Channel> {
+    // Warnings were already given when the page was built.
+    let ctx = FileContext {
+        warnings: Default::default(),
+        ..ctx.clone()
+    };
+    let doc = Document::parse_file(ctx.source_path.to_str().unwrap(), ctx.clone())
+        .map_err(|err| anyhow::anyhow!(err))?;
+    let page = ctx.url();
+    let items = doc
+        .entries(&ctx.config)
+        .into_iter()
+        .map(|entry| {
+            let link = format!("{}#{}", page, entry.id);
+
+            rss::Item {
+                title: Some(entry.title),
+                link: Some(link.clone()),
+                guid: Some(rss::Guid {
+                    value: link,
+                    permalink: true,
+                }),
+                description: Some(crate::absolute::absolutize(
+                    &entry.html,
+                    &ctx.site_url,
+                    &ctx.relative_path.with_extension("html"),
+                )),
+                pub_date: entry.date.map(|date| date.to_rfc2822()),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let mut channel = rss::Channel {
+        title: config
+            .title
+            .clone()
+            .or_else(|| doc.metadata.get("title").cloned())
+            .unwrap_or_else(|| config.file.clone()),
+        link: page,
+        description: config
+            .description
+            .clone()
+            .or_else(|| doc.metadata.get("description").cloned())
+            .unwrap_or_default(),
+        language: ctx.config.language.clone(),
+        generator: Some(format!(
+            "Impertio {} ({}), RSS Crate (https://crates.io/crates/rss)",
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_HOMEPAGE")
+        )),
+        docs: Some("https://www.rssboard.org/rss-specification".to_owned()),
+        items,
+        extensions: crate::license::extension(
+            doc.metadata
+                .get("license")
+                .or(ctx.config.license.as_ref())
+                .map(String::as_str),
+        ),
+        ..Default::default()
+    };
+
+    crate::license::add_namespace(&mut channel);
+
+    Ok(channel)
+}
+
+/// Writes the feed of the Org file `ctx` is for into `dir`.
+pub fn write(config: &ChangelogFeedConfig, ctx: &FileContext, dir: &Path) -> anyhow::Result<()> {
+    let path = dir.join(feed_path(config));
+
+    log::info!(phase = "rss"; "Generating `{}` (RSS)", path.display());
+
+    channel(config, ctx)?.pretty_write_to(crate::handler::writeable(&path)?, b'\t', 1)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        changelog::{channel, feed_path},
+        config::ChangelogFeedConfig,
+        handler::FileContext,
+    };
+
+    #[test]
+    fn releases() {
+        let dir = std::env::temp_dir().join(format!("impertio-changelog-{}", std::process::id()));
+        let file = dir.join("CHANGELOG.org");
+        let config = ChangelogFeedConfig {
+            file: "CHANGELOG.org".into(),
+            ..Default::default()
+        };
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            &file,
+            "#+TITLE: Changes\n\n\
+             * v1.1 <2024-05-02 Thu>\n\
+             #+BEGIN_EXPORT html\n<a href=\"bugs.html\">Fixed</a>\n#+END_EXPORT\n\
+             ** Details\nMore.\n\
+             * v1.0\nCLOSED: [2024-04-01 Mon 10:00]\nFirst.\n\
+             * Unreleased\nSoon.\n",
+        )
+        .unwrap();
+
+        let channel = channel(
+            &config,
+            &FileContext {
+                relative_path: "CHANGELOG.org".into(),
+                source_path: file,
+                site_url: "https://x.y".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(feed_path(&config), Path::new("CHANGELOG.xml"));
+        assert_eq!(channel.title, "Changes");
+        assert_eq!(channel.link, "https://x.y/CHANGELOG.html");
+
+        let items: Vec<_> = channel
+            .items
+            .iter()
+            .map(|item| {
+                (
+                    item.title.as_deref().unwrap(),
+                    item.link.as_deref().unwrap(),
+                    item.pub_date.as_deref(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            items,
+            vec![
+                (
+                    "v1.1",
+                    "https://x.y/CHANGELOG.html#v1-1-2024-05-02-thu",
+                    Some("Thu, 2 May 2024 00:00:00 +0000")
+                ),
+                (
+                    "v1.0",
+                    "https://x.y/CHANGELOG.html#v1-0",
+                    Some("Mon, 1 Apr 2024 10:00:00 +0000")
+                ),
+                ("Unreleased", "https://x.y/CHANGELOG.html#unreleased", None),
+            ]
+        );
+
+        let description = channel.items[0].description.as_deref().unwrap();
+
+        assert!(description.contains("https://x.y/bugs.html"));
+        assert!(description.contains("More."));
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! SHA-256 sidecars for copied assets, in the format `sha256sum -c` checks.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::ChecksumsConfig;
+
+/// Extension of the sidecar written next to each selected asset.
+pub const SIDECAR_EXTENSION: &str = "sha256";
+/// The consolidated file written into every directory with sidecars.
+pub const SHASUMS: &str = "SHA256SUMS";
+
+/// Hex-encoded SHA-256 of `contents`.
+pub fn sha256_hex(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether files with `ext` get a sidecar.
+pub fn selected(config: &ChecksumsConfig, ext: &str) -> bool {
+    config
+        .extensions
+        .iter()
+        .any(|selected| selected.eq_ignore_ascii_case(ext))
+}
+
+/// A `sha256sum` line for `contents` named `name`.
+fn line(contents: &[u8], name: &str) -> String {
+    format!("{}  {}\n", sha256_hex(contents), name)
+}
+
+/// `{output}.sha256`.
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    let mut sidecar = output.as_os_str().to_owned();
+
+    sidecar.push(format!(".{}", SIDECAR_EXTENSION));
+    sidecar.into()
+}
+
+/// Writes `{output}.sha256` for the file at `output`.
+pub fn write_sidecar(output: &Path) -> anyhow::Result<()> {
+    let name = output
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{:?} has no file name", output))?
+        .to_string_lossy();
+
+    std::fs::write(sidecar_path(output), line(&std::fs::read(output)?, &name))?;
+
+    Ok(())
+}
+
+/// Collects the sidecars under `dir` into a `SHA256SUMS` in each of their directories.
+pub fn write_shasums(dir: &Path) -> anyhow::Result<()> {
+    let mut sums: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !entry.file_type().is_file()
+            || path.extension().is_none_or(|ext| ext != SIDECAR_EXTENSION)
+        {
+            continue;
+        }
+
+        let asset = path.with_extension("");
+
+        // Only sidecars of assets that are still there.
+        if !asset.is_file() {
+            continue;
+        }
+
+        sums.entry(path.parent().unwrap_or(dir).to_owned())
+            .or_default()
+            .push(std::fs::read_to_string(path)?);
+    }
+
+    for (dir, lines) in sums {
+        std::fs::write(dir.join(SHASUMS), lines.concat())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        checksums::{selected, sha256_hex, write_shasums, write_sidecar, SHASUMS},
+        config::ChecksumsConfig,
+    };
+
+    #[test]
+    fn selection() {
+        let config = ChecksumsConfig {
+            extensions: vec!["gz".into(), "ZIP".into()],
+            shasums: None,
+        };
+
+        assert!(selected(&config, "gz"));
+        assert!(selected(&config, "zip"));
+        assert!(!selected(&config, "html"));
+    }
+
+    #[test]
+    fn sidecars() {
+        let dir = std::env::temp_dir().join(format!("impertio-checksums-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(dir.join("bin/b.tar.gz"), "hello").unwrap();
+        std::fs::write(dir.join("bin/a.zip"), "").unwrap();
+        write_sidecar(&dir.join("bin/b.tar.gz")).unwrap();
+        write_sidecar(&dir.join("bin/a.zip")).unwrap();
+
+        let hello = sha256_hex(b"hello");
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("bin/b.tar.gz.sha256")).unwrap(),
+            format!("{}  b.tar.gz\n", hello)
+        );
+
+        write_shasums(&dir).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("bin").join(SHASUMS)).unwrap(),
+            format!("{}  a.zip\n{}  b.tar.gz\n", sha256_hex(b""), hello)
+        );
+        assert!(!dir.join(SHASUMS).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use icu_collator::{options::CollatorOptions, Collator, CollatorBorrowed, CollatorPreferences};
+use icu_locale_core::Locale;
+
+/// A collator for `language` (a BCP 47 tag), falling back to the root collation when it is
+/// missing or invalid.
+fn collator(language: Option<&str>) -> CollatorBorrowed<'static> {
+    let locale = language
+        .and_then(|language| match language.parse::<Locale>() {
+            Ok(locale) => Some(locale),
+            Err(err) => {
+                log::warn!(
+                    "Invalid language `{}`, sorting by root collation: {}",
+                    language,
+                    err
+                );
+                None
+            }
+        })
+        .unwrap_or(Locale::UNKNOWN);
+
+    Collator::try_new(
+        CollatorPreferences::from(&locale),
+        CollatorOptions::default(),
+    )
+    .expect("compiled collation data covers every locale")
+}
+
+/// Sorts `items` by `key` the way readers of `language` expect, e.g. `ä` after `z` in Swedish.
+pub fn sort_by_key<T>(items: &mut [T], language: Option<&str>, key: impl Fn(&T) -> &str) {
+    let collator = collator(language);
+
+    items.sort_by(|a, b| collator.compare(key(a), key(b)));
+}
+
+#[cfg(test)]
+mod test {
+    use crate::collation::sort_by_key;
+
+    #[test]
+    fn per_language() {
+        let mut words = vec!["zebra", "Äpfel", "Banana"];
+
+        sort_by_key(&mut words, Some("de"), |word| word);
+        assert_eq!(words, vec!["Äpfel", "Banana", "zebra"]);
+
+        sort_by_key(&mut words, Some("sv"), |word| word);
+        assert_eq!(words, vec!["Banana", "zebra", "Äpfel"]);
+
+        sort_by_key(&mut words, Some("not a language!"), |word| word);
+        assert_eq!(words, vec!["Äpfel", "Banana", "zebra"]);
+    }
+}
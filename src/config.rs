@@ -1,12 +1,669 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
 use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
 pub struct Config {
+    /// The build profile, like `dev` or `prod`, from `--profile` rather than the file.
+    #[serde(skip)]
+    pub profile: String,
     pub site_url: String,
+    /// BCP 47 tag of the site's language, used to sort listings. Defaults to `rss.language`.
+    pub language: Option<String>,
     pub rss: Option<RSSConfig>,
+    pub handlers: Option<HandlerConfig>,
+    /// WASM plugins, relative to the source directory, loaded besides those in `plugins/`.
+    pub plugins: Option<Vec<String>>,
+    pub math: Option<MathConfig>,
+    pub diagrams: Option<DiagramsConfig>,
+    pub highlight: Option<HighlightConfig>,
+    pub notify: Option<NotifyConfig>,
+    pub well_known: Option<WellKnownConfig>,
+    /// Writes a map of output paths to strong ETags into the destination.
+    pub etags: Option<EtagFormat>,
+    /// Writes `anchors.json`, listing the fragments of every page, into the destination.
+    /// Defaults to `false`.
+    pub anchors: Option<bool>,
+    /// Writes `graph.json`, the pages and the links between them, into the destination.
+    /// Defaults to `false`.
+    pub graph: Option<bool>,
+    /// The license of articles without a `#+LICENSE`, like `CC-BY-4.0` or
+    /// `All rights reserved`. Every page's template gets it as `license`, and its URL, if it
+    /// has one, as `license_url`.
+    pub license: Option<String>,
+    /// Writes `licenses.json`, the articles under each license, into the destination.
+    /// Defaults to `false`.
+    pub licenses: Option<bool>,
+    /// Directories of the source to generate download index pages for.
+    pub downloads: Option<Vec<DownloadsConfig>>,
+    pub checksums: Option<ChecksumsConfig>,
+    /// BibTeX (`.bib`) or CSL-JSON (`.json`) files, relative to the source directory, that
+    /// `[cite:@key]` citations are looked up in, besides those of `#+BIBLIOGRAPHY`.
+    pub bibliography: Option<Vec<String>>,
+    /// Directories, relative to the source directory, whose images get a page each. Those
+    /// without an `index.org` get an `index.html` of their images, too. Directories whose
+    /// `index.org` calls `{{{gallery}}}` are galleries anyway.
+    pub galleries: Option<Vec<String>>,
+    /// External checkers, like spell or terminology checkers, run over the prose of each Org
+    /// page. Their findings are logged as warnings.
+    pub lint: Option<Vec<LintConfig>>,
+    /// Globs, relative to the destination, of built files whose `src`, `href` and `poster`
+    /// links are made absolute after rendering, e.g. pages meant to be emailed.
+    pub absolute_urls: Option<Vec<String>>,
+    /// Pages earlier builds published that are gone from this one are always reported; this
+    /// also writes redirect or "gone" pages at their URLs.
+    pub removed: Option<RemovedConfig>,
+    /// Delete files earlier builds wrote for source files that are gone, instead of only
+    /// warning about them. Defaults to `false`.
+    pub clean_orphans: Option<bool>,
+    /// Sizes, in bytes, that built files matching each glob, relative to the destination,
+    /// shouldn't exceed, e.g. `"*.html": 200000` or `"*.{png,jpg}": 1000000`. Larger files are
+    /// warned about. The longest matching glob applies, or of those as long, the first
+    /// alphabetically.
+    pub size_budgets: Option<HashMap<String, u64>>,
+    /// Check built pages for `<script>`s, `<iframe>`s and `<embed>`s loading from other sites,
+    /// like those pasted into export blocks. Those from hosts not allowed are warned about.
+    pub embeds: Option<EmbedsConfig>,
+    /// A template, relative to the source directory, for pages without a `root.html` in their
+    /// directory or above it. Without one, such pages fail to build.
+    pub fallback_template: Option<String>,
+    /// Gitignore-style patterns, relative to the source directory, of files and directories
+    /// that aren't built, e.g. `node_modules/` or `*.draft.org`. `.impertioignore` files in the
+    /// source add to them for their directory.
+    pub ignore: Option<Vec<String>>,
+    /// Also skip what `.gitignore` files in the source ignore. Defaults to `false`.
+    pub gitignore: Option<bool>,
+    /// Build Org files marked `#+DRAFT: t`. Defaults to `true` in the `dev` profile only.
+    pub drafts: Option<bool>,
+    /// Strip indentation and blank lines from rendered pages. Defaults to `true` outside the
+    /// `dev` profile.
+    pub minify: Option<bool>,
+    /// Bounds on rendering pages, so a theme's recursive include or runaway loop fails the
+    /// build instead of hanging it or running out of memory.
+    pub template_limits: Option<TemplateLimitsConfig>,
+    /// Directories of documentation with a subdirectory per release. Their pages' templates get
+    /// a version switcher as `versions`, each version gets a `sitemap.xml`, and the latest is
+    /// copied to `latest/`.
+    pub versioned_docs: Option<Vec<VersionedDocsConfig>>,
+    /// Org files, like `CHANGELOG.org`, that get an RSS feed of their top-level headings, so
+    /// releases can be followed without a page each.
+    pub changelog_feeds: Option<Vec<ChangelogFeedConfig>>,
+    /// Pages listing the articles of each term of a taxonomy, like `tags/rust/`, and an index
+    /// of its terms, like `tags/`.
+    pub taxonomies: Option<Vec<TaxonomyConfig>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct TaxonomyConfig {
+    /// `tags`, or the name of an Org keyword listing terms, like `categories` for
+    /// `#+CATEGORIES`. Also the directory its pages are written to.
+    pub name: String,
+    /// Articles per page. Later pages are `page/2/` and so on under the term's directory.
+    /// Defaults to all of them on one page.
+    pub page_size: Option<usize>,
+    /// Defaults to newest first.
+    pub sort: Option<TaxonomySort>,
+    /// Only list articles under this directory, relative to the source directory.
+    pub dir: Option<String>,
+    /// Terms with fewer articles get no page. Defaults to `1`.
+    pub min_articles: Option<usize>,
+    /// Give each term an RSS feed, `feed.xml` in its directory, linked from its pages.
+    /// Defaults to `false`.
+    pub feeds: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaxonomySort {
+    /// By publication date, else modification date, newest first.
+    #[default]
+    Date,
+    /// By title, in the site's language.
+    Title,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ChangelogFeedConfig {
+    /// Relative to the source directory.
+    pub file: String,
+    /// Where the feed is written, relative to the destination. Defaults to the file's path
+    /// with an `.xml` extension.
+    pub feed: Option<String>,
+    /// Defaults to the file's `#+TITLE`.
+    pub title: Option<String>,
+    /// Defaults to the file's `#+DESCRIPTION`.
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct EmbedsConfig {
+    /// Hosts pages may embed from, like `player.vimeo.com`, or `*.example.com` for a domain
+    /// and its subdomains. The site's own host is always allowed.
+    #[serde(default)]
+    pub allow: Vec<String>,
+}
+
+/// A directory like `docs` with a subdirectory per release, like `docs/v1` and `docs/v2`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct VersionedDocsConfig {
+    /// Relative to the source directory.
+    pub dir: String,
+    /// The versions' subdirectories, oldest first. Defaults to all of them, sorted.
+    pub versions: Option<Vec<String>>,
+    /// The version copied to `latest/`. Defaults to the last one.
+    pub latest: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct TemplateLimitsConfig {
+    /// How deeply `{% include %}`s may nest, counted as written, so a template including
+    /// itself fails however it's guarded. Defaults to `16`.
+    pub max_include_depth: Option<usize>,
+    /// Largest rendered page, in bytes. Defaults to 16 MiB.
+    pub max_output_size: Option<usize>,
+    /// Seconds a page may take to render. Defaults to `30`.
+    pub render_timeout: Option<u64>,
+}
+
+impl TemplateLimitsConfig {
+    pub fn max_include_depth(&self) -> usize {
+        self.max_include_depth.unwrap_or(16)
+    }
+
+    pub fn max_output_size(&self) -> usize {
+        self.max_output_size.unwrap_or(16 * 1024 * 1024)
+    }
+
+    pub fn render_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.render_timeout.unwrap_or(30))
+    }
+}
+
+/// A checker run over a page's prose, with the lines and columns of the Org source.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct LintConfig {
+    /// The program to run.
+    pub command: String,
+    /// `{file}` is replaced with the path of a `.txt` file holding the prose. Without it, the
+    /// prose is piped to the program's standard input.
+    pub args: Option<Vec<String>>,
+    /// A regex matched against each line of output, with `line`, `message` and optionally
+    /// `column` groups. Defaults to `line:column: message` after an optional path.
+    pub pattern: Option<String>,
+}
+
+/// What's left at the URLs of removed pages.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct RemovedConfig {
+    /// Write a page at each removed URL: a redirect if it has one, or else a notice that it's
+    /// gone. Defaults to `true`.
+    pub stubs: Option<bool>,
+    /// Where removed pages moved to, keyed by path like `/posts/old.html`. Targets are
+    /// resolved like links on the removed page.
+    pub redirects: Option<HashMap<String, String>>,
+}
+
+/// SHA-256 sidecars for assets copied into the destination.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ChecksumsConfig {
+    /// Extensions, without the dot, of the files that get a `.sha256` next to them.
+    pub extensions: Vec<String>,
+    /// Also collect them into a `SHA256SUMS` per directory. Defaults to `true`.
+    pub shasums: Option<bool>,
+}
+
+/// A directory of files, like PDFs or tarballs, that gets an `index.html` listing their sizes,
+/// modification dates and checksums.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct DownloadsConfig {
+    /// Relative to the source directory.
+    pub dir: String,
+    /// Defaults to `Downloads: {dir}`.
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum EtagFormat {
+    /// `etags.json`, an object keyed by path.
+    Json,
+    /// `etags.csv`, with `path,etag` rows.
+    Csv,
+}
+
+/// Files generated under `/.well-known/`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct WellKnownConfig {
+    pub security: Option<SecurityTxtConfig>,
+    /// Other entries, by file name, written verbatim.
+    pub files: Option<HashMap<String, String>>,
+}
+
+/// Fields of `security.txt` (RFC 9116).
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct SecurityTxtConfig {
+    /// `mailto:`, `https:` or `tel:` URIs; at least one is required.
+    pub contact: Vec<String>,
+    /// RFC 3339 date. Defaults to a year after the build.
+    pub expires: Option<String>,
+    pub encryption: Option<Vec<String>>,
+    pub acknowledgments: Option<Vec<String>>,
+    pub preferred_languages: Option<Vec<String>>,
+    pub policy: Option<Vec<String>>,
+    pub hiring: Option<Vec<String>>,
+}
+
+/// Services to tell about changed pages after a build. Nothing is sent unless configured.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct NotifyConfig {
+    pub index_now: Option<IndexNowConfig>,
+    /// URLs requested when anything changed. `{sitemap}` and `{feed}` are replaced with the
+    /// site's sitemap and feed URLs.
+    pub ping: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct IndexNowConfig {
+    /// Also written to `{key}.txt` in the destination, where IndexNow verifies it.
+    pub key: String,
+    /// Defaults to `https://api.indexnow.org/indexnow`.
+    pub endpoint: Option<String>,
+}
+
+/// Recursively overlays `overrides` onto `base`. Mappings are merged key by key; anything
+/// else, including `null`, replaces what was there.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Mapping(base), Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// The overrides of `name` and of every profile it `extends`, base first.
+fn profile_chain(profiles: &Mapping, name: &str) -> anyhow::Result<Vec<Value>> {
+    let mut chain = vec![];
+    let mut seen = HashSet::new();
+    let mut next = Some(name.to_owned());
+
+    while let Some(name) = next {
+        if !seen.insert(name.clone()) {
+            anyhow::bail!("Profile `{}` extends itself", name);
+        }
+
+        let Some(profile) = profiles.get(name.as_str()) else {
+            anyhow::bail!("No profile `{}` in the config", name);
+        };
+        let mut profile = profile.clone();
+
+        next = match profile
+            .as_mapping_mut()
+            .and_then(|profile| profile.remove("extends"))
+        {
+            Some(Value::String(parent)) => Some(parent),
+            Some(other) => anyhow::bail!("`extends` of profile `{}` is {:?}", name, other),
+            None => None,
+        };
+
+        chain.push(profile);
+    }
+
+    chain.reverse();
+
+    Ok(chain)
+}
+
+impl Config {
+    /// Parses `impertio.yaml` for `profile`. Settings under `profiles.{profile}` override the
+    /// top-level ones, after those of the profile it `extends`, e.g.:
+    ///
+    /// ```yaml
+    /// site_url: https://example.com
+    /// profiles:
+    ///   staging:
+    ///     site_url: https://staging.example.com
+    ///   dev:
+    ///     extends: staging
+    ///     rss: null
+    /// ```
+    ///
+    /// `dev` and `prod` don't need an entry.
+    pub fn load(yaml: &str, profile: &str) -> anyhow::Result<Self> {
+        let mut value: Value = serde_yaml::from_str(yaml)?;
+        let profiles = value
+            .as_mapping_mut()
+            .and_then(|config| config.remove("profiles"))
+            .unwrap_or(Value::Mapping(Mapping::new()));
+        let Value::Mapping(profiles) = profiles else {
+            anyhow::bail!("`profiles` must be a mapping of names to settings");
+        };
+
+        if profiles.contains_key(profile) || !matches!(profile, "dev" | "prod") {
+            for overrides in profile_chain(&profiles, profile)? {
+                merge(&mut value, overrides);
+            }
+        }
+
+        let mut config: Config = serde_yaml::from_value(value)?;
+
+        config.profile = profile.to_owned();
+
+        Ok(config)
+    }
+
+    pub fn is_dev(&self) -> bool {
+        self.profile == "dev"
+    }
+
+    pub fn drafts(&self) -> bool {
+        self.drafts.unwrap_or(self.is_dev())
+    }
+
+    pub fn minify(&self) -> bool {
+        self.minify.unwrap_or(!self.is_dev())
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language
+            .as_deref()
+            .or(self.rss.as_ref().and_then(|rss| rss.language.as_deref()))
+    }
+
+    /// What's wrong with settings that parse, but can't work with the source directory
+    /// `root`: a `site_url` that isn't one, or files and directories that aren't there.
+    pub fn problems(&self, root: &Path) -> Vec<String> {
+        let mut problems = vec![];
+
+        if !self.site_url.starts_with("https://") && !self.site_url.starts_with("http://") {
+            problems.push(format!(
+                "`site_url` {:?} isn't an HTTP(S) URL",
+                self.site_url
+            ));
+        } else if self.site_url.ends_with('/') {
+            problems.push(format!(
+                "`site_url` {:?} ends with `/`, so URLs would have `//`",
+                self.site_url
+            ));
+        }
+
+        let paths = [
+            ("plugins", self.plugins.clone().unwrap_or_default()),
+            (
+                "bibliography",
+                self.bibliography.clone().unwrap_or_default(),
+            ),
+            ("galleries", self.galleries.clone().unwrap_or_default()),
+            (
+                "fallback_template",
+                self.fallback_template.iter().cloned().collect(),
+            ),
+            (
+                "downloads",
+                self.downloads
+                    .iter()
+                    .flatten()
+                    .map(|downloads| downloads.dir.clone())
+                    .collect(),
+            ),
+            (
+                "versioned_docs",
+                self.versioned_docs
+                    .iter()
+                    .flatten()
+                    .map(|docs| docs.dir.clone())
+                    .collect(),
+            ),
+            (
+                "changelog_feeds",
+                self.changelog_feeds
+                    .iter()
+                    .flatten()
+                    .map(|feed| feed.file.clone())
+                    .collect(),
+            ),
+        ];
+
+        for (setting, paths) in paths {
+            for path in paths {
+                if !root.join(path.trim_start_matches('/')).exists() {
+                    problems.push(format!(
+                        "`{}` lists {:?}, which doesn't exist",
+                        setting, path
+                    ));
+                }
+            }
+        }
+
+        let mut taxonomies = HashSet::new();
+
+        for taxonomy in self.taxonomies.iter().flatten() {
+            if taxonomy.name.trim().is_empty() {
+                problems.push("`taxonomies` has one without a `name`".into());
+            } else if !taxonomies.insert(&taxonomy.name) {
+                problems.push(format!("`taxonomies` has {:?} twice", taxonomy.name));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Presence enables build-time syntax highlighting of source blocks.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct HighlightConfig {
+    /// A syntect theme name. Defaults to `InspiredGitHub`.
+    pub theme: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct HandlerConfig {
+    /// Maps an extension (without the dot, or `_default`) to a handler name, e.g. `txt: org`, or
+    /// a glob of paths relative to the root, e.g. `notes/**/*.txt: org`. Globs take precedence
+    /// over extensions, and longer globs over shorter ones, then those first alphabetically.
+    pub extensions: Option<HashMap<String, String>>,
+    /// Built-in handlers that should not be registered for their usual extensions.
+    pub disabled: Option<Vec<String>>,
+    /// Guess the type of files without an extension, like scripts, from their contents.
+    pub sniff: Option<bool>,
+    pub org: Option<OrgHandlerConfig>,
+    pub images: Option<ImagesHandlerConfig>,
+    pub asciidoc: Option<ConverterHandlerConfig>,
+    pub rst: Option<ConverterHandlerConfig>,
+    pub csv: Option<CsvHandlerConfig>,
+    pub html: Option<HtmlHandlerConfig>,
+    /// External commands to handle files with, by extension or glob like `extensions`.
+    pub commands: Option<HashMap<String, CommandHandlerConfig>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CsvHandlerConfig {
+    /// Defaults to `,`.
+    pub delimiter: Option<char>,
+    /// Whether the first row is the table's header. Defaults to `true`.
+    pub header: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct HtmlHandlerConfig {
+    /// Globs, relative to the root, of `.html` files to render into `root.html` as if they
+    /// started with `<!-- impertio:wrap -->`. Others are copied as they are.
+    pub wrap: Option<Vec<String>>,
+}
+
+/// For handlers that shell out to a converter, printing an HTML fragment for the source path.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ConverterHandlerConfig {
+    pub command: Option<String>,
+    /// Passed before the source path, which is always the last argument.
+    pub args: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct CommandHandlerConfig {
+    /// The program to run. Its stdout becomes the output file, unless it writes to `{output}`.
+    pub command: String,
+    /// `{input}` and `{output}` are replaced by the source and output paths, as in
+    /// `[{input}, -o, {output}]`. Without `{input}`, the source path is the last argument.
+    pub args: Option<Vec<String>>,
+    /// Extension of the output file. Defaults to `html`.
+    pub output_extension: Option<String>,
+    /// Render the output into `root.html`. Defaults to `true` for HTML output.
+    pub template: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct OrgHandlerConfig {
+    /// Copy the `.org` source next to the rendered page. Defaults to `true`.
+    pub publish_source: Option<bool>,
+    /// `#+TODO`-style sequences used when a file has none, e.g. `TODO NEXT | DONE`.
+    /// Defaults to `TODO | DONE`.
+    pub todo_keywords: Option<Vec<String>>,
+    /// Add a `¶` link to each heading's anchor. Defaults to `false`.
+    pub heading_anchors: Option<bool>,
+    /// Give the first paragraph and every this many after it an id from a hash of its text,
+    /// with an empty `<a class="paragraph-anchor">` to it, e.g. `1` for all of them. Defaults to
+    /// none.
+    pub paragraph_anchors: Option<usize>,
+    /// Heading levels in the table of contents of files that don't set `toc` in `#+OPTIONS`.
+    /// Defaults to none.
+    pub toc: Option<usize>,
+    pub toc_placement: Option<TocPlacement>,
+    /// Greater blocks rendered as admonitions, by lower-case block type. Overrides the built-in
+    /// `note`, `tip`, `important`, `warning` and `caution`.
+    pub admonitions: Option<HashMap<String, AdmonitionConfig>>,
+    /// Whether figures, tables and listings are numbered per page, the default, or across the
+    /// whole site.
+    pub numbering: Option<NumberingScope>,
+    /// Link types rendered as elements, like `kbd:C-x` or `[[menu:File > Save]]`, by type.
+    /// Overrides the built-in `kbd` and `menu`.
+    pub link_types: Option<HashMap<String, LinkTypeConfig>>,
+}
+
+/// How text after a link type, like the `C-c C-c` of `[[kbd:C-c C-c]]`, is rendered.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct LinkTypeConfig {
+    /// Defaults to `span`.
+    pub element: Option<String>,
+    pub class: Option<String>,
+    /// Puts each part of the text between this in an element of its own, inside the outer one,
+    /// e.g. the keys of a shortcut.
+    pub split: Option<String>,
+}
+
+/// How a block like `#+BEGIN_NOTE` is rendered: a `<div class="admonition admonition-note">`
+/// starting with its icon and title.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct AdmonitionConfig {
+    /// Defaults to the block type, capitalized.
+    pub title: Option<String>,
+    /// Text or HTML before the title, like an emoji or an icon font's `<i>`.
+    pub icon: Option<String>,
+    /// More classes for the `<div>`.
+    pub class: Option<String>,
+}
+
+/// Where the table of contents goes. Templates can use it as `toc` either way.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TocPlacement {
+    /// Before the article's content. The default.
+    Top,
+    /// Only where the template puts `{{ toc }}`.
+    Template,
+}
+
+/// Where figure, table and listing numbers start over.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberingScope {
+    /// On every page. The default.
+    Page,
+    /// Nowhere: numbers continue from page to page, in the order of their URLs.
+    Site,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct ImagesHandlerConfig {
+    /// Largest width, in pixels, embedded images are displayed at unless a width is given.
+    pub max_width: Option<u32>,
+}
+
+/// Presence renders `#+BEGIN_SRC dot` blocks to inline SVG with Graphviz, and enables the
+/// configured `mermaid` and `plantuml` renderers.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct DiagramsConfig {
+    /// The Graphviz program to run. Defaults to `dot`.
+    pub command: Option<String>,
+    /// Where rendered diagrams are kept by the hash of their source, relative to the source
+    /// directory. It isn't copied to the destination. Defaults to `.impertio-cache/diagrams`.
+    pub cache: Option<String>,
+    /// Renders `#+BEGIN_SRC mermaid` blocks.
+    pub mermaid: Option<DiagramRendererConfig>,
+    /// Renders `#+BEGIN_SRC plantuml` blocks.
+    pub plantuml: Option<DiagramRendererConfig>,
+}
+
+/// How blocks of a diagram language are rendered. With neither `command` nor `url`, they're
+/// left to a client-side renderer in a `<pre class="{language}">`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct DiagramRendererConfig {
+    /// A program that reads the source from its standard input and prints SVG, like `plantuml`
+    /// with `-tsvg -pipe`.
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+    /// A service the source is POSTed to, which responds with SVG, like
+    /// `https://kroki.io/mermaid/svg`.
+    pub url: Option<String>,
+}
+
+impl DiagramsConfig {
+    pub fn command(&self) -> &str {
+        self.command.as_deref().unwrap_or("dot")
+    }
+
+    pub fn cache(&self) -> &str {
+        self.cache.as_deref().unwrap_or(".impertio-cache/diagrams")
+    }
+}
+
+/// Presence enables LaTeX fragment passthrough for MathJax/KaTeX.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+pub struct MathConfig {
+    /// Defaults to `\(` and `\)`.
+    pub inline: Option<(String, String)>,
+    /// Defaults to `\[` and `\]`.
+    pub display: Option<(String, String)>,
+    /// Render fragments with KaTeX at build time instead of leaving them for client-side JS.
+    pub server_side: Option<bool>,
+}
+
+impl MathConfig {
+    pub fn inline_delimiters(&self) -> (&str, &str) {
+        self.inline
+            .as_ref()
+            .map_or(("\\(", "\\)"), |(open, close)| (open, close))
+    }
+
+    pub fn display_delimiters(&self) -> (&str, &str) {
+        self.display
+            .as_ref()
+            .map_or(("\\[", "\\]"), |(open, close)| (open, close))
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -24,7 +681,22 @@ pub struct RSSConfig {
     pub rating: Option<String>,
     pub text_input: Option<TextInput>,
     pub skip_hours: Option<Vec<String>>,
-    pub skip_days: Option<Vec<String>>
+    pub skip_days: Option<Vec<String>>,
+    /// Items in the main feed. Older items are moved into archive feeds (`feed-1` being the
+    /// oldest) of this many items each, linked as paged and archived feeds (RFC 5005).
+    pub page_size: Option<usize>,
+    pub websub: Option<WebSubConfig>,
+    /// Include each Org article's rendered page in its item, as `content:encoded`, with links
+    /// made absolute. Defaults to `false`.
+    pub content: Option<bool>,
+}
+
+/// A WebSub (PubSubHubbub) hub, advertised in the main feed.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct WebSubConfig {
+    pub hub: String,
+    /// Notify the hub of the main feed after a successful build. Defaults to `false`.
+    pub ping: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -50,3 +722,82 @@ pub struct TextInput {
     pub name: String,
     pub link: String,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+
+    const CONFIG: &str = "site_url: https://example.com
+rss:
+  title: Blog
+  link: https://example.com
+  description: Posts
+  language: en
+profiles:
+  staging:
+    site_url: https://staging.example.com
+    rss:
+      title: Staging
+  dev:
+    extends: staging
+    rss: null
+  loop:
+    extends: loop
+";
+
+    #[test]
+    fn profiles() {
+        let prod = Config::load(CONFIG, "prod").unwrap();
+
+        assert_eq!(prod.site_url, "https://example.com");
+        assert_eq!(prod.profile, "prod");
+
+        let staging = Config::load(CONFIG, "staging").unwrap();
+        let rss = staging.rss.unwrap();
+
+        assert_eq!(staging.site_url, "https://staging.example.com");
+        assert_eq!(rss.title, "Staging");
+        assert_eq!(rss.language.as_deref(), Some("en"));
+
+        let dev = Config::load(CONFIG, "dev").unwrap();
+
+        assert_eq!(dev.site_url, "https://staging.example.com");
+        assert_eq!(dev.rss, None);
+        assert!(dev.is_dev());
+    }
+
+    #[test]
+    fn problems() {
+        let root = std::env::temp_dir();
+        let config = Config::load(
+            "site_url: https://example.com/
+galleries: [impertio-no-such-gallery]
+taxonomies:
+  - name: tags
+  - name: tags
+",
+            "prod",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.problems(&root),
+            [
+                "`site_url` \"https://example.com/\" ends with `/`, so URLs would have `//`",
+                "`galleries` lists \"impertio-no-such-gallery\", which doesn't exist",
+                "`taxonomies` has \"tags\" twice",
+            ]
+        );
+        assert!(Config::load(CONFIG, "prod")
+            .unwrap()
+            .problems(&root)
+            .is_empty());
+    }
+
+    #[test]
+    fn bad_profiles() {
+        assert!(Config::load(CONFIG, "missing").is_err());
+        assert!(Config::load(CONFIG, "loop").is_err());
+        assert!(Config::load("site_url: x", "dev").is_ok());
+    }
+}
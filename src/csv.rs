@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! CSV files published as sortable HTML tables.
+
+use build_html::escape_html;
+
+/// Sorts a `table.sortable` by the column whose header is clicked, numerically if both cells
+/// are numbers.
+const SORT_SCRIPT: &str = r#"document.querySelectorAll("table.sortable").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (th, column) {
+    th.addEventListener("click", function () {
+      var body = table.tBodies[0];
+      var ascending = th.getAttribute("aria-sort") !== "ascending";
+      table.querySelectorAll("th").forEach(function (other) { other.removeAttribute("aria-sort"); });
+      th.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+      Array.from(body.rows).sort(function (a, b) {
+        var x = a.cells[column] ? a.cells[column].textContent : "";
+        var y = b.cells[column] ? b.cells[column].textContent : "";
+        var order = x === "" || y === "" || isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+        return ascending ? order : -order;
+      }).forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});"#;
+
+/// The records of `content`, as in RFC 4180: fields may be quoted, with `""` for a quote, and
+/// quoted fields may span lines. Blank lines are skipped.
+pub fn parse(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.len() > 1 || row.first().is_some_and(|field| !field.is_empty()));
+
+    rows
+}
+
+/// A sortable `<table>`, with the first row as its header if `header`, and a link to download
+/// the CSV from `source`.
+pub fn render(rows: &[Vec<String>], header: bool, source: &str) -> String {
+    let cells = |row: &[String], tag: &str| -> String {
+        row.iter()
+            .map(|cell| format!("<{0}>{1}</{0}>", tag, escape_html(cell)))
+            .collect()
+    };
+    let (head, body) = match rows.split_first() {
+        Some((first, rest)) if header => (Some(first), rest),
+        _ => (None, rows),
+    };
+
+    format!(
+        "<table class=\"csv sortable\">{}<tbody>{}</tbody></table><p><a class=\"csv-download\" href=\"{}\" download>Download CSV</a></p><script>{}</script>",
+        head.map(|head| format!("<thead><tr>{}</tr></thead>", cells(head, "th")))
+            .unwrap_or_default(),
+        body.iter()
+            .map(|row| format!("<tr>{}</tr>", cells(row, "td")))
+            .collect::<String>(),
+        escape_html(source),
+        SORT_SCRIPT
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::csv::{parse, render};
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            parse(
+                "name,notes\r\n\"Doe, Jane\",\"said \"\"hi\"\"\nthen left\"\n\nRoe,\n",
+                ','
+            ),
+            [
+                vec!["name", "notes"],
+                vec!["Doe, Jane", "said \"hi\"\nthen left"],
+                vec!["Roe", ""],
+            ]
+        );
+        assert_eq!(parse("a;b", ';'), [vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn table() {
+        let rows = parse("n,<b>\n1,2", ',');
+        let html = render(&rows, true, "data.csv");
+
+        assert!(html.starts_with(
+            "<table class=\"csv sortable\"><thead><tr><th>n</th><th>&lt;b&gt;</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr></tbody></table><p><a class=\"csv-download\" href=\"data.csv\" download>Download CSV</a></p><script>"
+        ));
+        assert!(render(&rows, false, "data.csv").contains("<tbody><tr><td>n</td>"));
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Data files, like `data/team.yaml`, available to every template as `data.team`, for pricing
+//! tables, team lists and the like.
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+/// Where data files are loaded from, relative to the source directory. The data files in it
+/// aren't built, but other files, like images, are.
+pub const DATA_DIR: &str = "data";
+
+/// Whether `path` is loaded as data if it's in [`DATA_DIR`]: a YAML, JSON or CSV file.
+pub fn is_data_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "yaml" | "yml" | "json" | "csv"
+            )
+        })
+}
+
+/// Whether `file` is one of the data files loaded from the source directory `root`.
+pub fn is_loaded(root: &Path, file: &Path) -> bool {
+    file.starts_with(root.join(DATA_DIR)) && is_data_file(file)
+}
+
+/// A CSV file's rows after the first, as objects keyed by the first row's fields.
+fn csv_rows(content: &str) -> Value {
+    let mut rows = crate::csv::parse(content, ',').into_iter();
+    let header = rows.next().unwrap_or_default();
+
+    Value::Array(
+        rows.map(|row| {
+            Value::Object(
+                header
+                    .iter()
+                    .cloned()
+                    .zip(row.into_iter().map(Value::String))
+                    .collect(),
+            )
+        })
+        .collect(),
+    )
+}
+
+/// The YAML, JSON and CSV files in `dir`, by name without their extension, with
+/// subdirectories as nested objects, e.g. `data/team/members.yaml` as `team.members`. Other
+/// files are skipped.
+pub fn load(dir: &Path) -> anyhow::Result<Value> {
+    let mut data = Map::new();
+
+    if !dir.is_dir() {
+        return Ok(Value::Object(data));
+    }
+
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path();
+
+        if !entry.file_type().is_file() || !is_data_file(path) {
+            continue;
+        }
+
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading `{}`", path.display()))?;
+        let value = match ext.as_str() {
+            "json" => serde_json::from_str(&content)
+                .with_context(|| format!("Parsing `{}`", path.display()))?,
+            "csv" => csv_rows(&content),
+            _ => serde_yaml::from_str(&content)
+                .with_context(|| format!("Parsing `{}`", path.display()))?,
+        };
+
+        let relative = path.strip_prefix(dir)?.with_extension("");
+        let mut names: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let name = names.pop().unwrap_or_default();
+        let mut parent = &mut data;
+
+        for dir in names {
+            let entry = parent
+                .entry(dir)
+                .or_insert_with(|| Value::Object(Map::new()));
+
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+
+            parent = entry.as_object_mut().unwrap();
+        }
+
+        parent.insert(name, value);
+    }
+
+    Ok(Value::Object(data))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::data::load;
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir().join(format!("impertio-data-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("team")).unwrap();
+        std::fs::write(dir.join("pricing.yaml"), "- plan: Free\n  price: 0\n").unwrap();
+        std::fs::write(dir.join("site.json"), r#"{"since": 2024}"#).unwrap();
+        std::fs::write(
+            dir.join("team/members.csv"),
+            "name,role\nAda,Lead\n\"Grace, H.\",Dev\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "skipped").unwrap();
+
+        let data = load(&dir).unwrap();
+
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let broken = load(&dir);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            data,
+            json!({
+                "pricing": [{"plan": "Free", "price": 0}],
+                "site": {"since": 2024},
+                "team": {
+                    "members": [
+                        {"name": "Ada", "role": "Lead"},
+                        {"name": "Grace, H.", "role": "Dev"},
+                    ],
+                },
+            })
+        );
+        assert!(broken.is_err());
+        assert_eq!(load(&dir).unwrap(), json!({}));
+    }
+}
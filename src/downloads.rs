@@ -0,0 +1,227 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Index pages for directories of downloads.
+
+use std::path::Path;
+
+use crate::{checksums, config::DownloadsConfig, template::Templates};
+use build_html::{escape_html, Container, ContainerType, Html, HtmlContainer};
+use chrono::{DateTime, Utc};
+
+/// A file offered for download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    pub name: String,
+    /// In bytes.
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+    /// Hex-encoded SHA-256 of the contents.
+    pub sha256: String,
+}
+
+/// The files directly in `dir`, by name, leaving out hidden files and index pages.
+pub fn list(dir: &Path) -> anyhow::Result<Vec<Download>> {
+    let mut downloads = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if !entry.file_type()?.is_file()
+            || name.starts_with('.')
+            || Path::new(&name)
+                .file_stem()
+                .is_some_and(|stem| stem == "index")
+        {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+
+        downloads.push(Download {
+            name,
+            size: metadata.len(),
+            modified: metadata.modified()?.into(),
+            sha256: checksums::sha256_hex(&std::fs::read(entry.path())?),
+        });
+    }
+
+    downloads.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(downloads)
+}
+
+/// `1536` → `1.5 KiB`.
+fn human_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut size = size as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// `a b&c.pdf` → `a%20b%26c.pdf`, for a file name in a URL.
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// A card for each download, like the article listing's.
+pub fn render(downloads: &[Download]) -> String {
+    let mut listing = Container::new(ContainerType::Div).with_attributes([("class", "downloads")]);
+
+    for download in downloads {
+        let name = escape_html(&download.name);
+        // Attribute values aren't escaped by `build_html`.
+        let card = Container::new(ContainerType::Div)
+            .with_attributes([
+                ("data-title", name.as_str()),
+                ("data-size", &download.size.to_string()),
+                (
+                    "data-last-modified",
+                    &escape_html(&download.modified.to_rfc3339()),
+                ),
+            ])
+            .with_paragraph_attr(&name, [("class", "card-title")])
+            .with_container(Container::new(ContainerType::Div).with_raw(format!(
+                "<span class=\"card-size\">{}</span><span class=\"card-time\">{}</span><code class=\"card-checksum\">sha256:{}</code>",
+                human_size(download.size),
+                escape_html(&download.modified.to_rfc3339()),
+                download.sha256
+            )));
+
+        listing.add_raw(format!(
+            "<a href=\"{}\" class=\"download-card\" download>{}</a>",
+            percent_encode(&download.name),
+            card.to_html_string()
+        ));
+    }
+
+    listing.to_html_string()
+}
+
+/// Renders `index.html` into each configured directory of `dest`, through `root.html`.
+/// Directories with an index page of their own are left alone.
+pub fn write(
+    config: &[DownloadsConfig],
+    root: &Path,
+    dest: &Path,
+    templates: &Templates,
+) -> anyhow::Result<()> {
+    for downloads in config {
+        let source = root.join(&downloads.dir);
+
+        if has_index(&source)? {
+            log::warn!(
+                "`{}` has an index page, not generating a downloads index.",
+                downloads.dir
+            );
+            continue;
+        }
+
+        log::info!(phase = "downloads"; "Generating the index of `{}`", downloads.dir);
+
+        let title = downloads
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Downloads: {}", downloads.dir));
+        let out = templates.render(
+            "root.html",
+            &source.join("index.html"),
+            &render(&list(&source)?),
+            Some([("title", title)].into()),
+        )?;
+        let dir = dest.join(&downloads.dir);
+
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("index.html"), out)?;
+    }
+
+    Ok(())
+}
+
+fn has_index(dir: &Path) -> anyhow::Result<bool> {
+    for entry in std::fs::read_dir(dir)? {
+        if Path::new(&entry?.file_name())
+            .file_stem()
+            .is_some_and(|stem| stem == "index")
+        {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::downloads::{human_size, list, render, Download};
+
+    #[test]
+    fn sizes() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn listing() {
+        let dir = std::env::temp_dir().join(format!("impertio-downloads-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.tar.gz"), "hello").unwrap();
+        std::fs::write(dir.join("a.pdf"), "").unwrap();
+        std::fs::write(dir.join("index.org"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let downloads = list(&dir).unwrap();
+
+        assert_eq!(
+            downloads
+                .iter()
+                .map(|download| (download.name.as_str(), download.size))
+                .collect::<Vec<_>>(),
+            [("a.pdf", 0), ("b.tar.gz", 5)]
+        );
+        assert_eq!(
+            downloads[1].sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rendered() {
+        let html = render(&[Download {
+            name: "a&b \"c\".pdf".into(),
+            size: 2048,
+            modified: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            sha256: "00ff".into(),
+        }]);
+
+        assert_eq!(
+            html,
+            "<div class=\"downloads\"><a href=\"a%26b%20%22c%22.pdf\" class=\"download-card\" download><div data-title=\"a&amp;b &quot;c&quot;.pdf\" data-size=\"2048\" data-last-modified=\"2024-01-01T00:00:00+00:00\"><p class=\"card-title\">a&amp;b &quot;c&quot;.pdf</p><div><span class=\"card-size\">2.0 KiB</span><span class=\"card-time\">2024-01-01T00:00:00+00:00</span><code class=\"card-checksum\">sha256:00ff</code></div></div></a></div>"
+        );
+    }
+}
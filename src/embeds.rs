@@ -0,0 +1,121 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Third-party scripts and frames in built pages, like those pasted into export blocks, which
+//! weigh pages down without showing up in their sources.
+
+use std::path::Path;
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+use crate::config::EmbedsConfig;
+
+lazy_static! {
+    static ref EMBED: Regex = Regex::new(
+        r#"(?is)<(?<tag>script|iframe|embed)\b[^>]*?\bsrc\s*=\s*(?<quote>["'])(?<url>.*?)\k<quote>"#
+    )
+    .unwrap();
+}
+
+/// The host of an absolute or protocol-relative `url`, if it has one.
+fn host(url: &str) -> Option<&str> {
+    let rest = url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .or_else(|| url.strip_prefix("//"))?;
+
+    rest.split(['/', '?', '#']).next().map(|authority| {
+        let authority = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host)| host);
+
+        authority.split(':').next().unwrap_or(authority)
+    })
+}
+
+/// Whether `host` is `allowed`, which may start with `*.` for a domain and its subdomains.
+fn allowed(host: &str, allowed: &str) -> bool {
+    match allowed.strip_prefix("*.") {
+        Some(domain) => {
+            host.eq_ignore_ascii_case(domain)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+        }
+        None => host.eq_ignore_ascii_case(allowed),
+    }
+}
+
+/// The `<script>`s, `<iframe>`s and `<embed>`s of `html` loading from hosts other than the
+/// site's and those allowed, as `tag url`.
+pub fn disallowed(html: &str, config: &EmbedsConfig, site_url: &str) -> Vec<String> {
+    let site = host(site_url);
+
+    EMBED
+        .captures_iter(html)
+        .filter_map(Result::ok)
+        .filter_map(|caps| {
+            let url = caps["url"].trim();
+            let host = host(url)?;
+
+            (Some(host) != site && !config.allow.iter().any(|allow| allowed(host, allow)))
+                .then(|| format!("<{}> {}", caps["tag"].to_ascii_lowercase(), url))
+        })
+        .collect()
+}
+
+/// The disallowed embeds of every page in `dir`, as `path: tag url`.
+pub fn check(dir: &Path, config: &EmbedsConfig, site_url: &str) -> anyhow::Result<Vec<String>> {
+    let mut found = vec![];
+
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() || entry.path().extension().is_none_or(|ext| ext != "html")
+        {
+            continue;
+        }
+
+        let Ok(html) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let relative = entry.path().strip_prefix(dir)?;
+
+        for embed in disallowed(&html, config, site_url) {
+            found.push(format!("{}: {}", relative.display(), embed));
+        }
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::EmbedsConfig, embeds::disallowed};
+
+    #[test]
+    fn embeds() {
+        let config = EmbedsConfig {
+            allow: vec!["*.youtube-nocookie.com".into(), "cdn.x.y".into()],
+        };
+
+        assert_eq!(
+            disallowed(
+                "<script src=\"/js/site.js\"></script>\
+                 <script async src=\"https://x.y/a.js\"></script>\
+                 <script src='//cdn.x.y/b.js'></script>\
+                 <IFRAME width=\"560\" src=\"https://www.youtube-nocookie.com/embed/x\"></IFRAME>\
+                 <iframe src=\"https://www.youtube.com/embed/x\"></iframe>\
+                 <script src=\"https://tracker.z:8080/t.js\"></script>\
+                 <img src=\"https://z.y/cat.png\" />",
+                &config,
+                "https://x.y",
+            ),
+            vec![
+                "<iframe> https://www.youtube.com/embed/x",
+                "<script> https://tracker.z:8080/t.js",
+            ]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! EXIF metadata from JPEG files' `APP1` segments, and IPTC captions from their `APP13`.
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+
+const IMAGE_DESCRIPTION: u16 = 0x010E;
+const MAKE: u16 = 0x010F;
+const MODEL: u16 = 0x0110;
+const DATE_TIME: u16 = 0x0132;
+const EXIF_IFD: u16 = 0x8769;
+const GPS_IFD: u16 = 0x8825;
+const DATE_TIME_ORIGINAL: u16 = 0x9003;
+const OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+
+const GPS_LATITUDE_REF: u16 = 1;
+const GPS_LATITUDE: u16 = 2;
+const GPS_LONGITUDE_REF: u16 = 3;
+const GPS_LONGITUDE: u16 = 4;
+
+/// The Photoshop image resource holding IPTC-IIM records.
+const IPTC_RESOURCE: u16 = 0x0404;
+/// The IPTC application record's Caption/Abstract.
+const IPTC_CAPTION: (u8, u8) = (2, 120);
+
+/// What an image's EXIF and IPTC data say about it.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Exif {
+    /// When the photo was taken, in UTC if the camera recorded its offset and as if taken in
+    /// UTC otherwise.
+    pub taken: Option<DateTime<Utc>>,
+    /// The camera's make and model, e.g. "Canon EOS R6".
+    pub camera: Option<String>,
+    /// Where the photo was taken.
+    pub location: Option<Location>,
+    /// The IPTC caption, or the EXIF image description if there isn't one.
+    pub caption: Option<String>,
+}
+
+/// GPS coordinates in decimal degrees, negative south and west.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// A TIFF structure, as EXIF data is, with its byte order.
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+/// An IFD entry: its tag, type, count, and the offset of its value or the value itself.
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: u32,
+    /// Where the 4-byte value field is, for values that fit in it.
+    at: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let tiff = Self {
+            data,
+            little_endian,
+        };
+
+        (tiff.u16(2)? == 42).then_some(tiff)
+    }
+
+    fn u16(&self, at: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// The entries of the IFD at `offset`.
+    fn entries(&self, offset: usize) -> Vec<Entry> {
+        let count = self.u16(offset).unwrap_or(0) as usize;
+
+        (0..count)
+            .map_while(|i| {
+                let at = offset + 2 + i * 12;
+
+                Some(Entry {
+                    tag: self.u16(at)?,
+                    kind: self.u16(at + 2)?,
+                    count: self.u32(at + 4)?,
+                    value: self.u32(at + 8)?,
+                    at: at + 8,
+                })
+            })
+            .collect()
+    }
+
+    /// The entries of the IFD `entry` points to.
+    fn sub_ifd(&self, entry: Option<&Entry>) -> Vec<Entry> {
+        entry
+            .map(|entry| self.entries(entry.value as usize))
+            .unwrap_or_default()
+    }
+
+    /// The first IFD's offset.
+    fn first_ifd(&self) -> Option<usize> {
+        self.u32(4).map(|offset| offset as usize)
+    }
+
+    /// An ASCII value, without its trailing NULs.
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        if entry.kind != 2 {
+            return None;
+        }
+
+        let len = entry.count as usize;
+        let start = if len <= 4 {
+            entry.at
+        } else {
+            entry.value as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+
+        Some(
+            String::from_utf8_lossy(bytes)
+                .trim_end_matches('\0')
+                .trim()
+                .to_owned(),
+        )
+    }
+
+    /// Degrees, minutes and seconds as three rationals, in degrees.
+    fn degrees(&self, entry: &Entry) -> Option<f64> {
+        if entry.kind != 5 || entry.count != 3 {
+            return None;
+        }
+
+        let start = entry.value as usize;
+        let mut degrees = 0.0;
+
+        for (i, unit) in [1.0, 60.0, 3600.0].into_iter().enumerate() {
+            let numerator = self.u32(start + i * 8)?;
+            let denominator = self.u32(start + i * 8 + 4)?;
+
+            if denominator == 0 {
+                return None;
+            }
+
+            degrees += numerator as f64 / denominator as f64 / unit;
+        }
+
+        Some(degrees)
+    }
+}
+
+/// The JPEG's metadata segments, up to the start of scan, as their markers and contents.
+fn segments(jpeg: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = vec![];
+
+    if jpeg.get(..2) != Some(&[0xFF, 0xD8]) {
+        return segments;
+    }
+
+    let mut at = 2;
+
+    while at + 4 <= jpeg.len() && jpeg[at] == 0xFF {
+        let marker = jpeg[at + 1];
+
+        // The image data follows the start of scan, with no metadata after it.
+        if marker == 0xDA {
+            break;
+        }
+
+        let len = u16::from_be_bytes([jpeg[at + 2], jpeg[at + 3]]) as usize;
+        let Some(segment) = jpeg.get(at + 4..at + 2 + len) else {
+            break;
+        };
+
+        segments.push((marker, segment));
+        at += 2 + len;
+    }
+
+    segments
+}
+
+/// The TIFF data of a JPEG's EXIF segment, if it has one.
+fn exif_segment<'a>(segments: &[(u8, &'a [u8])]) -> Option<&'a [u8]> {
+    segments.iter().find_map(|(marker, segment)| {
+        (*marker == 0xE1)
+            .then(|| segment.strip_prefix(b"Exif\0\0"))
+            .flatten()
+    })
+}
+
+/// The IPTC caption in a JPEG's Photoshop `APP13` segment, if it has one.
+fn iptc_caption(segments: &[(u8, &[u8])]) -> Option<String> {
+    let resources = segments.iter().find_map(|(marker, segment)| {
+        (*marker == 0xED)
+            .then(|| segment.strip_prefix(b"Photoshop 3.0\0"))
+            .flatten()
+    })?;
+    let mut at = 0;
+
+    // Image resources: `8BIM`, an ID, a padded Pascal string name, then padded sized data.
+    while resources.get(at..at + 4)? == b"8BIM" {
+        let id = u16::from_be_bytes(resources.get(at + 4..at + 6)?.try_into().ok()?);
+        let name_len = *resources.get(at + 6)? as usize;
+        let size_at = at + 6 + (name_len + 1).next_multiple_of(2);
+        let size = u32::from_be_bytes(resources.get(size_at..size_at + 4)?.try_into().ok()?);
+        let data = resources.get(size_at + 4..(size_at + 4).checked_add(size as usize)?)?;
+
+        if id == IPTC_RESOURCE {
+            return iptc_dataset(data, IPTC_CAPTION);
+        }
+
+        at = size_at + 4 + (size as usize).next_multiple_of(2);
+    }
+
+    None
+}
+
+/// An IPTC-IIM dataset's text: records are `0x1C`, record and dataset numbers, then a sized value.
+fn iptc_dataset(data: &[u8], (record, dataset): (u8, u8)) -> Option<String> {
+    let mut at = 0;
+
+    while *data.get(at)? == 0x1C {
+        let len = u16::from_be_bytes(data.get(at + 3..at + 5)?.try_into().ok()?) as usize;
+        let value = data.get(at + 5..at + 5 + len)?;
+
+        if data[at + 1] == record && data[at + 2] == dataset {
+            let text = String::from_utf8_lossy(value).trim().to_owned();
+
+            return (!text.is_empty()).then_some(text);
+        }
+
+        at += 5 + len;
+    }
+
+    None
+}
+
+/// `2024:05:01 12:30:00`, with the `+02:00` offset from `OffsetTimeOriginal` if there is one.
+fn parse_date(date: &str, offset: Option<&str>) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(date, "%Y:%m:%d %H:%M:%S").ok()?;
+    let offset = offset
+        .and_then(|offset| offset.parse::<FixedOffset>().ok())
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+
+    offset
+        .from_local_datetime(&naive)
+        .single()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// The entry for `tag`, if there is one.
+fn entry(entries: &[Entry], tag: u16) -> Option<&Entry> {
+    entries.iter().find(|entry| entry.tag == tag)
+}
+
+/// The camera from `Make` and `Model`, without the make if the model already starts with it.
+fn camera(make: Option<String>, model: Option<String>) -> Option<String> {
+    match (
+        make.filter(|make| !make.is_empty()),
+        model.filter(|model| !model.is_empty()),
+    ) {
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(model)
+        }
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    }
+}
+
+/// The EXIF and IPTC data of a JPEG file, if it has any.
+pub fn read(jpeg: &[u8]) -> Option<Exif> {
+    let segments = segments(jpeg);
+    let caption = iptc_caption(&segments);
+    let Some(tiff) = exif_segment(&segments).and_then(Tiff::new) else {
+        return caption.map(|caption| Exif {
+            caption: Some(caption),
+            ..Default::default()
+        });
+    };
+    let ifd0 = tiff
+        .first_ifd()
+        .map(|at| tiff.entries(at))
+        .unwrap_or_default();
+    let find = |entries: &[Entry], tag: u16| -> Option<String> {
+        entry(entries, tag).and_then(|entry| tiff.ascii(entry))
+    };
+    let exif_ifd = tiff.sub_ifd(entry(&ifd0, EXIF_IFD));
+    let gps_ifd = tiff.sub_ifd(entry(&ifd0, GPS_IFD));
+    let offset = find(&exif_ifd, OFFSET_TIME_ORIGINAL);
+    let coordinate = |tag: u16, ref_tag: u16, negative: &str| -> Option<f64> {
+        let degrees = entry(&gps_ifd, tag).and_then(|entry| tiff.degrees(entry))?;
+
+        Some(if find(&gps_ifd, ref_tag).as_deref() == Some(negative) {
+            -degrees
+        } else {
+            degrees
+        })
+    };
+
+    Some(Exif {
+        taken: find(&exif_ifd, DATE_TIME_ORIGINAL)
+            .and_then(|date| parse_date(&date, offset.as_deref()))
+            .or_else(|| find(&ifd0, DATE_TIME).and_then(|date| parse_date(&date, None))),
+        camera: camera(find(&ifd0, MAKE), find(&ifd0, MODEL)),
+        location: coordinate(GPS_LATITUDE, GPS_LATITUDE_REF, "S")
+            .zip(coordinate(GPS_LONGITUDE, GPS_LONGITUDE_REF, "W"))
+            .map(|(latitude, longitude)| Location {
+                latitude,
+                longitude,
+            }),
+        caption: caption.or_else(|| find(&ifd0, IMAGE_DESCRIPTION).filter(|d| !d.is_empty())),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::exif::{read, Location};
+
+    /// A JPEG with only an EXIF segment: IFD0 points to an EXIF IFD with `DateTimeOriginal` and
+    /// `OffsetTimeOriginal`, in big-endian byte order.
+    pub(crate) fn jpeg(date: &str, offset: &str) -> Vec<u8> {
+        let mut tiff: Vec<u8> = b"MM\0\x2a\0\0\0\x08".to_vec();
+        // IFD0: one entry, pointing to the EXIF IFD at 26.
+        tiff.extend([0, 1, 0x87, 0x69, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0]);
+        // EXIF IFD: two ASCII entries, with values after it at 56 and 76.
+        tiff.extend([0, 2]);
+        tiff.extend([0x90, 0x03, 0, 2, 0, 0, 0, 20, 0, 0, 0, 56]);
+        tiff.extend([0x90, 0x11, 0, 2, 0, 0, 0, 7, 0, 0, 0, 76]);
+        tiff.extend([0, 0, 0, 0]);
+        tiff.extend(date.as_bytes());
+        tiff.push(0);
+        tiff.extend(offset.as_bytes());
+        tiff.push(0);
+
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend(tiff);
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((segment.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(segment);
+        jpeg.extend([0xFF, 0xDA, 0, 2, 0xFF, 0xD9]);
+
+        jpeg
+    }
+
+    #[test]
+    fn capture_time() {
+        assert_eq!(
+            read(&jpeg("2024:05:01 12:30:00", "+02:00")).unwrap().taken,
+            Some(Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap())
+        );
+        assert_eq!(
+            read(&jpeg("2024:05:01 12:30:00", "bogus!")).unwrap().taken,
+            Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap())
+        );
+        assert_eq!(read(&jpeg("not a date", "")).unwrap().taken, None);
+        assert_eq!(read(b"\x89PNG"), None);
+        assert_eq!(read(&[0xFF, 0xD8, 0xFF, 0xE1, 0xFF]), None);
+    }
+
+    /// A little-endian IFD entry.
+    fn entry(tag: u16, kind: u16, count: u32, value: [u8; 4]) -> Vec<u8> {
+        [
+            &tag.to_le_bytes()[..],
+            &kind.to_le_bytes(),
+            &count.to_le_bytes(),
+            &value,
+        ]
+        .concat()
+    }
+
+    /// Rationals for degrees, minutes and seconds.
+    fn dms(d: u32, m: u32, s: u32, s_denominator: u32) -> Vec<u8> {
+        [d, 1, m, 1, s, s_denominator]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn camera_location_caption() {
+        let mut tiff: Vec<u8> = b"II\x2a\0\x08\0\0\0".to_vec();
+        // IFD0 at 8: `Make` at 50, `Model` at 56, and the GPS IFD at 70.
+        tiff.extend([3, 0]);
+        tiff.extend(entry(0x010F, 2, 6, 50u32.to_le_bytes()));
+        tiff.extend(entry(0x0110, 2, 13, 56u32.to_le_bytes()));
+        tiff.extend(entry(0x8825, 4, 1, 70u32.to_le_bytes()));
+        tiff.extend([0; 4]);
+        tiff.extend(b"Canon\0Canon EOS R6\0\0");
+        // GPS IFD at 70: 48°51'30.24" N and 2°17'40.2" W, with the rationals at 124 and 148.
+        tiff.extend([4, 0]);
+        tiff.extend(entry(1, 2, 2, *b"N\0\0\0"));
+        tiff.extend(entry(2, 5, 3, 124u32.to_le_bytes()));
+        tiff.extend(entry(3, 2, 2, *b"W\0\0\0"));
+        tiff.extend(entry(4, 5, 3, 148u32.to_le_bytes()));
+        tiff.extend([0; 4]);
+        tiff.extend(dms(48, 51, 3024, 100));
+        tiff.extend(dms(2, 17, 402, 10));
+
+        let exif = [&b"Exif\0\0"[..], &tiff].concat();
+        // A thumbnail resource, then IPTC records: the record version and a caption.
+        let iptc = [
+            &b"Photoshop 3.0\08BIM\x03\xED\x01x\0\0\0\x03abc\0"[..],
+            b"8BIM\x04\x04\0\0\0\0\0\x0F",
+            b"\x1C\x02\0\0\x02\0\x04\x1C\x02\x78\0\x03Eh?",
+        ]
+        .concat();
+        let mut jpeg = vec![0xFF, 0xD8];
+
+        for (marker, segment) in [(0xE1, exif), (0xED, iptc)] {
+            jpeg.extend([0xFF, marker]);
+            jpeg.extend(((segment.len() + 2) as u16).to_be_bytes());
+            jpeg.extend(segment);
+        }
+
+        let exif = read(&jpeg).unwrap();
+        let Location {
+            latitude,
+            longitude,
+        } = exif.location.unwrap();
+
+        assert_eq!(exif.camera.as_deref(), Some("Canon EOS R6"));
+        assert!((latitude - 48.8584).abs() < 1e-9);
+        assert!((longitude + 2.2945).abs() < 1e-9);
+        assert_eq!(exif.caption.as_deref(), Some("Eh?"));
+        assert_eq!(exif.taken, None);
+    }
+}
@@ -1,16 +1,85 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
-use crate::config::Config;
-use crate::handler::{CopyHandler, FileContext, FileHandler, OrgHandler};
+use crate::cache::BuildCache;
+use crate::config::{Config, NotifyConfig, WebSubConfig};
+use crate::handler::{
+    AsciiDocHandler, CommandHandler, CopyHandler, CsvHandler, FileContext, FileHandler,
+    HtmlHandler, IpynbHandler, OrgHandler, RstHandler,
+};
+use crate::logging::Warnings;
+use crate::manifest::Manifest;
+use crate::plugin::{load_plugins, WasmHandler};
 use crate::metadata::Metadata;
 use crate::template::Templates;
+use anyhow::Context;
+use sitemap_rs::image::Image;
 use sitemap_rs::url::Url;
 use sitemap_rs::url_set::UrlSet;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ffi::OsStr;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Where WASM plugins are loaded from, relative to the source directory.
+const PLUGIN_DIR: &str = "plugins";
+
+/// RFC 5005's namespace for marking archive feeds.
+const FEED_HISTORY_NAMESPACE: &str = "http://purl.org/syndication/history/1.0";
+
+/// Gitignore-style patterns of source files not to build, for its directory.
+const IGNORE_FILE: &str = ".impertioignore";
+
+/// Walks the source directory `dir`, skipping what `ignore`, `.impertioignore` files, and if
+/// `gitignore` is set, `.gitignore` files ignore, along with the `output` directory if it's
+/// inside `dir`, as `dir` joined with its relative path.
+pub(crate) fn walk(
+    dir: &str,
+    ignore: &[String],
+    gitignore: bool,
+    output: Option<PathBuf>,
+) -> anyhow::Result<ignore::Walk> {
+    let mut patterns = ignore::gitignore::GitignoreBuilder::new(dir);
+
+    for pattern in ignore {
+        patterns.add_line(None, pattern)?;
+    }
+
+    let patterns = patterns.build()?;
+
+    Ok(ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .git_ignore(gitignore)
+        .require_git(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .filter_entry(move |entry| {
+            output.as_deref() != Some(entry.path())
+                && !patterns
+                    .matched(entry.path(), entry.file_type().is_some_and(|kind| kind.is_dir()))
+                    .is_ignore()
+        })
+        .build())
+}
+
+/// Orders globs by precedence: longer ones, which are usually more specific, first, and those
+/// of the same length alphabetically, so which one wins doesn't depend on the order they were
+/// configured in.
+pub(crate) fn glob_precedence(a: &str, b: &str) -> std::cmp::Ordering {
+    b.len().cmp(&a.len()).then_with(|| a.cmp(b))
+}
+
+/// Built-in handler names and the extension each is registered for unless disabled.
+const BUILTIN_HANDLERS: [(&str, &str); 7] = [
+    ("org", "org"),
+    ("asciidoc", "adoc"),
+    ("rst", "rst"),
+    ("csv", "csv"),
+    ("ipynb", "ipynb"),
+    ("html", "html"),
+    ("copy", "_default"),
+];
 
 fn path_to_rel_path(root: PathBuf, path: PathBuf) -> PathBuf {
     match path.strip_prefix(root) {
@@ -22,7 +91,7 @@ fn path_to_rel_path(root: PathBuf, path: PathBuf) -> PathBuf {
     }
 }
 
-fn filter_file(file: &PathBuf) -> bool {
+fn filter_file(file: &Path) -> bool {
     let filename = file.file_name().unwrap().to_str().unwrap();
 
     let is_backup = filename.ends_with("~");
@@ -31,6 +100,7 @@ fn filter_file(file: &PathBuf) -> bool {
     file.is_file()
         && !is_buffer
         && !is_backup
+        && filename != IGNORE_FILE
         && !file
             .components()
             .any(|s| AsRef::<OsStr>::as_ref(&s).to_str() == Some(".git"))
@@ -39,44 +109,258 @@ fn filter_file(file: &PathBuf) -> bool {
 pub struct FileDispatcher {
     pub templates: Templates,
     handlers: HashMap<String, Box<dyn FileHandler>>,
-    config: Config,
+    /// The globs handlers are registered for, longest first.
+    patterns: Vec<(globset::GlobMatcher, String)>,
+    /// Plugins expanding Org macros, by macro name.
+    macros: Arc<HashMap<String, WasmHandler>>,
+    config: Arc<Config>,
 }
 
 impl FileDispatcher {
     pub fn new(data_dir: &str, config: Config) -> Self {
         let mut a = Self {
-            templates: Templates::new(Path::new(data_dir)),
+            templates: Templates::new(Path::new(data_dir))
+                .with_profile(&config.profile, config.minify())
+                .with_fallback(
+                    config
+                        .fallback_template
+                        .as_ref()
+                        .map(|template| Path::new(data_dir).join(template)),
+                )
+                .with_limits(config.template_limits.clone().unwrap_or_default())
+                .with_versions(
+                    &config.site_url,
+                    config.versioned_docs.clone().unwrap_or_default(),
+                )
+                .with_license(config.license.clone()),
             handlers: HashMap::new(),
-            config,
+            patterns: vec![],
+            macros: Default::default(),
+            config: Arc::new(config),
         };
 
-        a.register_handlers();
+        a.register_handlers(Path::new(data_dir));
 
         a
     }
 
-    fn register_handlers(&mut self) {
-        self.register_handler::<OrgHandler>("org");
-        self.register_handler::<CopyHandler>("_default");
+    fn register_handlers(&mut self, data_dir: &Path) {
+        let handler_config = self.config.handlers.clone().unwrap_or_default();
+        let disabled = handler_config.disabled.unwrap_or_default();
+
+        for (name, extension) in BUILTIN_HANDLERS {
+            if !disabled.iter().any(|disabled| disabled == name) {
+                self.register_named_handler(name, extension);
+            }
+        }
+
+        for extension in handler_config.commands.unwrap_or_default().keys() {
+            self.register_handler(extension, CommandHandler::default());
+        }
+
+        let declared: Vec<PathBuf> = self
+            .config
+            .plugins
+            .iter()
+            .flatten()
+            .map(|plugin| data_dir.join(plugin))
+            .collect();
+        let mut macros = HashMap::new();
+
+        for (plugin, extensions) in load_plugins(&data_dir.join(PLUGIN_DIR), &declared) {
+            for name in plugin.macros() {
+                macros.insert(name.clone(), plugin.clone());
+            }
+
+            for extension in extensions {
+                self.register_handler(&extension, plugin.clone());
+            }
+        }
+
+        self.macros = Arc::new(macros);
+
+        for (extension, name) in handler_config.extensions.unwrap_or_default() {
+            if !self.register_named_handler(&name, &extension) {
+                log::warn!("Unknown handler `{}` configured for `{}` files.", name, extension);
+            }
+        }
+    }
+
+    /// Registers a built-in handler by name, returning `false` if there is no such handler.
+    fn register_named_handler(&mut self, name: &str, extension: &str) -> bool {
+        match name {
+            "org" => self.register_handler(extension, OrgHandler::default()),
+            "asciidoc" => self.register_handler(extension, AsciiDocHandler::default()),
+            "rst" => self.register_handler(extension, RstHandler::default()),
+            "csv" => self.register_handler(extension, CsvHandler::default()),
+            "ipynb" => self.register_handler(extension, IpynbHandler::default()),
+            "html" => self.register_handler(extension, HtmlHandler::default()),
+            "copy" => self.register_handler(extension, CopyHandler::default()),
+            _ => return false,
+        }
+
+        true
     }
 
-    fn register_handler<H: FileHandler + 'static>(&mut self, extension: &str) {
+    /// Registers `handler` for files with `extension` (without the dot), for those without a
+    /// handler with `_default`, or for a glob of paths relative to the source directory, like
+    /// `docs/**/*.md`. Globs take precedence over extensions, and longer globs over shorter
+    /// ones, then those first alphabetically. Replaces the handler registered for it before, if
+    /// any.
+    pub fn register_handler(&mut self, extension: &str, handler: impl FileHandler + 'static) {
         self.handlers
-            .insert(extension.to_owned(), Box::new(H::new()));
+            .insert(extension.to_owned(), Box::new(handler));
+        self.patterns.retain(|(_, pattern)| pattern != extension);
+
+        if !extension.contains(['*', '?', '[', '{', '/']) {
+            return;
+        }
+
+        match globset::Glob::new(extension) {
+            Ok(glob) => {
+                self.patterns
+                    .push((glob.compile_matcher(), extension.to_owned()));
+                self.patterns
+                    .sort_by(|(_, a), (_, b)| glob_precedence(a, b));
+            }
+            Err(err) => log::warn!("Invalid handler glob `{}`: {}", extension, err),
+        }
     }
 
+    /// Runs `f` with the handler for the file, or returns `None` if no handler (not even `_default`) applies.
     fn handle<T, F: FnOnce(&mut Box<dyn FileHandler>, &FileContext) -> anyhow::Result<T>>(
         &mut self,
         ctx: &FileContext,
         f: F,
-    ) -> anyhow::Result<T> {
-        let mut handler = self
+    ) -> anyhow::Result<Option<T>> {
+        let Some(mut handler) = self
             .handlers
-            .get_mut(&ctx.ext)
+            .get(ctx.handler_key())
+            .or_else(|| self.handlers.get("_default"))
             .cloned()
-            .unwrap_or_else(|| self.handlers.get_mut("_default").unwrap().clone());
+        else {
+            log::debug!("No handler for {:?}, skipping.", ctx.relative_path);
+            return Ok(None);
+        };
+
+        f(&mut handler, ctx).map(Some)
+    }
+
+    /// An `index.html` of the images in each configured gallery without an `index.org`.
+    fn write_galleries(
+        &self,
+        root_path: &Path,
+        data_path: &Path,
+        metadata: &[Metadata],
+    ) -> anyhow::Result<()> {
+        for dir in self.config.galleries.iter().flatten() {
+            let dir = dir.trim_matches('/');
+
+            if root_path.join(dir).join("index.org").exists() {
+                continue;
+            }
+
+            let index_path = data_path.join(dir).join("index.html");
+            let photos = crate::gallery::photos(
+                metadata,
+                &format!("{}/{}", self.config.site_url, dir),
+            );
+
+            log::info!(phase = "gallery"; "Generating `{}`", index_path.display());
+
+            let out = self.templates.render(
+                "root.html",
+                &root_path.join(dir).join("index.html"),
+                &crate::gallery::grid(&photos),
+                Some(HashMap::from([(
+                    "title",
+                    Path::new(dir)
+                        .file_name()
+                        .map_or("Gallery".into(), |name| name.to_string_lossy().into_owned()),
+                )])),
+            )?;
+
+            crate::handler::writeable(&index_path)?.write_all(out.as_bytes())?;
+        }
+
+        Ok(())
+    }
 
-        f(&mut handler, ctx)
+    fn write_taxonomies(
+        &self,
+        root_path: &Path,
+        data_path: &Path,
+        metadata: &[Metadata],
+    ) -> anyhow::Result<()> {
+        for taxonomy in self.config.taxonomies.iter().flatten() {
+            let terms = crate::taxonomy::terms(
+                taxonomy,
+                metadata,
+                &self.config.site_url,
+                self.config.language(),
+            );
+
+            for term in &terms {
+                for (page, articles) in
+                    crate::taxonomy::pages(taxonomy, term, &self.config.site_url)
+                {
+                    let dir = crate::taxonomy::page_dir(taxonomy, term, page.page);
+                    let index_path = data_path.join(&dir).join("index.html");
+
+                    log::info!(phase = "taxonomy"; "Generating `{}`", index_path.display());
+
+                    let mut values = tera::Context::new();
+
+                    values.insert("taxonomy", &page);
+
+                    let out = self.templates.render_with(
+                        "root.html",
+                        &root_path.join(&dir).join("index.html"),
+                        &crate::taxonomy::content(&page, articles),
+                        Some(HashMap::from([("title", term.name.clone())])),
+                        values,
+                    )?;
+
+                    crate::handler::writeable(&index_path)?.write_all(out.as_bytes())?;
+                }
+
+                if taxonomy.feeds.unwrap_or(false) {
+                    let feed_path = data_path
+                        .join(&taxonomy.name)
+                        .join(&term.slug)
+                        .join("feed.xml");
+
+                    log::info!(phase = "rss"; "Generating `{}` (RSS)", feed_path.display());
+
+                    crate::taxonomy::channel(
+                        taxonomy,
+                        term,
+                        &self.config.site_url,
+                        self.config.language().map(str::to_owned),
+                    )
+                    .pretty_write_to(crate::handler::writeable(&feed_path)?, b'\t', 1)?;
+                }
+            }
+
+            if root_path.join(&taxonomy.name).join("index.org").exists() {
+                continue;
+            }
+
+            let index_path = data_path.join(&taxonomy.name).join("index.html");
+
+            log::info!(phase = "taxonomy"; "Generating `{}`", index_path.display());
+
+            let out = self.templates.render(
+                "root.html",
+                &root_path.join(&taxonomy.name).join("index.html"),
+                &crate::taxonomy::index(taxonomy, &terms, &self.config.site_url),
+                Some(HashMap::from([("title", taxonomy.name.clone())])),
+            )?;
+
+            crate::handler::writeable(&index_path)?.write_all(out.as_bytes())?;
+        }
+
+        Ok(())
     }
 
     fn create_context(
@@ -84,67 +368,483 @@ impl FileDispatcher {
         data_dir: PathBuf,
         root: PathBuf,
         rel_file: PathBuf,
-        metadata: Arc<Mutex<Vec<Metadata>>>
+        metadata: Arc<Mutex<Vec<Metadata>>>,
+        warnings: Arc<Mutex<Warnings>>,
     ) -> FileContext {
         let file: PathBuf = PathBuf::from_iter(vec![root.clone(), rel_file.clone()]);
         let new_file: PathBuf = PathBuf::from_iter(vec![data_dir, rel_file.clone()]);
 
-        FileContext::new(&self.config, &rel_file, &file, &new_file, &self.templates, metadata)
+        let mut ctx = FileContext::new(
+            &self.config,
+            &rel_file,
+            &file,
+            &new_file,
+            &self.templates,
+            metadata,
+            warnings,
+        );
+
+        let sniff = self
+            .config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.sniff)
+            .unwrap_or(false);
+
+        ctx.macros = self.macros.clone();
+        ctx.pattern = self
+            .patterns
+            .iter()
+            .find(|(glob, _)| glob.is_match(&rel_file))
+            .map(|(_, pattern)| pattern.clone());
+
+        if sniff && ctx.pattern.is_none() && ctx.ext.is_empty() {
+            if let Some(ext) = crate::sniff::sniff(&file) {
+                log::debug!("Detected {:?} as `{}` from its contents.", rel_file, ext);
+                ctx.ext = ext;
+            }
+        }
+
+        ctx
     }
 
-    pub fn handle_files(&mut self, data_dir: String, dir: String) -> anyhow::Result<()> {
-        let root_path = Path::new(&dir).canonicalize().unwrap();
-        let data_path = Path::new(&data_dir).canonicalize().unwrap();
-        let metadata_vec: Arc<Mutex<Vec<Metadata>>> = Arc::new(Mutex::new(vec![]));
+    /// Tells the configured services about `changed` URLs. Failures are only warned about,
+    /// since the build itself succeeded.
+    fn notify(
+        &self,
+        notify: &NotifyConfig,
+        changed: &[String],
+        data_path: &Path,
+    ) -> anyhow::Result<()> {
+        if let Some(index_now) = &notify.index_now {
+            std::fs::write(
+                data_path.join(format!("{}.txt", index_now.key)),
+                &index_now.key,
+            )?;
+        }
+
+        if changed.is_empty() {
+            log::info!(phase = "notify"; "No pages changed, not notifying anyone");
+            return Ok(());
+        }
+
+        if let Some(index_now) = &notify.index_now {
+            let endpoint = index_now
+                .endpoint
+                .as_deref()
+                .unwrap_or(crate::notify::INDEX_NOW_ENDPOINT);
+
+            if let Err(err) =
+                crate::notify::index_now(endpoint, &self.config.site_url, &index_now.key, changed)
+            {
+                log::warn!("Failed to submit URLs to IndexNow: {}", err);
+            }
+        }
+
+        for url in notify.ping.iter().flatten() {
+            if let Err(err) = crate::notify::ping(url, &self.config.site_url) {
+                log::warn!("Failed to ping `{}`: {}", url, err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The source files in `dir` to build into `data_path`, with a context each. Each file has
+    /// its own warnings, holding those extracting its metadata found.
+    fn discover(
+        &mut self,
+        dir: &str,
+        root_path: &Path,
+        data_path: &Path,
+        metadata_vec: Arc<Mutex<Vec<Metadata>>>,
+    ) -> anyhow::Result<Vec<FileContext>> {
+        self.templates = self
+            .templates
+            .clone()
+            .with_data(crate::data::load(&root_path.join(crate::data::DATA_DIR))?);
+
+        let diagram_cache = self
+            .config
+            .diagrams
+            .as_ref()
+            .map(|diagrams| root_path.join(diagrams.cache()));
+
+        // A destination inside the source would be built into itself on the next run.
+        let output = data_path
+            .strip_prefix(root_path)
+            .ok()
+            .filter(|output| !output.as_os_str().is_empty())
+            .map(|output| Path::new(dir).join(output));
 
-        let files: Vec<FileContext> = walkdir::WalkDir::new(dir.clone())
-            .into_iter()
-            .map(|file| file.as_ref().unwrap().path().canonicalize().unwrap())
-            .filter(filter_file)
+        let phase_start = Instant::now();
+        let files: Vec<FileContext> = walk(
+            dir,
+            self.config.ignore.as_deref().unwrap_or_default(),
+            self.config.gitignore.unwrap_or(false),
+            output,
+        )?
+        .filter_map(|file| match file {
+            Ok(file) => Some(file.path().canonicalize().unwrap()),
+            Err(err) => {
+                log::warn!("{}", err);
+                None
+            }
+        })
+            .filter(|file| {
+                filter_file(file)
+                    && !file.starts_with(root_path.join(PLUGIN_DIR))
+                    && !crate::data::is_loaded(root_path, file)
+                    && !file.starts_with(root_path.join(crate::archetype::ARCHETYPE_DIR))
+                    && !file.starts_with(root_path.join(crate::golden::TEMPLATE_TESTS_DIR))
+                    && !diagram_cache
+                        .as_ref()
+                        .is_some_and(|cache| file.starts_with(cache))
+            })
             .map(|file| {
                 self.create_context(
-                    data_path.clone(),
-                    root_path.clone(),
-                    path_to_rel_path(root_path.clone(), file.clone()),
-                    metadata_vec.clone()
+                    data_path.to_owned(),
+                    root_path.to_owned(),
+                    path_to_rel_path(root_path.to_owned(), file.clone()),
+                    metadata_vec.clone(),
+                    Default::default(),
                 )
             })
             .collect();
 
+        log::info!(
+            phase = "discover",
+            duration_ms = phase_start.elapsed().as_millis() as u64;
+            "Found {} files",
+            files.len()
+        );
+
+        let phase_start = Instant::now();
         let metadata: Vec<Metadata> = files
             .iter()
             .map(|ctx| self.handle(ctx, |handler, ctx| handler.extract_metadata(ctx.clone())))
-            .filter_map(|res| res.ok())
+            .filter_map(|res| res.ok().flatten())
             .collect();
 
-        metadata_vec.lock().unwrap().extend(metadata.clone());
+        metadata_vec.lock().unwrap().extend(metadata);
+
+        log::info!(
+            phase = "metadata",
+            duration_ms = phase_start.elapsed().as_millis() as u64;
+            "Extracted metadata for {} files",
+            metadata_vec.lock().unwrap().len()
+        );
+
+        Ok(files)
+    }
+
+    /// The template context the page built from `file`, in the source directory `dir`, is
+    /// rendered with, as JSON. Nothing is written to the destination.
+    pub fn context(&mut self, dir: String, file: &Path) -> anyhow::Result<serde_json::Value> {
+        let root_path = Path::new(&dir).canonicalize()?;
+        let file = file.canonicalize()?;
+        // Handlers write what they render, so they're given somewhere to throw it away.
+        let scratch =
+            std::env::temp_dir().join(format!("impertio-context-{}", std::process::id()));
+
+        std::fs::create_dir_all(&scratch)?;
+
+        let data_path = scratch.canonicalize()?;
+        let metadata_vec: Arc<Mutex<Vec<Metadata>>> = Arc::new(Mutex::new(vec![]));
+        let files = self.discover(&dir, &root_path, &data_path, metadata_vec)?;
+
+        let Some(mut ctx) = files.into_iter().find(|ctx| ctx.source_path == file) else {
+            std::fs::remove_dir_all(&scratch)?;
+            anyhow::bail!("{:?} isn't built as part of {:?}", file, dir);
+        };
+
+        ctx.stale = Some(true);
+        ctx.templates = self.templates.clone().with_capture();
+
+        let handled = self.handle(&ctx, |handler, ctx| handler.handle_file(ctx.clone()));
+
+        std::fs::remove_dir_all(&scratch)?;
+        handled?;
+
+        ctx.templates.captured(&file).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No page is rendered from {:?}, e.g. because it's copied as is or a draft",
+                file
+            )
+        })
+    }
+
+    /// Problems with the source directory `dir` and its config, without building it: Org files
+    /// that don't parse or warn as they're rendered, lint findings and broken links, by file and
+    /// line. Nothing is written.
+    pub fn check(&mut self, dir: String) -> anyhow::Result<Warnings> {
+        let root_path = Path::new(&dir).canonicalize()?;
+        // Only output paths are derived from it; nothing is written there.
+        let data_path = std::env::temp_dir().join(format!("impertio-check-{}", std::process::id()));
+        let metadata_vec: Arc<Mutex<Vec<Metadata>>> = Arc::new(Mutex::new(vec![]));
+        let mut problems = Warnings::default();
+
+        for problem in self.config.problems(&root_path) {
+            problems.add("Configuration problems", problem);
+        }
+
+        let files = self.discover(&dir, &root_path, &data_path, metadata_vec)?;
+        let warnings: Arc<Mutex<Warnings>> = Arc::new(Mutex::new(problems));
+
+        for ctx in files.iter().filter(|ctx| ctx.ext == "org") {
+            // Parsed by its path, so includes and images resolve like in a build, but reported
+            // relative to the source directory.
+            let file_warnings: Arc<Mutex<Warnings>> = Default::default();
+            let ctx = FileContext {
+                warnings: file_warnings.clone(),
+                ..ctx.clone()
+            };
+            let relative = ctx.relative_path.display().to_string();
+            let path = ctx.source_path.to_string_lossy().into_owned();
+            let source = std::fs::read_to_string(&ctx.source_path)?;
+            let anchors = crate::org::Document::parse(&source, &path, ctx.clone()).map(|doc| {
+                // Some problems only come up as the page is rendered.
+                doc.to_html(&self.config);
+                doc.anchors()
+            });
+
+            for (category, details) in file_warnings.lock().unwrap().iter() {
+                for detail in details {
+                    warnings
+                        .lock()
+                        .unwrap()
+                        .add(category, detail.replace(&path, &relative));
+                }
+            }
+
+            let anchors = match anchors {
+                Ok(anchors) => anchors,
+                Err(err) => {
+                    warnings.lock().unwrap().add(
+                        "Org files not parsed",
+                        format!("{}: {}", relative, err.replace(&path, &relative)),
+                    );
+                    continue;
+                }
+            };
+
+            for err in crate::lint::lint(
+                self.config.lint.as_deref().unwrap_or_default(),
+                &ctx.relative_path,
+                &source,
+            ) {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Lint checkers failed", format!("{}: {}", relative, err));
+            }
+
+            for (target, line) in crate::org::broken_links(
+                &source,
+                &anchors,
+                ctx.source_path.parent().unwrap_or(&root_path),
+                &root_path,
+            ) {
+                warnings.lock().unwrap().add(
+                    "Broken links",
+                    format!("{:?} at {}:{}", target, relative, line),
+                );
+            }
+        }
+
+        let problems = std::mem::take(&mut *warnings.lock().unwrap());
+
+        Ok(problems)
+    }
+
+    /// Builds the site in `dir` into `data_dir`. Returns the build's warnings, once they're
+    /// summarized.
+    pub fn handle_files(&mut self, data_dir: String, dir: String) -> anyhow::Result<Warnings> {
+        let root_path = Path::new(&dir).canonicalize()?;
+        std::fs::create_dir_all(&data_dir)?;
+        let data_path = Path::new(&data_dir).canonicalize()?;
+        let metadata_vec: Arc<Mutex<Vec<Metadata>>> = Arc::new(Mutex::new(vec![]));
+        let warnings: Arc<Mutex<Warnings>> = Arc::new(Mutex::new(Warnings::default()));
+        let mut files = self.discover(&dir, &root_path, &data_path, metadata_vec.clone())?;
+        let metadata: Vec<Metadata> = metadata_vec.lock().unwrap().clone();
+
+        let previous_cache = BuildCache::load(&data_path);
+        let mut cache = BuildCache::default();
+        let site_hash =
+            crate::cache::site_hash(&self.config, &root_path, &root_path.join(PLUGIN_DIR));
+        let pages_hash = crate::cache::metadata_hash(&metadata);
+
+        let versioned_hash = format!("{}{}", site_hash, pages_hash);
+
+        for ctx in files.iter_mut() {
+            // Versioned pages link to their counterparts in the other versions.
+            let versioned = self.config.versioned_docs.iter().flatten().any(|docs| {
+                ctx.relative_path
+                    .starts_with(docs.dir.trim_matches('/'))
+            });
+            let hash = crate::cache::inputs_hash(
+                &ctx.source_path,
+                &ctx.ext,
+                &self.templates.sources(&ctx.source_path),
+                &self.config,
+                if versioned { &versioned_hash } else { &site_hash },
+                &pages_hash,
+            );
+
+            ctx.stale = match &hash {
+                Some(hash) => previous_cache.stale(&ctx.relative_path, hash),
+                None => Some(true),
+            };
+
+            if let Some(hash) = hash {
+                cache
+                    .inputs
+                    .insert(ctx.relative_path.to_string_lossy().into_owned(), hash);
+            }
+        }
 
         let urls: Vec<Url> = metadata
             .iter()
             .filter_map(|meta| match meta {
+                Metadata::Article { robots, .. } if robots.noindex => None,
                 Metadata::Article { modified, url, .. } => {
                     let mut builder = Url::builder(url.to_string());
                     builder.last_modified((*modified).into());
                     builder.build().ok()
                 }
-                _ => None,
+                // Gallery images' own pages, with the images themselves.
+                Metadata::Image { url, .. } => {
+                    let relative = url.strip_prefix(&format!("{}/", self.config.site_url))?;
+                    let dir = relative.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+                    crate::gallery::is_gallery(&self.config, &root_path, Path::new(dir))
+                        .then(|| {
+                            Url::builder(format!("{}.html", url))
+                                .images(vec![Image::new(url.clone())])
+                                .build()
+                                .ok()
+                        })
+                        .flatten()
+                }
             })
             .collect();
 
-        files.iter().for_each(|ctx| {
+        let previous = Manifest::load(&data_path);
+        let mut manifest = Manifest::from_metadata(&metadata);
+        let phase_start = Instant::now();
+
+        for ctx in &files {
+            let file_start = Instant::now();
+            let extracted = std::mem::take(&mut *ctx.warnings.lock().unwrap());
+
             self.handle(ctx, |handler, ctx| handler.handle_file(ctx.clone()))
+                .with_context(|| format!("Handling `{}`", ctx.relative_path.display()))?;
+
+            let source = ctx.relative_path.to_string_lossy().into_owned();
+            let mut written: BTreeSet<String> = ctx
+                .outputs
+                .lock()
                 .unwrap()
-        });
+                .iter()
+                .filter_map(|output| output.strip_prefix(&data_path).ok())
+                .map(|output| output.to_string_lossy().replace('\\', "/"))
+                .collect();
 
-        if urls.len() > 0 {
+            // A file that was rendered was parsed again, finding the same problems as extracting
+            // its metadata did. Those of one that's up to date are still worth repeating.
+            let found = std::mem::take(&mut *ctx.warnings.lock().unwrap());
+
+            warnings
+                .lock()
+                .unwrap()
+                .merge(if written.is_empty() { extracted } else { found });
+
+            // What the last build wrote for the file and this one didn't rewrite, because it
+            // was up to date, is still the file's.
+            written.extend(
+                previous
+                    .outputs
+                    .get(&source)
+                    .into_iter()
+                    .flatten()
+                    .filter(|output| data_path.join(output).is_file())
+                    .cloned(),
+            );
+
+            if !written.is_empty() {
+                manifest.outputs.insert(source, written);
+            }
+
+            log::debug!(
+                phase = "render",
+                file:% = ctx.relative_path.display(),
+                duration_ms = file_start.elapsed().as_millis() as u64;
+                "Handled {:?}",
+                ctx.relative_path
+            );
+        }
+
+        log::info!(
+            phase = "render",
+            duration_ms = phase_start.elapsed().as_millis() as u64;
+            "Handled {} files",
+            files.len()
+        );
+
+        cache.save(&data_path)?;
+
+        let orphans = manifest.orphaned_outputs(&previous);
+
+        if self.config.clean_orphans.unwrap_or(false) {
+            let removed = crate::manifest::remove_outputs(&data_path, &orphans)?;
+
+            log::info!(phase = "clean"; "Removed {} files whose sources are gone", removed);
+        } else {
+            let left: Vec<String> = orphans
+                .into_iter()
+                .filter(|orphan| data_path.join(orphan).is_file())
+                .collect();
+
+            for orphan in &left {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Files left from sources that are gone (see `--clean-orphans`)", orphan);
+            }
+
+            manifest.keep_outputs(&previous, &left);
+        }
+
+        self.write_galleries(&root_path, &data_path, &metadata)?;
+        self.write_taxonomies(&root_path, &data_path, &metadata)?;
+
+        if let Some(globs) = &self.config.absolute_urls {
+            let rewritten =
+                crate::absolute::rewrite_outputs(&data_path, &self.config.site_url, globs)?;
+
+            log::info!(phase = "absolute"; "Made links absolute in {} files", rewritten);
+        }
+
+        if !urls.is_empty() {
             let sitemap_path = format!("{}/sitemap.xml", data_path.clone().display());
-            log::info!("Generating `{}`", sitemap_path);
+            log::info!(phase = "sitemap"; "Generating `{}`", sitemap_path);
             let sitemap_file =
                 std::fs::File::create(sitemap_path).expect("Unable to write sitemap.xml");
             let url_set = UrlSet::new(urls.clone()).expect("failed a <urlset> validation");
             url_set.write(sitemap_file)?;
         }
 
+        if let Some(versioned_docs) = &self.config.versioned_docs {
+            crate::versions::write_sitemaps(
+                versioned_docs,
+                &self.config.site_url,
+                &root_path,
+                &data_path,
+                &urls,
+            )?;
+            crate::versions::alias_latest(versioned_docs, &root_path, &data_path)?;
+        }
+
         if let Some(rss_config) = self.config.rss.clone() {
             let rss_builder = rss::Channel {
                 title: rss_config.title,
@@ -158,7 +858,7 @@ impl FileDispatcher {
                 last_build_date: None,
                 categories: rss_config
                     .categories
-                    .unwrap_or_else(|| vec![])
+                    .unwrap_or_default()
                     .iter()
                     .map(|category| rss::Category {
                         name: category.name.clone(),
@@ -188,62 +888,954 @@ impl FileDispatcher {
                     name: ti.name,
                     link: ti.link,
                 }),
-                skip_hours: rss_config.skip_hours.unwrap_or_else(|| vec![]),
-                skip_days: rss_config.skip_days.unwrap_or_else(|| vec![]),
+                skip_hours: rss_config.skip_hours.unwrap_or_default(),
+                skip_days: rss_config.skip_days.unwrap_or_default(),
                 extensions: Default::default(),
                 itunes_ext: None,
                 dublin_core_ext: None,
                 syndication_ext: None,
                 namespaces: Default::default(),
-                items: metadata
-                    .iter()
-                    .filter_map(|meta| match meta {
-                        Metadata::Article {
-                            title,
-                            description,
-                            modified,
-                            url,
-                            author,
-                            tags,
-                        } => Some(rss::Item {
-                            title: Some(title.to_string()),
-                            link: Some(url.to_string()),
-                            guid: Some(rss::Guid {
-                                value: url.to_string(),
-                                permalink: true,
-                            }),
-                            description: description.to_owned(),
-                            author: author.to_owned(),
-                            categories: tags
-                                .to_owned()
-                                .iter()
-                                .map(|tag| rss::Category {
-                                    name: tag.to_string(),
-                                    domain: None,
-                                })
-                                .collect(),
-                            comments: None,
-                            enclosure: None,
-                            pub_date: Some(modified.to_rfc2822()),
-                            source: None,
-                            content: None,
-                            extensions: Default::default(),
-                            itunes_ext: None,
-                            dublin_core_ext: None,
+                items: vec![],
+                atom_ext: None,
+            };
+
+            let mut articles: Vec<&Metadata> = metadata
+                .iter()
+                .filter(|meta| matches!(meta, Metadata::Article { .. }))
+                .collect();
+            articles.sort_by_key(|meta| match meta {
+                Metadata::Article { modified, .. } => std::cmp::Reverse(*modified),
+                _ => unreachable!(),
+            });
+
+            let items: Vec<rss::Item> = articles
+                .iter()
+                .filter_map(|meta| match meta {
+                    Metadata::Article {
+                        title,
+                        description,
+                        modified,
+                        published,
+                        url,
+                        author,
+                        tags,
+                        content,
+                        license,
+                        ..
+                    } => Some(rss::Item {
+                        title: Some(title.to_string()),
+                        link: Some(url.to_string()),
+                        guid: Some(rss::Guid {
+                            value: url.to_string(),
+                            permalink: true,
                         }),
-                        _ => None,
-                    })
-                    .collect(),
+                        description: description.to_owned(),
+                        author: author.to_owned(),
+                        categories: tags
+                            .to_owned()
+                            .iter()
+                            .map(|tag| rss::Category {
+                                name: tag.to_string(),
+                                domain: None,
+                            })
+                            .collect(),
+                        comments: None,
+                        enclosure: None,
+                        pub_date: Some(published.unwrap_or(*modified).to_rfc2822()),
+                        source: None,
+                        content: content.as_ref().map(|content| {
+                            crate::absolute::absolutize(
+                                content,
+                                &self.config.site_url,
+                                url.strip_prefix(&format!("{}/", self.config.site_url))
+                                    .map_or(Path::new(""), Path::new),
+                            )
+                        }),
+                        extensions: crate::license::extension(license.as_deref()),
+                        atom_ext: None,
+                        itunes_ext: None,
+                        dublin_core_ext: None,
+                    }),
+                    _ => None,
+                })
+                .collect();
+
+            let websub = rss_config.websub.clone();
+
+            for page in paginate_feed(items, rss_config.page_size) {
+                let mut channel = rss_builder.clone();
+                let rss_path = format!("{}/{}", data_path.clone().display(), page.name);
+                log::info!(phase = "rss"; "Generating `{}` (RSS)", rss_path);
+
+                let links = feed_links(&page, &self.config.site_url, websub.as_ref());
+
+                channel.items = page.items;
+
+                if !links.is_empty() {
+                    channel.atom_ext = Some(rss::extension::atom::AtomExtension { links });
+                }
+
+                if page.archive {
+                    channel
+                        .namespaces
+                        .insert("fh".into(), FEED_HISTORY_NAMESPACE.into());
+                    channel.extensions.insert(
+                        "fh".into(),
+                        BTreeMap::from_iter([(
+                            "archive".into(),
+                            vec![rss::extension::Extension {
+                                name: "fh:archive".into(),
+                                ..Default::default()
+                            }],
+                        )]),
+                    );
+                }
+
+                channel
+                    .extensions
+                    .extend(crate::license::extension(self.config.license.as_deref()));
+                crate::license::add_namespace(&mut channel);
+
+                let rss_file = std::fs::File::create(rss_path).expect("Unable to write RSS feed");
+
+                channel.pretty_write_to(rss_file, b'\t', 1)?;
+            }
+
+            if let Some(websub) = websub.filter(|websub| websub.ping.unwrap_or(false)) {
+                let topic = format!("{}/feed", self.config.site_url);
+
+                if let Err(err) = crate::notify::websub_publish(&websub.hub, &topic) {
+                    log::warn!("Failed to ping WebSub hub `{}`: {}", websub.hub, err);
+                }
+            }
+        }
+
+        for changelog in self.config.changelog_feeds.iter().flatten() {
+            let Some(ctx) = files
+                .iter()
+                .find(|ctx| ctx.relative_path == Path::new(&changelog.file))
+            else {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Changelog feeds of files that aren't built", &changelog.file);
+                continue;
             };
-            
-            let rss_path = format!("{}/feed", data_path.clone().display());
-            log::info!("Generating `{}` (RSS)", rss_path);
 
-            let rss_file = std::fs::File::create(rss_path).expect("Unable to write RSS feed");
+            crate::changelog::write(changelog, ctx, &data_path)?;
+        }
+
+        let changed = manifest.changed_since(&previous);
 
-            rss_builder.pretty_write_to(rss_file, b'\t', 1)?;
+        for url in manifest.track_removed(&previous) {
+            warnings.lock().unwrap().add("Published pages removed", url);
         }
 
-        Ok(())
+        manifest.save(&data_path)?;
+
+        if let Some(removed) = &self.config.removed {
+            crate::removed::write(removed, &self.config.site_url, &manifest, &data_path)?;
+        }
+
+        if let Some(well_known) = &self.config.well_known {
+            crate::well_known::write(well_known, &self.config.site_url, &data_path)?;
+        }
+
+        if let Some(checksums) = &self.config.checksums {
+            if checksums.shasums.unwrap_or(true) {
+                crate::checksums::write_shasums(&data_path)?;
+            }
+        }
+
+        if let Some(downloads) = &self.config.downloads {
+            crate::downloads::write(downloads, &root_path, &data_path, &self.templates)?;
+        }
+
+        if self.config.anchors.unwrap_or(false) {
+            crate::manifest::save_anchors(&data_path, &metadata)?;
+        }
+
+        if self.config.licenses.unwrap_or(false) {
+            let report = crate::license::Report::new(&metadata);
+
+            for (license, licensed) in &report.licenses {
+                log::info!(
+                    phase = "licenses";
+                    "{} articles under `{}`",
+                    licensed.articles.len(),
+                    license
+                );
+            }
+
+            log::info!(phase = "licenses"; "{} articles without a license", report.unlicensed.len());
+
+            report.save(&data_path)?;
+        }
+
+        if self.config.graph.unwrap_or(false) {
+            log::info!(phase = "graph"; "Collecting links between pages");
+            let graph =
+                crate::graph::Graph::from_outputs(&metadata, &self.config.site_url, &data_path);
+
+            for node in graph.unreachable(&self.config.site_url) {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Pages unreachable from the home page", node.url.clone());
+            }
+
+            graph.save(&data_path)?;
+        }
+
+        if let Some(notify) = &self.config.notify {
+            self.notify(notify, &changed, &data_path)?;
+        }
+
+        if let Some(format) = self.config.etags {
+            log::info!(phase = "etags"; "Hashing built files");
+            crate::manifest::save_etags(&data_path, format)?;
+        }
+
+        if let Some(budgets) = &self.config.size_budgets {
+            for file in crate::budget::over_budget(&data_path, budgets)? {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Files over their size budget", file);
+            }
+        }
+
+        if let Some(embeds) = &self.config.embeds {
+            for embed in crate::embeds::check(&data_path, embeds, &self.config.site_url)? {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .add("Third-party embeds not allowed", embed);
+            }
+        }
+
+        let warnings = std::mem::take(&mut *warnings.lock().unwrap());
+
+        warnings.summarize();
+
+        Ok(warnings)
+    }
+}
+
+/// One RSS document: the current `feed`, or the archive `feed-N`.
+struct FeedPage {
+    name: String,
+    items: Vec<rss::Item>,
+    /// `rel` and the name of the linked document.
+    links: Vec<(&'static str, String)>,
+    archive: bool,
+}
+
+/// The `atom:link`s of a feed document. The current feed advertises the WebSub hub, if any,
+/// along with its own URL as the topic.
+fn feed_links(
+    page: &FeedPage,
+    site_url: &str,
+    websub: Option<&WebSubConfig>,
+) -> Vec<rss::extension::atom::Link> {
+    let mut links: Vec<(&str, String)> = page
+        .links
+        .iter()
+        .map(|(rel, name)| (*rel, format!("{}/{}", site_url, name)))
+        .collect();
+
+    if let Some(websub) = websub.filter(|_| !page.archive) {
+        if !links.iter().any(|(rel, _)| *rel == "self") {
+            links.push(("self", format!("{}/{}", site_url, page.name)));
+        }
+
+        links.push(("hub", websub.hub.clone()));
+    }
+
+    links
+        .into_iter()
+        .map(|(rel, href)| rss::extension::atom::Link {
+            mime_type: (rel != "hub").then(|| "application/rss+xml".into()),
+            href,
+            rel: rel.into(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Splits newest-first `items` into the current feed and archives of `page_size` items each
+/// (RFC 5005). Archives are numbered from the oldest, so a full archive never changes.
+/// Every document links to its neighbours both as a paged feed and as an archive chain.
+fn paginate_feed(items: Vec<rss::Item>, page_size: Option<usize>) -> Vec<FeedPage> {
+    let page_size = page_size.unwrap_or(0);
+
+    if page_size == 0 || items.len() <= page_size {
+        return vec![FeedPage {
+            name: "feed".into(),
+            items,
+            links: vec![],
+            archive: false,
+        }];
+    }
+
+    let archives = (items.len() - 1) / page_size;
+    let name = |n: usize| {
+        if n == 0 {
+            "feed".to_owned()
+        } else {
+            format!("feed-{}", n)
+        }
+    };
+
+    let mut oldest_first = items;
+    oldest_first.reverse();
+
+    let mut pages: Vec<FeedPage> = oldest_first
+        .chunks(page_size)
+        .take(archives)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let n = i + 1;
+            let newer = if n == archives { 0 } else { n + 1 };
+            let mut links = vec![
+                ("self", name(n)),
+                ("current", name(0)),
+                ("first", name(0)),
+                ("last", name(1)),
+                ("previous", name(newer)),
+            ];
+
+            if n > 1 {
+                links.push(("next", name(n - 1)));
+                links.push(("prev-archive", name(n - 1)));
+            }
+
+            if n < archives {
+                links.push(("next-archive", name(n + 1)));
+            }
+
+            FeedPage {
+                name: name(n),
+                items: chunk.iter().rev().cloned().collect(),
+                links,
+                archive: true,
+            }
+        })
+        .collect();
+
+    pages.insert(
+        0,
+        FeedPage {
+            name: name(0),
+            items: oldest_first[archives * page_size..]
+                .iter()
+                .rev()
+                .cloned()
+                .collect(),
+            links: vec![
+                ("self", name(0)),
+                ("first", name(0)),
+                ("last", name(1)),
+                ("next", name(archives)),
+                ("prev-archive", name(archives)),
+            ],
+            archive: false,
+        },
+    );
+
+    pages
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::config::{CommandHandlerConfig, Config, HandlerConfig};
+    use crate::config::WebSubConfig;
+    use crate::files::{feed_links, filter_file, paginate_feed, walk, FeedPage, FileDispatcher};
+    use crate::handler::{CopyHandler, FileContext, FileHandler};
+    use crate::metadata::Metadata;
+
+    #[test]
+    fn handler_overrides() {
+        let fd = FileDispatcher::new(
+            "data",
+            Config {
+                handlers: Some(HandlerConfig {
+                    extensions: Some(HashMap::from_iter(vec![
+                        ("txt".into(), "org".into()),
+                        ("md".into(), "copy".into()),
+                        ("rst".into(), "nonexistent".into()),
+                    ])),
+                    disabled: Some(vec!["copy".into()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let mut extensions: Vec<&str> = fd.handlers.keys().map(|ext| ext.as_str()).collect();
+        extensions.sort();
+
+        assert_eq!(extensions, vec!["adoc", "csv", "html", "ipynb", "md", "org", "rst", "txt"]);
+    }
+
+    #[test]
+    fn handler_globs() {
+        let mut fd = FileDispatcher::new(
+            "data",
+            Config {
+                handlers: Some(HandlerConfig {
+                    extensions: Some(HashMap::from_iter(vec![
+                        ("notes/*.txt".into(), "copy".into()),
+                        ("notes/**/*.txt".into(), "org".into()),
+                    ])),
+                    commands: Some(HashMap::from_iter(vec![(
+                        "docs/*.md".into(),
+                        CommandHandlerConfig {
+                            command: "pandoc".into(),
+                            ..Default::default()
+                        },
+                    )])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let mut pattern = |path: &str| {
+            fd.create_context(
+                "out".into(),
+                "src".into(),
+                path.into(),
+                Default::default(),
+                Default::default(),
+            )
+            .pattern
+        };
+
+        assert_eq!(pattern("notes/a/b.txt").as_deref(), Some("notes/**/*.txt"));
+        assert_eq!(pattern("notes/b.txt").as_deref(), Some("notes/**/*.txt"));
+        assert_eq!(pattern("docs/a.md").as_deref(), Some("docs/*.md"));
+        assert_eq!(pattern("b.txt"), None);
+    }
+
+    #[test]
+    fn handler_glob_precedence() {
+        let winner = |globs: [&str; 2]| {
+            let mut fd = FileDispatcher::new("data", Default::default());
+
+            for glob in globs {
+                fd.register_handler(glob, CopyHandler::default());
+            }
+
+            fd.create_context(
+                "out".into(),
+                "src".into(),
+                "notes/aba.txt".into(),
+                Default::default(),
+                Default::default(),
+            )
+            .pattern
+        };
+
+        assert_eq!(
+            winner(["notes/a*.txt", "notes/*a.txt"]).as_deref(),
+            Some("notes/*a.txt")
+        );
+        assert_eq!(
+            winner(["notes/*a.txt", "notes/a*.txt"]).as_deref(),
+            Some("notes/*a.txt")
+        );
+    }
+
+    #[test]
+    fn ignored_files() {
+        let dir = std::env::temp_dir().join(format!("impertio-ignore-{}", std::process::id()));
+
+        for (file, contents) in [
+            ("a.org", ""),
+            ("node_modules/x.js", ""),
+            ("notes/b.draft.org", ""),
+            ("notes/b.org", ""),
+            ("sub/.impertioignore", "*.tmp\n"),
+            ("sub/c.tmp", ""),
+            ("c.tmp", ""),
+            (".gitignore", "build/\n"),
+            ("build/d.html", ""),
+        ] {
+            std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+
+        let files = |gitignore: bool| {
+            let mut files: Vec<String> = walk(
+                dir.to_str().unwrap(),
+                &["node_modules/".into(), "*.draft.org".into()],
+                gitignore,
+                None,
+            )
+            .unwrap()
+            .map(|entry| entry.unwrap().into_path())
+            .filter(|path| filter_file(path))
+            .map(|path| {
+                path.strip_prefix(&dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+
+            files.sort();
+            files
+        };
+        let (all, git) = (files(false), files(true));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            all,
+            [".gitignore", "a.org", "build/d.html", "c.tmp", "notes/b.org"]
+        );
+        assert_eq!(git, [".gitignore", "a.org", "c.tmp", "notes/b.org"]);
+    }
+
+    #[test]
+    fn custom_handler() {
+        #[derive(Clone)]
+        struct Shout {
+            suffix: String,
+        }
+
+        impl FileHandler for Shout {
+            fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+                let text = std::fs::read_to_string(&ctx.source_path)?;
+
+                std::fs::create_dir_all(ctx.output_path.parent().unwrap())?;
+                std::fs::write(&ctx.output_path, text.to_uppercase() + &self.suffix)?;
+
+                Ok(())
+            }
+
+            fn extract_metadata(&mut self, _ctx: FileContext) -> anyhow::Result<Metadata> {
+                Err(anyhow::anyhow!("No metadata."))
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("impertio-custom-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+
+        std::fs::create_dir_all(source.join("notes")).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(source.join("notes/a.txt"), "hi").unwrap();
+        std::fs::write(source.join("b.txt"), "hi").unwrap();
+
+        let mut fd = FileDispatcher::new(source.to_str().unwrap(), Default::default());
+
+        fd.register_handler(
+            "notes/*.txt",
+            Shout {
+                suffix: "!".into(),
+            },
+        );
+        fd.handle_files(
+            dest.to_str().unwrap().into(),
+            source.to_str().unwrap().into(),
+        )
+        .unwrap();
+
+        let shouted = std::fs::read_to_string(dest.join("notes/a.txt")).unwrap();
+        let copied = std::fs::read_to_string(dest.join("b.txt")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(shouted, "HI!");
+        assert_eq!(copied, "hi");
+    }
+
+    #[test]
+    fn output_inside_source() {
+        let dir = std::env::temp_dir().join(format!("impertio-nested-{}", std::process::id()));
+        let dest = dir.join("public");
+
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dir.join("a.txt"), "hi").unwrap();
+
+        for _ in 0..2 {
+            FileDispatcher::new(dir.to_str().unwrap(), Default::default())
+                .handle_files(dest.to_str().unwrap().into(), dir.to_str().unwrap().into())
+                .unwrap();
+        }
+
+        let copied = dest.join("a.txt").exists();
+        let nested = dest.join("public").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(copied);
+        assert!(!nested);
+    }
+
+    #[test]
+    fn latin1_html() {
+        let dir = std::env::temp_dir().join(format!("impertio-latin1-{}", std::process::id()));
+        let dest = dir.join("public");
+        let page = b"<p>Caf\xe9</p>";
+
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(dir.join("old.html"), page).unwrap();
+
+        FileDispatcher::new(dir.to_str().unwrap(), Default::default())
+            .handle_files(dest.to_str().unwrap().into(), dir.to_str().unwrap().into())
+            .unwrap();
+
+        let copied = std::fs::read(dest.join("old.html")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(copied, page);
+    }
+
+    #[test]
+    fn template_changes() {
+        let dir = std::env::temp_dir().join(format!("impertio-rebuild-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let build = || {
+            FileDispatcher::new(source.to_str().unwrap(), Default::default())
+                .handle_files(dest.to_str().unwrap().into(), source.to_str().unwrap().into())
+                .unwrap();
+
+            std::fs::read_to_string(dest.join("index.html")).unwrap()
+        };
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(source.join("index.org"), "Hi").unwrap();
+        std::fs::write(source.join("root.html"), "<main>{{ content }}</main>").unwrap();
+
+        let before = build();
+
+        std::fs::write(source.join("root.html"), "<body>{{ content }}</body>").unwrap();
+
+        let after = build();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(before.starts_with("<main>"));
+        assert!(after.starts_with("<body>"));
+    }
+
+    #[test]
+    fn page_context() {
+        let dir = std::env::temp_dir().join(format!("impertio-context-src-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.org"), "#+TITLE: Home\n\nHi").unwrap();
+        std::fs::write(dir.join("cat.txt"), "").unwrap();
+        std::fs::write(dir.join("root.html"), "{{ content }}").unwrap();
+
+        let mut fd = FileDispatcher::new(dir.to_str().unwrap(), Default::default());
+        let context = fd
+            .context(dir.to_str().unwrap().into(), &dir.join("index.org"))
+            .unwrap();
+        let copied = fd.context(dir.to_str().unwrap().into(), &dir.join("cat.txt"));
+        let written = std::fs::read_dir(&dir).unwrap().count();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(context["title"], "Home");
+        assert!(context["content"].as_str().unwrap().contains("Hi"));
+        assert!(copied.is_err());
+        assert_eq!(written, 3);
+    }
+
+    #[test]
+    fn sniffing() {
+        let dir = std::env::temp_dir().join(format!("impertio-sniff-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let config = Config {
+            handlers: Some(crate::config::HandlerConfig {
+                sniff: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("notes"), "#+TITLE: Notes\n\nHi").unwrap();
+        std::fs::write(source.join("style.css"), "* {\n  margin: 0;\n}\n").unwrap();
+        std::fs::write(source.join("data.yml"), "---\ntitle: Notes\n").unwrap();
+        std::fs::write(source.join("root.html"), "{{ content }}").unwrap();
+
+        FileDispatcher::new(source.to_str().unwrap(), config)
+            .handle_files(
+                dest.to_str().unwrap().into(),
+                source.to_str().unwrap().into(),
+            )
+            .unwrap();
+
+        let notes = std::fs::read_to_string(dest.join("notes.html")).unwrap();
+        let style = std::fs::read_to_string(dest.join("style.css")).unwrap();
+        let data = std::fs::read_to_string(dest.join("data.yml")).unwrap();
+        let converted = dest.join("style.html").exists() || dest.join("data.html").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(notes.contains("Hi"));
+        assert_eq!(style, "* {\n  margin: 0;\n}\n");
+        assert_eq!(data, "---\ntitle: Notes\n");
+        assert!(!converted);
+    }
+
+    #[test]
+    fn data_files() {
+        let dir = std::env::temp_dir().join(format!("impertio-data-src-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+
+        std::fs::create_dir_all(source.join("data")).unwrap();
+        std::fs::write(source.join("data/team.yaml"), "- name: Ada\n").unwrap();
+        std::fs::write(source.join("data/logo.png"), "").unwrap();
+        std::fs::write(source.join("index.org"), "Hi").unwrap();
+        std::fs::write(source.join("root.html"), "{{ data.team.0.name }}").unwrap();
+
+        FileDispatcher::new(source.to_str().unwrap(), Default::default())
+            .handle_files(
+                dest.to_str().unwrap().into(),
+                source.to_str().unwrap().into(),
+            )
+            .unwrap();
+
+        let page = std::fs::read_to_string(dest.join("index.html")).unwrap();
+        let copied = dest.join("data/logo.png").exists();
+        let loaded = dest.join("data/team.yaml").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(page, "Ada");
+        assert!(copied);
+        assert!(!loaded);
+    }
+
+    #[test]
+    fn warning_counts() {
+        let dir = std::env::temp_dir().join(format!("impertio-warnings-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let build = || {
+            FileDispatcher::new(source.to_str().unwrap(), Default::default())
+                .handle_files(
+                    dest.to_str().unwrap().into(),
+                    source.to_str().unwrap().into(),
+                )
+                .unwrap()
+        };
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(
+            source.join("index.org"),
+            "#+CAPTION: Gone\n[[./gone.png]]\n{{{nope}}}\n#+BEGIN: clocktable\n#+END:\n[[./cat.png]]\n",
+        )
+        .unwrap();
+        std::fs::write(source.join("cat.png"), "").unwrap();
+        std::fs::write(source.join("root.html"), "{{ content }}").unwrap();
+
+        // Once when the page is rendered, and again when it's up to date.
+        let builds = [build(), build()];
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        for warnings in builds {
+            let missing = warnings.details("Referenced images do not exist");
+
+            assert_eq!(missing.len(), 1);
+            assert!(missing[0].ends_with("index.org:2"));
+
+            for category in [
+                "Macros not defined",
+                "Org constructs not supported, skipped",
+                "Images without alt text or a caption",
+            ] {
+                assert_eq!(warnings.details(category).len(), 1, "{category}");
+            }
+        }
+    }
+
+    #[test]
+    fn check() {
+        let dir = std::env::temp_dir().join(format!("impertio-check-src-{}", std::process::id()));
+        let config = Config {
+            site_url: "https://x.y".into(),
+            galleries: Some(vec!["photos".into()]),
+            ..Default::default()
+        };
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("index.org"),
+            "* Intro\nSee [[*Intro]] and [[#outro]].\n#+INCLUDE: \"part.org\"\n\n\
+             #+CAPTION: A cat\n[[./cat.png]]\n\n#+CAPTION: A dog\n[[./dog.png]]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("part.org"), "Included.\n").unwrap();
+        std::fs::write(dir.join("cat.png"), "").unwrap();
+        std::fs::write(dir.join("broken.org"), "#+BEGIN_QUOTE\nNever closed.\n").unwrap();
+        std::fs::write(dir.join("root.html"), "{{ content }}").unwrap();
+
+        let problems = FileDispatcher::new(dir.to_str().unwrap(), config)
+            .check(dir.to_str().unwrap().into())
+            .unwrap();
+        let written = std::fs::read_dir(&dir).unwrap().count();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            problems.details("Configuration problems"),
+            ["`galleries` lists \"photos\", which doesn't exist"]
+        );
+        assert_eq!(
+            problems.details("Broken links"),
+            ["\"#outro\" at index.org:2"]
+        );
+        assert_eq!(
+            problems.details("Referenced images do not exist"),
+            ["\"./dog.png\" at index.org:9"]
+        );
+        assert_eq!(problems.details("Org files not parsed").len(), 1);
+        assert!(problems.details("Org files not parsed")[0].starts_with("broken.org: "));
+        assert_eq!(written, 5);
+    }
+
+    #[test]
+    fn orphaned_outputs() {
+        let dir = std::env::temp_dir().join(format!("impertio-clean-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let build = |clean: bool| {
+            let config = Config {
+                clean_orphans: Some(clean),
+                ..Default::default()
+            };
+
+            FileDispatcher::new(source.to_str().unwrap(), config)
+                .handle_files(dest.to_str().unwrap().into(), source.to_str().unwrap().into())
+                .unwrap();
+        };
+
+        std::fs::create_dir_all(source.join("old")).unwrap();
+        std::fs::create_dir_all(&dest).unwrap();
+        std::fs::write(source.join("a.txt"), "a").unwrap();
+        std::fs::write(source.join("old/b.txt"), "b").unwrap();
+        build(false);
+        std::fs::remove_dir_all(source.join("old")).unwrap();
+        build(false);
+
+        let kept = dest.join("old/b.txt").exists();
+
+        build(true);
+
+        let cleaned = !dest.join("old").exists() && dest.join("a.txt").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(kept);
+        assert!(cleaned);
+    }
+
+    #[test]
+    fn partly_rebuilt_outputs() {
+        let dir = std::env::temp_dir().join(format!("impertio-partly-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let build = || {
+            let config = Config {
+                clean_orphans: Some(true),
+                ..Default::default()
+            };
+
+            FileDispatcher::new(source.to_str().unwrap(), config)
+                .handle_files(
+                    dest.to_str().unwrap().into(),
+                    source.to_str().unwrap().into(),
+                )
+                .unwrap();
+        };
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("x.csv"), "a,b\n1,2\n").unwrap();
+        std::fs::write(source.join("root.html"), "{{ content }}").unwrap();
+        build();
+        std::fs::remove_file(dest.join("x.html")).unwrap();
+        build();
+
+        let rebuilt = dest.join("x.html").exists();
+        let kept = dest.join("x.csv").exists();
+
+        build();
+
+        let still_kept = dest.join("x.csv").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(rebuilt);
+        assert!(kept);
+        assert!(still_kept);
+    }
+
+    #[test]
+    fn feed_pages() {
+        let items = (1..=5)
+            .rev()
+            .map(|n| rss::Item {
+                title: Some(n.to_string()),
+                ..Default::default()
+            })
+            .collect();
+        let pages = paginate_feed(items, Some(2));
+        let titles = |page: &FeedPage| -> Vec<String> {
+            page.items
+                .iter()
+                .map(|item| item.title.clone().unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            pages.iter().map(|page| page.name.as_str()).collect::<Vec<_>>(),
+            vec!["feed", "feed-1", "feed-2"]
+        );
+        assert_eq!(titles(&pages[0]), vec!["5"]);
+        assert_eq!(titles(&pages[1]), vec!["2", "1"]);
+        assert_eq!(titles(&pages[2]), vec!["4", "3"]);
+        assert!(pages[0]
+            .links
+            .contains(&("prev-archive", "feed-2".to_owned())));
+        assert!(pages[2]
+            .links
+            .contains(&("prev-archive", "feed-1".to_owned())));
+        assert!(pages[1]
+            .links
+            .contains(&("next-archive", "feed-2".to_owned())));
+        assert!(pages[1].archive && !pages[0].archive);
+
+        assert_eq!(paginate_feed(vec![], Some(2)).len(), 1);
+    }
+
+    #[test]
+    fn websub_links() {
+        let websub = WebSubConfig {
+            hub: "https://hub.example.com/".into(),
+            ping: None,
+        };
+        let links = feed_links(
+            &paginate_feed(vec![], None)[0],
+            "https://example.com",
+            Some(&websub),
+        );
+
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| (link.rel.as_str(), link.href.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("self", "https://example.com/feed"),
+                ("hub", "https://hub.example.com/")
+            ]
+        );
     }
 }
@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Hand-written HTML fragments rendered into `root.html`.
+
+use std::{collections::HashMap, path::Path};
+
+use fancy_regex::Regex;
+use globset::{Glob, GlobSetBuilder};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref H1: Regex = Regex::new(r"(?is)<h1\b[^>]*>(?<title>.*?)</h1>").unwrap();
+}
+
+/// Starts a fragment that should be rendered into `root.html`.
+pub const MARKER: &str = "<!-- impertio:wrap -->";
+
+/// The fragment without its marker, if it's marked.
+pub fn strip_marker(content: &str) -> Option<&str> {
+    content.trim_start().strip_prefix(MARKER)
+}
+
+/// Whether the file at `relative` is one of `globs`. Templates themselves never are.
+pub fn matches(globs: &[String], relative: &Path) -> anyhow::Result<bool> {
+    if relative.file_name().is_some_and(|name| name == "root.html") {
+        return Ok(false);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+
+    for glob in globs {
+        builder.add(Glob::new(glob)?);
+    }
+
+    Ok(builder.build()?.is_match(relative))
+}
+
+/// Metadata under the keys Org keywords use: the title is the text of the first `<h1>`.
+pub fn metadata(content: &str) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+
+    if let Ok(Some(caps)) = H1.captures(content) {
+        let title = crate::org::plain_text(&caps["title"]);
+
+        if !title.trim().is_empty() {
+            metadata.insert("title".into(), title.trim().to_owned());
+        }
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::fragment::{matches, metadata, strip_marker};
+
+    #[test]
+    fn marker() {
+        assert_eq!(
+            strip_marker("\n<!-- impertio:wrap -->\n<p>Hi</p>"),
+            Some("\n<p>Hi</p>")
+        );
+        assert_eq!(strip_marker("<p>Hi</p><!-- impertio:wrap -->"), None);
+    }
+
+    #[test]
+    fn globs() {
+        let globs = vec!["pages/**/*.html".to_owned()];
+
+        assert!(matches(&globs, Path::new("pages/a/b.html")).unwrap());
+        assert!(!matches(&globs, Path::new("b.html")).unwrap());
+        assert!(!matches(&globs, Path::new("pages/root.html")).unwrap());
+        assert!(matches(&["[".to_owned()], Path::new("b.html")).is_err());
+    }
+
+    #[test]
+    fn title() {
+        assert_eq!(
+            metadata("<h1 class=\"x\">About <em>us</em></h1><h1>Other</h1>")["title"],
+            "About us"
+        );
+        assert!(metadata("<p>No heading</p>").is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Photo galleries: a grid of a directory's images, and a page for each.
+
+use std::path::Path;
+
+use build_html::escape_html;
+
+use crate::{config::Config, exif::Exif, metadata::Metadata};
+
+/// Marks a directory's `index.org` as its gallery.
+pub const MACRO: &str = "{{{gallery}}}";
+
+/// An image in a gallery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Photo {
+    pub url: String,
+    pub exif: Exif,
+}
+
+impl Photo {
+    /// The file name.
+    pub fn name(&self) -> &str {
+        self.url.rsplit('/').next().unwrap_or(&self.url)
+    }
+
+    /// The caption, or the file name if it has none, as alt text and its page's title.
+    pub fn title(&self) -> &str {
+        self.exif.caption.as_deref().unwrap_or(self.name())
+    }
+
+    /// Where its own page is.
+    pub fn page_url(&self) -> String {
+        format!("{}.html", self.url)
+    }
+
+    fn time(&self) -> String {
+        self.exif
+            .taken
+            .map(|taken| {
+                format!(
+                    "<time datetime=\"{}\">{}</time>",
+                    taken.to_rfc3339(),
+                    taken.format("%Y-%m-%d")
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// Its caption and capture time.
+    fn figcaption(&self) -> String {
+        let caption = self
+            .exif
+            .caption
+            .as_ref()
+            .map(|caption| format!("<span class=\"caption\">{}</span> ", escape_html(caption)))
+            .unwrap_or_default();
+
+        format!("{}{}", caption, self.time())
+    }
+
+    /// The camera and where the photo was taken, linking to a map.
+    fn details(&self) -> String {
+        let camera = self
+            .exif
+            .camera
+            .as_ref()
+            .map(|camera| format!("<dt>Camera</dt><dd>{}</dd>", escape_html(camera)))
+            .unwrap_or_default();
+        let location = self
+            .exif
+            .location
+            .map(|location| {
+                format!(
+                    "<dt>Location</dt><dd><a href=\"https://www.openstreetmap.org/?mlat={0:.6}&amp;mlon={1:.6}\">{0:.6}, {1:.6}</a></dd>",
+                    location.latitude, location.longitude
+                )
+            })
+            .unwrap_or_default();
+
+        if camera.is_empty() && location.is_empty() {
+            String::new()
+        } else {
+            format!("<dl class=\"photo-details\">{}{}</dl>", camera, location)
+        }
+    }
+}
+
+/// Whether the directory `dir`, relative to `root`, is a gallery: it's in the configured
+/// `galleries`, or its `index.org` calls `{{{gallery}}}`.
+pub fn is_gallery(config: &Config, root: &Path, dir: &Path) -> bool {
+    config
+        .galleries
+        .iter()
+        .flatten()
+        .any(|gallery| Path::new(gallery.trim_matches('/')) == dir)
+        || std::fs::read_to_string(root.join(dir).join("index.org"))
+            .is_ok_and(|index| index.contains(MACRO))
+}
+
+/// The images directly in the directory at `dir_url`, oldest first, then by name.
+pub fn photos(metadata: &[Metadata], dir_url: &str) -> Vec<Photo> {
+    let mut photos: Vec<Photo> = metadata
+        .iter()
+        .filter_map(|meta| match meta {
+            Metadata::Image { url, exif } => Some(Photo {
+                url: url.clone(),
+                exif: exif.clone(),
+            }),
+            _ => None,
+        })
+        .filter(|photo| {
+            photo
+                .url
+                .rsplit_once('/')
+                .is_some_and(|(dir, _)| dir == dir_url.trim_end_matches('/'))
+        })
+        .collect();
+
+    photos.sort_by(|a, b| (a.exif.taken, &a.url).cmp(&(b.exif.taken, &b.url)));
+
+    photos
+}
+
+/// A grid of lazily loaded images, each linking to itself with `data-lightbox` for lightbox
+/// scripts, and to its page with `data-page`.
+pub fn grid(photos: &[Photo]) -> String {
+    let items: String = photos
+        .iter()
+        .map(|photo| {
+            format!(
+                "<figure class=\"gallery-item\"><a href=\"{0}\" data-lightbox=\"gallery\" data-page=\"{1}\"><img src=\"{0}\" alt=\"{2}\" loading=\"lazy\" class=\"thumbnail\" /></a><figcaption>{3}</figcaption></figure>",
+                escape_html(&photo.url),
+                escape_html(&photo.page_url()),
+                escape_html(photo.title()),
+                photo.figcaption()
+            )
+        })
+        .collect();
+
+    format!("<div class=\"gallery\">{}</div>", items)
+}
+
+/// The page of `photos[index]`, with its camera and location, linking to its neighbours and back to the gallery.
+pub fn page(photos: &[Photo], index: usize) -> String {
+    let photo = &photos[index];
+    let link = |rel: &str, text: &str, photo: Option<&Photo>| {
+        photo
+            .map(|photo| {
+                format!(
+                    "<a rel=\"{}\" href=\"{}\">{}</a>",
+                    rel,
+                    escape_html(&photo.page_url()),
+                    text
+                )
+            })
+            .unwrap_or_default()
+    };
+
+    format!(
+        "<figure class=\"gallery-photo\"><img src=\"{}\" alt=\"{}\" /><figcaption>{}</figcaption></figure>{}<nav class=\"gallery-nav\">{}<a href=\"./\">Gallery</a>{}</nav>",
+        escape_html(&photo.url),
+        escape_html(photo.title()),
+        photo.figcaption(),
+        photo.details(),
+        link("prev", "Previous", index.checked_sub(1).and_then(|i| photos.get(i))),
+        link("next", "Next", photos.get(index + 1))
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        exif::{Exif, Location},
+        gallery::{grid, page, photos},
+        metadata::Metadata,
+    };
+
+    #[test]
+    fn gallery() {
+        let image = |url: &str, day: Option<u32>| Metadata::Image {
+            url: url.into(),
+            exif: Exif {
+                taken: day.map(|day| Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap()),
+                ..Default::default()
+            },
+        };
+        let metadata = [
+            image("https://x.y/trip/b.jpg", Some(2)),
+            image("https://x.y/trip/a.jpg", Some(3)),
+            image("https://x.y/trip/c.png", None),
+            image("https://x.y/trip/sub/d.jpg", None),
+            image("https://x.y/e.jpg", None),
+        ];
+        let photos = photos(&metadata, "https://x.y/trip/");
+
+        assert_eq!(
+            photos.iter().map(|photo| photo.name()).collect::<Vec<_>>(),
+            ["c.png", "b.jpg", "a.jpg"]
+        );
+        assert_eq!(
+            grid(&photos[1..2]),
+            "<div class=\"gallery\"><figure class=\"gallery-item\"><a href=\"https://x.y/trip/b.jpg\" data-lightbox=\"gallery\" data-page=\"https://x.y/trip/b.jpg.html\"><img src=\"https://x.y/trip/b.jpg\" alt=\"b.jpg\" loading=\"lazy\" class=\"thumbnail\" /></a><figcaption><time datetime=\"2024-05-02T00:00:00+00:00\">2024-05-02</time></figcaption></figure></div>"
+        );
+        assert_eq!(
+            page(&photos, 0),
+            "<figure class=\"gallery-photo\"><img src=\"https://x.y/trip/c.png\" alt=\"c.png\" /><figcaption></figcaption></figure><nav class=\"gallery-nav\"><a href=\"./\">Gallery</a><a rel=\"next\" href=\"https://x.y/trip/b.jpg.html\">Next</a></nav>"
+        );
+        assert!(page(&photos, 2).contains("<a rel=\"prev\" href=\"https://x.y/trip/b.jpg.html\">Previous</a><a href=\"./\">Gallery</a></nav>"));
+    }
+
+    #[test]
+    fn details() {
+        let mut photos = photos(
+            &[Metadata::Image {
+                url: "https://x.y/trip/a.jpg".into(),
+                exif: Exif {
+                    camera: Some("Canon EOS R6".into()),
+                    location: Some(Location {
+                        latitude: 48.8584,
+                        longitude: -2.2945,
+                    }),
+                    caption: Some("The \"tower\"".into()),
+                    ..Default::default()
+                },
+            }],
+            "https://x.y/trip",
+        );
+
+        assert!(grid(&photos).contains("alt=\"The &quot;tower&quot;\" loading=\"lazy\" class=\"thumbnail\" /></a><figcaption><span class=\"caption\">The &quot;tower&quot;</span> </figcaption>"));
+        assert!(page(&photos, 0).contains("</figure><dl class=\"photo-details\"><dt>Camera</dt><dd>Canon EOS R6</dd><dt>Location</dt><dd><a href=\"https://www.openstreetmap.org/?mlat=48.858400&amp;mlon=-2.294500\">48.858400, -2.294500</a></dd></dl><nav"));
+
+        photos[0].exif = Default::default();
+
+        assert!(page(&photos, 0).contains("</figure><nav"));
+    }
+}
@@ -0,0 +1,250 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Comparing rendered output against expected ("golden") files.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+use crate::template::Templates;
+
+/// Where template fixtures live, relative to the site's source directory.
+pub const TEMPLATE_TESTS_DIR: &str = "tests/templates";
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// The golden file was (re)written from the output.
+    Updated,
+    Failed {
+        /// 1-based line where the output first differs.
+        line: usize,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// Compares `actual` with the contents of `golden`, or writes it there when `update` is set.
+/// A missing golden file is a failure unless updating.
+pub fn compare(actual: &str, golden: &Path, update: bool) -> anyhow::Result<Outcome> {
+    if update {
+        std::fs::write(golden, actual)?;
+        return Ok(Outcome::Updated);
+    }
+
+    let expected = std::fs::read_to_string(golden).unwrap_or_default();
+
+    if expected == actual {
+        return Ok(Outcome::Passed);
+    }
+
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (e, a) => {
+                return Ok(Outcome::Failed {
+                    line,
+                    expected: e.unwrap_or_default().to_owned(),
+                    actual: a.unwrap_or_default().to_owned(),
+                })
+            }
+        }
+    }
+}
+
+/// `tests/templates/<name>.yaml`, rendered and compared with `<name>.html` next to it.
+#[derive(Deserialize, Debug)]
+struct TemplateFixture {
+    /// The page being rendered, relative to the source directory. Its `root.html` and those
+    /// above it are used. Defaults to `index.html`.
+    page: Option<PathBuf>,
+    /// Defaults to `root.html`.
+    template: Option<String>,
+    #[serde(default)]
+    context: serde_json::Value,
+}
+
+/// Renders every template fixture under `source` and compares it with its golden file.
+pub fn test_templates(source: &Path, update: bool) -> anyhow::Result<Vec<(PathBuf, Outcome)>> {
+    let dir = source.join(TEMPLATE_TESTS_DIR);
+    let templates = Templates::new(source);
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .with_context(|| format!("Reading `{}`", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+
+    fixtures.retain(|path| {
+        matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("yaml" | "yml")
+        )
+    });
+    fixtures.sort();
+
+    fixtures
+        .into_iter()
+        .map(|path| {
+            let fixture: TemplateFixture =
+                serde_yaml::from_str(&std::fs::read_to_string(&path)?)
+                    .with_context(|| format!("Parsing `{}`", path.display()))?;
+            let page = source.join(fixture.page.unwrap_or_else(|| "index.html".into()));
+            let context = match fixture.context {
+                serde_json::Value::Null => tera::Context::new(),
+                value => tera::Context::from_value(value)
+                    .with_context(|| format!("`context` in `{}`", path.display()))?,
+            };
+            let rendered = templates
+                .render_context(
+                    fixture.template.as_deref().unwrap_or("root.html"),
+                    &page,
+                    &context,
+                )
+                .with_context(|| format!("Rendering `{}`", path.display()))?;
+
+            Ok((
+                path.clone(),
+                compare(&rendered, &path.with_extension("html"), update)?,
+            ))
+        })
+        .collect()
+}
+
+/// Renders a source file to one output format.
+pub type Exporter<'a> = &'a dyn Fn(&Path) -> anyhow::Result<String>;
+
+/// Snapshot tests: every `<name>.<input>` file in `dir` is exported with each of `exporters`,
+/// keyed by output extension, and compared with `<name>.<extension>` next to it. Formats
+/// without a golden file are skipped unless updating.
+pub fn test_exports(
+    dir: &Path,
+    input: &str,
+    exporters: &[(&str, Exporter)],
+    update: bool,
+) -> anyhow::Result<Vec<(PathBuf, Outcome)>> {
+    let mut sources: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Reading `{}`", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+
+    sources.retain(|path| path.extension().and_then(|ext| ext.to_str()) == Some(input));
+    sources.sort();
+
+    let mut results = vec![];
+
+    for source in sources {
+        for (extension, export) in exporters {
+            let golden = source.with_extension(extension);
+
+            if !update && !golden.exists() {
+                continue;
+            }
+
+            let output =
+                export(&source).with_context(|| format!("Exporting `{}`", source.display()))?;
+
+            results.push((golden.clone(), compare(&output, &golden, update)?));
+        }
+    }
+
+    Ok(results)
+}
+
+/// Panics listing every failed result, for use in `#[test]`s.
+pub fn assert_passed(results: &[(PathBuf, Outcome)]) {
+    let failures: Vec<String> = results
+        .iter()
+        .filter_map(|(path, outcome)| match outcome {
+            Outcome::Failed {
+                line,
+                expected,
+                actual,
+            } => Some(format!(
+                "{}:{}\n  expected: {}\n  actual:   {}",
+                path.display(),
+                line,
+                expected,
+                actual
+            )),
+            _ => None,
+        })
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "Snapshots differ:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        config::Config,
+        golden::{assert_passed, compare, test_exports, test_templates, Exporter, Outcome},
+        org::Document,
+    };
+
+    #[test]
+    fn template_fixtures() {
+        let results = test_templates(Path::new("data"), false).unwrap();
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|(path, outcome)| (path.file_name().unwrap().to_str().unwrap(), outcome))
+                .collect::<Vec<_>>(),
+            vec![
+                ("nested.yaml", &Outcome::Passed),
+                ("root.yaml", &Outcome::Passed)
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatches() {
+        assert_eq!(
+            compare(
+                "a\nc\n",
+                Path::new("data/tests/templates/missing.html"),
+                false
+            )
+            .unwrap(),
+            Outcome::Failed {
+                line: 1,
+                expected: "".into(),
+                actual: "a".into()
+            }
+        );
+    }
+
+    /// Set `UPDATE_SNAPSHOTS=1` to rewrite the expected outputs.
+    #[test]
+    fn org_snapshots() {
+        let html: Exporter = &|path| {
+            Ok(
+                Document::parse_file(path.to_str().unwrap(), Default::default())
+                    .map_err(anyhow::Error::msg)?
+                    .to_html(&Config::default())
+                    + "\n",
+            )
+        };
+        let results = test_exports(
+            Path::new("data/snapshots/org"),
+            "org",
+            &[("html", html)],
+            std::env::var_os("UPDATE_SNAPSHOTS").is_some(),
+        )
+        .unwrap();
+
+        assert!(!results.is_empty());
+        assert_passed(&results);
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Which pages link to which, read from the built pages so template menus count too.
+
+use std::{collections::BTreeSet, path::Path};
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::Metadata;
+
+lazy_static! {
+    static ref LINK: Regex =
+        Regex::new(r#"(?is)<a\s[^>]*?\bhref\s*=\s*(?<quote>["'])(?<url>.*?)\k<quote>"#).unwrap();
+}
+
+/// Where the graph is written, relative to the destination.
+pub const FILE: &str = "graph.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub url: String,
+    pub title: String,
+}
+
+/// A link from the page at `source` to the page at `target`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+}
+
+/// The site's pages and the links between them. Links to anything else, and pages' links to
+/// themselves, are left out.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// `url` without its fragment or query, and with `index.html` for directories.
+fn page_url(url: &str) -> String {
+    let url = url.split(['#', '?']).next().unwrap_or(url);
+
+    if url.ends_with('/') {
+        format!("{}index.html", url)
+    } else {
+        url.to_owned()
+    }
+}
+
+impl Graph {
+    /// The pages of `metadata` and the links in their built files in `dir`.
+    pub fn from_outputs(metadata: &[Metadata], site_url: &str, dir: &Path) -> Self {
+        let nodes: Vec<Node> = metadata
+            .iter()
+            .filter_map(|meta| match meta {
+                Metadata::Article { url, title, .. } => Some(Node {
+                    url: url.clone(),
+                    title: title.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        let mut edges = BTreeSet::new();
+
+        for node in &nodes {
+            let Some(relative) = node.url.strip_prefix(&format!("{}/", site_url)) else {
+                continue;
+            };
+            let Ok(html) = std::fs::read_to_string(dir.join(relative)) else {
+                continue;
+            };
+
+            for target in Self::links(&html, site_url, Path::new(relative)) {
+                if target != node.url && nodes.iter().any(|node| node.url == target) {
+                    edges.insert(Edge {
+                        source: node.url.clone(),
+                        target,
+                    });
+                }
+            }
+        }
+
+        Self {
+            nodes,
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// The absolute URLs `<a>`s in the page at `page` link to.
+    fn links(html: &str, site_url: &str, page: &Path) -> Vec<String> {
+        LINK.captures_iter(html)
+            .filter_map(Result::ok)
+            .map(|caps| page_url(&crate::absolute::url(site_url, page, &caps["url"])))
+            .collect()
+    }
+
+    /// The previous build's graph, if it wrote one.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(FILE);
+        let json = std::fs::read_to_string(&path).map_err(|err| {
+            anyhow::anyhow!(
+                "Couldn't read {:?} ({}); build with `graph: true` first",
+                path,
+                err
+            )
+        })?;
+
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(dir.join(FILE), serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Pages no other page links to, besides the home page.
+    pub fn orphans(&self, site_url: &str) -> Vec<&Node> {
+        let home = format!("{}/index.html", site_url);
+
+        self.nodes
+            .iter()
+            .filter(|node| {
+                node.url != home && !self.edges.iter().any(|edge| edge.target == node.url)
+            })
+            .collect()
+    }
+
+    /// Pages that can't be reached by following links from the home page, including those
+    /// only other unreachable pages link to.
+    pub fn unreachable(&self, site_url: &str) -> Vec<&Node> {
+        let mut reached = BTreeSet::new();
+        let mut queue = vec![format!("{}/index.html", site_url)];
+
+        while let Some(url) = queue.pop() {
+            if !reached.insert(url.clone()) {
+                continue;
+            }
+
+            queue.extend(
+                self.edges
+                    .iter()
+                    .filter(|edge| edge.source == url)
+                    .map(|edge| edge.target.clone()),
+            );
+        }
+
+        self.nodes
+            .iter()
+            .filter(|node| !reached.contains(&node.url))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        graph::{Edge, Graph, Node},
+        metadata::Metadata,
+    };
+
+    #[test]
+    fn links() {
+        let dir = std::env::temp_dir().join(format!("impertio-graph-{}", std::process::id()));
+        let page = |url: &str, title: &str| {
+            Metadata::article(&format!("https://x.y/{}", url)).with_title(title)
+        };
+
+        std::fs::create_dir_all(dir.join("blog")).unwrap();
+        std::fs::write(
+            dir.join("index.html"),
+            "<a href=\"blog/\">Blog</a> <a href='blog/a.html#top'>A</a> <a href=\"https://z.y/\">Z</a>",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("blog/index.html"),
+            "<a class=\"x\" href=\"a.html\">A</a><a href=\"#toc\">Top</a><link href=\"b.html\" />",
+        )
+        .unwrap();
+        std::fs::write(dir.join("blog/a.html"), "<a href=\"../\">Home</a>").unwrap();
+        std::fs::write(dir.join("blog/b.html"), "<a href=\"/blog/a.html\">A</a>").unwrap();
+
+        let graph = Graph::from_outputs(
+            &[
+                page("index.html", "Home"),
+                page("blog/index.html", "Blog"),
+                page("blog/a.html", "A"),
+                page("blog/b.html", "B"),
+            ],
+            "https://x.y",
+            &dir,
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let edge = |source: &str, target: &str| Edge {
+            source: format!("https://x.y/{}", source),
+            target: format!("https://x.y/{}", target),
+        };
+
+        assert_eq!(
+            graph.edges,
+            vec![
+                edge("blog/a.html", "index.html"),
+                edge("blog/b.html", "blog/a.html"),
+                edge("blog/index.html", "blog/a.html"),
+                edge("index.html", "blog/a.html"),
+                edge("index.html", "blog/index.html"),
+            ]
+        );
+        assert_eq!(
+            graph
+                .orphans("https://x.y")
+                .iter()
+                .map(|node| node.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["B"]
+        );
+    }
+
+    #[test]
+    fn reachability() {
+        let node = |url: &str| Node {
+            url: format!("https://x.y/{}", url),
+            title: url.into(),
+        };
+        let edge = |source: &str, target: &str| Edge {
+            source: format!("https://x.y/{}", source),
+            target: format!("https://x.y/{}", target),
+        };
+        let graph = Graph {
+            nodes: vec![
+                node("index.html"),
+                node("a.html"),
+                node("b.html"),
+                node("c.html"),
+                node("d.html"),
+            ],
+            edges: vec![
+                edge("index.html", "a.html"),
+                edge("a.html", "b.html"),
+                edge("b.html", "index.html"),
+                edge("c.html", "d.html"),
+                edge("d.html", "c.html"),
+            ],
+        };
+
+        assert_eq!(
+            graph
+                .unreachable("https://x.y")
+                .iter()
+                .map(|node| node.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["c.html", "d.html"]
+        );
+        assert!(graph.orphans("https://x.y").is_empty());
+    }
+}
@@ -3,16 +3,22 @@
 
 use dyn_clone::{clone_trait_object, DynClone};
 use std::{
+    collections::HashMap,
     ffi::OsStr, io::Write, path::{Path, PathBuf}, sync::{Arc, Mutex}
 };
 
-use crate::{config::Config, metadata::Metadata, org::Document, template::Templates};
+use crate::{
+    checksums,
+    config::{CommandHandlerConfig, Config, HandlerConfig},
+    logging::Warnings, metadata::{Anchor, Metadata, Robots},
+    org::Document, plugin::WasmHandler, template::Templates,
+};
 
 fn file_changed(old: &Path, new: &Path) -> std::io::Result<bool> {
     Ok(!new.exists() || new.metadata()?.modified()? < old.metadata()?.modified()?)
 }
 
-fn writeable(path: &Path) -> std::io::Result<std::fs::File> {
+pub(crate) fn writeable(path: &Path) -> std::io::Result<std::fs::File> {
     use std::fs::{create_dir_all, File};
 
     create_dir_all(path.parent().unwrap())?;
@@ -26,19 +32,33 @@ pub struct FileContext {
     pub output_path: PathBuf,
     pub site_url: String,
     pub ext: String,
+    /// The glob in `handlers.extensions` or `handlers.commands` the file matched, whose handler
+    /// it gets instead of its extension's.
+    pub pattern: Option<String>,
+    pub config: Arc<Config>,
+    pub handler_config: HandlerConfig,
+    /// Plugins expanding Org macros, by macro name.
+    pub macros: Arc<HashMap<String, WasmHandler>>,
 
     pub templates: Templates,
     pub metadata: Arc<Mutex<Vec<Metadata>>>,
+    pub warnings: Arc<Mutex<Warnings>>,
+    /// The files the handler wrote through [`FileContext::writeable`], for the build manifest.
+    pub outputs: Arc<Mutex<Vec<PathBuf>>>,
+    /// Whether the build cache found the file's inputs changed, or `None` if it has no record
+    /// of them and the modification times decide.
+    pub stale: Option<bool>,
 }
 
 impl FileContext {
     pub fn new(
-        config: &Config,
+        config: &Arc<Config>,
         relative: &Path,
         source: &Path,
         output: &Path,
         templates: &Templates,
         metadata: Arc<Mutex<Vec<Metadata>>>,
+        warnings: Arc<Mutex<Warnings>>,
     ) -> Self {
         Self {
             relative_path: relative.to_owned(),
@@ -46,15 +66,62 @@ impl FileContext {
             output_path: output.to_owned(),
             ext: source
                 .extension()
-                .unwrap_or(&OsStr::new(""))
+                .unwrap_or(OsStr::new(""))
                 .to_str()
                 .unwrap_or("")
                 .to_string(),
+            pattern: None,
             site_url: config.site_url.clone(),
+            handler_config: config.handlers.clone().unwrap_or_default(),
+            macros: Default::default(),
+            config: config.clone(),
             templates: templates.clone(),
             metadata,
+            warnings,
+            outputs: Default::default(),
+            stale: None,
+        }
+    }
+}
+
+impl FileContext {
+    /// The source directory, which `relative_path` is relative to.
+    pub fn root(&self) -> &Path {
+        self.source_path
+            .ancestors()
+            .nth(self.relative_path.components().count())
+            .unwrap_or(Path::new(""))
+    }
+
+    /// What picks the file's handler: its matching glob, or else its extension.
+    pub fn handler_key(&self) -> &str {
+        self.pattern.as_deref().unwrap_or(&self.ext)
+    }
+
+    /// Whether `output`, built from this file, needs building: if the build cache says its
+    /// inputs changed or it's missing, or without a record, if it's older than the source.
+    pub fn outdated(&self, output: &Path) -> std::io::Result<bool> {
+        match self.stale {
+            Some(stale) => Ok(stale || !output.exists()),
+            None => file_changed(&self.source_path, output),
         }
     }
+
+    /// Creates `path` and the directories above it, remembering it as built from this file, so
+    /// it can be cleaned up once the file is gone.
+    pub fn writeable(&self, path: &Path) -> std::io::Result<std::fs::File> {
+        self.outputs.lock().unwrap().push(path.to_owned());
+        writeable(path)
+    }
+
+    /// The URL of the page rendered from this file.
+    pub fn url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.site_url,
+            self.relative_path.with_extension("html").display()
+        )
+    }
 }
 
 impl Default for FileContext {
@@ -65,42 +132,201 @@ impl Default for FileContext {
             site_url: "".into(),
             relative_path: Default::default(),
             ext: "org".into(),
+            pattern: None,
+            config: Default::default(),
+            handler_config: Default::default(),
+            macros: Default::default(),
             templates: Templates::new(&PathBuf::new()),
             metadata: Arc::new(Mutex::new(vec![])),
+            warnings: Arc::new(Mutex::new(Warnings::default())),
+            outputs: Default::default(),
+            stale: None,
         }
     }
 }
 
+/// Renders the files registered for it with
+/// [`FileDispatcher::register_handler`](crate::files::FileDispatcher::register_handler). It's
+/// cloned for each file, so state shared between files belongs behind an `Arc`.
 pub trait FileHandler: DynClone {
-    fn new() -> Self
-    where
-        Self: Sized;
     fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()>;
     fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata>;
 }
 
 clone_trait_object!(FileHandler);
 
-#[derive(Clone)]
+/// An article built from document keywords (`title`, `author`, `desc`, `tags` and `license`).
+fn article_metadata(
+    ctx: &FileContext,
+    metadata: &HashMap<String, String>,
+    published: Option<chrono::DateTime<chrono::Utc>>,
+    anchors: Vec<Anchor>,
+    summary: Option<String>,
+    numbered: HashMap<String, usize>,
+) -> anyhow::Result<Metadata> {
+    Ok(Metadata::Article {
+        title: metadata
+            .get("title")
+            .unwrap_or(
+                &ctx.output_path
+                    .file_stem()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+            )
+            .to_string(),
+        author: metadata.get("author").cloned(),
+        description: metadata.get("desc").cloned().or(summary.clone()),
+        modified: std::fs::metadata(ctx.source_path.clone())?
+            .modified()?
+            .into(),
+        // created: std::fs::metadata(ctx.source_path.clone())?.created()?.into(),
+        published,
+        anchors,
+        summary,
+        numbered,
+        content: None,
+        robots: metadata
+            .get("robots")
+            .map(|robots| Robots::parse(robots))
+            .unwrap_or_default(),
+        url: ctx.url(),
+        tags: metadata.get("tags").map(|tags| terms(tags)).unwrap_or_default(),
+        license: metadata
+            .get("license")
+            .cloned()
+            .or_else(|| ctx.config.license.clone()),
+        terms: ctx
+            .config
+            .taxonomies
+            .iter()
+            .flatten()
+            .filter(|taxonomy| taxonomy.name != "tags")
+            .filter_map(|taxonomy| {
+                let keyword = metadata.get(&taxonomy.name.to_ascii_lowercase())?;
+
+                Some((taxonomy.name.clone(), terms(keyword)))
+            })
+            .collect(),
+    })
+}
+
+/// The terms of a keyword like `#+TAGS`, separated by commas if it has any, else by spaces.
+fn terms(keyword: &str) -> Vec<String> {
+    keyword
+        .split(if keyword.contains(',') {
+            |c: char| c == ','
+        } else {
+            |c: char| c.is_whitespace()
+        })
+        .map(|term| term.trim().to_owned())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// The absolute URL of `link`, relative to the page of `ctx` unless it's already absolute.
+fn page_url(ctx: &FileContext, link: &str) -> String {
+    crate::absolute::url(
+        &ctx.site_url,
+        &ctx.relative_path,
+        link.strip_prefix("file:").unwrap_or(link),
+    )
+}
+
+/// Runs `command` with `args` followed by the source path, returning its stdout.
+fn run_command(command: &str, args: &[String], source: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut command = std::process::Command::new(command);
+
+    command.args(args).arg(source);
+    run(command, source)
+}
+
+/// Runs `command` on `source`, returning its stdout.
+fn run(mut command: std::process::Command, source: &Path) -> anyhow::Result<Vec<u8>> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|err| anyhow::anyhow!("Failed to run `{}`: {}", program, err))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "`{}` failed on {:?} ({}): {}",
+            program,
+            source,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Renders a page from the HTML fragment a converter prints for the source file.
+fn convert(
+    ctx: &FileContext,
+    format: &str,
+    command: &str,
+    args: &[String],
+    metadata: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let html_file = ctx.output_path.with_extension("html");
+
+    if !ctx.outdated(&html_file)? {
+        return Ok(());
+    }
+
+    log::info!(
+        phase = "parse",
+        file:% = ctx.relative_path.display();
+        "Parsing {} file {:?}",
+        format,
+        ctx.source_path
+    );
+
+    let body = run_command(command, args, &ctx.source_path)?;
+    let out = ctx.templates.render(
+        "root.html",
+        &ctx.source_path,
+        &String::from_utf8_lossy(&body),
+        Some(
+            metadata
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.to_owned()))
+                .collect(),
+        ),
+    )?;
+
+    ctx.writeable(&html_file)?.write_all(out.as_bytes())?;
+
+    Ok(())
+}
+
+#[derive(Clone, Default)]
 pub struct OrgHandler {}
 
 impl OrgHandler {
     fn parse_file(ctx: &FileContext) -> anyhow::Result<Document> {
-        Ok(crate::org::Document::parse_file(ctx.source_path.to_str().unwrap(), ctx.clone()).unwrap())
+        crate::org::Document::parse_file(ctx.source_path.to_str().unwrap(), ctx.clone())
+            .map_err(|err| anyhow::anyhow!(err))
     }
 }
 
 impl FileHandler for OrgHandler {
-    fn new() -> Self {
-        Self {}
-    }
-
     fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
         let file = ctx.source_path.clone();
         let html_file = ctx.output_path.with_extension("html");
         let source_file: PathBuf = ctx.output_path.with_extension("org");
+        let publish_source = ctx
+            .handler_config
+            .org
+            .as_ref()
+            .and_then(|org| org.publish_source)
+            .unwrap_or(true);
 
-        if !file_changed(&file, &html_file)? && !file_changed(&file, &source_file)? {
+        let source_changed = publish_source && ctx.outdated(&source_file)?;
+
+        if !ctx.outdated(&html_file)? && !source_changed {
             return Ok(());
         }
 
@@ -111,29 +337,88 @@ impl FileHandler for OrgHandler {
             .unwrap_or(file.to_str().unwrap())
         {
             "index" => log::info!(
+                phase = "parse",
+                file:% = ctx.relative_path.display();
                 "Parsing index of {:?}",
-                file.parent().unwrap_or(&Path::new("<root>"))
+                file.parent().unwrap_or(Path::new("<root>"))
+            ),
+            _ => log::info!(
+                phase = "parse",
+                file:% = ctx.relative_path.display();
+                "Parsing Org file {:?}",
+                file
             ),
-            _ => log::info!("Parsing Org file {:?}", file),
         }
 
         let parsed = Self::parse_file(&ctx)?;
 
-        let out = ctx.templates.render(
+        if parsed.is_draft() && !ctx.config.drafts() {
+            log::info!(
+                phase = "parse",
+                file:% = ctx.relative_path.display();
+                "Skipping draft {:?}",
+                file
+            );
+            return Ok(());
+        }
+
+        if let Some(lint) = &ctx.config.lint {
+            let source = std::fs::read_to_string(&file)?;
+
+            for err in crate::lint::lint(lint, &ctx.relative_path, &source) {
+                ctx.warnings.lock().unwrap().add(
+                    "Lint checkers failed",
+                    format!("{}: {}", ctx.relative_path.display(), err),
+                );
+            }
+        }
+
+        let mut context: HashMap<&str, String> = parsed
+            .metadata
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.to_owned()))
+            .collect();
+
+        // Normalized for `<meta name="robots">`.
+        if let Some(robots) = parsed.metadata.get("robots") {
+            match Robots::parse(robots).meta() {
+                Some(meta) => context.insert("robots", meta),
+                None => context.remove("robots"),
+            };
+        }
+
+        // For `og:description`.
+        if let Some(summary) = parsed.summary() {
+            context.entry("desc").or_insert(summary);
+        }
+
+        if let Some(toc) = parsed.table_of_contents(&ctx.config) {
+            context.insert("toc", toc);
+        }
+
+        // For `og:image` and `og:image:alt`.
+        if let Some((path, alt)) = parsed.images().into_iter().next() {
+            context.insert("image", page_url(&ctx, &path));
+            context.insert("image_alt", alt);
+        }
+
+        // Figure, table and listing numbers by kind, e.g. `numbering.Figure`.
+        let mut values = tera::Context::new();
+        values.insert("numbering", &parsed.numbering());
+
+        let out = ctx.templates.render_with(
             "root.html",
             &file,
-            &parsed.to_html(),
-            Some(
-                parsed
-                    .metadata
-                    .iter()
-                    .map(|(key, value)| (key.as_str(), value.to_owned()))
-                    .collect(),
-            ),
+            &parsed.to_html(&ctx.config),
+            Some(context),
+            values,
         )?;
 
-        writeable(&html_file)?.write_all(out.as_bytes())?;
-        writeable(&source_file)?.write_all(std::fs::read(file.clone())?.as_slice())?;
+        ctx.writeable(&html_file)?.write_all(out.as_bytes())?;
+
+        if publish_source {
+            ctx.writeable(&source_file)?.write_all(std::fs::read(file.clone())?.as_slice())?;
+        }
 
         Ok(())
     }
@@ -141,78 +426,481 @@ impl FileHandler for OrgHandler {
     fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
         let parsed = Self::parse_file(&ctx)?;
 
+        if parsed.is_draft() && !ctx.config.drafts() {
+            anyhow::bail!("{:?} is a draft", ctx.source_path);
+        }
+
+        let mut metadata = article_metadata(
+            &ctx,
+            &parsed.metadata,
+            parsed.published,
+            parsed.anchors(),
+            parsed.summary(),
+            parsed.numbered(),
+        )?;
+
+        if ctx.config.rss.as_ref().and_then(|rss| rss.content) == Some(true) {
+            if let Metadata::Article { content, .. } = &mut metadata {
+                *content = Some(parsed.to_html(&ctx.config));
+            }
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Extensions of files that are images, as far as metadata goes.
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webm", "gif"];
+
+#[derive(Clone, Default)]
+pub struct CopyHandler {}
+
+impl CopyHandler {
+    /// Writes the image's page next to it, `{name}.html`, if its directory is a gallery. It
+    /// links to its neighbours, so it's written whether or not the image changed.
+    fn gallery_page(ctx: &FileContext) -> anyhow::Result<()> {
+        let dir = ctx.relative_path.parent().unwrap_or(Path::new(""));
+
+        if !crate::gallery::is_gallery(&ctx.config, ctx.root(), dir) {
+            return Ok(());
+        }
+
+        let url = format!("{}/{}", ctx.site_url, ctx.relative_path.display());
+        let photos = crate::gallery::photos(
+            &ctx.metadata.lock().unwrap(),
+            url.rsplit_once('/').map_or("", |(dir, _)| dir),
+        );
+        let Some(index) = photos.iter().position(|photo| photo.url == url) else {
+            return Ok(());
+        };
+        let page_file = PathBuf::from(format!("{}.html", ctx.output_path.display()));
+        let out = ctx.templates.render(
+            "root.html",
+            &ctx.source_path,
+            &crate::gallery::page(&photos, index),
+            Some(HashMap::from([("title", photos[index].title().to_owned())])),
+        )?;
+
+        ctx.writeable(&page_file)?.write_all(out.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+impl FileHandler for CopyHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        if IMAGE_EXTENSIONS.contains(&ctx.ext.as_str()) {
+            Self::gallery_page(&ctx)?;
+        }
+
+        let checksum = ctx
+            .config
+            .checksums
+            .as_ref()
+            .is_some_and(|checksums| checksums::selected(checksums, &ctx.ext));
+
+        if ctx.outdated(&ctx.output_path)? {
+            ctx.warnings.lock().unwrap().add(
+                "Files not recognized, copied as-is",
+                ctx.relative_path.display().to_string(),
+            );
+
+            ctx.writeable(&ctx.output_path)?
+                .write_all(std::fs::read(&ctx.source_path)?.as_slice())?;
+        } else if !checksum || checksums::sidecar_path(&ctx.output_path).exists() {
+            return Ok(());
+        }
+
+        if checksum {
+            checksums::write_sidecar(&ctx.output_path)?;
+            ctx.outputs
+                .lock()
+                .unwrap()
+                .push(checksums::sidecar_path(&ctx.output_path));
+        }
+
+        Ok(())
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        match ctx.ext.as_str() {
+            ext if IMAGE_EXTENSIONS.contains(&ext) => Ok(Metadata::Image {
+                url: format!("{}/{}", ctx.site_url, ctx.relative_path.display()),
+                exif: match ext {
+                    "jpg" | "jpeg" => {
+                        crate::exif::read(&std::fs::read(&ctx.source_path)?).unwrap_or_default()
+                    }
+                    _ => Default::default(),
+                },
+            }),
+            "" => Err(anyhow::anyhow!("File has no extension. /shrug")),
+            _ => Err(anyhow::anyhow!("File type not extractable to metadata.")),
+        }
+    }
+}
+
+/// Runs the command configured for the file's extension or glob, using its stdout, or the file
+/// it writes to `{output}`, as the output.
+#[derive(Clone, Default)]
+pub struct CommandHandler {}
+
+impl CommandHandler {
+    fn command_config(ctx: &FileContext) -> anyhow::Result<&CommandHandlerConfig> {
+        ctx.handler_config
+            .commands
+            .as_ref()
+            .and_then(|commands| commands.get(ctx.handler_key()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("No command configured for `{}` files.", ctx.handler_key())
+            })
+    }
+
+    fn output_extension(config: &CommandHandlerConfig) -> &str {
+        config.output_extension.as_deref().unwrap_or("html")
+    }
+}
+
+impl FileHandler for CommandHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        let config = Self::command_config(&ctx)?;
+        let extension = Self::output_extension(config);
+        let output_file = ctx.output_path.with_extension(extension);
+
+        if !ctx.outdated(&output_file)? {
+            return Ok(());
+        }
+
+        log::info!(
+            phase = "parse",
+            file:% = ctx.relative_path.display();
+            "Running `{}` on {:?}",
+            config.command,
+            ctx.source_path
+        );
+
+        let args = config.args.clone().unwrap_or_default();
+        let writes_output = args.iter().any(|arg| arg.contains("{output}"));
+        let mut command = std::process::Command::new(&config.command);
+
+        command.args(args.iter().map(|arg| {
+            arg.replace("{input}", &ctx.source_path.display().to_string())
+                .replace("{output}", &output_file.display().to_string())
+        }));
+
+        if !args.iter().any(|arg| arg.contains("{input}")) {
+            command.arg(&ctx.source_path);
+        }
+
+        if writes_output {
+            std::fs::create_dir_all(output_file.parent().unwrap())?;
+        }
+
+        let stdout = run(command, &ctx.source_path)?;
+        let output = if writes_output {
+            std::fs::read(&output_file)?
+        } else {
+            stdout
+        };
+
+        if config.template.unwrap_or(extension == "html") {
+            let out = ctx.templates.render(
+                "root.html",
+                &ctx.source_path,
+                &String::from_utf8_lossy(&output),
+                Some(
+                    [(
+                        "title",
+                        ctx.source_path
+                            .file_stem()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    )]
+                    .into(),
+                ),
+            )?;
+
+            ctx.writeable(&output_file)?.write_all(out.as_bytes())?;
+        } else {
+            ctx.writeable(&output_file)?.write_all(&output)?;
+        }
+
+        Ok(())
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        let extension = Self::output_extension(Self::command_config(&ctx)?);
+
+        if extension != "html" {
+            return Err(anyhow::anyhow!("Only HTML output is extractable to metadata."));
+        }
+
         Ok(Metadata::Article {
-            title: parsed
-                .metadata
-                .get("title")
-                .unwrap_or(
-                    &ctx.output_path
-                        .file_stem()
-                        .unwrap()
-                        .to_str()
-                        .unwrap()
-                        .to_owned(),
-                )
+            title: ctx
+                .source_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
                 .to_string(),
-            author: parsed.metadata.get("author").cloned(),
-            description: parsed.metadata.get("desc").cloned(),
-            modified: std::fs::metadata(ctx.source_path.clone())?
-                .modified()?
-                .into(),
-            // created: std::fs::metadata(ctx.source_path.clone())?.created()?.into(),
+            description: None,
+            author: None,
+            tags: vec![],
+            terms: Default::default(),
+            license: ctx.config.license.clone(),
+            modified: std::fs::metadata(&ctx.source_path)?.modified()?.into(),
+            published: None,
+            anchors: vec![],
+            robots: Default::default(),
+            summary: None,
+            numbered: HashMap::new(),
+            content: None,
             url: format!(
                 "{}/{}",
                 ctx.site_url,
-                ctx.relative_path.clone().with_extension("html").display()
+                ctx.relative_path.with_extension(extension).display()
             ),
-            tags: if let Some(tags) = parsed.metadata.get("tags") {
-                tags.split(if tags.contains(",") {
-                    |c: char| c == ','
-                } else {
-                    |c: char| c.is_whitespace()
-                })
-                .map(|tag| tag.trim().to_owned())
-                .collect()
-            } else {
-                vec![]
-            },
         })
     }
 }
 
-#[derive(Clone)]
-pub struct CopyHandler {}
+/// Renders AsciiDoc with an external converter, `asciidoctor` by default,
+/// reading metadata from the document header.
+#[derive(Clone, Default)]
+pub struct AsciiDocHandler {}
 
-impl FileHandler for CopyHandler {
-    fn new() -> Self {
-        Self {}
+impl FileHandler for AsciiDocHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        let config = ctx.handler_config.asciidoc.clone().unwrap_or_default();
+        let metadata = crate::asciidoc::parse_header(&std::fs::read_to_string(&ctx.source_path)?);
+
+        convert(
+            &ctx,
+            "AsciiDoc",
+            config.command.as_deref().unwrap_or("asciidoctor"),
+            &config.args.unwrap_or_else(|| {
+                vec!["--embedded".into(), "--out-file".into(), "-".into()]
+            }),
+            &metadata,
+        )
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        let metadata = crate::asciidoc::parse_header(&std::fs::read_to_string(&ctx.source_path)?);
+
+        article_metadata(&ctx, &metadata, None, vec![], None, HashMap::new())
+    }
+}
+
+/// Renders reStructuredText with an external converter, `pandoc` by default,
+/// reading metadata from the title, docinfo fields and `.. meta::` directives.
+#[derive(Clone, Default)]
+pub struct RstHandler {}
+
+impl FileHandler for RstHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        let config = ctx.handler_config.rst.clone().unwrap_or_default();
+        let metadata = crate::rst::parse_header(&std::fs::read_to_string(&ctx.source_path)?);
+
+        convert(
+            &ctx,
+            "reStructuredText",
+            config.command.as_deref().unwrap_or("pandoc"),
+            &config.args.unwrap_or_else(|| {
+                vec!["--from".into(), "rst".into(), "--to".into(), "html".into()]
+            }),
+            &metadata,
+        )
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        let metadata = crate::rst::parse_header(&std::fs::read_to_string(&ctx.source_path)?);
+
+        article_metadata(&ctx, &metadata, None, vec![], None, HashMap::new())
     }
+}
 
+/// Renders CSV files as a sortable table, publishing the CSV next to it.
+#[derive(Clone, Default)]
+pub struct CsvHandler {}
+
+impl FileHandler for CsvHandler {
     fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
-        if !file_changed(&ctx.source_path, &ctx.output_path)? {
+        let html_file = ctx.output_path.with_extension("html");
+
+        if ctx.outdated(&ctx.output_path)? {
+            ctx.writeable(&ctx.output_path)?
+                .write_all(std::fs::read(&ctx.source_path)?.as_slice())?;
+        }
+
+        if !ctx.outdated(&html_file)? {
             return Ok(());
         }
 
-        log::warn!(
-            "File {:?} not recognized. Copying as-is...",
+        log::info!(
+            phase = "parse",
+            file:% = ctx.relative_path.display();
+            "Parsing CSV file {:?}",
             ctx.source_path
         );
 
-        writeable(&ctx.output_path)?.write_all(std::fs::read(ctx.source_path)?.as_slice())?;
+        let config = ctx.handler_config.csv.clone().unwrap_or_default();
+        let rows = crate::csv::parse(
+            &std::fs::read_to_string(&ctx.source_path)?,
+            config.delimiter.unwrap_or(','),
+        );
+        let out = ctx.templates.render(
+            "root.html",
+            &ctx.source_path,
+            &crate::csv::render(
+                &rows,
+                config.header.unwrap_or(true),
+                &ctx.source_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+            ),
+            Some(
+                [(
+                    "title",
+                    ctx.source_path
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string(),
+                )]
+                .into(),
+            ),
+        )?;
+
+        ctx.writeable(&html_file)?.write_all(out.as_bytes())?;
 
         Ok(())
     }
 
     fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
-        if let Some(ext) = ctx.source_path.extension() {
-            match ext.to_str().unwrap() {
-                "png" | "jpg" | "jpeg" | "webm" | "gif" => Ok(Metadata::Image {
-                    url: format!("{}/{}", ctx.site_url, ctx.relative_path.display()),
-                }),
-                _ => Err(anyhow::anyhow!("File type not extractable to metadata.")),
-            }
-        } else {
-            Err(anyhow::anyhow!("File has no extension. /shrug"))
+        article_metadata(&ctx, &HashMap::new(), None, vec![], None, HashMap::new())
+    }
+}
+
+/// Renders Jupyter notebooks' Markdown, code and outputs as an article.
+#[derive(Clone, Default)]
+pub struct IpynbHandler {}
+
+impl FileHandler for IpynbHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        let html_file = ctx.output_path.with_extension("html");
+
+        if !ctx.outdated(&html_file)? {
+            return Ok(());
         }
+
+        log::info!(
+            phase = "parse",
+            file:% = ctx.relative_path.display();
+            "Parsing notebook {:?}",
+            ctx.source_path
+        );
+
+        let (metadata, body) = crate::ipynb::render(&std::fs::read_to_string(&ctx.source_path)?)?;
+        let out = ctx.templates.render(
+            "root.html",
+            &ctx.source_path,
+            &body,
+            Some(
+                metadata
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.to_owned()))
+                    .collect(),
+            ),
+        )?;
+
+        ctx.writeable(&html_file)?.write_all(out.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        let (metadata, _) = crate::ipynb::render(&std::fs::read_to_string(&ctx.source_path)?)?;
+
+        article_metadata(&ctx, &metadata, None, vec![], None, HashMap::new())
+    }
+}
+
+/// Renders `.html` fragments into `root.html` if they start with
+/// [`crate::fragment::MARKER`] or match `handlers.html.wrap`, and copies other HTML as is.
+#[derive(Clone, Default)]
+pub struct HtmlHandler {}
+
+impl HtmlHandler {
+    /// The fragment to render into `root.html`, if the file is one. HTML that isn't UTF-8, like
+    /// old Latin-1 pages, is never a fragment.
+    fn fragment(ctx: &FileContext) -> anyhow::Result<Option<String>> {
+        let Ok(content) = String::from_utf8(std::fs::read(&ctx.source_path)?) else {
+            return Ok(None);
+        };
+
+        if let Some(fragment) = crate::fragment::strip_marker(&content) {
+            return Ok(Some(fragment.to_owned()));
+        }
+
+        let globs = ctx
+            .handler_config
+            .html
+            .as_ref()
+            .and_then(|html| html.wrap.clone())
+            .unwrap_or_default();
+
+        Ok(crate::fragment::matches(&globs, &ctx.relative_path)?.then_some(content))
+    }
+}
+
+impl FileHandler for HtmlHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        let Some(fragment) = Self::fragment(&ctx)? else {
+            return CopyHandler::default().handle_file(ctx);
+        };
+
+        if !ctx.outdated(&ctx.output_path)? {
+            return Ok(());
+        }
+
+        log::info!(
+            phase = "parse",
+            file:% = ctx.relative_path.display();
+            "Wrapping HTML fragment {:?}",
+            ctx.source_path
+        );
+
+        let metadata = crate::fragment::metadata(&fragment);
+        let out = ctx.templates.render(
+            "root.html",
+            &ctx.source_path,
+            &fragment,
+            Some(
+                metadata
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.to_owned()))
+                    .collect(),
+            ),
+        )?;
+
+        ctx.writeable(&ctx.output_path)?.write_all(out.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        let Some(fragment) = Self::fragment(&ctx)? else {
+            return Err(anyhow::anyhow!("Only wrapped HTML is extractable to metadata."));
+        };
+
+        article_metadata(
+            &ctx,
+            &crate::fragment::metadata(&fragment),
+            None,
+            vec![],
+            None,
+            HashMap::new(),
+        )
     }
 }
@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! A starter site for `impertio init`: a config, a page template and a home page that build
+//! as they are.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+const CONFIG: &str = "# The URL the site is served at, without a trailing slash.
+site_url: {site_url}
+# Used to sort listings.
+language: en
+";
+
+const ROOT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{{ title | default(value="Untitled") }}</title>
+    {% if desc is defined %}<meta name="description" content="{{ desc }}">{% endif %}
+  </head>
+  <body{% if profile == "dev" %} class="dev"{% endif %}>
+    <main>
+      {% if title is defined %}<h1>{{ title }}</h1>{% endif %}
+      {{ content }}
+    </main>
+  </body>
+</html>
+"#;
+
+const INDEX: &str = "#+TITLE: Welcome
+#+DESC: A site built with Impertio.
+
+This page is =index.org=. Edit it, or add more Org files next to it, and see them built with:
+
+#+BEGIN_SRC sh
+impertio serve . -d ../public
+#+END_SRC
+
+Every page is rendered with =root.html=, and =impertio.yaml= configures the site.
+";
+
+/// Writes a starter `impertio.yaml`, `root.html` and `index.org` into `dir`, creating it if
+/// needed, and returns their paths. Fails without writing anything if any of them exists.
+pub fn init(dir: &Path, site_url: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let files = [
+        (
+            dir.join("impertio.yaml"),
+            CONFIG.replace("{site_url}", site_url.trim_end_matches('/')),
+        ),
+        (dir.join("root.html"), ROOT_TEMPLATE.to_owned()),
+        (dir.join("index.org"), INDEX.to_owned()),
+    ];
+
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        anyhow::bail!("{:?} already exists", path);
+    }
+
+    for (path, content) in &files {
+        crate::handler::writeable(path)?.write_all(content.as_bytes())?;
+    }
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{config::Config, files::FileDispatcher, init::init};
+
+    #[test]
+    fn starter_site() {
+        let dir = std::env::temp_dir().join(format!("impertio-init-{}", std::process::id()));
+        let source = dir.join("site");
+        let dest = dir.join("public");
+
+        let created = init(&source, "https://example.com/").unwrap();
+        let config = Config::load(
+            &std::fs::read_to_string(source.join("impertio.yaml")).unwrap(),
+            "prod",
+        )
+        .unwrap();
+
+        assert_eq!(created.len(), 3);
+        assert_eq!(config.site_url, "https://example.com");
+        assert!(init(&source, "https://example.com").is_err());
+
+        let source = source.to_string_lossy().into_owned();
+
+        FileDispatcher::new(&source, config)
+            .handle_files(dest.to_string_lossy().into_owned(), source)
+            .unwrap();
+
+        let page = std::fs::read_to_string(dest.join("index.html")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(page.contains("<title>Welcome</title>"));
+        assert!(
+            page.contains("<meta name=\"description\" content=\"A site built with Impertio.\">")
+        );
+    }
+}
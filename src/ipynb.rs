@@ -0,0 +1,320 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Jupyter notebooks as articles: Markdown cells, code cells and their text and image outputs.
+
+use std::collections::HashMap;
+
+use build_html::escape_html;
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+use serde_json::Value;
+
+lazy_static! {
+    static ref IMAGE: Regex = Regex::new(r"!\[(?<alt>[^\]]*)\]\((?<url>[^)\s]+)\)").unwrap();
+    static ref LINK: Regex = Regex::new(r"\[(?<text>[^\]]+)\]\((?<url>[^)\s]+)\)").unwrap();
+    static ref BOLD: Regex =
+        Regex::new(r"\*\*(?<text>.+?)\*\*|(?<!\w)__(?<under>.+?)__(?!\w)").unwrap();
+    static ref ITALIC: Regex =
+        Regex::new(r"\*(?<text>[^*\s][^*]*?)\*|(?<!\w)_(?<under>[^_\s][^_]*?)_(?!\w)").unwrap();
+    static ref HEADING: Regex = Regex::new(r"^(?<level>#{1,6})\s+(?<title>.+?)\s*#*\s*$").unwrap();
+    static ref LIST_ITEM: Regex =
+        Regex::new(r"^\s*(?:(?<bullet>[-*+])|(?<number>\d+)[.)])\s+(?<text>.*)$").unwrap();
+    static ref RULE: Regex =
+        Regex::new(r"^\s*(?:(?:-\s*){3,}|(?:\*\s*){3,}|(?:_\s*){3,})$").unwrap();
+}
+
+/// Markdown emphasis, links, images and code spans in `text`, which is escaped first.
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            // Odd parts are between backticks.
+            if i % 2 == 1 {
+                return format!("<code>{}</code>", escape_html(part));
+            }
+
+            let part = escape_html(part);
+            let part = IMAGE.replace_all(&part, |caps: &Captures| {
+                format!("<img src=\"{}\" alt=\"{}\" />", &caps["url"], &caps["alt"])
+            });
+            let part = LINK.replace_all(&part, |caps: &Captures| {
+                format!("<a href=\"{}\">{}</a>", &caps["url"], &caps["text"])
+            });
+            let part = BOLD.replace_all(&part, |caps: &Captures| {
+                format!(
+                    "<strong>{}</strong>",
+                    caps.name("text").or(caps.name("under")).unwrap().as_str()
+                )
+            });
+
+            ITALIC
+                .replace_all(&part, |caps: &Captures| {
+                    format!(
+                        "<em>{}</em>",
+                        caps.name("text").or(caps.name("under")).unwrap().as_str()
+                    )
+                })
+                .into_owned()
+        })
+        .collect()
+}
+
+/// The common subset of Markdown found in notebooks: ATX headings, paragraphs, lists,
+/// blockquotes, fenced code, rules and inline markup. Raw HTML is escaped.
+pub fn markdown(text: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = vec![];
+    // The list being built, if any: whether it's ordered, and its items.
+    let mut list: Option<(bool, Vec<String>)> = None;
+    let mut lines = text.lines();
+
+    fn flush(out: &mut String, paragraph: &mut Vec<&str>, list: &mut Option<(bool, Vec<String>)>) {
+        if !paragraph.is_empty() {
+            *out += &format!("<p>{}</p>", inline(&paragraph.join(" ")));
+            paragraph.clear();
+        }
+
+        if let Some((ordered, items)) = list.take() {
+            let tag = if ordered { "ol" } else { "ul" };
+
+            *out += &format!(
+                "<{0}>{1}</{0}>",
+                tag,
+                items
+                    .iter()
+                    .map(|item| format!("<li>{}</li>", inline(item)))
+                    .collect::<String>()
+            );
+        }
+    }
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some(language) = trimmed.strip_prefix("```") {
+            flush(&mut out, &mut paragraph, &mut list);
+
+            let code: Vec<&str> = lines
+                .by_ref()
+                .take_while(|line| !line.trim().starts_with("```"))
+                .collect();
+
+            out += &match language.trim() {
+                "" => "<pre><code>".to_owned(),
+                language => format!("<pre><code class=\"language-{}\">", escape_html(language)),
+            };
+            out += &escape_html(&code.join("\n"));
+            out += "</code></pre>";
+        } else if trimmed.is_empty() {
+            flush(&mut out, &mut paragraph, &mut list);
+        } else if let Ok(Some(caps)) = HEADING.captures(trimmed) {
+            flush(&mut out, &mut paragraph, &mut list);
+
+            let level = caps["level"].len();
+
+            out += &format!("<h{0}>{1}</h{0}>", level, inline(&caps["title"]));
+        } else if RULE.is_match(trimmed).unwrap_or(false) {
+            flush(&mut out, &mut paragraph, &mut list);
+            out += "<hr />";
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush(&mut out, &mut paragraph, &mut list);
+            out += &format!("<blockquote><p>{}</p></blockquote>", inline(quote.trim()));
+        } else if let Ok(Some(caps)) = LIST_ITEM.captures(line) {
+            let ordered = caps.name("number").is_some();
+
+            if !paragraph.is_empty() || list.as_ref().is_some_and(|(o, _)| *o != ordered) {
+                flush(&mut out, &mut paragraph, &mut list);
+            }
+
+            list.get_or_insert((ordered, vec![]))
+                .1
+                .push(caps["text"].to_owned());
+        } else if let Some((_, items)) = list.as_mut().filter(|_| line.starts_with(' ')) {
+            // A continuation of the last item.
+            if let Some(item) = items.last_mut() {
+                *item += &format!(" {}", trimmed);
+            }
+        } else {
+            if list.is_some() {
+                flush(&mut out, &mut paragraph, &mut list);
+            }
+
+            paragraph.push(trimmed);
+        }
+    }
+
+    flush(&mut out, &mut paragraph, &mut list);
+
+    out
+}
+
+/// A cell's `source` or an output's `text`, which may be split into lines.
+fn text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// A code cell's output, preferring images, then HTML, then plain text.
+fn output(output: &Value) -> String {
+    match output.get("output_type").and_then(Value::as_str) {
+        Some("stream") => {
+            let stderr = output.get("name").and_then(Value::as_str) == Some("stderr");
+
+            format!(
+                "<pre class=\"output{}\">{}</pre>",
+                if stderr { " stderr" } else { "" },
+                escape_html(text(output.get("text")).trim_end())
+            )
+        }
+        Some("execute_result" | "display_data") => {
+            let Some(data) = output.get("data") else {
+                return String::new();
+            };
+
+            for mime in ["image/png", "image/jpeg", "image/gif"] {
+                if data.get(mime).is_some() {
+                    let image: String = text(data.get(mime)).split_whitespace().collect();
+
+                    return format!(
+                        "<div class=\"output\"><img src=\"data:{};base64,{}\" alt=\"Output\" /></div>",
+                        mime, image
+                    );
+                }
+            }
+
+            for mime in ["image/svg+xml", "text/html"] {
+                if data.get(mime).is_some() {
+                    return format!("<div class=\"output\">{}</div>", text(data.get(mime)));
+                }
+            }
+
+            format!(
+                "<pre class=\"output\">{}</pre>",
+                escape_html(text(data.get("text/plain")).trim_end())
+            )
+        }
+        Some("error") => format!(
+            "<pre class=\"output error\">{}: {}</pre>",
+            escape_html(
+                output
+                    .get("ename")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Error")
+            ),
+            escape_html(output.get("evalue").and_then(Value::as_str).unwrap_or(""))
+        ),
+        _ => String::new(),
+    }
+}
+
+/// The notebook as an article, and its metadata under the keys Org keywords use. The title is
+/// the notebook's `title` metadata, or else its first heading.
+pub fn render(notebook: &str) -> anyhow::Result<(HashMap<String, String>, String)> {
+    let notebook: Value = serde_json::from_str(notebook)?;
+    let mut metadata: HashMap<String, String> = HashMap::new();
+    let language = notebook
+        .pointer("/metadata/kernelspec/language")
+        .or(notebook.pointer("/metadata/language_info/name"))
+        .and_then(Value::as_str);
+    let mut html = String::new();
+
+    if let Some(title) = notebook.pointer("/metadata/title").and_then(Value::as_str) {
+        metadata.insert("title".into(), title.to_owned());
+    }
+
+    if let Some(author) = notebook
+        .pointer("/metadata/authors/0/name")
+        .and_then(Value::as_str)
+    {
+        metadata.insert("author".into(), author.to_owned());
+    }
+
+    let cells = notebook
+        .get("cells")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow::anyhow!("Notebook has no cells."))?;
+
+    for cell in cells {
+        let source = text(cell.get("source"));
+
+        match cell.get("cell_type").and_then(Value::as_str) {
+            Some("markdown") => {
+                if !metadata.contains_key("title") {
+                    if let Some(title) = source
+                        .lines()
+                        .find_map(|line| HEADING.captures(line.trim()).ok().flatten())
+                    {
+                        metadata.insert("title".into(), title["title"].to_owned());
+                    }
+                }
+
+                html += &markdown(&source);
+            }
+            Some("code") => {
+                html += &match language {
+                    Some(language) => {
+                        format!("<pre><code class=\"language-{}\">", escape_html(language))
+                    }
+                    None => "<pre><code>".to_owned(),
+                };
+                html += &escape_html(&source);
+                html += "</code></pre>";
+
+                for out in cell
+                    .get("outputs")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    html += &output(out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((metadata, format!("<div class=\"article\">{}</div>", html)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ipynb::{markdown, render};
+
+    #[test]
+    fn markdown_subset() {
+        assert_eq!(
+            markdown("# A *b* <c>\n\nSome **bold** and `x < y`\ntext, [a link](https://e.x).\n\n- one\n- two\n  more\n1. first\n\n```python\nprint(1)\n```\n---"),
+            "<h1>A <em>b</em> &lt;c&gt;</h1><p>Some <strong>bold</strong> and <code>x &lt; y</code> text, <a href=\"https://e.x\">a link</a>.</p><ul><li>one</li><li>two more</li></ul><ol><li>first</li></ol><pre><code class=\"language-python\">print(1)</code></pre><hr />"
+        );
+        assert_eq!(markdown("snake_case_name"), "<p>snake_case_name</p>");
+    }
+
+    #[test]
+    fn notebook() {
+        let (metadata, html) = render(
+            r##"{
+  "metadata": {"kernelspec": {"language": "python"}},
+  "cells": [
+    {"cell_type": "markdown", "source": ["# Analysis\n", "Intro."]},
+    {"cell_type": "code", "source": "print('hi')\n1 + 1", "outputs": [
+      {"output_type": "stream", "name": "stdout", "text": ["hi\n"]},
+      {"output_type": "execute_result", "data": {"text/plain": ["2"]}},
+      {"output_type": "display_data", "data": {"image/png": "iVBOR\nw0=", "text/plain": "<Figure>"}}
+    ]},
+    {"cell_type": "raw", "source": "ignored"}
+  ]
+}"##,
+        )
+        .unwrap();
+
+        assert_eq!(metadata["title"], "Analysis");
+        assert_eq!(
+            html,
+            "<div class=\"article\"><h1>Analysis</h1><p>Intro.</p><pre><code class=\"language-python\">print(&#39;hi&#39;)\n1 + 1</code></pre><pre class=\"output\">hi</pre><pre class=\"output\">2</pre><div class=\"output\"><img src=\"data:image/png;base64,iVBORw0=\" alt=\"Output\" /></div></div>"
+        );
+    }
+}
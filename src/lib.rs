@@ -4,6 +4,40 @@
 pub mod files;
 pub mod org;
 pub mod template;
+pub mod golden;
 pub mod handler;
 pub mod metadata;
-pub mod config;
\ No newline at end of file
+pub mod collation;
+pub mod config;
+pub mod asciidoc;
+pub mod rst;
+pub mod logging;
+pub mod manifest;
+pub mod notify;
+pub mod plugin;
+pub mod sniff;
+pub mod well_known;
+pub mod downloads;
+pub mod checksums;
+pub mod minify;
+pub mod csv;
+pub mod ipynb;
+pub mod fragment;
+pub mod exif;
+pub mod gallery;
+pub mod lint;
+pub mod absolute;
+pub mod removed;
+pub mod graph;
+pub mod cache;
+pub mod versions;
+pub mod budget;
+pub mod embeds;
+pub mod watch;
+pub mod serve;
+pub mod changelog;
+pub mod data;
+pub mod archetype;
+pub mod taxonomy;
+pub mod init;
+pub mod license;
@@ -0,0 +1,189 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Articles' licenses, from `#+LICENSE` or the site's `license`: their URLs, for templates and
+//! the `creativeCommons` RSS module, and a report of which articles are under which.
+
+use std::{collections::BTreeMap, path::Path};
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+use rss::extension::{Extension, ExtensionMap};
+use serde::Serialize;
+
+use crate::metadata::Metadata;
+
+lazy_static! {
+    static ref CREATIVE_COMMONS: Regex =
+        Regex::new(r"(?i)^CC[- ]?(?<kind>BY(?:[- ](?:NC|SA|ND))*)(?:[- ](?<version>\d\.\d))?$")
+            .unwrap();
+    static ref CC0: Regex = Regex::new(r"(?i)^CC[- ]?0(?:[- ]1\.0)?$").unwrap();
+}
+
+/// Where the report is written, relative to the destination.
+pub const FILE: &str = "licenses.json";
+
+/// The namespace of the `creativeCommons` RSS module.
+pub const NAMESPACE: &str = "http://backend.userland.com/creativeCommonsRssModule";
+
+/// The URL of `license`: itself if it's one, the deed of a Creative Commons license like
+/// `CC-BY-SA-4.0` or `CC BY 3.0` (`4.0` if it has no version) or `CC0`, else `None`.
+pub fn url(license: &str) -> Option<String> {
+    let license = license.trim();
+
+    if license.starts_with("https://") || license.starts_with("http://") {
+        return Some(license.to_owned());
+    }
+
+    if CC0.is_match(license).unwrap_or(false) {
+        return Some("https://creativecommons.org/publicdomain/zero/1.0/".into());
+    }
+
+    let caps = CREATIVE_COMMONS.captures(license).ok()??;
+
+    Some(format!(
+        "https://creativecommons.org/licenses/{}/{}/",
+        caps["kind"].to_ascii_lowercase().replace(' ', "-"),
+        caps.name("version")
+            .map_or("4.0", |version| version.as_str())
+    ))
+}
+
+/// The `creativeCommons:license` of an item or channel under `license`, if it has a URL.
+pub fn extension(license: Option<&str>) -> ExtensionMap {
+    let Some(url) = license.and_then(url) else {
+        return ExtensionMap::default();
+    };
+
+    BTreeMap::from([(
+        "creativeCommons".into(),
+        BTreeMap::from([(
+            "license".into(),
+            vec![Extension {
+                name: "creativeCommons:license".into(),
+                value: Some(url),
+                ..Default::default()
+            }],
+        )]),
+    )])
+}
+
+/// Declares the `creativeCommons` namespace in `channel` if it or its items use it.
+pub fn add_namespace(channel: &mut rss::Channel) {
+    if channel.extensions.contains_key("creativeCommons")
+        || channel
+            .items
+            .iter()
+            .any(|item| item.extensions.contains_key("creativeCommons"))
+    {
+        channel
+            .namespaces
+            .insert("creativeCommons".into(), NAMESPACE.into());
+    }
+}
+
+/// The URLs of the articles under each license, and of those without one.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Report {
+    pub licenses: BTreeMap<String, LicensedArticles>,
+    pub unlicensed: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct LicensedArticles {
+    pub url: Option<String>,
+    pub articles: Vec<String>,
+}
+
+impl Report {
+    pub fn new(metadata: &[Metadata]) -> Self {
+        let mut report = Self::default();
+
+        for meta in metadata {
+            let Metadata::Article { url, license, .. } = meta else {
+                continue;
+            };
+
+            match license {
+                Some(license) => report
+                    .licenses
+                    .entry(license.clone())
+                    .or_insert_with(|| LicensedArticles {
+                        url: self::url(license),
+                        articles: vec![],
+                    })
+                    .articles
+                    .push(url.clone()),
+                None => report.unlicensed.push(url.clone()),
+            }
+        }
+
+        for licensed in report.licenses.values_mut() {
+            licensed.articles.sort();
+        }
+
+        report.unlicensed.sort();
+
+        report
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(dir.join(FILE), serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        license::{extension, url, Report},
+        metadata::Metadata,
+    };
+
+    #[test]
+    fn licenses() {
+        assert_eq!(
+            url("CC-BY-SA-4.0").as_deref(),
+            Some("https://creativecommons.org/licenses/by-sa/4.0/")
+        );
+        assert_eq!(
+            url("cc by-nc-nd 3.0").as_deref(),
+            Some("https://creativecommons.org/licenses/by-nc-nd/3.0/")
+        );
+        assert_eq!(
+            url("CC BY").as_deref(),
+            Some("https://creativecommons.org/licenses/by/4.0/")
+        );
+        assert_eq!(
+            url("CC0-1.0").as_deref(),
+            Some("https://creativecommons.org/publicdomain/zero/1.0/")
+        );
+        assert_eq!(
+            url(" https://x.y/license ").as_deref(),
+            Some("https://x.y/license")
+        );
+        assert_eq!(url("All rights reserved"), None);
+        assert!(extension(Some("All rights reserved")).is_empty());
+        assert_eq!(
+            extension(Some("CC-BY-4.0"))["creativeCommons"]["license"][0].value(),
+            Some("https://creativecommons.org/licenses/by/4.0/")
+        );
+
+        let article =
+            |url: &str, license: Option<&str>| Metadata::article(url).with_license(license);
+        let report = Report::new(&[
+            article("https://x.y/c.html", Some("CC-BY-4.0")),
+            article("https://x.y/a.html", Some("CC-BY-4.0")),
+            article("https://x.y/b.html", Some("All rights reserved")),
+            article("https://x.y/d.html", None),
+        ]);
+
+        assert_eq!(
+            report.licenses["CC-BY-4.0"].articles,
+            ["https://x.y/a.html", "https://x.y/c.html"]
+        );
+        assert_eq!(report.licenses["All rights reserved"].url, None);
+        assert_eq!(report.unlicensed, ["https://x.y/d.html"]);
+    }
+}
@@ -0,0 +1,289 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! External checkers, like spell or terminology checkers, run over the prose of Org pages.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+use crate::config::LintConfig;
+
+lazy_static! {
+    static ref FINDING: Regex =
+        Regex::new(r"^(?:[^:]*?:)??(?<line>\d+)(?::(?<column>\d+))?:\s*(?<message>.+)$").unwrap();
+    static ref OPAQUE_BLOCK: Regex =
+        Regex::new(r"(?i)^\s*#\+begin_(src|example|export|comment)\b").unwrap();
+    static ref BLOCK_END: Regex = Regex::new(r"(?i)^\s*#\+end_").unwrap();
+    static ref PROSE_KEYWORD: Regex =
+        Regex::new(r"(?i)^\s*#\+(title|subtitle|description|desc|caption):\s*").unwrap();
+    static ref DRAWER: Regex = Regex::new(r"^\s*:[\w-]+:\s*$").unwrap();
+    static ref DRAWER_END: Regex = Regex::new(r"(?i)^\s*:end:\s*$").unwrap();
+    static ref HIDDEN_LINE: Regex =
+        Regex::new(r"^\s*(#(\+|\s|$)|:(\s|$)|(CLOSED|SCHEDULED|DEADLINE):|\|[-+|\s]*$)").unwrap();
+    static ref HEADLINE: Regex =
+        Regex::new(r"^\*+\s+(?:(?:TODO|DONE)\s+)?(?:\[#.\]\s+)?").unwrap();
+    static ref TAGS: Regex = Regex::new(r"\s+:[\w@#%:]+:\s*$").unwrap();
+    static ref BULLET: Regex =
+        Regex::new(r"^\s*(?:[-+]|\s\*|\d+[.)])\s+(?:\[[ X-]\]\s+)?").unwrap();
+    static ref LINK: Regex =
+        Regex::new(r"\[\[(?<target>[^\]\[]+)\](?:\[(?<description>[^\]\[]+)\])?\]").unwrap();
+    static ref HIDDEN: Regex =
+        Regex::new(r#"\{\{\{.*?\}\}\}|\[(?:fn|cite)[:/][^\]]*\]|(?<=^|[\s(])([=~])\S(?:.*?\S)?\1(?=$|[\s.,;:!?)'"])"#)
+            .unwrap();
+    static ref EMPHASIS: Regex =
+        Regex::new(r#"(?<=^|[\s("'])(?<marker>[*/_+])(?<text>\S(?:.*?\S)?)\k<marker>(?=$|[\s.,;:!?)'"])"#).unwrap();
+}
+
+/// Numbers the files checkers read, so concurrent builds don't share them.
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Something a checker found, on a line of the page and maybe at a column of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+fn blank(text: &str) -> String {
+    " ".repeat(text.chars().count())
+}
+
+/// Blanks out the markup of a line's inline text, keeping its prose where it was.
+fn inline(line: &str) -> String {
+    let line = LINK.replace_all(line, |caps: &Captures| match caps.name("description") {
+        Some(description) => format!(
+            "{}{}  ",
+            blank(&caps[0][..description.start() - caps.get(0).unwrap().start()]),
+            description.as_str()
+        ),
+        None => blank(&caps[0]),
+    });
+    let line = HIDDEN.replace_all(&line, |caps: &Captures| blank(&caps[0]));
+
+    EMPHASIS
+        .replace_all(&line, |caps: &Captures| format!(" {} ", &caps["text"]))
+        .into_owned()
+}
+
+/// The prose of an Org document, line for line and column for column: markup, code, keywords,
+/// drawers and tags are replaced by spaces or blank lines, so checkers' locations are the
+/// source's.
+pub fn plain_text(org: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut in_block = false;
+    let mut in_drawer = false;
+
+    for line in org.lines() {
+        let text = if in_block {
+            in_block = !BLOCK_END.is_match(line).unwrap_or(false);
+            String::new()
+        } else if in_drawer {
+            in_drawer = !DRAWER_END.is_match(line).unwrap_or(false);
+            String::new()
+        } else if OPAQUE_BLOCK.is_match(line).unwrap_or(false) {
+            in_block = true;
+            String::new()
+        } else if let Ok(Some(keyword)) = PROSE_KEYWORD.find(line) {
+            blank(keyword.as_str()) + &inline(&line[keyword.end()..])
+        } else if DRAWER.is_match(line).unwrap_or(false) {
+            in_drawer = true;
+            String::new()
+        } else if HIDDEN_LINE.is_match(line).unwrap_or(false) {
+            String::new()
+        } else if let Ok(Some(stars)) = HEADLINE.find(line) {
+            let title = TAGS.replace(&line[stars.end()..], "");
+
+            blank(stars.as_str()) + &inline(&title)
+        } else if line.trim_start().starts_with('|') {
+            inline(&line.replace('|', " "))
+        } else if let Ok(Some(bullet)) = BULLET.find(line) {
+            blank(bullet.as_str()) + &inline(&line[bullet.end()..])
+        } else {
+            inline(line)
+        };
+
+        lines.push(text.trim_end().to_owned());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Findings in a checker's output, one per line matching `pattern`, or `line:column: message`
+/// after an optional path by default. Other lines are ignored.
+pub fn findings(output: &str, pattern: Option<&str>) -> anyhow::Result<Vec<Finding>> {
+    let custom = pattern.map(Regex::new).transpose()?;
+    let pattern = custom.as_ref().unwrap_or(&FINDING);
+
+    Ok(output
+        .lines()
+        .filter_map(|line| pattern.captures(line).ok().flatten())
+        .filter_map(|caps| {
+            Some(Finding {
+                line: caps.name("line")?.as_str().parse().ok()?,
+                column: caps
+                    .name("column")
+                    .and_then(|column| column.as_str().parse().ok()),
+                message: caps.name("message")?.as_str().trim().to_owned(),
+            })
+        })
+        .collect())
+}
+
+/// Runs the checker over `text`: written to a `.txt` file whose path replaces `{file}` in its
+/// arguments, or piped to its standard input if none has `{file}`. Checkers often exit with an
+/// error when they find something, so only failing to run them is an error.
+pub fn check(config: &LintConfig, text: &str) -> anyhow::Result<Vec<Finding>> {
+    let args = config.args.clone().unwrap_or_default();
+    let file = args.iter().any(|arg| arg.contains("{file}")).then(|| {
+        std::env::temp_dir().join(format!(
+            "impertio-lint-{}-{}.txt",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ))
+    });
+    let mut command = Command::new(&config.command);
+
+    if let Some(file) = &file {
+        std::fs::write(file, text)?;
+        command.args(
+            args.iter()
+                .map(|arg| arg.replace("{file}", &file.display().to_string())),
+        );
+    } else {
+        command.args(&args).stdin(Stdio::piped());
+    }
+
+    let output = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes())?;
+            }
+
+            child.wait_with_output()
+        });
+
+    if let Some(file) = &file {
+        let _ = std::fs::remove_file(file);
+    }
+
+    let output =
+        output.map_err(|err| anyhow::anyhow!("Failed to run `{}`: {}", config.command, err))?;
+
+    findings(
+        &String::from_utf8_lossy(&output.stdout),
+        config.pattern.as_deref(),
+    )
+}
+
+/// Runs every checker over the prose of the Org source at `relative`, logging their findings
+/// with its locations. Returns the errors of those that failed to run.
+pub fn lint(configs: &[LintConfig], relative: &Path, org: &str) -> Vec<anyhow::Error> {
+    let text = plain_text(org);
+    let mut failed = vec![];
+
+    for config in configs {
+        let findings = match check(config, &text) {
+            Ok(findings) => findings,
+            Err(err) => {
+                failed.push(err);
+                continue;
+            }
+        };
+
+        for finding in findings {
+            let location = match finding.column {
+                Some(column) => format!("{}:{}:{}", relative.display(), finding.line, column),
+                None => format!("{}:{}", relative.display(), finding.line),
+            };
+
+            log::warn!(
+                phase = "lint",
+                file:% = relative.display();
+                "{}: {}",
+                location,
+                finding.message
+            );
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lint::{findings, plain_text, Finding};
+
+    #[test]
+    fn prose() {
+        let org = r#"#+TITLE: The *Rust* book
+#+OPTIONS: toc:nil
+* TODO [#A] Setting up [[https://rustup.rs][rustup]] :setup:
+:PROPERTIES:
+:ID: 1
+:END:
+Run ~rustup update~ for /the latest/ {{{version}}}.[fn:1]
+#+BEGIN_SRC sh
+rustup update
+#+END_SRC
+- [X] Check [[https://x.y]] twice
+| Name | Use |
+|------+-----|
+"#;
+
+        assert_eq!(
+            plain_text(org),
+            "         The  Rust  book\n\n            Setting up                      rustup\n\n\n\nRun                 for  the latest               .\n\n\n\n      Check                 twice\n  Name   Use\n\n"
+        );
+        assert_eq!(plain_text(org).lines().count(), org.lines().count());
+        assert_eq!(
+            plain_text(org).lines().nth(2).unwrap().find("rustup"),
+            org.lines().nth(2).unwrap().find("rustup]]")
+        );
+    }
+
+    #[test]
+    fn output() {
+        let output =
+            "/tmp/a.txt:3:7: Did you mean \"Rust\"?\n12: Avoid \"utilize\".\nSummary: 2 problems\n";
+
+        assert_eq!(
+            findings(output, None).unwrap(),
+            [
+                Finding {
+                    line: 3,
+                    column: Some(7),
+                    message: "Did you mean \"Rust\"?".into()
+                },
+                Finding {
+                    line: 12,
+                    column: None,
+                    message: "Avoid \"utilize\".".into()
+                }
+            ]
+        );
+        assert_eq!(
+            findings(
+                "utilize (line 4): use \"use\"",
+                Some(r"\(line (?<line>\d+)\): (?<message>.+)")
+            )
+            .unwrap(),
+            [Finding {
+                line: 4,
+                column: None,
+                message: "use \"use\"".into()
+            }]
+        );
+        assert!(findings("", Some("(")).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::{collections::BTreeMap, io::Write};
+
+use clap::ValueEnum;
+use log::{
+    kv::{Key, Value, VisitSource},
+    Log, Metadata, Record,
+};
+use serde_json::{Map, Number};
+
+const LOG_ENV: &str = "IMPERTIO_LOG";
+const LOG_STYLE_ENV: &str = "IMPERTIO_LOG_STYLE";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines via env_logger.
+    #[default]
+    Text,
+    /// One JSON object per line, for log pipelines.
+    Json,
+}
+
+/// Install the global logger for the given format.
+/// Both formats honor `IMPERTIO_LOG` for filtering.
+pub fn init(format: LogFormat) {
+    match format {
+        LogFormat::Text => {
+            let log_environ = env_logger::Env::new()
+                .filter(LOG_ENV)
+                .write_style(LOG_STYLE_ENV);
+            let mut log_builder = env_logger::Builder::new();
+
+            log_builder.filter_level(log::LevelFilter::Info);
+            log_builder.parse_env(log_environ);
+            log_builder.init();
+        }
+        LogFormat::Json => {
+            let mut filter_builder = env_logger::filter::Builder::new();
+
+            filter_builder.filter_level(log::LevelFilter::Info);
+
+            if let Ok(filters) = std::env::var(LOG_ENV) {
+                filter_builder.parse(&filters);
+            }
+
+            let logger = JsonLogger {
+                filter: filter_builder.build(),
+            };
+
+            log::set_max_level(logger.filter.filter());
+            log::set_boxed_logger(Box::new(logger)).expect("Logger was already initialized.");
+        }
+    }
+}
+
+/// Emits every record as a JSON object on stderr.
+/// Key-values attached to a record (`phase`, `file`, `duration_ms`, ...) become top-level fields.
+pub struct JsonLogger {
+    filter: env_logger::filter::Filter,
+}
+
+impl JsonLogger {
+    fn to_json(record: &Record) -> serde_json::Value {
+        let mut fields = Map::new();
+
+        fields.insert("timestamp".into(), chrono::Utc::now().to_rfc3339().into());
+        fields.insert(
+            "level".into(),
+            record.level().as_str().to_lowercase().into(),
+        );
+        fields.insert("target".into(), record.target().into());
+        fields.insert("message".into(), record.args().to_string().into());
+
+        // A failing visitor only means some fields are missing, which isn't worth dropping the record over.
+        let _ = record.key_values().visit(&mut FieldVisitor(&mut fields));
+
+        serde_json::Value::Object(fields)
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        let _ = writeln!(std::io::stderr().lock(), "{}", Self::to_json(record));
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Repeated warnings grouped by category, summarized once at the end of a build.
+/// Each individual warning is only logged at debug level, along with the summary.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    categories: BTreeMap<String, Vec<String>>,
+}
+
+impl Warnings {
+    pub fn add(&mut self, category: &str, detail: impl Into<String>) {
+        self.categories
+            .entry(category.to_owned())
+            .or_default()
+            .push(detail.into());
+    }
+
+    /// Adds the warnings of `other`, like those of a single file.
+    pub fn merge(&mut self, other: Warnings) {
+        for (category, details) in other.categories {
+            self.categories.entry(category).or_default().extend(details);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.categories.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    pub fn details(&self, category: &str) -> &[String] {
+        self.categories.get(category).map_or(&[], Vec::as_slice)
+    }
+
+    /// Each category and its details, by category.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.categories
+            .iter()
+            .map(|(category, details)| (category.as_str(), details.as_slice()))
+    }
+
+    /// Log one warning per category.
+    pub fn summarize(&self) {
+        for (category, details) in &self.categories {
+            for detail in details {
+                log::debug!(category = category.as_str(); "{}", detail);
+            }
+
+            match details.as_slice() {
+                [detail] => {
+                    log::warn!(category = category.as_str(), count = 1; "{}: {}", category, detail)
+                }
+                _ => log::warn!(
+                    category = category.as_str(),
+                    count = details.len();
+                    "{}: {} occurrences (set IMPERTIO_LOG=debug for details)",
+                    category,
+                    details.len()
+                ),
+            }
+        }
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let value = if let Some(b) = value.to_bool() {
+            b.into()
+        } else if let Some(n) = value.to_u64() {
+            n.into()
+        } else if let Some(n) = value.to_i64() {
+            n.into()
+        } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+            serde_json::Value::Number(n)
+        } else {
+            value.to_string().into()
+        };
+
+        self.0.insert(key.as_str().to_owned(), value);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logging::{JsonLogger, Warnings};
+
+    #[test]
+    fn warnings_grouped() {
+        let mut warnings = Warnings::default();
+
+        warnings.add("Files not recognized", "a.txt");
+        warnings.add("Files not recognized", "b.txt");
+        warnings.add("Missing images", "cat.png");
+
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings.categories.len(), 2);
+        assert_eq!(
+            warnings.categories["Files not recognized"],
+            vec!["a.txt", "b.txt"]
+        );
+    }
+
+    #[test]
+    fn json_fields() {
+        let kvs: [(&str, log::kv::Value); 3] = [
+            ("phase", "render".into()),
+            ("file", "index.org".into()),
+            ("duration_ms", 12u64.into()),
+        ];
+
+        let json = JsonLogger::to_json(
+            &log::Record::builder()
+                .args(format_args!("Rendered"))
+                .level(log::Level::Warn)
+                .target("impertio")
+                .key_values(&kvs)
+                .build(),
+        );
+
+        assert_eq!(json["level"], "warn");
+        assert_eq!(json["message"], "Rendered");
+        assert_eq!(json["phase"], "render");
+        assert_eq!(json["file"], "index.org");
+        assert_eq!(json["duration_ms"], 12);
+    }
+}
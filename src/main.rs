@@ -1,45 +1,380 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
 
-use clap::Parser;
-use impertio::config::Config;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use impertio::{config::Config, golden::Outcome, graph::Graph, logging::LogFormat};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
-    #[arg(help = "The source directory.")]
-    source: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// `impertio SRC -d DEST` is short for `impertio build SRC -d DEST`.
+    #[command(flatten)]
+    build: BuildArgs,
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text, help = "The format of log output")]
+    log_format: LogFormat,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct BuildArgs {
+    #[arg(required = true, help = "The source directory.")]
+    source: Option<String>,
     #[arg(short, long, default_value = ".", help = "The destination directory")]
     dest: String,
+    #[arg(long, default_value = "prod", help = "The build profile: `dev`, `prod` or one under `profiles` in impertio.yaml")]
+    profile: String,
+    #[arg(long, help = "Delete files earlier builds wrote for sources that are gone")]
+    clean_orphans: bool,
+    #[arg(long, help = "Keep rebuilding whenever the source changes")]
+    watch: bool,
 }
 
-fn main() -> anyhow::Result<()>{
-    let log_environ = env_logger::Env::new()
-        .filter("IMPERTIO_LOG")
-        .write_style("IMPERTIO_LOG_STYLE");
-    let mut log_builder = env_logger::Builder::new();
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Build a site")]
+    Build(BuildArgs),
+    #[command(about = "Build a site, then rebuild it whenever the source changes")]
+    Watch(BuildArgs),
+    #[command(about = "Build and serve a site locally, rebuilding it when the source changes")]
+    Serve {
+        #[command(flatten)]
+        build: BuildArgs,
+        #[arg(long, default_value = "127.0.0.1:8000", help = "The address to serve at")]
+        addr: String,
+    },
+    #[command(about = "Delete what builds wrote for source files, and the build cache")]
+    Clean {
+        #[arg(short, long, default_value = ".", help = "The destination directory")]
+        dest: PathBuf,
+    },
+    #[command(about = "Create a starter site: impertio.yaml, root.html and index.org")]
+    Init {
+        #[arg(default_value = ".", help = "The directory to create it in")]
+        dir: PathBuf,
+        #[arg(long, default_value = "http://127.0.0.1:8000", help = "The URL the site is served at")]
+        site_url: String,
+    },
+    #[command(about = "Start a new Org article from its section's archetype")]
+    New {
+        #[arg(help = "The article's title")]
+        title: String,
+        #[arg(short, long, help = "The directory to create it in, e.g. `blog`")]
+        section: Option<String>,
+        #[arg(short, long, value_delimiter = ',', help = "Comma-separated tags")]
+        tags: Vec<String>,
+        #[arg(long, default_value = ".", help = "The source directory")]
+        source: PathBuf,
+    },
+    #[command(about = "Print shell completions to stdout")]
+    Completions {
+        #[arg(value_enum, help = "The shell to generate completions for")]
+        shell: Shell,
+    },
+    #[command(about = "Print a man page in roff format to stdout")]
+    Manpage,
+    #[command(about = "Render tests/templates fixtures and compare them with their golden files")]
+    TestTemplates {
+        #[arg(default_value = ".", help = "The source directory")]
+        source: PathBuf,
+        #[arg(long, help = "Overwrite the golden files with the current output")]
+        update: bool,
+    },
+    #[command(about = "Normalize the formatting of Org files")]
+    Fmt {
+        #[arg(default_value = ".", help = "Files or directories to format")]
+        paths: Vec<PathBuf>,
+        #[arg(long, help = "List unformatted files and fail instead of writing them")]
+        check: bool,
+    },
+    #[command(about = "Print the template context of the page built from a file, as JSON")]
+    Context {
+        #[arg(help = "The source file")]
+        file: PathBuf,
+        #[arg(default_value = ".", help = "The source directory")]
+        source: PathBuf,
+        #[arg(long, default_value = "prod", help = "The build profile")]
+        profile: String,
+    },
+    #[command(about = "Check the config and Org files for problems, without building the site")]
+    Check {
+        #[arg(default_value = ".", help = "The source directory")]
+        source: PathBuf,
+        #[arg(long, default_value = "prod", help = "The build profile")]
+        profile: String,
+    },
+    #[command(about = "Report on a site built with `graph: true`")]
+    Report {
+        #[arg(value_enum, help = "What to report")]
+        report: Report,
+        #[arg(default_value = ".", help = "The source directory")]
+        source: PathBuf,
+        #[arg(short, long, default_value = ".", help = "The destination directory")]
+        dest: PathBuf,
+        #[arg(long, default_value = "prod", help = "The build profile the site was built with")]
+        profile: String,
+    },
+}
 
-    log_builder.filter_level(log::LevelFilter::Info);
-    log_builder.parse_env(log_environ);
-    log_builder.init();
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Report {
+    /// Pages no other page links to.
+    Orphans,
+    /// Pages that following links from the home page never leads to.
+    Unreachable,
+}
 
+fn main() -> anyhow::Result<()>{
     let args = Args::parse();
 
-    let mut config_path = PathBuf::from_str(&args.source)?;
+    match args.command {
+        Some(Command::Completions { shell }) => {
+            let mut command = Args::command();
+            let name = command.get_name().to_owned();
+
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+
+            return Ok(());
+        }
+        Some(Command::Manpage) => {
+            clap_mangen::Man::new(Args::command()).render(&mut std::io::stdout())?;
+
+            return Ok(());
+        }
+        Some(Command::Fmt { paths, check }) => {
+            let changed = impertio::org::format::format_paths(&paths, check)?;
+
+            for path in &changed {
+                println!("{}", path.display());
+            }
+
+            if check && !changed.is_empty() {
+                anyhow::bail!("{} files are not formatted", changed.len());
+            }
+
+            return Ok(());
+        }
+        Some(Command::TestTemplates { source, update }) => {
+            let results = impertio::golden::test_templates(&source, update)?;
+            let mut failed = 0;
+
+            for (fixture, outcome) in &results {
+                match outcome {
+                    Outcome::Passed => println!("ok       {}", fixture.display()),
+                    Outcome::Updated => println!("updated  {}", fixture.display()),
+                    Outcome::Failed {
+                        line,
+                        expected,
+                        actual,
+                    } => {
+                        failed += 1;
+                        println!("FAILED   {} (line {})", fixture.display(), line);
+                        println!("  expected: {}", expected);
+                        println!("  actual:   {}", actual);
+                    }
+                }
+            }
+
+            if failed > 0 {
+                anyhow::bail!("{} of {} template tests failed", failed, results.len());
+            }
+
+            return Ok(());
+        }
+        Some(Command::Report {
+            report,
+            source,
+            dest,
+            profile,
+        }) => {
+            let config = Config::load(
+                &std::fs::read_to_string(source.join("impertio.yaml"))?,
+                &profile,
+            )?;
+            let graph = Graph::load(&dest)?;
+
+            let nodes = match report {
+                Report::Orphans => graph.orphans(&config.site_url),
+                Report::Unreachable => graph.unreachable(&config.site_url),
+            };
+
+            for node in nodes {
+                println!("{}  {}", node.url, node.title);
+            }
+
+            return Ok(());
+        }
+        Some(Command::Context {
+            file,
+            source,
+            profile,
+        }) => {
+            let config = Config::load(
+                &std::fs::read_to_string(source.join("impertio.yaml"))?,
+                &profile,
+            )?;
+            let source = source.to_string_lossy().into_owned();
+            let mut fd = impertio::files::FileDispatcher::new(&source, config);
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&fd.context(source, &file)?)?
+            );
+
+            return Ok(());
+        }
+        Some(Command::Check { source, profile }) => {
+            let config = Config::load(
+                &std::fs::read_to_string(source.join("impertio.yaml"))?,
+                &profile,
+            )
+            .map_err(|err| anyhow::anyhow!("impertio.yaml: {}", err))?;
+            let source = source.to_string_lossy().into_owned();
+            let problems = impertio::files::FileDispatcher::new(&source, config).check(source)?;
+
+            for (category, details) in problems.iter() {
+                for detail in details {
+                    println!("{}: {}", category, detail);
+                }
+            }
+
+            if !problems.is_empty() {
+                anyhow::bail!("{} problems found", problems.len());
+            }
+
+            return Ok(());
+        }
+        Some(Command::Build(build)) => return run_build(build, args.log_format),
+        Some(Command::Watch(build)) => {
+            return run_build(
+                BuildArgs {
+                    watch: true,
+                    ..build
+                },
+                args.log_format,
+            )
+        }
+        Some(Command::Serve { build, addr }) => {
+            impertio::logging::init(args.log_format);
+            build_site(&build)?;
+
+            let source = build.source.clone().expect("required");
+            let dest = build.dest.clone();
+
+            std::thread::spawn({
+                let dest = dest.clone();
+
+                move || {
+                    let watched = impertio::watch::watch(
+                        Path::new(&source),
+                        Path::new(&dest),
+                        &build.profile,
+                        || build_site(&build),
+                    );
+
+                    if let Err(err) = watched {
+                        log::error!("Stopped watching for changes: {:#}", err);
+                    }
+                }
+            });
+
+            return impertio::serve::serve(Path::new(&dest), &addr);
+        }
+        Some(Command::Init { dir, site_url }) => {
+            for path in impertio::init::init(&dir, &site_url)? {
+                println!("{}", path.display());
+            }
+
+            return Ok(());
+        }
+        Some(Command::New {
+            title,
+            section,
+            tags,
+            source,
+        }) => {
+            let path = impertio::archetype::create(
+                &source,
+                &title,
+                section.as_deref(),
+                &tags,
+                chrono::Local::now().date_naive(),
+            )?;
+
+            println!("{}", path.display());
+
+            return Ok(());
+        }
+        Some(Command::Clean { dest }) => {
+            impertio::logging::init(args.log_format);
+
+            let removed = impertio::manifest::clean(&dest)?;
+
+            log::info!(phase = "clean"; "Removed {} built files", removed);
+
+            return Ok(());
+        }
+        None => {}
+    }
+
+    run_build(args.build, args.log_format)
+}
+
+/// Builds the site once, with its config as it is now.
+fn build_site(args: &BuildArgs) -> anyhow::Result<()> {
+    let source = args.source.clone().expect("required");
+    let mut config_path = PathBuf::from_str(&source)?;
     config_path.push("impertio.yaml");
 
-    let config: Config = serde_yaml::from_str(&std::fs::read_to_string(config_path)?)?;
+    let mut config = Config::load(&std::fs::read_to_string(config_path)?, &args.profile)?;
+
+    if args.clean_orphans {
+        config.clean_orphans = Some(true);
+    }
 
-    log::info!("Beginning to process `{}`", args.source);
+    let mut fd = impertio::files::FileDispatcher::new(&source, config);
+
+    fd.handle_files(args.dest.clone(), source)?;
+
+    Ok(())
+}
+
+fn run_build(args: BuildArgs, log_format: LogFormat) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let source = args.source.clone().expect("required unless a subcommand is given");
+
+    impertio::logging::init(log_format);
+
+    log::info!("Beginning to process `{}`", source);
     log::info!("Outputting to `{}`", args.dest);
+    log::info!("Using the `{}` profile", args.profile);
+
+    build_site(&args)?;
 
-    let mut fd = impertio::files::FileDispatcher::new(&args.source, config);
-    
-    fd.handle_files(args.dest, args.source)?;
+    log::info!(phase = "done", duration_ms = start.elapsed().as_millis() as u64; "Done.");
 
-    log::info!("Done.");
+    if args.watch {
+        impertio::watch::watch(
+            Path::new(&source),
+            Path::new(&args.dest),
+            &args.profile,
+            || build_site(&args),
+        )?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,424 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::EtagFormat,
+    metadata::{Anchor, Metadata},
+};
+
+/// What a build published, kept in the destination between builds to tell what changed.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Manifest {
+    /// Page URLs and when their sources were last modified.
+    pub pages: BTreeMap<String, DateTime<Utc>>,
+    /// URLs of pages earlier builds published that have been gone since, until they return.
+    #[serde(default)]
+    pub removed: BTreeSet<String>,
+    /// The files built from each source file, relative to the destination, by the source's
+    /// path relative to the source directory.
+    #[serde(default)]
+    pub outputs: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Manifest {
+    pub const FILE: &'static str = ".impertio-manifest.json";
+
+    pub fn from_metadata(metadata: &[Metadata]) -> Self {
+        Self {
+            pages: metadata
+                .iter()
+                .filter_map(|meta| match meta {
+                    Metadata::Article { url, modified, .. } => Some((url.clone(), *modified)),
+                    _ => None,
+                })
+                .collect(),
+            removed: BTreeSet::new(),
+            outputs: BTreeMap::new(),
+        }
+    }
+
+    /// The previous build's manifest, or an empty one if there is none.
+    pub fn load(dir: &Path) -> Self {
+        std::fs::read_to_string(dir.join(Self::FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(dir.join(Self::FILE), serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// URLs that are new or modified since `previous`.
+    pub fn changed_since(&self, previous: &Manifest) -> Vec<String> {
+        self.pages
+            .iter()
+            .filter(|(url, modified)| previous.pages.get(*url) != Some(modified))
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+
+    /// Remembers the URLs `previous` published, or remembered, that aren't pages anymore.
+    /// Returns those `previous` still published.
+    pub fn track_removed(&mut self, previous: &Manifest) -> Vec<String> {
+        self.removed = previous
+            .removed
+            .iter()
+            .chain(previous.pages.keys())
+            .filter(|url| !self.pages.contains_key(*url))
+            .cloned()
+            .collect();
+
+        previous
+            .pages
+            .keys()
+            .filter(|url| !self.pages.contains_key(*url))
+            .cloned()
+            .collect()
+    }
+
+    /// Files `previous` built that this build didn't, because their sources are gone or build
+    /// into something else now.
+    pub fn orphaned_outputs(&self, previous: &Manifest) -> Vec<String> {
+        let current: BTreeSet<&String> = self.outputs.values().flatten().collect();
+
+        previous
+            .outputs
+            .values()
+            .flatten()
+            .filter(|output| !current.contains(output))
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Keeps tracking `outputs` of `previous`, so files left in place are still known to be
+    /// orphans next time.
+    pub fn keep_outputs(&mut self, previous: &Manifest, outputs: &[String]) {
+        for (source, built) in &previous.outputs {
+            for output in built.iter().filter(|output| outputs.contains(output)) {
+                self.outputs
+                    .entry(source.clone())
+                    .or_default()
+                    .insert(output.clone());
+            }
+        }
+    }
+}
+
+/// Deletes `outputs`, relative to `dir`, and the directories they leave empty. Returns how many
+/// were deleted.
+pub fn remove_outputs(dir: &Path, outputs: &[String]) -> anyhow::Result<usize> {
+    let mut removed = 0;
+
+    for output in outputs {
+        let path = dir.join(output);
+
+        if !path.is_file() {
+            continue;
+        }
+
+        std::fs::remove_file(&path)?;
+        removed += 1;
+
+        for parent in path.ancestors().skip(1) {
+            if parent == dir || std::fs::remove_dir(parent).is_err() {
+                break;
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Deletes the files builds wrote into `dir` for source files, and the build cache, so the
+/// next build starts over. The pages published are still remembered, for removed pages.
+/// Returns how many files were deleted.
+pub fn clean(dir: &Path) -> anyhow::Result<usize> {
+    let mut manifest = Manifest::load(dir);
+    let outputs: Vec<String> = std::mem::take(&mut manifest.outputs)
+        .into_values()
+        .flatten()
+        .collect();
+    let removed = remove_outputs(dir, &outputs)?;
+
+    if dir.join(Manifest::FILE).is_file() {
+        manifest.save(dir)?;
+    }
+
+    if dir.join(crate::cache::BuildCache::FILE).is_file() {
+        std::fs::remove_file(dir.join(crate::cache::BuildCache::FILE))?;
+    }
+
+    Ok(removed)
+}
+
+/// A strong ETag for `contents`: its quoted SHA-256.
+fn etag(contents: &[u8]) -> String {
+    let hex: String = Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    format!("\"{}\"", hex)
+}
+
+/// ETags of every file built into `dir`, keyed by URL path, e.g. `/posts/a.html`.
+pub fn etags(dir: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut etags = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy();
+
+        if !entry.file_type().is_file()
+            || name == Manifest::FILE
+            || name == crate::cache::BuildCache::FILE
+            || name == "etags.json"
+            || name == "etags.csv"
+        {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .strip_prefix(dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        etags.insert(format!("/{}", path), etag(&std::fs::read(entry.path())?));
+    }
+
+    Ok(etags)
+}
+
+/// Writes the ETags of `dir` to `etags.json` or `etags.csv` in it, for servers to send.
+pub fn save_etags(dir: &Path, format: EtagFormat) -> anyhow::Result<()> {
+    let etags = etags(dir)?;
+
+    match format {
+        EtagFormat::Json => std::fs::write(
+            dir.join("etags.json"),
+            serde_json::to_string_pretty(&etags)?,
+        )?,
+        EtagFormat::Csv => std::fs::write(dir.join("etags.csv"), etags_csv(&etags))?,
+    }
+
+    Ok(())
+}
+
+/// An anchor with the page it's on, as listed in `anchors.json`.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+struct AnchorEntry<'a> {
+    /// The page's URL.
+    page: &'a str,
+    /// The URL of the anchor itself, `page#id`.
+    url: String,
+    #[serde(flatten)]
+    anchor: &'a Anchor,
+}
+
+fn anchor_entries(metadata: &[Metadata]) -> Vec<AnchorEntry<'_>> {
+    let mut entries: Vec<AnchorEntry> = metadata
+        .iter()
+        .filter_map(|meta| match meta {
+            Metadata::Article { url, anchors, .. } => Some(anchors.iter().map(move |anchor| {
+                AnchorEntry {
+                    page: url,
+                    url: format!("{}#{}", url, anchor.id),
+                    anchor,
+                }
+            })),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    // Pages in a stable order; anchors stay in document order.
+    entries.sort_by(|a, b| a.page.cmp(b.page));
+
+    entries
+}
+
+/// Writes every page's anchors to `anchors.json` in `dir`, for link completion in other tools.
+pub fn save_anchors(dir: &Path, metadata: &[Metadata]) -> anyhow::Result<()> {
+    std::fs::write(
+        dir.join("anchors.json"),
+        serde_json::to_string_pretty(&anchor_entries(metadata))?,
+    )?;
+
+    Ok(())
+}
+
+fn etags_csv(etags: &BTreeMap<String, String>) -> String {
+    let quote = |field: &str| {
+        if field.contains(['"', ',', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_owned()
+        }
+    };
+
+    std::iter::once("path,etag\n".to_owned())
+        .chain(
+            etags
+                .iter()
+                .map(|(path, etag)| format!("{},{}\n", quote(path), quote(etag))),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        manifest::{anchor_entries, etag, etags_csv, remove_outputs, Manifest},
+        metadata::{Anchor, AnchorKind, Metadata},
+    };
+
+    #[test]
+    fn changes() {
+        let old = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let new = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let previous = Manifest {
+            pages: [("/a.html".into(), old), ("/b.html".into(), old)].into(),
+            ..Default::default()
+        };
+        let current = Manifest {
+            pages: [
+                ("/a.html".into(), old),
+                ("/b.html".into(), new),
+                ("/c.html".into(), new),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        assert_eq!(current.changed_since(&previous), vec!["/b.html", "/c.html"]);
+        assert_eq!(current.changed_since(&Manifest::default()).len(), 3);
+    }
+
+    #[test]
+    fn removals() {
+        let date = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let previous = Manifest {
+            pages: [("/a.html".into(), date), ("/b.html".into(), date)].into(),
+            removed: ["/c.html".into(), "/d.html".into()].into(),
+            ..Default::default()
+        };
+        let mut current = Manifest {
+            pages: [("/a.html".into(), date), ("/d.html".into(), date)].into(),
+            ..Default::default()
+        };
+
+        assert_eq!(current.track_removed(&previous), vec!["/b.html"]);
+        assert_eq!(current.removed, ["/b.html".into(), "/c.html".into()].into());
+    }
+
+    #[test]
+    fn orphans() {
+        let outputs = |outputs: &[(&str, &[&str])]| {
+            outputs
+                .iter()
+                .map(|(source, built)| {
+                    (
+                        source.to_string(),
+                        built.iter().map(|path| path.to_string()).collect(),
+                    )
+                })
+                .collect()
+        };
+        let previous = Manifest {
+            outputs: outputs(&[
+                ("a.org", &["a.html", "a.org"]),
+                ("old/b.org", &["old/b.html", "old/b.org"]),
+            ]),
+            ..Default::default()
+        };
+        let current = Manifest {
+            outputs: outputs(&[("a.org", &["a.html"]), ("new/b.org", &["new/b.html"])]),
+            ..Default::default()
+        };
+        let orphans = current.orphaned_outputs(&previous);
+
+        assert_eq!(orphans, vec!["a.org", "old/b.html", "old/b.org"]);
+
+        let dir = std::env::temp_dir().join(format!("impertio-orphans-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("old")).unwrap();
+
+        for file in ["a.html", "a.org", "old/b.html"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let removed = remove_outputs(&dir, &orphans).unwrap();
+        let left = (dir.join("a.html").exists(), dir.join("old").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(removed, 2);
+        assert_eq!(left, (true, false));
+    }
+
+    #[test]
+    fn etag_map() {
+        let tag = etag(b"hello");
+
+        assert_eq!(
+            tag,
+            "\"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\""
+        );
+        assert_eq!(
+            etags_csv(&[("/a.html".to_owned(), tag.clone())].into()),
+            format!("path,etag\n/a.html,\"{}\"\n", tag.replace('"', "\"\""))
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let page = |url: &str, ids: &[&str]| {
+            Metadata::article(url)
+                .with_title("Page")
+                .with_modified(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+                .with_anchors(
+                    ids.iter()
+                        .map(|id| Anchor {
+                            id: id.to_string(),
+                            kind: AnchorKind::Heading,
+                            title: None,
+                        })
+                        .collect(),
+                )
+        };
+        let metadata = [
+            page("https://x.y/b.html", &["setup", "usage"]),
+            page("https://x.y/a.html", &["intro"]),
+            Metadata::Image {
+                url: "https://x.y/c.png".into(),
+                exif: Default::default(),
+            },
+        ];
+
+        assert_eq!(
+            serde_json::to_value(anchor_entries(&metadata)).unwrap(),
+            serde_json::json!([
+                {"page": "https://x.y/a.html", "url": "https://x.y/a.html#intro", "id": "intro", "kind": "heading", "title": null},
+                {"page": "https://x.y/b.html", "url": "https://x.y/b.html#setup", "id": "setup", "kind": "heading", "title": null},
+                {"page": "https://x.y/b.html", "url": "https://x.y/b.html#usage", "id": "usage", "kind": "heading", "title": null},
+            ])
+        );
+    }
+}
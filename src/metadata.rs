@@ -1,6 +1,12 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+// Nearly all metadata is articles' anyway.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum Metadata {
     Article {
@@ -8,13 +14,194 @@ pub enum Metadata {
         description: Option<String>,
         author: Option<String>,
         tags: Vec<String>,
+        /// Terms of the configured taxonomies besides `tags`, by taxonomy, from the keyword
+        /// named like it, e.g. `#+CATEGORIES`.
+        terms: BTreeMap<String, Vec<String>>,
+        /// From `#+LICENSE`, else the site's `license`, like `CC-BY-4.0` or
+        /// `All rights reserved`.
+        license: Option<String>,
 
         modified: chrono::DateTime<chrono::Utc>,
         // created: chrono::DateTime<chrono::Utc>,
+        /// When the article says it was published, if it does.
+        published: Option<chrono::DateTime<chrono::Utc>>,
 
         url: String,
+        /// Fragments the page can be linked to with.
+        anchors: Vec<Anchor>,
+        /// From `#+ROBOTS`.
+        robots: Robots,
+        /// The text before the excerpt separator, if the page has one. Also the description,
+        /// unless there's a `#+DESC`.
+        summary: Option<String>,
+        /// How many figures, tables and listings of each kind the page numbers.
+        numbered: HashMap<String, usize>,
+        /// The rendered page, for feeds that include it.
+        content: Option<String>,
     },
     Image {
         url: String,
+        /// What its EXIF and IPTC data say, for JPEGs.
+        exif: crate::exif::Exif,
     },
 }
+
+/// Articles for tests, with only the fields they care about set.
+#[cfg(test)]
+impl Metadata {
+    /// An untitled article at `url`, modified at the epoch, without anything else.
+    pub(crate) fn article(url: &str) -> Self {
+        Self::Article {
+            title: String::new(),
+            description: None,
+            author: None,
+            tags: vec![],
+            terms: BTreeMap::new(),
+            license: None,
+            modified: Default::default(),
+            published: None,
+            url: url.into(),
+            anchors: vec![],
+            robots: Default::default(),
+            summary: None,
+            numbered: HashMap::new(),
+            content: None,
+        }
+    }
+
+    pub(crate) fn with_title(mut self, value: &str) -> Self {
+        if let Self::Article { title, .. } = &mut self {
+            *title = value.into();
+        }
+        self
+    }
+
+    pub(crate) fn with_tags(mut self, values: &[&str]) -> Self {
+        if let Self::Article { tags, .. } = &mut self {
+            *tags = values.iter().map(|tag| tag.to_string()).collect();
+        }
+        self
+    }
+
+    /// Sets the article's terms of `taxonomy`.
+    pub(crate) fn with_terms(mut self, taxonomy: &str, values: &[&str]) -> Self {
+        if let Self::Article { terms, .. } = &mut self {
+            terms.insert(
+                taxonomy.into(),
+                values.iter().map(|term| term.to_string()).collect(),
+            );
+        }
+        self
+    }
+
+    pub(crate) fn with_license(mut self, value: Option<&str>) -> Self {
+        if let Self::Article { license, .. } = &mut self {
+            *license = value.map(Into::into);
+        }
+        self
+    }
+
+    pub(crate) fn with_modified(mut self, value: chrono::DateTime<chrono::Utc>) -> Self {
+        if let Self::Article { modified, .. } = &mut self {
+            *modified = value;
+        }
+        self
+    }
+
+    pub(crate) fn with_anchors(mut self, values: Vec<Anchor>) -> Self {
+        if let Self::Article { anchors, .. } = &mut self {
+            *anchors = values;
+        }
+        self
+    }
+
+    /// Sets how many of the `kind` of element, like `Figure`, the article numbers.
+    pub(crate) fn with_numbered(mut self, kind: &str, count: usize) -> Self {
+        if let Self::Article { numbered, .. } = &mut self {
+            numbered.insert(kind.into(), count);
+        }
+        self
+    }
+}
+
+/// A fragment of a page, like a heading's `id` or a `#+NAME`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Anchor {
+    pub id: String,
+    pub kind: AnchorKind,
+    /// The heading's title or the element's caption, if any.
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorKind {
+    Heading,
+    Name,
+    /// An `#+INDEX` term, whose title is the term.
+    Index,
+}
+
+/// Directives for crawlers, like `#+ROBOTS: noindex, nofollow`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Robots {
+    /// Left out of the sitemap.
+    pub noindex: bool,
+    pub nofollow: bool,
+}
+
+impl Robots {
+    /// Comma- or space-separated directives; `none` means both. Others are ignored.
+    pub fn parse(directives: &str) -> Self {
+        let mut robots = Self::default();
+
+        for directive in directives
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(str::to_ascii_lowercase)
+        {
+            match directive.as_str() {
+                "noindex" => robots.noindex = true,
+                "nofollow" => robots.nofollow = true,
+                "none" => {
+                    robots.noindex = true;
+                    robots.nofollow = true;
+                }
+                _ => {}
+            }
+        }
+
+        robots
+    }
+
+    /// The `content` of a `<meta name="robots">`, unless it would be empty.
+    pub fn meta(&self) -> Option<String> {
+        let directives: Vec<&str> = [(self.noindex, "noindex"), (self.nofollow, "nofollow")]
+            .into_iter()
+            .filter_map(|(set, directive)| set.then_some(directive))
+            .collect();
+
+        (!directives.is_empty()).then(|| directives.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metadata::Robots;
+
+    #[test]
+    fn robots() {
+        assert_eq!(
+            Robots::parse("NOINDEX, nofollow"),
+            Robots {
+                noindex: true,
+                nofollow: true
+            }
+        );
+        assert_eq!(Robots::parse("none"), Robots::parse("noindex nofollow"));
+        assert_eq!(Robots::parse("noarchive").meta(), None);
+        assert_eq!(
+            Robots::parse("nofollow").meta().as_deref(),
+            Some("nofollow")
+        );
+    }
+}
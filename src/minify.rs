@@ -0,0 +1,74 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! A conservative HTML minifier: it only removes whitespace that can't change the rendering.
+
+/// Elements whose contents are kept exactly as they are.
+const PRESERVED: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// How many preserved elements `line` opens, minus how many it closes.
+fn preserved_depth_change(line: &str) -> isize {
+    let line = line.to_ascii_lowercase();
+
+    PRESERVED
+        .iter()
+        .map(|tag| {
+            let opened = line.matches(&format!("<{}>", tag)).count()
+                + line.matches(&format!("<{} ", tag)).count();
+            let closed = line.matches(&format!("</{}>", tag)).count();
+
+            opened as isize - closed as isize
+        })
+        .sum()
+}
+
+/// `html` without indentation, trailing whitespace and blank lines, except inside `<pre>`,
+/// `<textarea>`, `<script>` and `<style>`.
+pub fn html(html: &str) -> String {
+    let mut lines: Vec<&str> = vec![];
+    let mut depth: isize = 0;
+
+    for line in html.lines() {
+        let verbatim = depth > 0;
+
+        depth = (depth + preserved_depth_change(line)).max(0);
+
+        if verbatim {
+            lines.push(line);
+            continue;
+        }
+
+        let line = if depth > 0 {
+            line.trim_start()
+        } else {
+            line.trim()
+        };
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::minify::html;
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(
+            html("<html>\n  <body>\n\n    <p>Hello,   world</p>  \n  </body>\n</html>\n"),
+            "<html>\n<body>\n<p>Hello,   world</p>\n</body>\n</html>"
+        );
+    }
+
+    #[test]
+    fn preserved() {
+        assert_eq!(
+            html("  <div>\n    <pre class=\"src\">fn main() {\n    println!();\n}\n  </pre>\n    <SCRIPT>\n  let x;\n</SCRIPT>\n  </div>"),
+            "<div>\n<pre class=\"src\">fn main() {\n    println!();\n}\n  </pre>\n<SCRIPT>\n  let x;\n</SCRIPT>\n</div>"
+        );
+    }
+}
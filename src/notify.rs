@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Telling other services that the site changed, after a build.
+
+/// Asks a WebSub hub to fetch `topic` and push it to subscribers.
+pub fn websub_publish(hub: &str, topic: &str) -> anyhow::Result<()> {
+    log::info!(phase = "notify"; "Pinging WebSub hub `{}` for `{}`", hub, topic);
+
+    ureq::post(hub).send_form([("hub.mode", "publish"), ("hub.url", topic)])?;
+
+    Ok(())
+}
+
+/// Where IndexNow submissions go unless configured otherwise; it shares them with every
+/// participating search engine.
+pub const INDEX_NOW_ENDPOINT: &str = "https://api.indexnow.org/indexnow";
+/// The most URLs IndexNow accepts in one submission.
+const INDEX_NOW_BATCH: usize = 10_000;
+
+/// `https://example.com/blog` → `example.com`
+fn host(site_url: &str) -> &str {
+    let rest = site_url
+        .split_once("://")
+        .map_or(site_url, |(_, rest)| rest);
+
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+fn index_now_payloads(site_url: &str, key: &str, urls: &[String]) -> Vec<serde_json::Value> {
+    urls.chunks(INDEX_NOW_BATCH)
+        .map(|urls| {
+            serde_json::json!({
+                "host": host(site_url),
+                "key": key,
+                "keyLocation": format!("{}/{}.txt", site_url, key),
+                "urlList": urls,
+            })
+        })
+        .collect()
+}
+
+/// Submits changed URLs to IndexNow. The key must be published at `{site_url}/{key}.txt`.
+pub fn index_now(endpoint: &str, site_url: &str, key: &str, urls: &[String]) -> anyhow::Result<()> {
+    for payload in index_now_payloads(site_url, key, urls) {
+        log::info!(
+            phase = "notify";
+            "Submitting {} URLs to IndexNow at `{}`",
+            payload["urlList"].as_array().map_or(0, Vec::len),
+            endpoint
+        );
+
+        ureq::post(endpoint)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .send(payload.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Requests a ping URL after replacing `{sitemap}` and `{feed}` with the site's URLs.
+pub fn ping(url: &str, site_url: &str) -> anyhow::Result<()> {
+    let url = url
+        .replace("{sitemap}", &format!("{}/sitemap.xml", site_url))
+        .replace("{feed}", &format!("{}/feed", site_url));
+
+    log::info!(phase = "notify"; "Pinging `{}`", url);
+
+    ureq::get(&url).call()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::notify::{host, index_now_payloads};
+
+    #[test]
+    fn hosts() {
+        assert_eq!(host("https://example.com"), "example.com");
+        assert_eq!(host("https://example.com:8080/blog"), "example.com:8080");
+        assert_eq!(host("example.com/blog"), "example.com");
+    }
+
+    #[test]
+    fn index_now_payload() {
+        let payloads = index_now_payloads(
+            "https://example.com",
+            "abc123",
+            &["https://example.com/a.html".into()],
+        );
+
+        assert_eq!(
+            payloads,
+            vec![serde_json::json!({
+                "host": "example.com",
+                "key": "abc123",
+                "keyLocation": "https://example.com/abc123.txt",
+                "urlList": ["https://example.com/a.html"],
+            })]
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashSet;
+
+use crate::{
+    metadata::{Anchor, AnchorKind},
+    org::{
+        xref::{for_each_node, for_each_section},
+        Node, Section,
+    },
+};
+
+/// `Größe & Gewicht!` → `größe-gewicht`. Letters and digits of any script are kept.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() {
+        "section".into()
+    } else {
+        slug.into()
+    }
+}
+
+fn assign_in(sections: &mut [Section], used: &mut HashSet<String>) {
+    for section in sections {
+        if section.commented {
+            continue;
+        }
+
+        if let Some(Node::Heading {
+            title,
+            properties,
+            id,
+            ..
+        }) = section.nodes.first_mut()
+        {
+            let base = properties
+                .get("CUSTOM_ID")
+                .cloned()
+                .unwrap_or_else(|| slugify(title));
+            let mut candidate = base.clone();
+            let mut n = 0;
+
+            while used.contains(&candidate) {
+                n += 1;
+                candidate = format!("{}-{}", base, n);
+            }
+
+            used.insert(candidate.clone());
+            *id = candidate;
+        }
+
+        assign_in(&mut section.children, used);
+    }
+}
+
+/// `#+NAME`s, which are anchors too.
+fn names(sections: &[Section], used: &mut HashSet<String>) {
+    fn in_nodes(nodes: &[Node], used: &mut HashSet<String>) {
+        for node in nodes {
+            match node {
+                Node::Labeled {
+                    name: Some(name), ..
+                } => {
+                    used.insert(name.clone());
+                }
+                Node::GreaterBlock { contents, .. } => in_nodes(contents, used),
+                _ => {}
+            }
+        }
+    }
+
+    for section in sections {
+        in_nodes(&section.nodes, used);
+        names(&section.children, used);
+    }
+}
+
+/// `index-` and a slug of each `#+INDEX` term, made unique like headings' ids.
+fn index_terms(sections: &mut [Section], used: &mut HashSet<String>) {
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::IndexTerm { term, id } = node {
+                let base = format!("index-{}", slugify(term));
+                let mut candidate = base.clone();
+                let mut n = 0;
+
+                while used.contains(&candidate) {
+                    n += 1;
+                    candidate = format!("{}-{}", base, n);
+                }
+
+                used.insert(candidate.clone());
+                *id = candidate;
+            }
+        })
+    });
+}
+
+/// Gives every exported heading a unique `id`: its `CUSTOM_ID` property or a slug of its
+/// title, with `-1`, `-2`, … added to repeats in document order and to `#+NAME`s. Index terms
+/// get ids after the headings.
+pub fn assign(sections: &mut [Section]) {
+    let mut used = HashSet::new();
+
+    names(sections, &mut used);
+    assign_in(sections, &mut used);
+    index_terms(sections, &mut used);
+}
+
+fn number_in(sections: &mut [Section], prefix: &str, depth: usize, levels: usize) {
+    let mut count = 0;
+
+    for section in sections.iter_mut().filter(|section| !section.commented) {
+        let Some(Node::Heading { number, .. }) = section.nodes.first_mut() else {
+            continue;
+        };
+
+        count += 1;
+
+        let own = format!("{}{}", prefix, count);
+
+        *number = Some(own.clone());
+
+        if depth < levels {
+            number_in(
+                &mut section.children,
+                &format!("{}.", own),
+                depth + 1,
+                levels,
+            );
+        }
+    }
+}
+
+/// Numbers the exported headings `1`, `1.1`, `2.3.1`, … by their place in the section tree,
+/// down to `levels` deep.
+pub fn number(sections: &mut [Section], levels: usize) {
+    if levels > 0 {
+        number_in(sections, "", 1, levels);
+    }
+}
+
+/// The headings and `#+NAME`s of exported sections, in document order.
+pub fn list(sections: &[Section]) -> Vec<Anchor> {
+    fn in_nodes(nodes: &[Node], anchors: &mut Vec<Anchor>) {
+        for node in nodes {
+            match node {
+                Node::Heading { title, id, .. } if !id.is_empty() => anchors.push(Anchor {
+                    id: id.clone(),
+                    kind: AnchorKind::Heading,
+                    title: Some(title.clone()),
+                }),
+                Node::Labeled {
+                    name: Some(name),
+                    caption,
+                    ..
+                } => anchors.push(Anchor {
+                    id: name.clone(),
+                    kind: AnchorKind::Name,
+                    title: caption.clone(),
+                }),
+                Node::IndexTerm { term, id } => anchors.push(Anchor {
+                    id: id.clone(),
+                    kind: AnchorKind::Index,
+                    title: Some(term.clone()),
+                }),
+                Node::GreaterBlock { contents, .. } => in_nodes(contents, anchors),
+                _ => {}
+            }
+        }
+    }
+
+    let mut anchors = vec![];
+
+    for section in sections.iter().filter(|section| !section.commented) {
+        in_nodes(&section.nodes, &mut anchors);
+        anchors.extend(list(&section.children));
+    }
+
+    anchors
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        metadata::{Anchor, AnchorKind},
+        org::{anchors::slugify, Document, Node, Section},
+    };
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("Größe & Gewicht"), "größe-gewicht");
+        assert_eq!(slugify("日本語 テキスト"), "日本語-テキスト");
+        assert_eq!(slugify("?!"), "section");
+    }
+
+    #[test]
+    fn duplicates() {
+        let doc = Document::parse(
+            "* Setup\n** Setup\n* Setup 1\n* Setup\n* Custom\n:PROPERTIES:\n:CUSTOM_ID: mine\n:END:\n* Squares\n#+NAME: squares\n| 1 |",
+            "anchors.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        fn ids(sections: &[Section], out: &mut Vec<String>) {
+            for section in sections {
+                if let Some(Node::Heading { id, .. }) = section.nodes.first() {
+                    out.push(id.clone());
+                }
+
+                ids(&section.children, out);
+            }
+        }
+
+        let mut out = vec![];
+        ids(&doc.sections, &mut out);
+
+        assert_eq!(
+            out,
+            vec![
+                "setup",
+                "setup-1",
+                "setup-1-1",
+                "setup-2",
+                "mine",
+                "squares-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        let doc = Document::parse(
+            "#+OPTIONS: num:t\n* A\n*** A.deep\n** A.b\n* COMMENT Skipped\n* B\n** B.a\n*** B.a.i\n**** Too deep",
+            "anchors.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        fn numbers(sections: &[Section], out: &mut Vec<Option<String>>) {
+            for section in sections {
+                if let Some(Node::Heading { number, .. }) = section.nodes.first() {
+                    out.push(number.clone());
+                }
+
+                numbers(&section.children, out);
+            }
+        }
+
+        let mut found = vec![];
+        numbers(&doc.sections, &mut found);
+
+        assert_eq!(
+            found,
+            [
+                Some("1"),
+                Some("1.1"),
+                Some("1.2"),
+                None,
+                Some("2"),
+                Some("2.1"),
+                Some("2.1.1"),
+                None
+            ]
+            .map(|number| number.map(str::to_owned))
+        );
+    }
+
+    #[test]
+    fn listed() {
+        let doc = Document::parse(
+            "* Intro\n#+NAME: squares\n#+CAPTION: Squares\n| 1 |\n* COMMENT Hidden\n** Also hidden\n* Usage",
+            "anchors.org",
+            Default::default(),
+        )
+        .unwrap();
+        let anchor = |id: &str, kind, title: &str| Anchor {
+            id: id.into(),
+            kind,
+            title: Some(title.into()),
+        };
+
+        assert_eq!(
+            doc.anchors(),
+            vec![
+                anchor("intro", AnchorKind::Heading, "Intro"),
+                anchor("squares", AnchorKind::Name, "Squares"),
+                anchor("usage", AnchorKind::Heading, "Usage"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+/// What `:exports` asks to be shown. Nothing is evaluated, so results are never available.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Exports {
+    #[default]
+    Code,
+    Results,
+    Both,
+    None,
+}
+
+impl Exports {
+    pub fn shows_code(&self) -> bool {
+        matches!(self, Self::Code | Self::Both)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LineNumbers {
+    /// `-n [start]`
+    Start(usize),
+    /// `+n [offset]`, continuing from the previous numbered block.
+    Continue(usize),
+}
+
+/// The arguments of a `#+BEGIN_SRC` line.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct HeaderArgs {
+    pub language: Option<String>,
+    pub exports: Exports,
+    pub line_numbers: Option<LineNumbers>,
+    /// 1-based lines within the block, from `:hl_lines`.
+    pub highlighted_lines: Vec<usize>,
+    /// Every `:key value` pair, including those handled above.
+    pub parameters: HashMap<String, String>,
+}
+
+impl HeaderArgs {
+    pub fn parse(args: &[String]) -> Self {
+        let mut header = Self::default();
+        let mut args = args.iter().peekable();
+
+        if let Some(language) = args.next_if(|arg| !arg.starts_with([':', '-', '+'])) {
+            header.language = Some(language.to_owned());
+        }
+
+        while let Some(arg) = args.next() {
+            if arg == "-n" || arg == "+n" {
+                let number = args.next_if(|next| next.parse::<usize>().is_ok());
+
+                header.line_numbers = Some(if arg == "-n" {
+                    LineNumbers::Start(number.map_or(1, |n| n.parse().unwrap()))
+                } else {
+                    LineNumbers::Continue(number.map_or(0, |n| n.parse().unwrap()))
+                });
+            } else if let Some(key) = arg.strip_prefix(':') {
+                let mut value: Vec<&str> = vec![];
+
+                while let Some(word) = args.next_if(|next| !next.starts_with(':')) {
+                    value.push(word);
+                }
+
+                header
+                    .parameters
+                    .insert(key.to_ascii_lowercase(), value.join(" "));
+            }
+        }
+
+        if let Some(exports) = header.parameters.get("exports") {
+            header.exports = match exports.as_str() {
+                "results" => Exports::Results,
+                "both" => Exports::Both,
+                "none" => Exports::None,
+                _ => Exports::Code,
+            };
+        }
+
+        if let Some(lines) = header.parameters.get("hl_lines") {
+            header.highlighted_lines = parse_line_ranges(lines);
+        }
+
+        header
+    }
+}
+
+/// `1, 3-5 7` → `[1, 3, 4, 5, 7]`
+fn parse_line_ranges(ranges: &str) -> Vec<usize> {
+    ranges
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|range| match range.split_once('-') {
+            Some((start, end)) => Some((start.parse().ok()?..=end.parse().ok()?).collect()),
+            None => Some(vec![range.parse().ok()?]),
+        })
+        .flatten()
+        .collect()
+}
+
+/// Splits block arguments on whitespace, keeping "quoted strings" together.
+/// A backslash escapes the character after it.
+pub fn split_args(args: &str) -> Vec<String> {
+    let mut split: Vec<String> = vec![];
+    let mut buf = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut started = false;
+
+    for c in args.chars() {
+        if escaped {
+            buf.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+            started = true;
+        } else if c == '"' {
+            quoted = !quoted;
+            started = true;
+        } else if c.is_whitespace() && !quoted {
+            if started {
+                split.push(std::mem::take(&mut buf));
+                started = false;
+            }
+        } else {
+            buf.push(c);
+            started = true;
+        }
+    }
+
+    if started {
+        split.push(buf);
+    }
+
+    split
+}
+
+#[cfg(test)]
+mod test {
+    use crate::org::babel::{split_args, Exports, HeaderArgs, LineNumbers};
+
+    #[test]
+    fn splitting() {
+        assert_eq!(
+            split_args(r#"python  :var x="a b" :title "say \"hi\"""#),
+            vec!["python", ":var", "x=a b", ":title", "say \"hi\""]
+        );
+        assert_eq!(split_args(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn header_args() {
+        let header = HeaderArgs::parse(&split_args(
+            "rust -n 10 :exports both :results none :hl_lines 1,3-4",
+        ));
+
+        assert_eq!(header.language, Some("rust".into()));
+        assert_eq!(header.exports, Exports::Both);
+        assert_eq!(header.line_numbers, Some(LineNumbers::Start(10)));
+        assert_eq!(header.highlighted_lines, vec![1, 3, 4]);
+        assert_eq!(header.parameters["results"], "none");
+    }
+
+    #[test]
+    fn no_language() {
+        let header = HeaderArgs::parse(&split_args("+n :exports results"));
+
+        assert_eq!(header.language, None);
+        assert_eq!(header.line_numbers, Some(LineNumbers::Continue(0)));
+        assert!(!header.exports.shows_code());
+    }
+}
@@ -0,0 +1,605 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! org-cite: `[cite:@key]` citations, looked up in BibTeX or CSL-JSON bibliographies and
+//! rendered author-year, with `#+PRINT_BIBLIOGRAPHY:` listing the cited references.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use build_html::escape_html;
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+use crate::{
+    handler::FileContext,
+    org::{
+        xref::{for_each_node, for_each_section},
+        Node, Section,
+    },
+};
+
+lazy_static! {
+    static ref CITATION: Regex =
+        Regex::new(r"\[cite(?:/(?<style>[\w/-]+))?:(?<body>[^\]\[]*@[^\]\[]*)\]").unwrap();
+    static ref KEY: Regex = Regex::new(r"@(?<key>[\w:.#$%&+?<>~/-]*\w)").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Name {
+    pub family: String,
+    pub given: Option<String>,
+}
+
+/// A reference in a bibliography.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Entry {
+    pub key: String,
+    pub authors: Vec<Name>,
+    pub year: Option<String>,
+    pub title: Option<String>,
+    /// The journal or book it appeared in.
+    pub container: Option<String>,
+    pub publisher: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Entry {
+    /// `Doe`, `Doe and Roe` or `Doe et al.`, falling back to the title or key.
+    fn short_authors(&self) -> String {
+        match self.authors.as_slice() {
+            [] => self.title.clone().unwrap_or_else(|| self.key.clone()),
+            [one] => one.family.clone(),
+            [one, two] => format!("{} and {}", one.family, two.family),
+            [one, ..] => format!("{} et al.", one.family),
+        }
+    }
+
+    fn year(&self) -> &str {
+        self.year.as_deref().unwrap_or("n.d.")
+    }
+
+    /// A line of the bibliography, like `Doe, Jane (2020). <em>Title</em>. Journal.`
+    fn to_html(&self) -> String {
+        let names: Vec<String> = self
+            .authors
+            .iter()
+            .map(|name| match &name.given {
+                Some(given) => format!("{}, {}", name.family, given),
+                None => name.family.clone(),
+            })
+            .collect();
+        let mut out = match names.len() {
+            0 => String::new(),
+            1 | 2 => names.join(" and ") + " ",
+            n => format!("{}, and {} ", names[..n - 1].join(", "), names[n - 1]),
+        };
+
+        out = escape_html(&out) + &format!("({}).", escape_html(self.year()));
+
+        if let Some(title) = &self.title {
+            out += &format!(" <em>{}</em>.", escape_html(title));
+        }
+
+        for part in [&self.container, &self.publisher].into_iter().flatten() {
+            out += &format!(" {}.", escape_html(part));
+        }
+
+        if let Some(url) = &self.url {
+            out += &format!(" <a href=\"{0}\">{0}</a>", escape_html(url));
+        }
+
+        format!("<li id=\"{}\">{}</li>", anchor(&self.key), out)
+    }
+}
+
+fn anchor(key: &str) -> String {
+    escape_html(&format!("cite-{}", key))
+}
+
+/// `Doe, Jane` or `Jane Doe`.
+fn bibtex_name(name: &str) -> Name {
+    match name.split_once(',') {
+        Some((family, given)) => Name {
+            family: family.trim().to_owned(),
+            given: Some(given.trim().to_owned()).filter(|given| !given.is_empty()),
+        },
+        None => match name.trim().rsplit_once(' ') {
+            Some((given, family)) => Name {
+                family: family.to_owned(),
+                given: Some(given.trim().to_owned()),
+            },
+            None => Name {
+                family: name.trim().to_owned(),
+                given: None,
+            },
+        },
+    }
+}
+
+/// A field value without braces, escapes and repeated whitespace.
+fn clean(value: &str) -> String {
+    value
+        .replace(['{', '}'], "")
+        .replace("\\&", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads up to the `close` that isn't inside braces, and past it.
+fn until(chars: &[char], i: &mut usize, close: char) -> String {
+    let mut depth = 0;
+    let mut out = String::new();
+
+    while let Some(&c) = chars.get(*i) {
+        *i += 1;
+
+        match c {
+            c if c == close && depth == 0 => break,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+fn skip(chars: &[char], i: &mut usize, f: impl Fn(char) -> bool) -> String {
+    let start = *i;
+
+    while chars.get(*i).is_some_and(|c| f(*c)) {
+        *i += 1;
+    }
+
+    chars[start..*i].iter().collect()
+}
+
+/// The entries of a `.bib` file. `@string` abbreviations and `#` concatenation aren't supported.
+pub fn parse_bibtex(source: &str) -> Vec<Entry> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut entries = vec![];
+    let mut i = 0;
+
+    while let Some(at) = chars[i..].iter().position(|c| *c == '@') {
+        i += at + 1;
+
+        let kind = skip(&chars, &mut i, char::is_alphanumeric).to_ascii_lowercase();
+
+        skip(&chars, &mut i, char::is_whitespace);
+
+        let close = match chars.get(i) {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+
+        i += 1;
+
+        if matches!(kind.as_str(), "comment" | "string" | "preamble") {
+            until(&chars, &mut i, close);
+            continue;
+        }
+
+        let key = skip(&chars, &mut i, |c| c != ',' && c != close)
+            .trim()
+            .to_owned();
+        let mut fields: HashMap<String, String> = HashMap::new();
+
+        loop {
+            skip(&chars, &mut i, |c| c.is_whitespace() || c == ',');
+
+            match chars.get(i) {
+                None => break,
+                Some(&c) if c == close => {
+                    i += 1;
+                    break;
+                }
+                _ => {}
+            }
+
+            let name = skip(&chars, &mut i, |c| c != '=' && c != ',' && c != close)
+                .trim()
+                .to_ascii_lowercase();
+
+            if chars.get(i) != Some(&'=') {
+                continue;
+            }
+
+            i += 1;
+            skip(&chars, &mut i, char::is_whitespace);
+
+            let value = match chars.get(i) {
+                Some('{') => {
+                    i += 1;
+                    until(&chars, &mut i, '}')
+                }
+                Some('"') => {
+                    i += 1;
+                    until(&chars, &mut i, '"')
+                }
+                _ => skip(&chars, &mut i, |c| c != ',' && c != close),
+            };
+
+            fields.insert(name, clean(&value));
+        }
+
+        entries.push(Entry {
+            key,
+            authors: fields
+                .get("author")
+                .or(fields.get("editor"))
+                .map(|names| names.split(" and ").map(bibtex_name).collect())
+                .unwrap_or_default(),
+            year: fields.get("year").cloned().or_else(|| {
+                fields
+                    .get("date")
+                    .and_then(|date| date.get(..4))
+                    .map(str::to_owned)
+            }),
+            title: fields.get("title").cloned(),
+            container: fields
+                .get("journal")
+                .or(fields.get("journaltitle"))
+                .or(fields.get("booktitle"))
+                .cloned(),
+            publisher: fields.get("publisher").cloned(),
+            url: fields.get("url").cloned().or_else(|| {
+                fields
+                    .get("doi")
+                    .map(|doi| format!("https://doi.org/{}", doi))
+            }),
+        });
+    }
+
+    entries
+}
+
+/// The entries of a CSL-JSON array.
+pub fn parse_csl_json(source: &str) -> anyhow::Result<Vec<Entry>> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(source)?;
+    let string = |item: &serde_json::Value, field: &str| {
+        item.get(field).and_then(|value| match value {
+            serde_json::Value::String(value) => Some(value.clone()),
+            serde_json::Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        })
+    };
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            Some(Entry {
+                key: string(item, "id")?,
+                authors: item
+                    .get("author")
+                    .or(item.get("editor"))
+                    .and_then(|names| names.as_array())
+                    .map(|names| {
+                        names
+                            .iter()
+                            .filter_map(|name| {
+                                Some(Name {
+                                    family: string(name, "family")
+                                        .or_else(|| string(name, "literal"))?,
+                                    given: string(name, "given"),
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                year: item
+                    .pointer("/issued/date-parts/0/0")
+                    .map(|year| year.to_string().trim_matches('"').to_owned())
+                    .or_else(|| {
+                        item.pointer("/issued/raw")
+                            .and_then(|raw| raw.as_str()?.get(..4))
+                            .map(str::to_owned)
+                    }),
+                title: string(item, "title"),
+                container: string(item, "container-title"),
+                publisher: string(item, "publisher"),
+                url: string(item, "URL")
+                    .or_else(|| string(item, "DOI").map(|doi| format!("https://doi.org/{}", doi))),
+            })
+        })
+        .collect())
+}
+
+/// A `.json` file is read as CSL-JSON, anything else as BibTeX.
+pub fn load(path: &Path) -> anyhow::Result<Vec<Entry>> {
+    let source = std::fs::read_to_string(path)?;
+
+    if path.extension().is_some_and(|ext| ext == "json") {
+        parse_csl_json(&source)
+    } else {
+        Ok(parse_bibtex(&source))
+    }
+}
+
+/// The configured bibliographies, relative to the source directory, and those of
+/// `#+BIBLIOGRAPHY`, relative to the file.
+fn paths(metadata: &HashMap<String, String>, filename: &str, ctx: &FileContext) -> Vec<PathBuf> {
+    let root = ctx.root();
+    let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+
+    ctx.config
+        .bibliography
+        .iter()
+        .flatten()
+        .map(|path| root.join(path))
+        .chain(
+            metadata
+                .get("bibliography")
+                .into_iter()
+                .flat_map(|paths| paths.split_whitespace())
+                .map(|path| dir.join(path)),
+        )
+        .collect()
+}
+
+/// One `[cite:…]`, with the keys it cites, in order.
+fn citation(
+    caps: &Captures,
+    entries: &HashMap<String, Entry>,
+    cited: &mut Vec<String>,
+    unknown: &mut Vec<String>,
+) -> String {
+    let style = caps.name("style").map_or("", |style| style.as_str());
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    let mut items = vec![];
+
+    for part in caps["body"].split(';') {
+        let Ok(Some(key)) = KEY.captures(part) else {
+            if items.is_empty() {
+                prefix = part.trim().to_owned();
+            } else {
+                suffix = part.trim().to_owned();
+            }
+
+            continue;
+        };
+
+        let whole = key.get(0).unwrap();
+        let key = key["key"].to_owned();
+        let (before, after) = (part[..whole.start()].trim(), part[whole.end()..].trim());
+        let link = match entries.get(&key) {
+            Some(entry) => {
+                if !cited.contains(&key) {
+                    cited.push(key.clone());
+                }
+
+                let text = match style {
+                    "t" | "text" => format!("{} ({})", entry.short_authors(), entry.year()),
+                    "na" | "noauthor" => entry.year().to_owned(),
+                    _ => format!("{} {}", entry.short_authors(), entry.year()),
+                };
+
+                format!("<a href=\"#{}\">{}</a>", anchor(&key), escape_html(&text))
+            }
+            None => {
+                unknown.push(key.clone());
+                escape_html(&key)
+            }
+        };
+        let mut item = if before.is_empty() {
+            link
+        } else {
+            format!("{} {}", before, link)
+        };
+
+        if after.starts_with(',') {
+            item += after;
+        } else if !after.is_empty() {
+            item += &format!(" {}", after);
+        }
+
+        items.push(item);
+    }
+
+    let mut inner = items.join("; ");
+
+    if !prefix.is_empty() {
+        inner = format!("{} {}", prefix, inner);
+    }
+
+    if !suffix.is_empty() {
+        inner = format!("{}, {}", inner, suffix);
+    }
+
+    if !matches!(style, "t" | "text") {
+        inner = format!("({})", inner);
+    }
+
+    format!("<span class=\"citation\">{}</span>", inner)
+}
+
+/// Renders the citations in paragraphs and fills in `#+PRINT_BIBLIOGRAPHY:` with the cited
+/// references, sorted by author and year. Bibliographies are only read if there's a citation.
+pub fn resolve(
+    sections: &mut [Section],
+    metadata: &HashMap<String, String>,
+    filename: &str,
+    ctx: &FileContext,
+) {
+    let mut citations = false;
+
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::Paragraph(text) = node {
+                citations = citations || CITATION.is_match(text).unwrap_or(false);
+            }
+        });
+    });
+
+    if !citations {
+        return;
+    }
+
+    let mut entries: HashMap<String, Entry> = HashMap::new();
+
+    for path in paths(metadata, filename, ctx) {
+        match load(&path) {
+            Ok(loaded) => {
+                entries.extend(loaded.into_iter().map(|entry| (entry.key.clone(), entry)))
+            }
+            Err(err) => ctx
+                .warnings
+                .lock()
+                .unwrap()
+                .add("Unreadable bibliographies", format!("{:?}: {}", path, err)),
+        }
+    }
+
+    let mut cited: Vec<String> = vec![];
+    let mut unknown: Vec<String> = vec![];
+
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::Paragraph(text) = node {
+                *text = CITATION
+                    .replace_all(text, |caps: &Captures| {
+                        citation(caps, &entries, &mut cited, &mut unknown)
+                    })
+                    .into_owned();
+            }
+        });
+    });
+
+    for key in unknown {
+        ctx.warnings.lock().unwrap().add(
+            "Unknown citation keys",
+            format!("`{}` in {}", key, filename),
+        );
+    }
+
+    let mut references: Vec<&Entry> = cited.iter().filter_map(|key| entries.get(key)).collect();
+
+    references.sort_by_key(|entry| (entry.short_authors(), entry.year().to_owned()));
+
+    let list = if references.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<div class=\"bibliography\"><ul>{}</ul></div>",
+            references
+                .iter()
+                .map(|entry| entry.to_html())
+                .collect::<String>()
+        )
+    };
+
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::Bibliography(contents) = node {
+                contents.clone_from(&list);
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        config::Config,
+        handler::FileContext,
+        org::{
+            cite::{parse_bibtex, parse_csl_json, Name},
+            Document,
+        },
+    };
+
+    const BIB: &str = r#"@comment{ignored}
+@article{doe2020,
+  author = {Doe, Jane and Roe, Richard},
+  title = {On {Things}},
+  journal = "Journal of Stuff",
+  year = 2020,
+  doi = {10.1000/xyz},
+}
+
+@book{smith, author = {John Smith}, title = {A Book}, publisher = {Press}, date = {2019-05-01}}
+"#;
+
+    #[test]
+    fn bibtex() {
+        let entries = parse_bibtex(BIB);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "doe2020");
+        assert_eq!(
+            entries[0].authors[1],
+            Name {
+                family: "Roe".into(),
+                given: Some("Richard".into())
+            }
+        );
+        assert_eq!(entries[0].title.as_deref(), Some("On Things"));
+        assert_eq!(entries[0].container.as_deref(), Some("Journal of Stuff"));
+        assert_eq!(entries[0].year.as_deref(), Some("2020"));
+        assert_eq!(
+            entries[0].url.as_deref(),
+            Some("https://doi.org/10.1000/xyz")
+        );
+        assert_eq!(entries[1].authors[0].family, "Smith");
+        assert_eq!(entries[1].year.as_deref(), Some("2019"));
+    }
+
+    #[test]
+    fn csl_json() {
+        let entries = parse_csl_json(
+            r#"[{"id": "a", "author": [{"family": "Doe", "given": "J."}, {"literal": "ACME"}],
+                 "title": "T", "issued": {"date-parts": [[2021, 3]]}, "URL": "https://a.example"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(entries[0].key, "a");
+        assert_eq!(entries[0].authors[1].family, "ACME");
+        assert_eq!(entries[0].year.as_deref(), Some("2021"));
+        assert_eq!(entries[0].url.as_deref(), Some("https://a.example"));
+    }
+
+    #[test]
+    fn rendered() {
+        let dir = std::env::temp_dir().join(format!("impertio-cite-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("refs.bib"), BIB).unwrap();
+
+        let ctx = FileContext {
+            config: Arc::new(Config::default()),
+            ..Default::default()
+        };
+        let doc = Document::parse(
+            "#+BIBLIOGRAPHY: refs.bib\nAs [cite/t:@doe2020] shows [cite:see @smith p. 3;@doe2020, ch. 2] and [cite:@nobody].\n\n* References\n#+PRINT_BIBLIOGRAPHY:",
+            dir.join("notes.org").to_str().unwrap(),
+            ctx.clone(),
+        )
+        .unwrap();
+        let html = doc.to_html(&Config::default());
+
+        assert!(html.contains(
+            "As <span class=\"citation\"><a href=\"#cite-doe2020\">Doe and Roe (2020)</a></span> shows <span class=\"citation\">(see <a href=\"#cite-smith\">Smith 2019</a> p. 3; <a href=\"#cite-doe2020\">Doe and Roe 2020</a>, ch. 2)</span> and <span class=\"citation\">(nobody)</span>."
+        ));
+        assert!(html.contains(
+            "<div class=\"bibliography\"><ul><li id=\"cite-doe2020\">Doe, Jane and Roe, Richard (2020). <em>On Things</em>. Journal of Stuff. <a href=\"https://doi.org/10.1000/xyz\">https://doi.org/10.1000/xyz</a></li><li id=\"cite-smith\">Smith, John (2019). <em>A Book</em>. Press.</li></ul></div>"
+        ));
+        assert_eq!(
+            ctx.warnings
+                .lock()
+                .unwrap()
+                .details("Unknown citation keys"),
+            [format!("`nobody` in {}", dir.join("notes.org").display())]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
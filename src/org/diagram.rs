@@ -0,0 +1,273 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Diagram source blocks: Graphviz `dot`, rendered to inline SVG, and `mermaid` and `plantuml`,
+//! rendered by a program or a service, or left to a client-side renderer.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use build_html::escape_html;
+
+use crate::{
+    checksums::sha256_hex,
+    config::{Config, DiagramsConfig},
+    handler::writeable,
+    org::babel::{Exports, HeaderArgs},
+};
+
+/// How a diagram language is rendered.
+enum Renderer<'a> {
+    /// A program that reads the source from its standard input and prints SVG.
+    Command(&'a str, Vec<String>),
+    /// A service the source is POSTed to, which responds with SVG.
+    Url(&'a str),
+    /// `<pre class="{language}">`, for a renderer in the browser.
+    Client,
+}
+
+impl Renderer<'_> {
+    /// What the SVGs it renders are cached by, besides their source.
+    fn key(&self) -> String {
+        match self {
+            Self::Command(command, args) => format!("{} {}", command, args.join(" ")),
+            Self::Url(url) => url.to_string(),
+            Self::Client => String::new(),
+        }
+    }
+
+    fn svg(&self, source: &str) -> anyhow::Result<String> {
+        match self {
+            Self::Command(command, args) => run(command, args, source),
+            Self::Url(url) => Ok(ureq::post(*url)
+                .content_type("text/plain")
+                .send(source)?
+                .body_mut()
+                .read_to_string()?),
+            Self::Client => unreachable!("client-side diagrams aren't rendered"),
+        }
+    }
+}
+
+/// The renderer configured for the language, if any: `dot` is rendered by Graphviz whenever
+/// diagrams are configured, others only if they're configured themselves.
+fn renderer<'a>(diagrams: &'a DiagramsConfig, language: &str) -> Option<Renderer<'a>> {
+    let config = match language {
+        "dot" => return Some(Renderer::Command(diagrams.command(), vec!["-Tsvg".into()])),
+        "mermaid" => diagrams.mermaid.as_ref()?,
+        "plantuml" => diagrams.plantuml.as_ref()?,
+        _ => return None,
+    };
+
+    Some(match (&config.command, &config.url) {
+        (Some(command), _) => Renderer::Command(command, config.args.clone().unwrap_or_default()),
+        (None, Some(url)) => Renderer::Url(url),
+        (None, None) => Renderer::Client,
+    })
+}
+
+/// Whether the block is a diagram to render: its language is configured, and it doesn't ask for
+/// only its code, or nothing, to be exported.
+pub fn renders(config: &Config, args: &str) -> bool {
+    let header = HeaderArgs::parse(&super::babel::split_args(args));
+
+    config
+        .diagrams
+        .as_ref()
+        .zip(header.language.as_deref())
+        .is_some_and(|(diagrams, language)| renderer(diagrams, language).is_some())
+        && !matches!(
+            header.parameters.get("exports").map(String::as_str),
+            Some("code" | "none")
+        )
+}
+
+/// Whether the code is shown before the diagram, with `:exports both`.
+pub fn shows_code(args: &[String]) -> bool {
+    HeaderArgs::parse(args).exports == Exports::Both
+}
+
+/// The `<svg>` element, without the XML declaration, doctype and comments before it.
+fn svg_element(svg: &str) -> &str {
+    svg.find("<svg").map_or(svg, |start| &svg[start..]).trim()
+}
+
+/// Runs the program with the source on its standard input, returning what it prints.
+fn run(command: &str, args: &[String], source: &str) -> anyhow::Result<String> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| anyhow::anyhow!("Failed to run `{}`: {}", command, err))?;
+
+    child.stdin.take().unwrap().write_all(source.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "`{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The diagram of the block with these arguments: an inline SVG in a `<div class="diagram">`,
+/// or the source in a `<pre>` for a client-side renderer. Rendered SVGs are cached under the
+/// source directory by the hash of the renderer and the source, so unchanged diagrams aren't
+/// rendered again.
+pub fn render(
+    source: &str,
+    args: &[String],
+    config: &Config,
+    root: &Path,
+) -> anyhow::Result<String> {
+    let diagrams = config.diagrams.clone().unwrap_or_default();
+    let language = HeaderArgs::parse(args).language.unwrap_or_default();
+    let Some(renderer) = renderer(&diagrams, &language) else {
+        anyhow::bail!("No renderer for `{}` diagrams", language);
+    };
+
+    if let Renderer::Client = renderer {
+        return Ok(format!(
+            "<pre class=\"{}\">{}</pre>",
+            escape_html(&language),
+            escape_html(source)
+        ));
+    }
+
+    let cached = root.join(diagrams.cache()).join(format!(
+        "{}.svg",
+        sha256_hex(format!("{}\n{}", renderer.key(), source).as_bytes())
+    ));
+    let svg = match std::fs::read_to_string(&cached) {
+        Ok(svg) => svg,
+        Err(_) => {
+            let svg = svg_element(&renderer.svg(source)?).to_owned();
+
+            writeable(&cached)?.write_all(svg.as_bytes())?;
+
+            svg
+        }
+    };
+
+    Ok(format!("<div class=\"diagram\">{}</div>", svg))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        checksums::sha256_hex,
+        config::{Config, DiagramsConfig},
+        handler::FileContext,
+        logging::Warnings,
+        org::{diagram::svg_element, Document, Node},
+    };
+
+    #[test]
+    fn svg() {
+        assert_eq!(
+            svg_element(
+                "<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<!-- G -->\n<svg width=\"8pt\"></svg>\n"
+            ),
+            "<svg width=\"8pt\"></svg>"
+        );
+    }
+
+    #[test]
+    fn cached_and_failed() {
+        let root = std::env::temp_dir().join(format!("impertio-diagrams-{}", std::process::id()));
+        let source = "digraph { a -> b }";
+        let ctx = FileContext {
+            source_path: root.join("graph.org"),
+            relative_path: "graph.org".into(),
+            config: Arc::new(Config {
+                diagrams: Some(DiagramsConfig {
+                    command: Some("impertio-no-such-dot".into()),
+                    cache: Some("cache".into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            warnings: Arc::new(Mutex::new(Warnings::default())),
+            ..Default::default()
+        };
+        let org = format!(
+            "#+CAPTION: Two nodes.\n#+BEGIN_SRC dot :file graph.svg :exports both\n{}\n#+END_SRC",
+            source
+        );
+
+        let doc = Document::parse(&org, "graph.org", ctx.clone()).unwrap();
+
+        assert!(matches!(
+            &doc.sections[0].nodes[0],
+            Node::Labeled { kind, node, .. }
+                if kind == "Listing" && matches!(node.as_ref(), Node::LesserBlock { type_, .. } if type_ == "src")
+        ));
+        assert_eq!(
+            ctx.warnings
+                .lock()
+                .unwrap()
+                .details("Diagrams not rendered")
+                .len(),
+            1
+        );
+
+        let cached = root.join("cache").join(format!(
+            "{}.svg",
+            sha256_hex(format!("impertio-no-such-dot -Tsvg\n{}", source).as_bytes())
+        ));
+
+        std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+        std::fs::write(&cached, "<svg></svg>").unwrap();
+
+        let doc = Document::parse(&org, "graph.org", ctx.clone()).unwrap();
+        let html = doc.to_html(&Default::default());
+
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(html.contains("<pre"), "{}", html);
+        assert!(html.contains(
+            "<figure class=\"figure\"><figcaption><span class=\"figure-number\">Figure 1:</span> Two nodes.</figcaption><div class=\"diagram\"><svg></svg></div></figure>"
+        ));
+
+        let doc = Document::parse(&org.replace("both", "code"), "graph.org", ctx).unwrap();
+
+        assert!(!doc.to_html(&Default::default()).contains("diagram"));
+    }
+
+    #[test]
+    fn client_side() {
+        let ctx = FileContext {
+            config: Arc::new(Config {
+                diagrams: Some(DiagramsConfig {
+                    mermaid: Some(Default::default()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let html = |source: &str| {
+            Document::parse(source, "graph.org", ctx.clone())
+                .unwrap()
+                .to_html(&Default::default())
+        };
+
+        assert!(html("#+BEGIN_SRC mermaid\ngraph TD; A-->B\n#+END_SRC")
+            .contains("<pre class=\"mermaid\">graph TD; A--&gt;B</pre>"));
+        assert!(!html("#+BEGIN_SRC plantuml\n@startuml\n@enduml\n#+END_SRC")
+            .contains("class=\"plantuml\""));
+    }
+}
@@ -0,0 +1,280 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Org's `\name` entities, as in `org-entities`.
+
+/// Entity names and what they stand for. Characters with a meaning in HTML stay escaped.
+const ENTITIES: &[(&str, &str)] = &[
+    // Greek
+    ("Alpha", "Α"),
+    ("Beta", "Β"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Epsilon", "Ε"),
+    ("Zeta", "Ζ"),
+    ("Eta", "Η"),
+    ("Theta", "Θ"),
+    ("Iota", "Ι"),
+    ("Kappa", "Κ"),
+    ("Lambda", "Λ"),
+    ("Mu", "Μ"),
+    ("Nu", "Ν"),
+    ("Xi", "Ξ"),
+    ("Omicron", "Ο"),
+    ("Pi", "Π"),
+    ("Rho", "Ρ"),
+    ("Sigma", "Σ"),
+    ("Tau", "Τ"),
+    ("Upsilon", "Υ"),
+    ("Phi", "Φ"),
+    ("Chi", "Χ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("omicron", "ο"),
+    ("pi", "π"),
+    ("varpi", "ϖ"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("sigmaf", "ς"),
+    ("varsigma", "ς"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    // Punctuation and typography
+    ("nbsp", "\u{a0}"),
+    ("ensp", "\u{2002}"),
+    ("emsp", "\u{2003}"),
+    ("thinsp", "\u{2009}"),
+    ("shy", "\u{ad}"),
+    ("ndash", "–"),
+    ("mdash", "—"),
+    ("hellip", "…"),
+    ("dots", "…"),
+    ("laquo", "«"),
+    ("raquo", "»"),
+    ("lsaquo", "‹"),
+    ("rsaquo", "›"),
+    ("lsquo", "‘"),
+    ("rsquo", "’"),
+    ("ldquo", "“"),
+    ("rdquo", "”"),
+    ("sbquo", "‚"),
+    ("bdquo", "„"),
+    ("iexcl", "¡"),
+    ("iquest", "¿"),
+    ("para", "¶"),
+    ("sect", "§"),
+    ("middot", "·"),
+    ("bull", "•"),
+    ("bullet", "•"),
+    ("dagger", "†"),
+    ("Dagger", "‡"),
+    ("ddagger", "‡"),
+    ("prime", "′"),
+    ("Prime", "″"),
+    ("copy", "©"),
+    ("reg", "®"),
+    ("trade", "™"),
+    ("deg", "°"),
+    ("checkmark", "✓"),
+    // HTML's own
+    ("amp", "&amp;"),
+    ("lt", "&lt;"),
+    ("gt", "&gt;"),
+    ("quot", "&quot;"),
+    ("backslash", "\\"),
+    // Currency
+    ("cent", "¢"),
+    ("pound", "£"),
+    ("yen", "¥"),
+    ("euro", "€"),
+    ("EUR", "€"),
+    ("curren", "¤"),
+    // Maths
+    ("pm", "±"),
+    ("plusmn", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "⋅"),
+    ("minus", "−"),
+    ("ast", "∗"),
+    ("le", "≤"),
+    ("leq", "≤"),
+    ("ge", "≥"),
+    ("geq", "≥"),
+    ("ne", "≠"),
+    ("neq", "≠"),
+    ("approx", "≈"),
+    ("sim", "∼"),
+    ("equiv", "≡"),
+    ("propto", "∝"),
+    ("infin", "∞"),
+    ("infty", "∞"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("radic", "√"),
+    ("sqrt", "√"),
+    ("forall", "∀"),
+    ("exist", "∃"),
+    ("exists", "∃"),
+    ("empty", "∅"),
+    ("emptyset", "∅"),
+    ("isin", "∈"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("ni", "∋"),
+    ("sub", "⊂"),
+    ("subset", "⊂"),
+    ("sup", "⊃"),
+    ("supset", "⊃"),
+    ("sube", "⊆"),
+    ("subseteq", "⊆"),
+    ("supe", "⊇"),
+    ("supseteq", "⊇"),
+    ("cap", "∩"),
+    ("cup", "∪"),
+    ("and", "∧"),
+    ("wedge", "∧"),
+    ("or", "∨"),
+    ("vee", "∨"),
+    ("not", "¬"),
+    ("neg", "¬"),
+    ("there4", "∴"),
+    ("therefore", "∴"),
+    ("because", "∵"),
+    ("frac12", "½"),
+    ("frac14", "¼"),
+    ("frac34", "¾"),
+    ("sup1", "¹"),
+    ("sup2", "²"),
+    ("sup3", "³"),
+    ("micro", "µ"),
+    ("hbar", "ℏ"),
+    ("ell", "ℓ"),
+    ("aleph", "ℵ"),
+    ("Re", "ℜ"),
+    ("Im", "ℑ"),
+    ("wp", "℘"),
+    // Arrows
+    ("larr", "←"),
+    ("leftarrow", "←"),
+    ("gets", "←"),
+    ("rarr", "→"),
+    ("rightarrow", "→"),
+    ("to", "→"),
+    ("uarr", "↑"),
+    ("uparrow", "↑"),
+    ("darr", "↓"),
+    ("downarrow", "↓"),
+    ("harr", "↔"),
+    ("leftrightarrow", "↔"),
+    ("lArr", "⇐"),
+    ("Leftarrow", "⇐"),
+    ("rArr", "⇒"),
+    ("Rightarrow", "⇒"),
+    ("uArr", "⇑"),
+    ("dArr", "⇓"),
+    ("hArr", "⇔"),
+    ("Leftrightarrow", "⇔"),
+    ("crarr", "↵"),
+    ("mapsto", "↦"),
+    // Letters
+    ("Agrave", "À"),
+    ("agrave", "à"),
+    ("Aacute", "Á"),
+    ("aacute", "á"),
+    ("Acirc", "Â"),
+    ("acirc", "â"),
+    ("Auml", "Ä"),
+    ("auml", "ä"),
+    ("Aring", "Å"),
+    ("aring", "å"),
+    ("AElig", "Æ"),
+    ("aelig", "æ"),
+    ("Ccedil", "Ç"),
+    ("ccedil", "ç"),
+    ("Egrave", "È"),
+    ("egrave", "è"),
+    ("Eacute", "É"),
+    ("eacute", "é"),
+    ("Ecirc", "Ê"),
+    ("ecirc", "ê"),
+    ("Euml", "Ë"),
+    ("euml", "ë"),
+    ("Iacute", "Í"),
+    ("iacute", "í"),
+    ("Iuml", "Ï"),
+    ("iuml", "ï"),
+    ("Ntilde", "Ñ"),
+    ("ntilde", "ñ"),
+    ("Oacute", "Ó"),
+    ("oacute", "ó"),
+    ("Ouml", "Ö"),
+    ("ouml", "ö"),
+    ("Oslash", "Ø"),
+    ("oslash", "ø"),
+    ("OElig", "Œ"),
+    ("oelig", "œ"),
+    ("szlig", "ß"),
+    ("Uacute", "Ú"),
+    ("uacute", "ú"),
+    ("Uuml", "Ü"),
+    ("uuml", "ü"),
+    ("yuml", "ÿ"),
+    ("eth", "ð"),
+    ("thorn", "þ"),
+    // Miscellaneous
+    ("star", "⋆"),
+    ("spades", "♠"),
+    ("clubs", "♣"),
+    ("hearts", "♥"),
+    ("diams", "♦"),
+    ("smiley", "☺"),
+    ("frowny", "☹"),
+];
+
+/// What `\name` stands for, if it's an entity.
+pub fn lookup(name: &str) -> Option<&'static str> {
+    ENTITIES
+        .iter()
+        .find(|(entity, _)| *entity == name)
+        .map(|(_, replacement)| *replacement)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::org::entities::lookup;
+
+    #[test]
+    fn lookups() {
+        assert_eq!(lookup("alpha"), Some("α"));
+        assert_eq!(lookup("Alpha"), Some("Α"));
+        assert_eq!(lookup("rarr"), Some("→"));
+        assert_eq!(lookup("lt"), Some("&lt;"));
+        assert_eq!(lookup("nonsense"), None);
+    }
+}
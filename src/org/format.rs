@@ -0,0 +1,204 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Normalizes Org source without going through the parser, so nothing is lost.
+
+use std::path::{Path, PathBuf};
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+use super::serialize;
+
+lazy_static! {
+    static ref KEYWORD: Regex =
+        Regex::new(r"^(?<indent>\s*)#\+(?<name>[A-Za-z_-]+)(?<rest>:.*|\s.*)?$").unwrap();
+    static ref VERBATIM_BEGIN: Regex =
+        Regex::new(r"(?i)^\s*#\+begin_(?<kind>src|example|export)\b").unwrap();
+    static ref HEADING: Regex = Regex::new(r"^\*+\s").unwrap();
+    static ref TABLE: Regex = Regex::new(r"^(?<indent>\s*)\|").unwrap();
+}
+
+/// `contents` with keyword names uppercased, one blank line before each heading, blank runs
+/// collapsed, trailing whitespace removed and tables aligned. Block contents are kept as is.
+pub fn format(contents: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut table: Vec<&str> = vec![];
+    let mut verbatim: Option<String> = None;
+
+    for line in contents.lines() {
+        if let Some(kind) = &verbatim {
+            if line
+                .trim()
+                .to_lowercase()
+                .starts_with(&format!("#+end_{}", kind))
+            {
+                lines.push(keyword(line.trim_end()));
+                verbatim = None;
+            } else {
+                lines.push(line.into());
+            }
+
+            continue;
+        }
+
+        let line = line.trim_end();
+
+        if TABLE.is_match(line).unwrap_or(false) {
+            table.push(line);
+            continue;
+        }
+
+        if !table.is_empty() {
+            lines.push(align(&table));
+            table.clear();
+        }
+
+        if let Ok(Some(captures)) = VERBATIM_BEGIN.captures(line) {
+            verbatim = Some(captures["kind"].to_lowercase());
+        }
+
+        if line.is_empty() {
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+        } else if HEADING.is_match(line).unwrap_or(false) {
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+
+            lines.push(line.into());
+        } else {
+            lines.push(keyword(line));
+        }
+    }
+
+    if !table.is_empty() {
+        lines.push(align(&table));
+    }
+
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+
+    if lines.is_empty() {
+        return String::new();
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// `line` with an uppercased keyword name, if it's a keyword line.
+fn keyword(line: &str) -> String {
+    match KEYWORD.captures(line) {
+        Ok(Some(captures)) => format!(
+            "{}#+{}{}",
+            &captures["indent"],
+            captures["name"].to_uppercase(),
+            captures.name("rest").map_or("", |rest| rest.as_str())
+        ),
+        _ => line.into(),
+    }
+}
+
+/// Contiguous table lines, aligned with the first line's indentation.
+fn align(lines: &[&str]) -> String {
+    let indent = TABLE
+        .captures(lines[0])
+        .ok()
+        .flatten()
+        .map_or("", |captures| captures.name("indent").unwrap().as_str());
+
+    let rows: Vec<Option<Vec<String>>> = lines
+        .iter()
+        .map(|line| {
+            let line = line.trim();
+
+            if line.starts_with("|-") {
+                return None;
+            }
+
+            let inner = line.strip_prefix('|').unwrap_or(line);
+            let inner = inner.strip_suffix('|').unwrap_or(inner);
+
+            Some(
+                inner
+                    .split('|')
+                    .map(|cell| cell.trim().to_owned())
+                    .collect(),
+            )
+        })
+        .collect();
+
+    serialize::table(&rows, indent)
+}
+
+/// Formats the `.org` files in `paths`, which may be files or directories, returning those
+/// that changed. Nothing is written when `check` is set.
+pub fn format_paths(paths: &[PathBuf], check: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut changed = vec![];
+
+    for path in paths {
+        for entry in walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+        {
+            let entry = entry?;
+
+            if !entry.file_type().is_file() || !is_org(entry.path()) {
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(entry.path())?;
+            let formatted = format(&contents);
+
+            if formatted != contents {
+                if !check {
+                    std::fs::write(entry.path(), &formatted)?;
+                }
+
+                changed.push(entry.path().to_owned());
+            }
+        }
+    }
+
+    Ok(changed)
+}
+
+fn is_org(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "org")
+}
+
+#[cfg(test)]
+mod test {
+    use super::format;
+
+    #[test]
+    fn normalizes() {
+        let input = "#+title: Test   \n* One\ntext\n\n\n\n** Two\n|a|bb|\n|-\n| ccc | d |\n#+begin_quote\nq\n#+end_quote\n\n\n";
+
+        assert_eq!(
+            format(input),
+            "#+TITLE: Test\n\n* One\ntext\n\n** Two\n| a   | bb |\n|-----+----|\n| ccc | d  |\n#+BEGIN_QUOTE\nq\n#+END_QUOTE\n"
+        );
+    }
+
+    #[test]
+    fn keeps_verbatim_blocks() {
+        let input = "#+begin_src python  \nx = 1   \n\n\n#+title: no\n#+end_src\n";
+
+        assert_eq!(
+            format(input),
+            "#+BEGIN_SRC python\nx = 1   \n\n\n#+title: no\n#+END_SRC\n"
+        );
+    }
+
+    #[test]
+    fn idempotent() {
+        let once = format("* A\n  | x | y |\n  |---\n  | 10 | 2 |\n* B\n#+options: toc:nil\n");
+
+        assert_eq!(format(&once), once);
+        assert!(once.contains("\n\n* B\n"));
+        assert!(once.contains("  | 10 | 2 |\n"));
+    }
+}
@@ -1,80 +1,868 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
-use crate::org::{Document, Node};
-use build_html::{Container, ContainerType, Html, HtmlContainer, Table};
+use crate::{
+    checksums::sha256_hex,
+    config::AdmonitionConfig,
+    org::{
+        babel::{HeaderArgs, LineNumbers},
+        options::ExportOptions,
+        Document, Node, Section,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use build_html::{escape_html, Container, ContainerType, Html, HtmlContainer, Table};
+use lazy_static::lazy_static;
+use syntect::{
+    easy::HighlightLines,
+    highlighting::ThemeSet,
+    html::{start_highlighted_html_snippet, styled_line_to_highlighted_html, IncludeBackground},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+}
+
+/// Renders a heading's subtree collapsed, in `<details>` with the heading as its `<summary>`.
+pub(crate) const DETAILS_TAG: &str = "details";
 
 pub struct HtmlBuilder {
     builder: Container,
+    theme: Option<String>,
+    /// Where a `+n` source block continues numbering from.
+    next_line_number: Cell<usize>,
+    /// Tab groups rendered so far, to give each unique ids.
+    tab_groups: Cell<usize>,
+    heading_anchors: bool,
+    /// Give every this many paragraphs an id.
+    paragraph_anchors: Option<usize>,
+    /// Paragraphs rendered so far.
+    paragraphs: Cell<usize>,
+    /// Paragraph ids given so far, to make repeats unique.
+    paragraph_ids: RefCell<HashSet<String>>,
+    table_of_contents: Option<String>,
+    /// The document's `#+OPTIONS`.
+    options: ExportOptions,
+    /// Configured admonitions, by block type.
+    admonitions: HashMap<String, AdmonitionConfig>,
 }
 
+/// Switches the tabs of the group with the given id on click, and with the arrow, Home and End
+/// keys.
+const TABS_SCRIPT: &str = r#"(function (id) {
+  var tabs = Array.from(document.getElementById(id).querySelectorAll("[role=tab]"));
+  function select(tab) {
+    tabs.forEach(function (other) {
+      var selected = other === tab;
+      other.setAttribute("aria-selected", selected);
+      other.tabIndex = selected ? 0 : -1;
+      document.getElementById(other.getAttribute("aria-controls")).hidden = !selected;
+    });
+    tab.focus();
+  }
+  tabs.forEach(function (tab, i) {
+    tab.addEventListener("click", function () { select(tab); });
+    tab.addEventListener("keydown", function (event) {
+      var next = { ArrowLeft: i - 1, ArrowRight: i + 1, Home: 0, End: tabs.length - 1 }[event.key];
+      if (next === undefined) return;
+      event.preventDefault();
+      select(tabs[(next + tabs.length) % tabs.length]);
+    });
+  });
+})"#;
+
+/// Block types rendered as admonitions unless configured otherwise, with their icons.
+const ADMONITIONS: [(&str, &str); 5] = [
+    ("note", "ℹ️"),
+    ("tip", "💡"),
+    ("important", "❗"),
+    ("warning", "⚠️"),
+    ("caution", "🔥"),
+];
+
 impl HtmlBuilder {
     pub fn new() -> Self {
         Self {
             builder: Container::new(ContainerType::Div).with_attributes(vec![("class", "article")]),
+            theme: None,
+            next_line_number: Cell::new(1),
+            tab_groups: Cell::new(0),
+            heading_anchors: false,
+            paragraph_anchors: None,
+            paragraphs: Cell::new(0),
+            paragraph_ids: RefCell::new(HashSet::new()),
+            table_of_contents: None,
+            options: ExportOptions::default(),
+            admonitions: HashMap::new(),
         }
     }
 
-    pub fn from_document(&mut self, doc: &Document) -> String {
-        for section in &doc.sections {
-            if section.commented {
+    /// Render these greater blocks as admonitions, besides the built-in ones.
+    pub fn with_admonitions(mut self, admonitions: HashMap<String, AdmonitionConfig>) -> Self {
+        self.admonitions = admonitions;
+        self
+    }
+
+    /// `<div class="admonition admonition-{type}">`, if `type_` is an admonition.
+    fn admonition(&self, type_: &str, inner: &str) -> Option<String> {
+        let default_icon = ADMONITIONS
+            .iter()
+            .find(|(admonition, _)| *admonition == type_)
+            .map(|(_, icon)| *icon);
+        let config = self.admonitions.get(type_);
+
+        if config.is_none() && default_icon.is_none() {
+            return None;
+        }
+
+        let config = config.cloned().unwrap_or_default();
+        let title = config.title.unwrap_or_else(|| {
+            let mut chars = type_.chars();
+
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        });
+        let icon = config
+            .icon
+            .or(default_icon.map(str::to_owned))
+            .map(|icon| format!("<span class=\"admonition-icon\">{}</span> ", icon))
+            .unwrap_or_default();
+        let class = config
+            .class
+            .map(|class| format!(" {}", escape_html(&class)))
+            .unwrap_or_default();
+
+        Some(format!(
+            "<div class=\"admonition admonition-{}{}\"><p class=\"admonition-title\">{}{}</p>{}</div>",
+            escape_html(type_),
+            class,
+            icon,
+            escape_html(&title),
+            inner
+        ))
+    }
+
+    /// A `#+BEGIN_TABS` block's `#+BEGIN_TAB name` blocks as tabs in the WAI-ARIA pattern: a
+    /// `tablist` of buttons, each controlling a `tabpanel`, switched by click or arrow keys.
+    /// Anything else in the block comes before the tabs.
+    fn tabs(&self, contents: &[Node]) -> String {
+        let group = self.tab_groups.get() + 1;
+        let mut before = Fragment::default();
+        let mut buttons = String::new();
+        let mut panels = String::new();
+        let mut count = 0;
+
+        self.tab_groups.set(group);
+
+        for node in contents {
+            let Node::GreaterBlock {
+                type_,
+                args,
+                contents,
+            } = node
+            else {
+                self.add_node(&mut before, node);
                 continue;
+            };
+
+            if type_ != "tab" {
+                self.add_node(&mut before, node);
+                continue;
+            }
+
+            let mut panel = Fragment::default();
+            let first = count == 0;
+            let id = format!("tabs-{}-{}", group, count + 1);
+            let name = if args.is_empty() {
+                format!("Tab {}", count + 1)
+            } else {
+                escape_html(args)
+            };
+
+            for node in contents {
+                self.add_node(&mut panel, node);
             }
 
+            buttons += &format!(
+                "<button type=\"button\" role=\"tab\" id=\"{0}\" aria-controls=\"{0}-panel\" aria-selected=\"{1}\"{2}>{3}</button>",
+                id,
+                first,
+                if first { "" } else { " tabindex=\"-1\"" },
+                name
+            );
+            panels += &format!(
+                "<div role=\"tabpanel\" id=\"{0}-panel\" aria-labelledby=\"{0}\" tabindex=\"0\"{1}>{2}</div>",
+                id,
+                if first { "" } else { " hidden" },
+                panel.0
+            );
+            count += 1;
+        }
+
+        format!(
+            "{0}<div class=\"tabs\" id=\"tabs-{1}\"><div role=\"tablist\">{2}</div>{3}</div><script>{4}(\"tabs-{1}\");</script>",
+            before.0, group, buttons, panels, TABS_SCRIPT
+        )
+    }
+
+    /// Put a rendered table of contents before the content.
+    pub fn with_table_of_contents(mut self, toc: String) -> Self {
+        self.table_of_contents = Some(toc);
+        self
+    }
+
+    /// Add a `¶` link to each heading's anchor.
+    pub fn with_heading_anchors(mut self) -> Self {
+        self.heading_anchors = true;
+        self
+    }
+
+    /// Give the first paragraph and every `every`th after it an id from a hash of its text, so
+    /// links to it survive edits elsewhere, with an empty `paragraph-anchor` link to it for
+    /// scripts to offer.
+    pub fn with_paragraph_anchors(mut self, every: usize) -> Self {
+        self.paragraph_anchors = Some(every.max(1));
+        self
+    }
+
+    /// `p-` and the start of the hash of the paragraph's text, with `-1`, `-2`, … added to
+    /// repeats.
+    fn paragraph_id(&self, content: &str) -> String {
+        let text = plain_text(content)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let base = format!("p-{}", &sha256_hex(text.as_bytes())[..8]);
+        let mut ids = self.paragraph_ids.borrow_mut();
+        let mut candidate = base.clone();
+        let mut n = 0;
+
+        while ids.contains(&candidate) {
+            n += 1;
+            candidate = format!("{}-{}", base, n);
+        }
+
+        ids.insert(candidate.clone());
+        candidate
+    }
+
+    /// Highlight source blocks at build time with the given syntect theme.
+    pub fn with_highlighting(mut self, theme: &str) -> Self {
+        if THEMES.themes.contains_key(theme) {
+            self.theme = Some(theme.to_owned());
+        } else {
+            log::warn!("Unknown highlighting theme `{}`, not highlighting.", theme);
+        }
+
+        self
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_document(&mut self, doc: &Document) -> String {
+        let mut builder = std::mem::take(&mut self.builder);
+
+        self.options = doc.options.clone();
+
+        if let Some(toc) = &self.table_of_contents {
+            builder.add_raw(toc);
+        }
+
+        for section in &doc.sections {
+            self.add_section(&mut builder, section);
+        }
+
+        self.builder = builder;
+        self.builder.to_html_string()
+    }
+
+    /// One of `doc`'s sections on its own, without the article around it.
+    #[allow(clippy::wrong_self_convention)]
+    pub fn from_section(&mut self, doc: &Document, section: &Section) -> String {
+        let mut fragment = Fragment::default();
+
+        self.options = doc.options.clone();
+        self.add_section(&mut fragment, section);
+
+        fragment.0
+    }
+
+    /// A `<section>` holding the heading, its content and its subsections. The content before
+    /// the first heading isn't wrapped.
+    fn add_section<C: HtmlContainer>(&self, container: &mut C, section: &Section) {
+        if section.commented {
+            return;
+        }
+
+        if !matches!(section.nodes.first(), Some(Node::Heading { .. })) {
             for node in &section.nodes {
-                match node {
-                    Node::Heading { level, title, .. } => {
-                        self.builder.add_header(*level, title);
+                self.add_node(container, node);
+            }
+
+            return;
+        }
+
+        let mut inner = Container::new(ContainerType::Section);
+
+        if let Some(Node::Heading { tags, .. }) = section.nodes.first() {
+            if tags.iter().any(|tag| tag == DETAILS_TAG) {
+                let (mut summary, mut body) = (Fragment::default(), Fragment::default());
+
+                self.add_node(&mut summary, &section.nodes[0]);
+
+                for node in &section.nodes[1..] {
+                    self.add_node(&mut body, node);
+                }
+
+                for child in &section.children {
+                    self.add_section(&mut body, child);
+                }
+
+                inner.add_raw(format!(
+                    "<details><summary>{}</summary>{}</details>",
+                    summary.0, body.0
+                ));
+                container.add_container(inner);
+
+                return;
+            }
+        }
+
+        for node in &section.nodes {
+            self.add_node(&mut inner, node);
+        }
+
+        for child in &section.children {
+            self.add_section(&mut inner, child);
+        }
+
+        container.add_container(inner);
+    }
+
+    fn add_node<C: HtmlContainer>(&self, container: &mut C, node: &Node) {
+        match node {
+            Node::Heading {
+                level,
+                title,
+                todo_state,
+                priority,
+                completion,
+                tags,
+                id,
+                number,
+                ..
+            } => {
+                let mut header = title.clone();
+
+                if let Some(number) = number {
+                    header = format!("<span class=\"section-number\">{}</span> {}", number, header);
+                }
+
+                if let Some(priority) = priority.as_ref().filter(|_| self.options.priority) {
+                    header = format!(
+                        "<span class=\"priority priority-{0}\">[#{0}]</span> {1}",
+                        escape_html(priority),
+                        header
+                    );
+                }
+
+                if let Some(state) = todo_state.as_ref().filter(|_| self.options.todo) {
+                    header = format!(
+                        "<span class=\"todo-keyword todo-{0}\">{0}</span> {1}",
+                        escape_html(state),
+                        header
+                    );
+                }
+
+                if let Some(completion) = completion {
+                    header = format!(
+                        "{} <span class=\"statistics-cookie{}\">[{}]</span>",
+                        header,
+                        if Self::is_complete(completion) { " done" } else { "" },
+                        escape_html(completion)
+                    );
+                }
+
+                let tags: Vec<&String> = tags.iter().filter(|tag| *tag != DETAILS_TAG).collect();
+
+                if self.options.tags && !tags.is_empty() {
+                    header = format!(
+                        "{} <span class=\"tag\">{}</span>",
+                        header,
+                        tags.iter()
+                            .map(|tag| format!("<span class=\"{0}\">{0}</span>", escape_html(tag)))
+                            .collect::<Vec<_>>()
+                            .join("")
+                    );
+                }
+
+                if self.heading_anchors && !id.is_empty() {
+                    header = format!(
+                        "{} <a class=\"heading-anchor\" href=\"#{}\">¶</a>",
+                        header,
+                        escape_html(id)
+                    );
+                }
+
+                if id.is_empty() {
+                    container.add_header(*level, header);
+                } else {
+                    container.add_header_attr(*level, header, [("id", id.as_str())]);
+                }
+            }
+            Node::Planning(entries) => {
+                container.add_paragraph_attr(
+                    entries
+                        .iter()
+                        .map(|(kind, timestamp)| {
+                            format!(
+                                "<span class=\"timestamp-kind\">{}:</span> {}",
+                                kind,
+                                timestamp.to_html()
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    [("class", "planning")],
+                );
+            }
+            Node::Paragraph(content) => {
+                let count = self.paragraphs.replace(self.paragraphs.get() + 1);
+
+                match self.paragraph_anchors {
+                    Some(every) if count.is_multiple_of(every) => {
+                        let id = self.paragraph_id(content);
+
+                        container.add_paragraph_attr(
+                            format!(
+                                "{} <a class=\"paragraph-anchor\" href=\"#{}\" aria-label=\"Link to this paragraph\"></a>",
+                                content.replace('\n', "<br />"),
+                                id
+                            ),
+                            [("id", id.as_str())],
+                        );
                     }
-                    Node::Paragraph(content) => {
-                        self.builder.add_paragraph(content.replace("\n", "<br />"));
+                    _ => container.add_paragraph(content.replace('\n', "<br />")),
+                }
+            }
+            Node::HorizontalRule => container.add_raw("<hr />"),
+            // Where "Read more" links in listings lead.
+            Node::ExcerptSeparator => container.add_raw("<span id=\"more\"></span>"),
+            Node::IndexTerm { id, .. } => container.add_raw(format!(
+                "<span id=\"{}\" class=\"index-term\"></span>",
+                escape_html(id)
+            )),
+            Node::Bibliography(contents) => container.add_raw(contents),
+            Node::FixedWidth(contents) => container.add_raw(format!(
+                "<pre class=\"example\">{}</pre>",
+                escape_html(contents)
+            )),
+            Node::LesserBlock {
+                type_,
+                args,
+                contents,
+            } => match type_.as_str() {
+                "src" => {
+                    let header = HeaderArgs::parse(args);
+
+                    if header.exports.shows_code() {
+                        container.add_raw(self.source_block(contents, &header));
                     }
-                    Node::LesserBlock {
-                        type_,
-                        args,
-                        contents,
-                    } => match type_.as_str() {
-                        "src" => {
-                            if args.len() > 0 {
-                                self.builder.add_preformatted(format!(
-                                    "<code class=\"language-{}\">{}</code>",
-                                    args[0], contents
-                                ));
-                            } else {
-                                self.builder
-                                    .add_preformatted(format!("<code>{}</code>", contents));
-                            }
-                        }
-                        "export" => {
-                            if args.last() == Some(&"html".to_owned()) {
-                                self.builder.add_raw(contents);
-                            }
-                        }
-                        _ => {
-                            todo!();
-                        }
+                }
+                "export" => {
+                    if args.last() == Some(&"html".to_owned()) {
+                        container.add_raw(contents);
+                    }
+                }
+                "example" => {
+                    container.add_preformatted(escape_html(contents));
+                }
+                "verse" => {
+                    container.add_paragraph_attr(
+                        contents
+                            .lines()
+                            .map(|line| {
+                                let indent = line.len() - line.trim_start().len();
+                                "&nbsp;".repeat(indent) + line.trim_start()
+                            })
+                            .collect::<Vec<String>>()
+                            .join("<br />"),
+                        [("class", "verse")],
+                    );
+                }
+                _ => {
+                    container.add_preformatted(escape_html(contents));
+                }
+            },
+            Node::GreaterBlock {
+                type_, contents, ..
+            } if type_ == "tabs" => container.add_raw(self.tabs(contents)),
+            Node::GreaterBlock { type_, contents, .. } => {
+                let mut inner = Fragment::default();
+
+                for node in contents {
+                    self.add_node(&mut inner, node);
+                }
+
+                if let Some(admonition) = self.admonition(type_, &inner.0) {
+                    container.add_raw(admonition);
+                    return;
+                }
+
+                container.add_raw(match type_.as_str() {
+                    "quote" => format!("<blockquote>{}</blockquote>", inner.0),
+                    "center" => format!(
+                        "<div class=\"center\" style=\"text-align: center\">{}</div>",
+                        inner.0
+                    ),
+                    _ => format!("<div class=\"{}\">{}</div>", escape_html(type_), inner.0),
+                });
+            }
+            Node::Labeled {
+                kind,
+                number,
+                name,
+                caption,
+                node,
+            } => {
+                let mut inner = Fragment::default();
+                let id = name
+                    .as_ref()
+                    .map(|name| format!(" id=\"{}\"", escape_html(name)))
+                    .unwrap_or_default();
+
+                // The figure's caption is the image's, and goes after it.
+                if let (
+                    Node::Image {
+                        path, attributes, ..
                     },
-                    Node::Table { rows } => {
-                        self.builder.add_table(Table::from(rows));
+                    Some(caption),
+                ) = (node.as_ref(), caption)
+                {
+                    container.add_raw(format!(
+                        "<figure{} class=\"figure\">{}<figcaption><span class=\"figure-number\">{} {}:</span> {}</figcaption></figure>",
+                        id,
+                        Self::image(path, Some(caption), attributes),
+                        kind,
+                        number.unwrap_or_default(),
+                        caption
+                    ));
+                    return;
+                }
+
+                self.add_node(&mut inner, node);
+
+                container.add_raw(match caption {
+                    Some(caption) => format!(
+                        "<figure{} class=\"{}\"><figcaption><span class=\"figure-number\">{} {}:</span> {}</figcaption>{}</figure>",
+                        id,
+                        kind.to_ascii_lowercase(),
+                        kind,
+                        number.unwrap_or_default(),
+                        caption,
+                        inner.0
+                    ),
+                    None => format!("<div{}>{}</div>", id, inner.0),
+                });
+            }
+            Node::Table { rows, header_rows } => {
+                let mut table = Table::new();
+
+                for (i, row) in rows.iter().enumerate() {
+                    if i < *header_rows {
+                        table.add_header_row(row);
+                    } else {
+                        table.add_body_row(row);
                     }
                 }
+
+                container.add_table(table);
+            }
+            Node::Image {
+                path,
+                caption,
+                attributes,
+            } => {
+                let image = Self::image(path, caption.as_deref(), attributes);
+
+                if let Some(caption) = caption {
+                    container.add_raw(format!(
+                        "<figure>{}<figcaption>{}</figcaption></figure>",
+                        image, caption
+                    ));
+                } else {
+                    container.add_raw(image);
+                }
             }
         }
+    }
 
-        self.builder.to_html_string()
+    /// A `<pre>` for a source block, with line numbers and highlighted lines if requested.
+    fn source_block(&self, contents: &str, header: &HeaderArgs) -> String {
+        let (open, lines, close) = match self.highlight(contents, header.language.as_deref()) {
+            Some((open, lines)) => (open, lines, "\n</pre>".to_owned()),
+            None => (
+                match &header.language {
+                    Some(language) => format!("<pre><code class=\"language-{}\">", language),
+                    None => "<pre><code>".to_owned(),
+                },
+                contents.split('\n').map(|line| line.to_owned()).collect(),
+                "</code></pre>".to_owned(),
+            ),
+        };
+
+        let first_line_number = match header.line_numbers {
+            Some(LineNumbers::Start(start)) => Some(start),
+            Some(LineNumbers::Continue(offset)) => Some(self.next_line_number.get() + offset),
+            None => None,
+        };
+
+        if let Some(first) = first_line_number {
+            self.next_line_number.set(first + lines.len());
+        }
+
+        if first_line_number.is_none() && header.highlighted_lines.is_empty() {
+            return open + &lines.join("\n") + &close;
+        }
+
+        let lines: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let class = if header.highlighted_lines.contains(&(index + 1)) {
+                    "line highlight"
+                } else {
+                    "line"
+                };
+                let number = first_line_number.map_or_else(String::new, |first| {
+                    format!("<span class=\"line-number\">{}</span>", first + index)
+                });
+
+                format!("<span class=\"{}\">{}{}</span>", class, number, line)
+            })
+            .collect();
+
+        open + &lines.join("\n") + &close
+    }
+
+    /// The opening `<pre>` and HTML for each line of a source block,
+    /// or `None` if highlighting is off or the language is unknown.
+    fn highlight(&self, contents: &str, language: Option<&str>) -> Option<(String, Vec<String>)> {
+        let theme = &THEMES.themes[self.theme.as_ref()?];
+        let syntax = SYNTAXES.find_syntax_by_token(language?)?;
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let (open, background) = start_highlighted_html_snippet(theme);
+        let mut lines: Vec<String> = vec![];
+
+        for line in LinesWithEndings::from(&(contents.to_owned() + "\n")) {
+            let regions = highlighter.highlight_line(line, &SYNTAXES).ok()?;
+            let html =
+                styled_line_to_highlighted_html(&regions, IncludeBackground::IfDifferent(background))
+                    .ok()?;
+
+            lines.push(html.replace('\n', ""));
+        }
+
+        Some((open, lines))
+    }
+
+    /// Whether a statistics cookie such as `3/3` or `100%` counts everything as done.
+    fn is_complete(completion: &str) -> bool {
+        match completion.split_once('/') {
+            Some((done, total)) => done == total,
+            None => completion
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .is_ok_and(|percent| percent >= 100.0),
+        }
+    }
+
+    /// An `<img>` tag, using the file name as alt text unless one was given.
+    fn image(path: &str, caption: Option<&str>, attributes: &[(String, String)]) -> String {
+        let mut html = format!(
+            "<img src=\"{}\" alt=\"{}\"",
+            escape_html(path),
+            escape_html(&alt_text(path, caption, attributes))
+        );
+
+        for (key, value) in attributes.iter().filter(|(key, _)| key != "alt") {
+            html += &format!(" {}=\"{}\"", escape_html(key), escape_html(value));
+        }
+
+        html + " />"
+    }
+}
+
+/// Rendered inline markup as text: without tags or basic entities, on one line.
+pub(crate) fn plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
     }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// An image's `:alt` attribute, else its caption, else its file name.
+pub(crate) fn alt_text(path: &str, caption: Option<&str>, attributes: &[(String, String)]) -> String {
+    attributes
+        .iter()
+        .find(|(key, _)| key == "alt")
+        .map(|(_, alt)| alt.clone())
+        .or(caption.map(str::to_owned))
+        .unwrap_or_else(|| Path::new(path).file_name().unwrap_or_default().to_string_lossy().into_owned())
+}
+
+/// HTML elements without an enclosing tag, for the insides of blocks.
+#[derive(Debug, Default)]
+struct Fragment(String);
+
+impl Html for Fragment {
+    fn to_html_string(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl HtmlContainer for Fragment {
+    fn add_html<H: Html>(&mut self, html: H) {
+        self.0 += &html.to_html_string();
+    }
+}
+
+/// Renders LaTeX to HTML at build time.
+/// Errors are rendered in place by KaTeX rather than failing the build.
+pub(crate) fn katex(tex: &str, display: bool) -> String {
+    let opts = katex::Opts::builder()
+        .display_mode(display)
+        .throw_on_error(false)
+        .build()
+        .unwrap();
+
+    katex::render_with_opts(tex, &opts).unwrap_or_else(|err| {
+        log::warn!("KaTeX failed to render `{}`: {}", tex, err);
+        build_html::escape_html(tex)
+    })
 }
 
 #[cfg(test)]
 mod test {
-    use crate::org::{html::HtmlBuilder, Document};
+    use std::collections::HashMap;
+
+    use crate::{
+        checksums::sha256_hex,
+        config::AdmonitionConfig,
+        org::{html::HtmlBuilder, Document},
+    };
 
     #[test]
     fn headings() {
         assert_eq!(
             HtmlBuilder::new()
                 .from_document(&Document::parse("* Hello, World!", "heading.org", Default::default()).unwrap()),
-            "<div class=\"article\"><h1>Hello, World!</h1></div>"
+            "<div class=\"article\"><section><h1 id=\"hello-world\">Hello, World!</h1></section></div>"
+        )
+    }
+
+    #[test]
+    fn todo_keywords() {
+        assert_eq!(
+            HtmlBuilder::new()
+                .from_document(&Document::parse("* NEXT Write\n#+TODO: NEXT | DONE", "todo.org", Default::default()).unwrap()),
+            "<div class=\"article\"><section><h1 id=\"write\"><span class=\"todo-keyword todo-NEXT\">NEXT</span> Write</h1></section></div>"
+        )
+    }
+
+    #[test]
+    fn priorities_and_cookies() {
+        assert_eq!(
+            HtmlBuilder::new()
+                .from_document(&Document::parse("* TODO [#A] Books [1/2]\n* Chores [100%] :home:", "cookies.org", Default::default()).unwrap()),
+            "<div class=\"article\"><section><h1 id=\"books\"><span class=\"todo-keyword todo-TODO\">TODO</span> <span class=\"priority priority-A\">[#A]</span> Books <span class=\"statistics-cookie\">[1/2]</span></h1></section><section><h1 id=\"chores\">Chores <span class=\"statistics-cookie done\">[100%]</span></h1></section></div>"
+        )
+    }
+
+    #[test]
+    fn export_options() {
+        let html = |org: &str| {
+            HtmlBuilder::new().from_document(&Document::parse(org, "options.org", Default::default()).unwrap())
+        };
+
+        assert_eq!(
+            html("#+OPTIONS: num:2 tags:t todo:nil pri:nil\n* TODO [#A] One :work:\n** Two\n*** Three\n* Four"),
+            "<div class=\"article\"><section><h1 id=\"one\"><span class=\"section-number\">1</span> One <span class=\"tag\"><span class=\"work\">work</span></span></h1><section><h2 id=\"two\"><span class=\"section-number\">1.1</span> Two</h2><section><h3 id=\"three\">Three</h3></section></section></section><section><h1 id=\"four\"><span class=\"section-number\">2</span> Four</h1></section></div>"
+        );
+        assert_eq!(
+            html("* TODO One :work:"),
+            "<div class=\"article\"><section><h1 id=\"one\"><span class=\"todo-keyword todo-TODO\">TODO</span> One</h1></section></div>"
+        );
+    }
+
+    #[test]
+    fn heading_anchors() {
+        assert_eq!(
+            HtmlBuilder::new()
+                .with_heading_anchors()
+                .from_document(&Document::parse("* Set up", "anchors.org", Default::default()).unwrap()),
+            "<div class=\"article\"><section><h1 id=\"set-up\">Set up <a class=\"heading-anchor\" href=\"#set-up\">¶</a></h1></section></div>"
+        )
+    }
+
+    #[test]
+    fn paragraph_anchors() {
+        let html = |every: usize, source: &str| {
+            HtmlBuilder::new()
+                .with_paragraph_anchors(every)
+                .from_document(&Document::parse(source, "anchors.org", Default::default()).unwrap())
+        };
+        let id = |text: &str| format!("p-{}", &sha256_hex(text.as_bytes())[..8]);
+        let anchored = html(2, "One one\n\nTwo\n\nOne\n  one\n\nFour");
+
+        assert_eq!(
+            anchored,
+            format!(
+                "<div class=\"article\"><p id=\"{0}\">One one <a class=\"paragraph-anchor\" href=\"#{0}\" aria-label=\"Link to this paragraph\"></a></p><p>Two</p><p id=\"{0}-1\">One one <a class=\"paragraph-anchor\" href=\"#{0}-1\" aria-label=\"Link to this paragraph\"></a></p><p>Four</p></div>",
+                id("One one")
+            )
+        );
+        // Inserting a paragraph before it doesn't change a paragraph's id.
+        assert!(html(1, "New\n\nTwo").contains(&format!("<p id=\"{}\">Two ", id("Two"))));
+    }
+
+    #[test]
+    fn nested_sections() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(
+                &Document::parse(
+                    "Intro\n* A\n** A.1\nText\n** COMMENT A.2\n* B",
+                    "nested.org",
+                    Default::default()
+                )
+                .unwrap()
+            ),
+            "<div class=\"article\"><p>Intro</p><section><h1 id=\"a\">A</h1><section><h2 id=\"a-1\">A.1</h2><p>Text</p></section></section><section><h1 id=\"b\">B</h1></section></div>"
         )
     }
 
@@ -97,6 +885,107 @@ Hai!"#,
         )
     }
 
+    #[test]
+    fn breaks_and_rules() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(
+                &Document::parse(
+                    "Roses are red, \\\\\n  violets are blue\\\\\n-----\n  \\[ a \\\\\n  b \\]\n---",
+                    "breaks.org",
+                    Default::default()
+                )
+                .unwrap()
+            ),
+            "<div class=\"article\"><p>Roses are red,<br />violets are blue</p><hr /><p>\\[ a \\\\ b \\]<br />---</p></div>"
+        )
+    }
+
+    #[test]
+    fn admonitions() {
+        let doc = Document::parse(
+            "#+BEGIN_WARNING\nCareful.\n#+END_WARNING\n#+BEGIN_EXERCISE\nTry it.\n#+END_EXERCISE",
+            "admonitions.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            HtmlBuilder::new().from_document(&doc),
+            "<div class=\"article\"><div class=\"admonition admonition-warning\"><p class=\"admonition-title\"><span class=\"admonition-icon\">⚠️</span> Warning</p><p>Careful.</p></div><div class=\"exercise\"><p>Try it.</p></div></div>"
+        );
+        assert_eq!(
+            HtmlBuilder::new()
+                .with_admonitions(HashMap::from([(
+                    "exercise".into(),
+                    AdmonitionConfig {
+                        title: Some("Your turn".into()),
+                        icon: None,
+                        class: Some("boxed".into()),
+                    }
+                )]))
+                .from_document(&doc),
+            "<div class=\"article\"><div class=\"admonition admonition-warning\"><p class=\"admonition-title\"><span class=\"admonition-icon\">⚠️</span> Warning</p><p>Careful.</p></div><div class=\"admonition admonition-exercise boxed\"><p class=\"admonition-title\">Your turn</p><p>Try it.</p></div></div>"
+        );
+    }
+
+    #[test]
+    fn tabs() {
+        let html = HtmlBuilder::new().from_document(
+            &Document::parse(
+                "#+BEGIN_TABS\n#+BEGIN_TAB Linux <x86>\nRun =make=.\n#+END_TAB\n#+BEGIN_TAB\nRun it.\n#+END_TAB\n#+END_TABS",
+                "tabs.org",
+                Default::default(),
+            )
+            .unwrap(),
+        );
+
+        assert!(html.starts_with(
+            "<div class=\"article\"><div class=\"tabs\" id=\"tabs-1\"><div role=\"tablist\"><button type=\"button\" role=\"tab\" id=\"tabs-1-1\" aria-controls=\"tabs-1-1-panel\" aria-selected=\"true\">Linux &lt;x86&gt;</button><button type=\"button\" role=\"tab\" id=\"tabs-1-2\" aria-controls=\"tabs-1-2-panel\" aria-selected=\"false\" tabindex=\"-1\">Tab 2</button></div><div role=\"tabpanel\" id=\"tabs-1-1-panel\" aria-labelledby=\"tabs-1-1\" tabindex=\"0\"><p>Run <code>make</code>.</p></div><div role=\"tabpanel\" id=\"tabs-1-2-panel\" aria-labelledby=\"tabs-1-2\" tabindex=\"0\" hidden><p>Run it.</p></div></div><script>"
+        ));
+        assert!(html.ends_with("(\"tabs-1\");</script></div>"));
+    }
+
+    #[test]
+    fn details() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(
+                &Document::parse(
+                    "* FAQ\n** Why? :faq:details:\nBecause.\n*** More\n#+ATTR_HTML: :details t\n** Spoiler\nHidden.",
+                    "details.org",
+                    Default::default()
+                )
+                .unwrap()
+            ),
+            "<div class=\"article\"><section><h1 id=\"faq\">FAQ</h1><section><details><summary><h2 id=\"why\">Why?</h2></summary><p>Because.</p><section><h3 id=\"more\">More</h3></section></details></section><section><details><summary><h2 id=\"spoiler\">Spoiler</h2></summary><p>Hidden.</p></details></section></section></div>"
+        )
+    }
+
+    #[test]
+    fn excerpt_separator() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(
+                &Document::parse("Summary.\n# more\nBody.", "more.org", Default::default())
+                    .unwrap()
+            ),
+            "<div class=\"article\"><p>Summary.</p><span id=\"more\"></span><p>Body.</p></div>"
+        )
+    }
+
+    #[test]
+    fn fixed_width() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(
+                &Document::parse(
+                    "Output:\n: $ ls <dir>\n:   a  b\n:\n  : c\nDone.",
+                    "fixed_width.org",
+                    Default::default()
+                )
+                .unwrap()
+            ),
+            "<div class=\"article\"><p>Output:</p><pre class=\"example\">$ ls &lt;dir&gt;\n  a  b\n\nc</pre><p>Done.</p></div>"
+        )
+    }
+
     #[test]
     fn py_src() {
         assert_eq!(
@@ -107,6 +996,85 @@ print('Hello, world!')
         )
     }
 
+    #[test]
+    fn images() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"[[file:img/cat.png]]
+
+#+CAPTION: A cat.
+#+ATTR_HTML: :alt A "good" cat :width 300
+[[./cat.jpg]]"#, "images.org", Default::default()).unwrap()),
+            "<div class=\"article\"><img src=\"img/cat.png\" alt=\"cat.png\" /><figure class=\"figure\"><img src=\"./cat.jpg\" alt=\"A &quot;good&quot; cat\" width=\"300\" /><figcaption><span class=\"figure-number\">Figure 1:</span> A cat.</figcaption></figure></div>"
+        )
+    }
+
+    #[test]
+    fn highlighted_src() {
+        let html = HtmlBuilder::new()
+            .with_highlighting("InspiredGitHub")
+            .from_document(
+                &Document::parse(
+                    "#+BEGIN_SRC rust\nfn main() {}\n#+END_SRC\n#+BEGIN_SRC nonexistent\nfn main() {}\n#+END_SRC",
+                    "highlight.org",
+                    Default::default(),
+                )
+                .unwrap(),
+            );
+
+        assert!(html.starts_with("<div class=\"article\"><pre style=\"background-color:"));
+        assert!(html.contains("<span style=\""));
+        assert!(html.ends_with(
+            "<pre><code class=\"language-nonexistent\">fn main() {}</code></pre></div>"
+        ));
+    }
+
+    #[test]
+    fn src_header_args() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"#+BEGIN_SRC python -n 3 :hl_lines 2
+a = 1
+b = 2
+#+END_SRC
+#+BEGIN_SRC python +n
+c = 3
+#+END_SRC
+#+BEGIN_SRC python :exports results
+hidden = True
+#+END_SRC"#, "src_header_args.org", Default::default()).unwrap()),
+            "<div class=\"article\"><pre><code class=\"language-python\"><span class=\"line\"><span class=\"line-number\">3</span>a = 1</span>\n<span class=\"line highlight\"><span class=\"line-number\">4</span>b = 2</span></code></pre><pre><code class=\"language-python\"><span class=\"line\"><span class=\"line-number\">5</span>c = 3</span></code></pre></div>"
+        )
+    }
+
+    #[test]
+    fn verse_and_example() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"#+BEGIN_VERSE
+Roses are red,
+  violets are blue.
+#+END_VERSE
+#+BEGIN_EXAMPLE
+<b>not bold</b>
+#+END_EXAMPLE"#, "verse.org", Default::default()).unwrap()),
+            "<div class=\"article\"><p class=\"verse\">Roses are red,<br />&nbsp;&nbsp;violets are blue.</p><pre>&lt;b&gt;not bold&lt;/b&gt;</pre></div>"
+        )
+    }
+
+    #[test]
+    fn greater_blocks() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"#+BEGIN_QUOTE
+Quoted.
+#+END_QUOTE
+#+BEGIN_CENTER
+Centered.
+#+END_CENTER
+#+BEGIN_ASIDE
+Aside.
+#+END_ASIDE"#, "greater_blocks.org", Default::default()).unwrap()),
+            "<div class=\"article\"><blockquote><p>Quoted.</p></blockquote><div class=\"center\" style=\"text-align: center\"><p>Centered.</p></div><div class=\"aside\"><p>Aside.</p></div></div>"
+        )
+    }
+
     #[test]
     fn table() {
         assert_eq!(
@@ -114,7 +1082,37 @@ print('Hello, world!')
 | a | b | c |
 | 1 | 2 | 3 |
 "#, "table.org", Default::default()).unwrap()),
-            "<div class=\"article\"><table><thead></thead><tbody><tr><td></td><td>a</td><td>b</td><td>c</td><td></td></tr><tr><td></td><td>1</td><td>2</td><td>3</td><td></td></tr></tbody></table></div>"
+            "<div class=\"article\"><table><thead></thead><tbody><tr><td>a</td><td>b</td><td>c</td></tr><tr><td>1</td><td>2</td><td>3</td></tr></tbody></table></div>"
+        )
+    }
+
+    #[test]
+    fn table_header() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"
+|---+---|
+| a | b |
+|---+---|
+| 1 | 2 |
+|---+---|
+| 3 | 4 |
+"#, "table.org", Default::default()).unwrap()),
+            "<div class=\"article\"><table><thead><tr><th>a</th><th>b</th></tr></thead><tbody><tr><td>1</td><td>2</td></tr><tr><td>3</td><td>4</td></tr></tbody></table></div>"
+        )
+    }
+
+    #[test]
+    fn captions() {
+        assert_eq!(
+            HtmlBuilder::new().from_document(&Document::parse(r#"#+CAPTION: Letters
+#+NAME: letters
+| a |
+
+#+NAME: unnumbered
+#+BEGIN_EXAMPLE
+x
+#+END_EXAMPLE"#, "captions.org", Default::default()).unwrap()),
+            "<div class=\"article\"><figure id=\"letters\" class=\"table\"><figcaption><span class=\"figure-number\">Table 1:</span> Letters</figcaption><table><thead></thead><tbody><tr><td>a</td></tr></tbody></table></figure><div id=\"unnumbered\"><pre>x</pre></div></div>"
         )
     }
 }
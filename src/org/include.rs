@@ -0,0 +1,167 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::path::{Path, PathBuf};
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+use crate::org::babel::split_args;
+
+lazy_static! {
+    static ref INCLUDE: Regex = Regex::new(r"^\s*#\+(?i:include):\s*(?<args>.*)$").unwrap();
+}
+
+/// `:lines "5-10"` keeps lines 5 through 9, as in Org. Either end may be left out.
+fn select_lines(content: &str, range: &str) -> String {
+    let (start, end) = range.split_once('-').unwrap_or((range, ""));
+    let start = start.trim().parse::<usize>().unwrap_or(1).max(1);
+    let end = end.trim().parse::<usize>().ok();
+
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(n, _)| *n >= start && end.is_none_or(|end| *n < end))
+        .map(|(_, line)| line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn expand_inner(content: &str, path: &Path, stack: &mut Vec<PathBuf>) -> Result<String, String> {
+    let mut out: Vec<String> = vec![];
+
+    for line in content.split('\n') {
+        let Some(caps) = INCLUDE.captures(line).ok().flatten() else {
+            out.push(line.to_owned());
+            continue;
+        };
+
+        let args = split_args(&caps["args"]);
+        let Some(file) = args.first() else {
+            return Err(format!("{:?}: #+INCLUDE without a file.", path));
+        };
+
+        let included = path.parent().unwrap_or(Path::new("")).join(file);
+        let canonical = included
+            .canonicalize()
+            .map_err(|err| format!("{:?}: cannot include {:?}: {}", path, included, err))?;
+        let mut contents = std::fs::read_to_string(&canonical)
+            .map_err(|err| format!("{:?}: cannot include {:?}: {}", path, included, err))?;
+
+        let block: Vec<&String> = args[1..]
+            .iter()
+            .take_while(|arg| !arg.starts_with(':'))
+            .collect();
+
+        if let Some(lines) = args
+            .iter()
+            .position(|arg| arg == ":lines")
+            .and_then(|i| args.get(i + 1))
+        {
+            contents = select_lines(&contents, lines);
+        }
+
+        match block.split_first() {
+            Some((type_, rest)) => {
+                let type_ = type_.to_ascii_uppercase();
+                let args = rest
+                    .iter()
+                    .map(|arg| format!(" {}", arg))
+                    .collect::<String>();
+
+                out.push(format!("#+BEGIN_{}{}", type_, args));
+                out.push(contents.trim_end_matches('\n').to_owned());
+                out.push(format!("#+END_{}", type_));
+            }
+            None => {
+                if stack.contains(&canonical) {
+                    let cycle = stack
+                        .iter()
+                        .chain(std::iter::once(&canonical))
+                        .map(|path| format!("{:?}", path))
+                        .collect::<Vec<_>>()
+                        .join(" -> ");
+
+                    return Err(format!("Include cycle: {}", cycle));
+                }
+
+                stack.push(canonical.clone());
+                out.push(
+                    expand_inner(&contents, &canonical, stack)?
+                        .trim_end_matches('\n')
+                        .to_owned(),
+                );
+                stack.pop();
+            }
+        }
+    }
+
+    Ok(out.join("\n"))
+}
+
+/// Replaces every `#+INCLUDE: "file" [src lang | example | export backend] [:lines "a-b"]`
+/// with the contents of `file`, relative to `path`. Files included as Org are expanded
+/// recursively; including a file that is already being included is an error.
+pub fn expand(content: &str, path: &Path) -> Result<String, String> {
+    let mut stack = path.canonicalize().into_iter().collect();
+
+    expand_inner(content, path, &mut stack)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use crate::org::include::{expand, select_lines};
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(select_lines("a\nb\nc\nd", "2-4"), "b\nc");
+        assert_eq!(select_lines("a\nb\nc\nd", "3-"), "c\nd");
+        assert_eq!(select_lines("a\nb\nc\nd", "-2"), "a");
+    }
+
+    #[test]
+    fn includes() {
+        let dir = dir("impertio-include-test");
+        std::fs::write(
+            dir.join("shared/footer.org"),
+            "#+include: \"license.org\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("shared/license.org"), "MIT licensed.").unwrap();
+        std::fs::write(dir.join("main.rs"), "use std::io;\n\nfn main() {}\n").unwrap();
+
+        let expanded = expand(
+            "Text\n#+INCLUDE: \"main.rs\" src rust :lines \"3-\"\n#+INCLUDE: \"shared/footer.org\"",
+            &dir.join("article.org"),
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            expanded.unwrap(),
+            "Text\n#+BEGIN_SRC rust\nfn main() {}\n#+END_SRC\nMIT licensed."
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let dir = dir("impertio-include-cycle-test");
+        std::fs::write(dir.join("a.org"), "#+INCLUDE: \"b.org\"").unwrap();
+        std::fs::write(dir.join("b.org"), "#+INCLUDE: \"a.org\"").unwrap();
+
+        let err = expand("#+INCLUDE: \"a.org\"", &dir.join("article.org")).unwrap_err();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(err.starts_with("Include cycle:"), "{}", err);
+    }
+}
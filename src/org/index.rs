@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! The site's index of `#+INDEX` terms, for `{{{index}}}`.
+
+use build_html::escape_html;
+
+use crate::{
+    collation,
+    metadata::{AnchorKind, Metadata},
+};
+
+/// A term and the pages it's on: their titles and links to each occurrence.
+struct Entry {
+    term: String,
+    occurrences: Vec<(String, String)>,
+}
+
+/// Every article's index terms, sorted for `language` and grouped under their initials. Terms
+/// differing only in case are one entry, spelled as first found. Each occurrence links back to
+/// its page, numbered after the first on the same page.
+pub fn render(metadata: &[Metadata], language: Option<&str>) -> String {
+    let mut articles: Vec<&Metadata> = metadata
+        .iter()
+        .filter(|meta| matches!(meta, Metadata::Article { .. }))
+        .collect();
+    let mut entries: Vec<Entry> = vec![];
+
+    collation::sort_by_key(&mut articles, language, |meta| match meta {
+        Metadata::Article { title, .. } => title,
+        _ => "",
+    });
+
+    for meta in articles {
+        let Metadata::Article {
+            title,
+            url,
+            anchors,
+            ..
+        } = meta
+        else {
+            continue;
+        };
+
+        for anchor in anchors
+            .iter()
+            .filter(|anchor| anchor.kind == AnchorKind::Index)
+        {
+            let Some(term) = &anchor.title else {
+                continue;
+            };
+            let index = match entries
+                .iter()
+                .position(|entry| entry.term.to_lowercase() == term.to_lowercase())
+            {
+                Some(index) => index,
+                None => {
+                    entries.push(Entry {
+                        term: term.clone(),
+                        occurrences: vec![],
+                    });
+                    entries.len() - 1
+                }
+            };
+
+            entries[index]
+                .occurrences
+                .push((title.clone(), format!("{}#{}", url, anchor.id)));
+        }
+    }
+
+    collation::sort_by_key(&mut entries, language, |entry| &entry.term);
+
+    let mut html = String::new();
+    let mut initial: Option<String> = None;
+
+    for entry in &entries {
+        let first = entry
+            .term
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().collect::<String>())
+            .unwrap_or_default();
+
+        if initial.as_ref() != Some(&first) {
+            if initial.is_some() {
+                html += "</ul>";
+            }
+
+            html += &format!("<h2>{}</h2><ul>", escape_html(&first));
+            initial = Some(first);
+        }
+
+        let links: Vec<String> = entry
+            .occurrences
+            .iter()
+            .enumerate()
+            .map(|(i, (title, href))| {
+                let repeat = entry.occurrences[..i]
+                    .iter()
+                    .filter(|(other, _)| other == title)
+                    .count();
+
+                format!(
+                    "<a href=\"{}\">{}{}</a>",
+                    escape_html(href),
+                    escape_html(title),
+                    if repeat > 0 {
+                        format!(" ({})", repeat + 1)
+                    } else {
+                        String::new()
+                    }
+                )
+            })
+            .collect();
+
+        html += &format!(
+            "<li><span class=\"index-entry\">{}</span> {}</li>",
+            escape_html(&entry.term),
+            links.join(", ")
+        );
+    }
+
+    if initial.is_some() {
+        html += "</ul>";
+    }
+
+    format!("<div class=\"index\">{}</div>", html)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        handler::FileContext,
+        metadata::Metadata,
+        org::{index::render, Document},
+    };
+
+    #[test]
+    fn index() {
+        let page = |title: &str, url: &str, source: &str| {
+            Metadata::article(url).with_title(title).with_anchors(
+                Document::parse(source, "page.org", Default::default())
+                    .unwrap()
+                    .anchors(),
+            )
+        };
+        let metadata = vec![
+            page(
+                "Setup",
+                "https://example.com/setup.html",
+                "#+INDEX: Cargo\n* Cargo\n#+INDEX: cargo\n#+INDEX: Build & test",
+            ),
+            page("About", "https://example.com/about.html", "#+INDEX: Cargo"),
+        ];
+
+        assert_eq!(
+            render(&metadata, None),
+            "<div class=\"index\"><h2>B</h2><ul><li><span class=\"index-entry\">Build &amp; test</span> <a href=\"https://example.com/setup.html#index-build-test\">Setup</a></li></ul><h2>C</h2><ul><li><span class=\"index-entry\">Cargo</span> <a href=\"https://example.com/about.html#index-cargo\">About</a>, <a href=\"https://example.com/setup.html#index-cargo\">Setup</a>, <a href=\"https://example.com/setup.html#index-cargo-1\">Setup (2)</a></li></ul></div>"
+        );
+
+        let ctx = FileContext {
+            metadata: Arc::new(Mutex::new(metadata)),
+            ..Default::default()
+        };
+        let html = Document::parse("{{{index}}}", "index.org", ctx)
+            .unwrap()
+            .to_html(&Default::default());
+
+        assert!(html.contains("<h1 id=\"index\">Index</h1><div class=\"index\"><h2>B</h2>"));
+    }
+}
@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use build_html::escape_html;
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+use crate::{
+    config::{LinkTypeConfig, MathConfig, OrgHandlerConfig},
+    org::{
+        entities, math,
+        options::{ExportOptions, SubSuperscripts},
+        timestamp,
+    },
+};
+
+lazy_static! {
+    static ref CODE: Regex = Regex::new(
+        r#"src_(?<language>[\w+-]+)(?:\[[^\]\n]*\])?\{(?<src>[^}\n]*)\}|(?<![^\s\-({'"])(?<marker>[=~])(?<code>\S|\S[^\n]*?\S)\k<marker>(?=[\s\-.,;:!?'")}\[]|$)"#
+    )
+    .unwrap();
+    static ref TYPOGRAPHY: Regex = Regex::new(
+        r"(?<link>\[\[.*?\]\])|(?<=\S)(?<script>[_^])(?:\{(?<braced>[^{}\n]*)\}|(?<bare>[[:alnum:]]+))|(?<special>\\-|---|--|\.\.\.)|\\(?<entity>[A-Za-z]+\d*)(?:\{\})?"
+    )
+    .unwrap();
+    static ref TYPED_LINK: Regex = Regex::new(
+        r#"\[\[(?<type>[A-Za-z]+):(?<bracketed>[^\]\[]+)\]\]|(?<![\w:/\[])(?<bare_type>[A-Za-z]+):(?<bare>[^\s\[\]<>]*[^\s\[\]<>.,;:!?)'"])"#
+    )
+    .unwrap();
+}
+
+/// The built-in link types, and those configured for `org`.
+pub fn link_types(org: Option<&OrgHandlerConfig>) -> HashMap<String, LinkTypeConfig> {
+    let mut types = HashMap::from([
+        (
+            "kbd".to_owned(),
+            LinkTypeConfig {
+                element: Some("kbd".into()),
+                class: Some("shortcut".into()),
+                split: Some(" ".into()),
+            },
+        ),
+        (
+            "menu".to_owned(),
+            LinkTypeConfig {
+                element: Some("span".into()),
+                class: Some("menu".into()),
+                split: Some(">".into()),
+            },
+        ),
+    ]);
+
+    types.extend(org.and_then(|org| org.link_types.clone()).unwrap_or_default());
+
+    types
+}
+
+/// `text` in the element `config` asks for, split into inner elements if it asks to.
+fn typed_link(text: &str, config: &LinkTypeConfig) -> String {
+    let element = escape_html(config.element.as_deref().unwrap_or("span"));
+    let class = config
+        .class
+        .as_ref()
+        .map(|class| format!(" class=\"{}\"", escape_html(class)))
+        .unwrap_or_default();
+    let inner = match config.split.as_deref() {
+        Some(split) if !split.is_empty() => {
+            // Spaces around a visible separator are for readability.
+            let joiner = match split.trim() {
+                "" => " ".to_owned(),
+                visible => format!(" {} ", escape_html(visible)),
+            };
+
+            text.split(split)
+                .map(str::trim)
+                .filter(|part| !part.is_empty())
+                .map(|part| format!("<{0}>{1}</{0}>", element, escape_html(part)))
+                .collect::<Vec<String>>()
+                .join(&joiner)
+        }
+        _ => escape_html(text.trim()),
+    };
+
+    format!("<{0}{1}>{2}</{0}>", element, class, inner)
+}
+
+fn code(caps: &Captures) -> String {
+    match caps.name("language") {
+        Some(language) => format!(
+            "<code class=\"language-{}\">{}</code>",
+            escape_html(language.as_str()),
+            escape_html(&caps["src"])
+        ),
+        None => format!("<code>{}</code>", escape_html(&caps["code"])),
+    }
+}
+
+/// `a_b`/`a^b`, entities and special strings as `options` ask, outside of links.
+fn typography(text: &str, options: &ExportOptions) -> String {
+    TYPOGRAPHY
+        .replace_all(text, |caps: &Captures| {
+            let whole = caps[0].to_owned();
+
+            if let Some(script) = caps.name("script") {
+                let body = match (options.sub_superscripts, caps.name("braced")) {
+                    (SubSuperscripts::Off, _) => return whole,
+                    (SubSuperscripts::Braced, None) => return whole,
+                    (_, Some(braced)) => braced.as_str(),
+                    (_, None) => &caps["bare"],
+                };
+                let tag = if script.as_str() == "_" { "sub" } else { "sup" };
+
+                format!("<{0}>{1}</{0}>", tag, body)
+            } else if let Some(special) = caps.name("special") {
+                if !options.special_strings {
+                    return whole;
+                }
+
+                match special.as_str() {
+                    "\\-" => "&shy;",
+                    "---" => "&mdash;",
+                    "--" => "&ndash;",
+                    _ => "&hellip;",
+                }
+                .to_owned()
+            } else if let Some(entity) = caps.name("entity") {
+                match entities::lookup(entity.as_str()) {
+                    Some(replacement) if options.entities => replacement.to_owned(),
+                    _ => whole,
+                }
+            } else {
+                whole
+            }
+        })
+        .into_owned()
+}
+
+/// Renders inline `src_lang{...}`, `~code~` and `=verbatim=` spans as `<code>`, and links of
+/// the types in `links` as their elements.
+/// The text around them has its timestamps and, when enabled, math fragments replaced, but is
+/// otherwise kept as-is, except for what `^:`, `e:` and `-:` in `options` enable.
+pub fn render(
+    text: &str,
+    math: Option<&MathConfig>,
+    options: &ExportOptions,
+    links: &HashMap<String, LinkTypeConfig>,
+) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    let push_plain = |out: &mut String, text: &str| {
+        let text = timestamp::render(&math::outside_fragments(text, |text| {
+            typography(text, options)
+        }));
+
+        match math {
+            Some(math) => *out += &math::replace_fragments(&text, math),
+            None => *out += &text,
+        }
+    };
+    let push_text = |out: &mut String, text: &str| {
+        let mut last = 0;
+
+        for caps in TYPED_LINK.captures_iter(text).filter_map(Result::ok) {
+            let type_ = caps.name("type").or(caps.name("bare_type")).unwrap();
+            let Some(config) = links.get(&type_.as_str().to_ascii_lowercase()) else {
+                continue;
+            };
+            let whole = caps.get(0).unwrap();
+
+            push_plain(out, &text[last..whole.start()]);
+            *out += &typed_link(
+                caps.name("bracketed").or(caps.name("bare")).unwrap().as_str(),
+                config,
+            );
+            last = whole.end();
+        }
+
+        push_plain(out, &text[last..]);
+    };
+
+    for caps in CODE.captures_iter(text).filter_map(Result::ok) {
+        let whole = caps.get(0).unwrap();
+
+        push_text(&mut out, &text[last..whole.start()]);
+        out += &code(&caps);
+        last = whole.end();
+    }
+
+    push_text(&mut out, &text[last..]);
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{
+        config::{LinkTypeConfig, MathConfig},
+        org::{
+            inline::{link_types, render},
+            options::ExportOptions,
+        },
+    };
+
+    #[test]
+    fn code_spans() {
+        assert_eq!(
+            render(
+                "Run src_python[:exports code]{print(1 < 2)}, then ~cargo build~ or =a=b=.",
+                None,
+                &ExportOptions::default(),
+                &HashMap::new()
+            ),
+            "Run <code class=\"language-python\">print(1 &lt; 2)</code>, then <code>cargo build</code> or <code>a=b</code>."
+        );
+        assert_eq!(render("x = y ~ z, a=b", None, &ExportOptions::default(), &HashMap::new()), "x = y ~ z, a=b");
+    }
+
+    #[test]
+    fn math_outside_code() {
+        assert_eq!(
+            render(
+                "$x$ and =$y$=",
+                Some(&MathConfig::default()),
+                &ExportOptions::default(),
+                &HashMap::new()
+            ),
+            "\\(x\\) and <code>$y$</code>"
+        );
+    }
+
+    #[test]
+    fn options() {
+        let text = "H_2O, x^{10}, snake_case -- so... [[https://a.b/c_d]] $e^x$ ~a--b~";
+
+        assert_eq!(
+            render(text, None, &ExportOptions::parse("^:nil"), &HashMap::new()),
+            text.replace("~a--b~", "<code>a--b</code>")
+        );
+        assert_eq!(
+            render(text, None, &ExportOptions::parse("^:t -:t"), &HashMap::new()),
+            "H<sub>2O</sub>, x<sup>10</sup>, snake<sub>case</sub> &ndash; so&hellip; [[https://a.b/c_d]] $e^x$ <code>a--b</code>"
+        );
+        assert_eq!(
+            render(text, None, &ExportOptions::default(), &HashMap::new()),
+            "H_2O, x<sup>10</sup>, snake_case -- so... [[https://a.b/c_d]] $e^x$ <code>a--b</code>"
+        );
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            render(
+                "\\alpha{}-decay, \\pi\\to \\unknown, $\\beta$ and ~\\gamma~",
+                None,
+                &ExportOptions::default(),
+                &HashMap::new()
+            ),
+            "α-decay, π→ \\unknown, $\\beta$ and <code>\\gamma</code>"
+        );
+        assert_eq!(
+            render("\\alpha", None, &ExportOptions::parse("e:nil"), &HashMap::new()),
+            "\\alpha"
+        );
+    }
+
+    #[test]
+    fn typed_links() {
+        let mut links = link_types(None);
+
+        links.insert(
+            "gui".into(),
+            LinkTypeConfig {
+                element: None,
+                class: Some("gui".into()),
+                split: None,
+            },
+        );
+
+        assert_eq!(
+            render(
+                "Press [[kbd:C-c C-c]] or kbd:M-x, then [[menu:File > Save]] and gui:OK. Not =kbd:q= or [[https://a.b/kbd:x]].",
+                None,
+                &ExportOptions::default(),
+                &links
+            ),
+            "Press <kbd class=\"shortcut\"><kbd>C-c</kbd> <kbd>C-c</kbd></kbd> or <kbd class=\"shortcut\"><kbd>M-x</kbd></kbd>, then <span class=\"menu\"><span>File</span> &gt; <span>Save</span></span> and <span class=\"gui\">OK</span>. Not <code>kbd:q</code> or [[https://a.b/kbd:x]]."
+        );
+        assert_eq!(
+            render("see:this", None, &ExportOptions::default(), &links),
+            "see:this"
+        );
+    }
+}
@@ -1,8 +1,6 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
-use std::env::args;
-
 use fancy_regex::{Match, Regex};
 use lazy_static::lazy_static;
 
@@ -32,12 +30,15 @@ pub enum TokenKind {
     },
 
     /// | cell | cell | cell |
+    /// |------+------+------|
     /// | cell | cell | cell |
     Table {
         rows: Vec<Vec<String>>,
+        /// Rows before the first horizontal rule.
+        header_rows: usize,
     },
 
-    /// (?stars:\*+) (?todo_state:(?:TODO)|(?:DONE))? (?priority:#\[[a-zA-Z0-9]\])? (?title:[^\n]+) (?tags:\:([a-zA-Z0-9_@#%]\:)+)
+    /// (?stars:\*+) (?todo_state:(?:TODO)|(?:DONE)|…)? (?priority:\[#[a-zA-Z0-9]\])? (?title:[^\n]+) (?tags:\:([a-zA-Z0-9_@#%]\:)+)
     /// level = stars.size()
     /// commented = title.starts_with(“COMMENT”)
     /// archived = tags.contains(“ARCHIVE”)
@@ -77,7 +78,7 @@ pub enum TokenKind {
     /// End Blocks
 
     /// #+NAME: content
-    /// Note: #+INCLUDE: does not count as a Keyword and is immediately replaced with the file contents.
+    /// Note: #+INCLUDE: does not count as a Keyword and is replaced with the file contents before lexing.
     Keyword {
         name: String,
         content: String,
@@ -104,7 +105,17 @@ pub enum TokenKind {
     Macro {
         name: String,
         args: Vec<String>,
-    }, /*
+    },
+
+    /// -----
+    HorizontalRule,
+
+    /// : text
+    /// : more text
+    FixedWidth {
+        lines: Vec<String>,
+    },
+    /*
        /// \[(?label:[a-zA-Z0-9_-])\]: (?contents:.+)
        /// It ends at the next footnote definition, the next heading, two consecutive blank lines, or the end of buffer.
        FootNote {
@@ -113,6 +124,29 @@ pub enum TokenKind {
        },*/
 }
 
+impl TokenKind {
+    /// What the token is called in warnings.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::EmptyLine => "empty line".into(),
+            Self::Paragraph { .. } => "paragraph".into(),
+            Self::Table { .. } => "table".into(),
+            Self::Heading { .. } => "heading".into(),
+            Self::Planning { _type, .. } => format!("planning line `{}`", _type),
+            Self::LesserBlock { _type, .. } | Self::GreaterBlock { _type, .. } => {
+                format!("`{}` block", _type)
+            }
+            Self::Keyword { name, .. } => format!("keyword `{}`", name),
+            Self::Comment { .. } => "comment".into(),
+            Self::Drawer { name, .. } => format!("drawer `{}`", name),
+            Self::DynBlock { .. } => "dynamic block".into(),
+            Self::Macro { name, .. } => format!("macro `{}`", name),
+            Self::HorizontalRule => "horizontal rule".into(),
+            Self::FixedWidth { .. } => "fixed-width area".into(),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenKind,
@@ -144,20 +178,103 @@ pub struct Lexer {
     valid_for_initial_drawer: bool,
     state: State,
     tokens: Vec<Token>,
+    heading_regex: Regex,
+}
+
+/// Keywords in `#+TODO`-style sequences, e.g. `TODO NEXT(n) | DONE CANCELLED(c@)`.
+pub fn parse_todo_keywords<S: AsRef<str>>(sequences: &[S]) -> Vec<String> {
+    sequences
+        .iter()
+        .flat_map(|sequence| sequence.as_ref().split_whitespace())
+        .filter(|word| *word != "|")
+        .map(|word| word.split('(').next().unwrap_or(word).to_owned())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn heading_regex(todo_keywords: &[String]) -> Regex {
+    let todo_state = if todo_keywords.is_empty() {
+        String::new()
+    } else {
+        format!(
+            r"(?<todo_state>(?:{})\s+)?",
+            todo_keywords
+                .iter()
+                .map(|keyword| fancy_regex::escape(keyword))
+                .collect::<Vec<_>>()
+                .join("|")
+        )
+    };
+
+    Regex::new(&format!(
+        r#"(?<stars>\*+)\s+{}(?<priority>\[#[a-zA-Z0-9]\]\s+)?(?<title>[^\n]+?)(?<tags>\s+\:([a-zA-Z0-9_@#%]+\:)+)?(?:\s+\[(?<completion_amount>(?:\d+\/\d+)|(?:[\d.]+%))\])?$"#,
+        todo_state
+    ))
+    .unwrap()
 }
 
 lazy_static! {
-    static ref HEADING_REGEX: Regex = Regex::new(r#"(?<stars>\*+)\s+(?<todo_state>(?:(?!COMMENT)[A-Z]{2,})\s+)?(?<priority>#\[[a-zA-Z0-9]\]\s+)?(?<title>[^\n]+?)(?<tags>\s+\:([a-zA-Z0-9_@#%]+\:)+)?(?:\s+\[(?<completion_amount>(?:\d+\/\d+)|(?:[\d.]+%))\])?$"#).unwrap();
-    static ref PLANNING_REGEX: Regex = Regex::new(r"^\s+(?<type>\w+):\s*(?<value>[^\n]+)").unwrap();
-    static ref DRAWER_REGEX: Regex = Regex::new(r"^\s+:(?<name>[\w_-]+):").unwrap();
-    static ref CLOSE_DRAWER_REGEX: Regex = Regex::new(r"(?i)^\s+:end:").unwrap();
+    static ref COOKIE: Regex = Regex::new(r"\s*\[(?<amount>\d+/\d+|[\d.]+%)\]").unwrap();
+    static ref TODO_KEYWORD: Regex = Regex::new(r"(?i)^#\+(?:SEQ_|TYP_)?TODO:\s*(?<sequence>.+)$").unwrap();
+    static ref PLANNING_REGEX: Regex = Regex::new(r"^\s*(?<type>SCHEDULED|DEADLINE|CLOSED):\s*(?<value>[^\n]+)").unwrap();
+    static ref DRAWER_REGEX: Regex = Regex::new(r"^\s*:(?!(?i:end):)(?<name>[\w_-]+):\s*$").unwrap();
+    static ref CLOSE_DRAWER_REGEX: Regex = Regex::new(r"(?i)^\s*:end:\s*$").unwrap();
     static ref BLOCK_REGEX: Regex = Regex::new(r"(?i)^#\+BEGIN(?:_(?<type>[a-zA-Z]+))?:?\s*(?<args>(?:.+)?)$").unwrap();
-    static ref CLOSE_BLOCK_REGEX: Regex = Regex::new(r"(?i)^#\+END(?:_(?<type>[a-zA-Z]+))").unwrap();
+    static ref CLOSE_BLOCK_REGEX: Regex = Regex::new(r"(?i)^#\+END(?:_(?<type>[a-zA-Z]+)|:)").unwrap();
     static ref COMMENT_REGEX: Regex = Regex::new(r"^#\s+(?<content>.+)").unwrap();
+    static ref HORIZONTAL_RULE: Regex = Regex::new(r"^\s*-{5,}\s*$").unwrap();
+    static ref FIXED_WIDTH: Regex = Regex::new(r"^\s*:(?: (?<text>.*))?$").unwrap();
     static ref INDENTED: Regex = Regex::new(r"^\s+").unwrap();
     static ref TABLE_ROW: Regex = Regex::new(r"^(?<cells>\|.+)+\|?").unwrap();
-    static ref KEYWORD: Regex = Regex::new(r"^#\+(?<name>[a-zA-Z_]+):\s*(?<value>.+)$").unwrap();
-    static ref MACRO: Regex = Regex::new(r"{{{(?<name>[-\w\d_]+)(?:\((?<args>.*)\))?}}}").unwrap();
+    static ref KEYWORD: Regex = Regex::new(r"^#\+(?<name>[a-zA-Z_]+):\s*(?<value>.*)$").unwrap();
+    static ref MACRO: Regex = Regex::new(r"^\s*{{{(?<name>[-\w\d_]+)(?:\((?<args>.*)\))?}}}\s*$").unwrap();
+}
+
+/// Whether `text` ends inside a display fragment or LaTeX environment.
+fn in_math(text: &str) -> bool {
+    let count = |pattern: &str| text.matches(pattern).count();
+
+    count("\\[") > count("\\]")
+        || count("$$") % 2 == 1
+        || count("\\begin{") > count("\\end{")
+}
+
+/// `1, 2\, still 2` → `["1", "2, still 2"]`
+pub fn split_macro_args(args_str: &str) -> Vec<String> {
+    if args_str.is_empty() {
+        return vec![];
+    }
+
+    let mut args: Vec<String> = vec![];
+    let mut buf: Vec<char> = vec![];
+    let mut escaped = false;
+
+    args_str.chars().for_each(|c| {
+        if !escaped && c == ',' {
+            args.push(buf.iter().collect::<String>().trim().into());
+            buf = vec![];
+        } else if escaped && c == '\\' {
+            buf.push('\\');
+            escaped = false;
+        } else if escaped && c == ',' {
+            buf.push(',');
+            escaped = false;
+        } else if escaped {
+            buf.push('\\');
+            buf.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else {
+            buf.push(c);
+        }
+    });
+
+    if !buf.is_empty() {
+        args.push(buf.iter().collect::<String>().trim().into());
+    }
+
+    args
 }
 
 impl Lexer {
@@ -170,9 +287,16 @@ impl Lexer {
             valid_for_initial_drawer: true,
             state: State::Default,
             tokens: vec![],
+            heading_regex: heading_regex(&["TODO".into(), "DONE".into()]),
         }
     }
 
+    /// Recognizes these TODO keywords unless the file sets its own with `#+TODO`.
+    pub fn with_todo_keywords(mut self, todo_keywords: &[String]) -> Self {
+        self.heading_regex = heading_regex(todo_keywords);
+        self
+    }
+
     fn wrap(&self, kind: TokenKind) -> Option<Token> {
         Some(Token {
             location: self.current_location.clone(),
@@ -181,7 +305,18 @@ impl Lexer {
     }
 
     pub fn lex(&mut self, content: &str) -> Result<Vec<Token>, String> {
-        let lines = content.split(|char| char == '\n');
+        // `#+TODO` applies to the whole file, wherever it is.
+        let sequences: Vec<String> = content
+            .lines()
+            .filter_map(|line| TODO_KEYWORD.captures(line).ok().flatten())
+            .map(|caps| caps["sequence"].to_owned())
+            .collect();
+
+        if !sequences.is_empty() {
+            self.heading_regex = heading_regex(&parse_todo_keywords(&sequences));
+        }
+
+        let lines = content.split('\n');
 
         for line in lines {
             if let Some(token) = self.handle_line(line) {
@@ -201,8 +336,22 @@ impl Lexer {
             self.current_location = self.current_location.incremented();
         }
 
-        if self.state != State::Default {
-            return Err("Unexpected EOF.".into());
+        match &self.state {
+            State::Default => {}
+            State::Drawer { name, start, .. } => {
+                return Err(format!(
+                    "Drawer `{}` at {}:{} is never closed",
+                    name, start.file, start.line
+                ))
+            }
+            State::Block { _type, start, .. } => {
+                return Err(format!(
+                    "Block `{}` at {}:{} is never closed",
+                    _type.as_deref().unwrap_or(""),
+                    start.file,
+                    start.line
+                ))
+            }
         }
 
         Ok(self
@@ -219,15 +368,17 @@ impl Lexer {
             .map(|line| {
                 INDENTED
                     .find(line)
-                    .unwrap()
+                    .ok()
+                    .flatten()
                     .map_or_else(|| 0, |mtch: Match| mtch.end())
             })
             .reduce(std::cmp::min)
-            .unwrap();
+            .unwrap_or(0);
 
         lines
             .iter()
-            .map(|line| (&line[shared_indent..]).to_owned())
+            // Mixed multi-byte whitespace can put the shared indent inside a character.
+            .map(|line| line.get(shared_indent..).unwrap_or(line.trim_start()).to_owned())
             .collect()
     }
 
@@ -327,16 +478,19 @@ impl Lexer {
         lines: Vec<String>,
         start: Location,
     ) -> Option<Token> {
-        if let Ok(Some(caps)) = CLOSE_BLOCK_REGEX.captures(line) {
-            if caps
-                .name("type")
-                .map(match_to_str)
-                .map(|x| x.to_ascii_lowercase())
-                != _type
-            {
-                panic!("Closing a block of a different type.")
-            }
+        // Closing a block of a different type is just another line of this one.
+        let closes = CLOSE_BLOCK_REGEX
+            .captures(line)
+            .ok()
+            .flatten()
+            .is_some_and(|caps| {
+                caps.name("type")
+                    .map(match_to_str)
+                    .map(|x| x.to_ascii_lowercase())
+                    == _type
+            });
 
+        if closes {
             let token = self.construct_block(_type, lines, args, start.clone());
 
             self.state = State::Default;
@@ -358,10 +512,24 @@ impl Lexer {
         }
     }
 
+    /// The cells of a table row, or `None` for a horizontal rule.
+    fn table_row(line: &str) -> Option<Vec<String>> {
+        let line = line.trim();
+
+        if line.starts_with("|-") {
+            return None;
+        }
+
+        let line = line.strip_prefix('|').unwrap_or(line);
+        let line = line.strip_suffix('|').unwrap_or(line);
+
+        Some(line.split('|').map(|x| x.trim().to_owned()).collect())
+    }
+
     fn handle_normal(&mut self, line: &str) -> Option<Token> {
         if line.trim() == "" {
             self.wrap(TokenKind::EmptyLine)
-        } else if let Ok(Some(caps)) = HEADING_REGEX.captures(line) {
+        } else if let Ok(Some(caps)) = self.heading_regex.captures(line) {
             let tags: Vec<String> = caps
                 .name("tags")
                 .map(|x| match_to_str(x))
@@ -371,31 +539,44 @@ impl Lexer {
                 .map(|x| x.to_owned())
                 .collect();
 
+            let mut title: String = caps["title"].into();
+            let mut completion_amount = caps.name("completion_amount").map(match_to_str);
+
+            // Cookies are usually in the title, before any tags.
+            if completion_amount.is_none() {
+                if let Ok(Some(cookie)) = COOKIE.captures(&title) {
+                    let range = cookie.get(0).unwrap().range();
+
+                    completion_amount = Some(cookie["amount"].to_owned());
+                    title = format!("{}{}", &title[..range.start], &title[range.end..])
+                        .trim()
+                        .to_owned();
+                }
+            }
+
             self.wrap(TokenKind::Heading {
-                level: u8::try_from(caps["stars"].len()).unwrap(),
+                level: u8::try_from(caps["stars"].len()).unwrap_or(u8::MAX),
                 todo_state: caps.name("todo_state").map(match_to_str),
                 priority: caps
                     .name("priority")
                     .map(match_to_str)
                     .map(|x| (x[2..x.len() - 1]).to_owned()),
-                commented: caps["title"].starts_with("COMMENT"),
-                title: caps["title"].into(),
-                archived: tags.contains(&"ARCHIVED".to_owned()),
+                commented: title.starts_with("COMMENT"),
+                title,
+                archived: tags.contains(&"ARCHIVE".to_owned()),
                 tags,
-                completion_amount: caps.name("completion_amount").map(match_to_str),
+                completion_amount,
             })
-        } else if {
-            matches!(
-                self.tokens.last(),
-                Some(Token {
-                    kind: TokenKind::Planning { .. },
-                    ..
-                }) | Some(Token {
-                    kind: TokenKind::Heading { .. },
-                    ..
-                })
-            ) && matches!(PLANNING_REGEX.captures(line), Ok(Some(_)))
-        } {
+        } else if matches!(
+            self.tokens.last(),
+            Some(Token {
+                kind: TokenKind::Planning { .. },
+                ..
+            }) | Some(Token {
+                kind: TokenKind::Heading { .. },
+                ..
+            })
+        ) && matches!(PLANNING_REGEX.captures(line), Ok(Some(_))) {
             let caps = PLANNING_REGEX.captures(line).unwrap().unwrap();
             self.wrap(TokenKind::Planning {
                 _type: caps["type"].into(),
@@ -427,97 +608,80 @@ impl Lexer {
             })
         } else if let Ok(Some(caps)) = KEYWORD.captures(line) {
             self.wrap(TokenKind::Keyword {
-                name: caps["name"].to_ascii_lowercase().into(),
+                name: caps["name"].to_ascii_lowercase(),
                 content: caps["value"].into(),
             })
         } else if let Ok(Some(caps)) = MACRO.captures(line) {
             self.wrap(TokenKind::Macro {
-                name: caps["name"].to_ascii_lowercase().into(),
-                args: if let Some(args_match) = caps.name("args") {
-                    let args_str = args_match.as_str();
-
-                    if args_str == "" {
-                        vec![]
-                    } else {
-                        let mut args: Vec<String> = vec![];
-                        let mut buf: Vec<char> = vec![];
-                        let mut escaped = false;
-
-                        args_str.chars().for_each(|c| {
-                            if !escaped && c == ',' {
-                                args.push(buf.iter().collect::<String>().trim().into());
-                                buf = vec![];
-                            } else if escaped && c == '\\' {
-                                buf.push('\\');
-                                escaped = false;
-                            } else if escaped && c == ',' {
-                                buf.push(',');
-                                escaped = false;
-                            } else if escaped {
-                                buf.push('\\');
-                                buf.push(c);
-                                escaped = false;
-                            } else if c == '\\' {
-                                escaped = true;
-                            } else {
-                                buf.push(c);
-                            }
-                        });
-
-                        if buf != vec![] {
-                            args.push(buf.iter().collect::<String>().trim().into());
-                        }
-
-                        args
-                    }
-                } else {
-                    vec![]
-                },
+                name: caps["name"].to_ascii_lowercase(),
+                args: caps
+                    .name("args")
+                    .map(|args| split_macro_args(args.as_str()))
+                    .unwrap_or_default(),
             })
-        } else if TABLE_ROW.is_match(line).unwrap() {
-            match self.tokens.last().clone() {
+        } else if HORIZONTAL_RULE.is_match(line).unwrap_or(false) {
+            self.wrap(TokenKind::HorizontalRule)
+        } else if let Ok(Some(caps)) = FIXED_WIDTH.captures(line) {
+            let text = caps
+                .name("text")
+                .map_or(String::new(), |text| text.as_str().trim_end().to_owned());
+
+            match self.tokens.last_mut() {
                 Some(Token {
-                    kind: TokenKind::Table { rows },
+                    kind: TokenKind::FixedWidth { lines },
                     ..
                 }) => {
-                    let len = self.tokens.len() - 1;
+                    lines.push(text);
 
-                    let mut tmp_rows = rows.to_owned();
-                    tmp_rows.push(
-                        line.trim()
-                            .split("|")
-                            .map(|x| x.trim().to_owned())
-                            .collect::<Vec<_>>(),
-                    );
+                    None
+                }
+                _ => self.wrap(TokenKind::FixedWidth {
+                    lines: vec![text],
+                }),
+            }
+        } else if TABLE_ROW.is_match(line).unwrap_or(false) {
+            let row = Self::table_row(line);
 
-                    self.tokens[len] = Token {
-                        kind: TokenKind::Table { rows: tmp_rows },
-                        ..self.tokens.last().unwrap().to_owned()
-                    };
+            match self.tokens.last_mut() {
+                Some(Token {
+                    kind: TokenKind::Table { rows, header_rows },
+                    ..
+                }) => {
+                    match row {
+                        Some(row) => rows.push(row),
+                        None if *header_rows == 0 => *header_rows = rows.len(),
+                        None => {}
+                    }
 
                     None
                 }
                 _ => self.wrap(TokenKind::Table {
-                    rows: vec![line
-                        .trim()
-                        .split("|")
-                        .map(|x| x.trim().to_owned())
-                        .collect::<Vec<_>>()],
+                    rows: row.into_iter().collect(),
+                    header_rows: 0,
                 }),
             }
         } else {
-            match self.tokens.last().clone() {
+            match self.tokens.last() {
                 Some(Token {
                     kind: TokenKind::Paragraph { content },
                     ..
                 }) => {
                     let len = self.tokens.len() - 1;
+                    let content = content.trim_end();
+                    // `\\` at the end of a line breaks it, unless it's in a LaTeX environment.
+                    let (content, forced_break) = match content.strip_suffix("\\\\") {
+                        Some(before) if !in_math(content) => (before.trim_end(), true),
+                        _ => (content, false),
+                    };
+
                     self.tokens[len] = Token {
                         kind: TokenKind::Paragraph {
-                            content: if let Ok(Some(_)) = INDENTED.captures(line) {
-                                content.trim_end().to_owned() + " " + line.trim_start()
+                            content: if forced_break {
+                                content.to_owned() + "\n" + line.trim_start()
+                            } else if let Ok(Some(_)) = INDENTED.captures(line) {
+                                content.to_owned() + " " + line.trim_start()
                             } else {
-                                content.trim_end().to_owned() + "\n" + line
+                                content.to_owned() + "\n" + line
                             },
                         },
                         ..self.tokens.last().unwrap().to_owned()
@@ -535,7 +699,7 @@ impl Lexer {
 
 #[cfg(test)]
 mod test {
-    use crate::org::lex::Lexer;
+    use crate::org::lex::{parse_todo_keywords, Lexer};
     use crate::org::lex::{Location, Token, TokenKind};
 
     #[test]
@@ -543,14 +707,51 @@ mod test {
         assert_eq!(
             Lexer::new("headings.org").lex(
                 r#"
-* TODO #[A] COMMENT test :abc: [3%]
+* TODO [#A] COMMENT test :abc: [3%]
     DEADLINE: tomorrow
     :drawer:
     something: nothing
     :enD:
 "#
             ),
-            Ok(vec![])
+            Ok(vec![
+                Token {
+                    kind: TokenKind::Heading {
+                        level: 1,
+                        todo_state: Some("TODO".into()),
+                        priority: Some("A".into()),
+                        commented: true,
+                        title: "COMMENT test".into(),
+                        tags: vec!["abc".into()],
+                        archived: false,
+                        completion_amount: Some("3%".into())
+                    },
+                    location: Location {
+                        file: "headings.org".into(),
+                        line: 2
+                    }
+                },
+                Token {
+                    kind: TokenKind::Planning {
+                        _type: "DEADLINE".into(),
+                        value: "tomorrow".into()
+                    },
+                    location: Location {
+                        file: "headings.org".into(),
+                        line: 3
+                    }
+                },
+                Token {
+                    kind: TokenKind::Drawer {
+                        name: "drawer".into(),
+                        contents: vec!["    something: nothing".into()]
+                    },
+                    location: Location {
+                        file: "headings.org".into(),
+                        line: 4
+                    }
+                }
+            ])
         )
     }
 
@@ -558,11 +759,20 @@ mod test {
     fn zeroth_section() {
         assert_eq!(
             Lexer::new("zero.org").lex(
-                r#"    :drawer:
-    abc: another
-    :end:"#
+                r#":drawer:
+abc: another
+:end:"#
             ),
-            Ok(vec![])
+            Ok(vec![Token {
+                kind: TokenKind::Drawer {
+                    name: "drawer".into(),
+                    contents: vec!["abc: another".into()]
+                },
+                location: Location {
+                    file: "zero.org".into(),
+                    line: 1
+                }
+            }])
         )
     }
 
@@ -729,4 +939,57 @@ noooo"#
             ])
         )
     }
+
+    #[test]
+    fn todo_keywords() {
+        let headings = |lexer: &mut Lexer| {
+            lexer
+                .lex("#+TODO: NEXT(n) WAIT(w@/!) | CANCELLED(c)\n* NEXT Write\n* TODO Read\n* CANCELLED Sleep")
+                .unwrap()
+                .into_iter()
+                .filter_map(|token| match token.kind {
+                    TokenKind::Heading {
+                        todo_state, title, ..
+                    } => Some((todo_state, title)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            headings(&mut Lexer::new("todo.org")),
+            vec![
+                (Some("NEXT".into()), "Write".into()),
+                (None, "TODO Read".into()),
+                (Some("CANCELLED".into()), "Sleep".into())
+            ]
+        );
+        assert_eq!(
+            parse_todo_keywords(&["TODO NEXT(n) | DONE", "BUG | FIXED"]),
+            vec!["TODO", "NEXT", "DONE", "BUG", "FIXED"]
+        );
+    }
+
+    #[test]
+    fn cookies() {
+        let heading = Lexer::new("cookies.org")
+            .lex("* TODO [#B] Reading list [2/5] :books:")
+            .unwrap()
+            .remove(0)
+            .kind;
+
+        assert_eq!(
+            heading,
+            TokenKind::Heading {
+                level: 1,
+                todo_state: Some("TODO".into()),
+                priority: Some("B".into()),
+                commented: false,
+                title: "Reading list".into(),
+                tags: vec!["books".into()],
+                archived: false,
+                completion_amount: Some("2/5".into())
+            }
+        );
+    }
 }
@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::path::Path;
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+use crate::{
+    metadata::{Anchor, AnchorKind},
+    org::Document,
+};
+
+lazy_static! {
+    static ref LINK: Regex = Regex::new(r"\[\[(?<target>[^\]\[]+)\](?:\[[^\]\[]*\])?\]").unwrap();
+    static ref SCHEME: Regex = Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").unwrap();
+    static ref FILE_NAME: Regex = Regex::new(r"^[^\s/]+\.[A-Za-z0-9]+$").unwrap();
+    static ref BLOCK_START: Regex = Regex::new(r"(?i)^\s*#\+BEGIN_(SRC|EXAMPLE|EXPORT)\b").unwrap();
+    static ref BLOCK_END: Regex = Regex::new(r"(?i)^\s*#\+END_(SRC|EXAMPLE|EXPORT)\b").unwrap();
+}
+
+/// Whether `path`, relative to `dir` or the source directory `root` if it starts with `/`,
+/// exists, or is the page built from an Org file that does.
+fn file_exists(path: &str, dir: &Path, root: &Path) -> bool {
+    let path = path.split("::").next().unwrap_or_default();
+    let file = match path.strip_prefix('/') {
+        Some(path) => root.join(path),
+        None => dir.join(path),
+    };
+
+    file.exists()
+        || (file.extension().is_some_and(|ext| ext == "html")
+            && file.with_extension("org").exists())
+}
+
+/// Whether `target` is a link to something in the page or the site that isn't there: a
+/// `[[#id]]` or `[[*Title]]` heading, a `[[name]]`, or a file.
+fn broken(target: &str, anchors: &[Anchor], source: &str, dir: &Path, root: &Path) -> bool {
+    let target = target.trim();
+
+    if let Some(path) = target.strip_prefix("file:") {
+        return !file_exists(path, dir, root);
+    }
+
+    if let Some(id) = target.strip_prefix('#') {
+        return !anchors
+            .iter()
+            .any(|anchor| anchor.kind == AnchorKind::Heading && anchor.id == id);
+    }
+
+    if let Some(title) = target.strip_prefix('*') {
+        return !anchors.iter().any(|anchor| {
+            anchor.kind == AnchorKind::Heading && anchor.title.as_deref() == Some(title.trim())
+        });
+    }
+
+    // Coderefs, and links of other types, like `https:` or `kbd:`, aren't checked.
+    if target.starts_with('(') || SCHEME.is_match(target).unwrap_or(false) {
+        return false;
+    }
+
+    if ["/", "./", "../", "~"]
+        .iter()
+        .any(|prefix| target.starts_with(prefix))
+        || FILE_NAME.is_match(target).unwrap_or(false)
+    {
+        return !target.starts_with('~') && !file_exists(target, dir, root);
+    }
+
+    !anchors
+        .iter()
+        .any(|anchor| anchor.id == target || anchor.title.as_deref() == Some(target))
+        && !source.contains(&format!("<<{}>>", target))
+}
+
+/// The targets of links in the Org `source` that lead nowhere, with their line numbers.
+/// `anchors` are the page's, and file links are looked up relative to the file's directory
+/// `dir` and the source directory `root`. Links in source, example and export blocks are
+/// code, not links.
+pub fn broken_links(
+    source: &str,
+    anchors: &[Anchor],
+    dir: &Path,
+    root: &Path,
+) -> Vec<(String, usize)> {
+    let mut in_block = false;
+    let mut found = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        if in_block {
+            in_block = !BLOCK_END.is_match(line).unwrap_or(false);
+            continue;
+        }
+
+        if BLOCK_START.is_match(line).unwrap_or(false) {
+            in_block = true;
+            continue;
+        }
+
+        // Images are checked as they're parsed.
+        if line.starts_with("# ") || line == "#" || Document::image_link(line).is_some() {
+            continue;
+        }
+
+        for caps in LINK.captures_iter(line).filter_map(Result::ok) {
+            if broken(&caps["target"], anchors, source, dir, root) {
+                found.push((caps["target"].to_owned(), i + 1));
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use crate::org::{links::broken_links, Document};
+
+    #[test]
+    fn broken() {
+        let dir = std::env::temp_dir().join(format!("impertio-links-{}", std::process::id()));
+        let source = "#+TITLE: Links
+
+* Setup
+:PROPERTIES:
+:CUSTOM_ID: setup
+:END:
+See [[#setup]], [[*Setup]], [[squares]], [[file:other.org][other]] and [[other.html]].
+Not [[#gone]], [[*Teardown]], [[nothing]], [[./missing.png]] or [[file:gone.org::*A]].
+[[https://x.y][Elsewhere]], [[kbd:C-c]] and [[(ref)]] aren't checked.
+
+#+NAME: squares
+| 1 | 1 |
+
+[[file:missing.png]]
+
+#+BEGIN_SRC org
+[[#in-code]]
+#+END_SRC
+# [[#commented]]
+";
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("other.org"), "").unwrap();
+
+        let doc = Document::parse(source, "links.org", Default::default()).unwrap();
+        let found = broken_links(source, &doc.anchors(), &dir, &dir);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                ("#gone".to_owned(), 8),
+                ("*Teardown".into(), 8),
+                ("nothing".into(), 8),
+                ("./missing.png".into(), 8),
+                ("file:gone.org::*A".into(), 8),
+            ]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+use crate::org::lex::split_macro_args;
+
+/// Expansions may call other macros, up to this depth.
+const MAX_DEPTH: usize = 16;
+
+lazy_static! {
+    static ref DEFINITION: Regex =
+        Regex::new(r"^\s*#\+(?i:macro):\s*(?<name>[-\w]+)(?:\s+(?<template>.*))?$").unwrap();
+    static ref CALL: Regex =
+        Regex::new(r"\{\{\{(?<name>[-\w]+)(?:\((?<args>.*?)\))?\}\}\}").unwrap();
+    static ref VERBATIM_BLOCK: Regex =
+        Regex::new(r"(?i)^\s*#\+BEGIN_(?<type>SRC|EXAMPLE|EXPORT)\b").unwrap();
+}
+
+/// `$1` through `$9` are replaced with the arguments; missing ones are empty.
+fn apply(template: &str, args: &[String]) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match chars.peek().and_then(|next| next.to_digit(10)) {
+            Some(n) if c == '$' && n > 0 => {
+                chars.next();
+                out += args.get(n as usize - 1).map_or("", |arg| arg.as_str());
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn expand_line(line: &str, macros: &HashMap<String, String>, depth: usize) -> String {
+    if depth >= MAX_DEPTH {
+        return line.to_owned();
+    }
+
+    let mut changed = false;
+    let expanded = CALL.replace_all(line, |caps: &Captures| {
+        match macros.get(&caps["name"].to_ascii_lowercase()) {
+            Some(template) => {
+                changed = true;
+                apply(
+                    template,
+                    &split_macro_args(caps.name("args").map_or("", |args| args.as_str())),
+                )
+            }
+            None => caps[0].to_owned(),
+        }
+    });
+
+    if changed {
+        expand_line(&expanded, macros, depth + 1)
+    } else {
+        expanded.into_owned()
+    }
+}
+
+/// Collects `#+MACRO: name template` definitions and expands `{{{name(args)}}}` calls to them,
+/// outside of src, example and export blocks. Definitions are blanked out so line numbers hold,
+/// and calls to undefined macros are left for the parser.
+pub fn expand(content: &str) -> String {
+    let macros: HashMap<String, String> = content
+        .split('\n')
+        .filter_map(|line| DEFINITION.captures(line).ok().flatten())
+        .map(|caps| {
+            (
+                caps["name"].to_ascii_lowercase(),
+                caps.name("template")
+                    .map_or("", |template| template.as_str())
+                    .trim()
+                    .to_owned(),
+            )
+        })
+        .collect();
+
+    if macros.is_empty() {
+        return content.to_owned();
+    }
+
+    let mut verbatim: Option<String> = None;
+
+    content
+        .split('\n')
+        .map(|line| {
+            if let Some(type_) = &verbatim {
+                if line
+                    .trim_start()
+                    .to_ascii_uppercase()
+                    .starts_with(&format!("#+END_{}", type_))
+                {
+                    verbatim = None;
+                }
+
+                line.to_owned()
+            } else if let Ok(Some(caps)) = VERBATIM_BLOCK.captures(line) {
+                verbatim = Some(caps["type"].to_ascii_uppercase());
+                line.to_owned()
+            } else if DEFINITION.is_match(line).unwrap_or(false) {
+                String::new()
+            } else {
+                expand_line(line, &macros, 0)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::org::macros::expand;
+
+    #[test]
+    fn definitions() {
+        assert_eq!(
+            expand(
+                r#"#+MACRO: greet Hello, $1 and $2!
+#+macro: shout {{{greet(*$1*, you)}}}
+{{{shout(World)}}} Then {{{greet(a\, b, c)}}} {{{listing(/)}}}
+#+BEGIN_SRC org
+{{{greet(x)}}}
+#+END_SRC"#
+            ),
+            r#"
+
+Hello, *World* and you! Then Hello, a, b and c! {{{listing(/)}}}
+#+BEGIN_SRC org
+{{{greet(x)}}}
+#+END_SRC"#
+        );
+    }
+
+    #[test]
+    fn recursion_limit() {
+        assert_eq!(
+            expand("#+MACRO: loop {{{loop}}}\n{{{loop}}}"),
+            "\n{{{loop}}}"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+
+use crate::{config::MathConfig, org::html};
+
+lazy_static! {
+    static ref FRAGMENT: Regex = Regex::new(
+        r"(?s)\\\((?<inline>.+?)\\\)|\\\[(?<display>.+?)\\\]|\$\$(?<display_dollars>.+?)\$\$|(?<![\w$])\$(?=\S)(?<inline_dollar>[^$]+?)(?<=\S)\$(?![\w$])"
+    )
+    .unwrap();
+}
+
+/// A LaTeX fragment, verbatim, between the configured delimiters.
+/// With `server_side` set, it is rendered by KaTeX instead.
+fn delimited(tex: &str, display: bool, config: &MathConfig) -> String {
+    if config.server_side == Some(true) {
+        return html::katex(tex.trim(), display);
+    }
+
+    let (open, close) = if display {
+        config.display_delimiters()
+    } else {
+        config.inline_delimiters()
+    };
+
+    format!("{}{}{}", open, tex.trim(), close)
+}
+
+/// A standalone display fragment, outside of any paragraph.
+pub fn render_display(tex: &str, config: &MathConfig) -> String {
+    format!("<div class=\"math\">{}</div>", delimited(tex, true, config))
+}
+
+fn fragment(caps: &Captures) -> (String, bool) {
+    if let Some(tex) = caps.name("inline").or(caps.name("inline_dollar")) {
+        (tex.as_str().to_owned(), false)
+    } else {
+        (
+            caps.name("display")
+                .or(caps.name("display_dollars"))
+                .unwrap()
+                .as_str()
+                .to_owned(),
+            true,
+        )
+    }
+}
+
+/// The contents of a paragraph made up of nothing but one display fragment.
+pub fn display_fragment(text: &str) -> Option<String> {
+    let caps = FRAGMENT.captures(text.trim()).ok()??;
+    let whole = caps.get(0).unwrap();
+
+    match fragment(&caps) {
+        (tex, true) if whole.start() == 0 && whole.end() == text.trim().len() => Some(tex),
+        _ => None,
+    }
+}
+
+/// `text` with `f` applied to everything but its fragments, which are kept as is.
+pub fn outside_fragments(text: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+
+    for whole in FRAGMENT.find_iter(text).map_while(Result::ok) {
+        out += &f(&text[last..whole.start()]);
+        out += whole.as_str();
+        last = whole.end();
+    }
+
+    out + &f(&text[last..])
+}
+
+/// Replaces every fragment in a paragraph with its delimited form.
+/// Display fragments stay inline with the text, as they do in Org.
+pub fn replace_fragments(text: &str, config: &MathConfig) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+
+    // Matching can fail on pathological input; what's left is kept as text.
+    for caps in FRAGMENT.captures_iter(text).map_while(Result::ok) {
+        let whole = caps.get(0).unwrap();
+        let (tex, display) = fragment(&caps);
+
+        out += &text[last..whole.start()];
+        // Newlines would otherwise become `<br />` in the paragraph.
+        out += &delimited(&tex.replace('\n', " "), display, config);
+        last = whole.end();
+    }
+
+    out + &text[last..]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::MathConfig,
+        org::math::{display_fragment, replace_fragments},
+    };
+
+    #[test]
+    fn fragments() {
+        let config = MathConfig::default();
+
+        assert_eq!(
+            replace_fragments("$a^2$, \\(b\n+ c\\) and $$d$$ cost $5 or $6.", &config),
+            "\\(a^2\\), \\(b + c\\) and \\[d\\] cost $5 or $6."
+        );
+    }
+
+    #[test]
+    fn delimiters() {
+        let config = MathConfig {
+            inline: Some(("@@".into(), "@@".into())),
+            display: None,
+            server_side: None,
+        };
+
+        assert_eq!(replace_fragments("$x$", &config), "@@x@@");
+    }
+
+    #[test]
+    fn server_side() {
+        let config = MathConfig {
+            server_side: Some(true),
+            ..Default::default()
+        };
+
+        let rendered = replace_fragments("Euler: $e^{i\\pi} + 1 = 0$", &config);
+
+        assert!(rendered.starts_with("Euler: <span class=\"katex\">"));
+        assert!(!rendered.contains('$') && !rendered.contains('\n'));
+    }
+
+    #[test]
+    fn display_paragraph() {
+        assert_eq!(display_fragment("\\[ x = y \\]"), Some(" x = y ".into()));
+        assert_eq!(display_fragment("\\[ x \\] and more"), None);
+        assert_eq!(display_fragment("\\( x \\)"), None);
+    }
+}
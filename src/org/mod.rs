@@ -1,35 +1,125 @@
 // SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
 // SPDX-License-Identifier: MIT
 
-use std::collections::HashMap;
+use std::{collections::HashMap, path::Path};
 
+mod anchors;
+mod babel;
+mod cite;
+mod diagram;
+mod entities;
+pub mod format;
 mod html;
+mod include;
+mod index;
+mod inline;
 mod lex;
+mod links;
+mod macros;
+mod math;
+pub mod options;
+mod serialize;
+mod timestamp;
+mod toc;
+mod xref;
 
 use build_html::{Container, ContainerType, Html, HtmlContainer};
+pub(crate) use html::plain_text;
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
 use lex::{Lexer, TokenKind};
+use options::ExportOptions;
+use timestamp::Timestamp;
 
-use crate::{handler::FileContext, metadata::Metadata};
+pub use anchors::slugify;
+pub use links::broken_links;
+
+use crate::{
+    collation,
+    config::{Config, TocPlacement},
+    handler::FileContext,
+    metadata::Metadata,
+};
 
 type Inner = String;
 
+/// Keywords that apply to the element following them instead of the document.
+const AFFILIATED_KEYWORDS: [&str; 3] = ["caption", "name", "attr_html"];
+/// How deeply greater blocks may nest before parsing gives up.
+const MAX_NESTING: usize = 64;
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "bmp"];
+
+lazy_static! {
+    static ref IMAGE_LINK: Regex = Regex::new(r"^\[\[(?:file:)?(?<path>[^\]]+)\]\]$").unwrap();
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum Node {
     Heading {
         level: u8,
         title: Inner,
         todo_state: Option<String>,
+        /// `A` for `[#A]`.
+        priority: Option<String>,
+        /// A statistics cookie's `3/7` or `40%`.
+        completion: Option<String>,
         tags: Vec<String>,
         commented: bool,
+        /// From the `:PROPERTIES:` drawer, with upper-case keys.
+        properties: HashMap<String, String>,
+        /// Unique in the document, once it is parsed.
+        id: String,
+        /// Like `2.1`, if `num:` numbers the section.
+        number: Option<String>,
     },
     Paragraph(String),
+    HorizontalRule,
+    /// Lines starting with `: `, kept as they are.
+    FixedWidth(Inner),
+    /// `# more`, ending the summary shown in listings and feeds.
+    ExcerptSeparator,
+    /// `#+INDEX: term`, which the site's `{{{index}}}` links to.
+    IndexTerm {
+        term: String,
+        /// Unique in the document, once it is parsed.
+        id: String,
+    },
+    /// Where `#+PRINT_BIBLIOGRAPHY:` lists the cited references, rendered once the whole
+    /// document is parsed.
+    Bibliography(Inner),
+    /// `SCHEDULED`, `DEADLINE` or `CLOSED` and when.
+    Planning(Vec<(String, Timestamp)>),
     LesserBlock {
         type_: String,
         args: Vec<String>,
         contents: Inner,
     },
+    GreaterBlock {
+        type_: String,
+        /// What follows `#+BEGIN_TYPE`, such as a tab's name.
+        args: String,
+        contents: Vec<Node>,
+    },
     Table {
         rows: Vec<Vec<Inner>>,
+        /// Leading rows that form the `<thead>`.
+        header_rows: usize,
+    },
+    Image {
+        path: String,
+        caption: Option<Inner>,
+        attributes: Vec<(String, String)>,
+    },
+    /// A figure, table or listing with a `#+NAME` to link to, or a `#+CAPTION`.
+    Labeled {
+        /// `Figure`, `Table` or `Listing`.
+        kind: String,
+        /// Counted per kind among captioned elements, once the whole document is parsed, and
+        /// continuing from earlier pages if numbering is site-wide.
+        number: Option<usize>,
+        name: Option<String>,
+        caption: Option<Inner>,
+        node: Box<Node>,
     },
 }
 
@@ -37,62 +127,565 @@ pub enum Node {
 pub struct Section {
     pub nodes: Vec<Node>,
     pub commented: bool,
+    /// Sections under this one's heading, at deeper levels.
+    pub children: Vec<Section>,
+}
+
+impl Section {
+    /// The heading's level, or 0 for the content before the first heading.
+    fn level(&self) -> u8 {
+        match self.nodes.first() {
+            Some(Node::Heading { level, .. }) => *level,
+            _ => 0,
+        }
+    }
+
+    /// Moves each section into the closest preceding one with a shallower heading.
+    fn nest(flat: Vec<Section>) -> Vec<Section> {
+        fn close(stack: &mut Vec<Section>, roots: &mut Vec<Section>) {
+            let section = stack.pop().unwrap();
+
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(section),
+                None => roots.push(section),
+            }
+        }
+
+        let mut roots = vec![];
+        let mut stack: Vec<Section> = vec![];
+
+        for section in flat {
+            let level = section.level();
+
+            while stack
+                .last()
+                .is_some_and(|open| level == 0 || open.level() >= level)
+            {
+                close(&mut stack, &mut roots);
+            }
+
+            if level == 0 {
+                roots.push(section);
+            } else {
+                stack.push(section);
+            }
+        }
+
+        while !stack.is_empty() {
+            close(&mut stack, &mut roots);
+        }
+
+        roots
+    }
+}
+
+/// A top-level heading's section, like a release in a changelog.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// The heading as plain text, without its timestamp.
+    pub title: String,
+    pub id: String,
+    /// The heading's timestamp, else the section's first planning or active timestamp.
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+    /// The rendered section, heading included.
+    pub html: String,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Document {
     pub metadata: HashMap<String, String>,
     pub sections: Vec<Section>,
+    /// The first active timestamp, in keywords, planning lines or paragraphs.
+    pub published: Option<chrono::DateTime<chrono::Utc>>,
+    pub options: ExportOptions,
+}
+
+/// A link to `article` with its title, description, date and author, as `{{{listing}}}` and
+/// taxonomy pages list it. `None` for anything but articles.
+pub fn article_card(article: &Metadata) -> Option<String> {
+    let Metadata::Article {
+        title,
+        description,
+        author,
+        tags,
+        modified,
+        url,
+        summary,
+        ..
+    } = article
+    else {
+        return None;
+    };
+    let mut attributes = vec![
+        ("data-title".to_owned(), title.to_string()),
+        ("data-last-modified".to_owned(), modified.to_rfc3339()),
+    ];
+
+    if let Some(description) = description {
+        attributes.push(("data-description".into(), description.to_string()));
+    }
+
+    if let Some(author) = author {
+        attributes.push(("data-author".into(), author.to_string()));
+    }
+
+    if !tags.is_empty() {
+        attributes.push(("data-tags".into(), tags.join(", ")));
+    }
+
+    let mut container: Container = Container::new(ContainerType::Div).with_attributes(attributes);
+
+    container.add_paragraph_attr(title, [("class", "card-title")]);
+
+    if let Some(description) = description {
+        container.add_paragraph(description);
+    }
+
+    let mut end_container = Container::new(ContainerType::Div).with_raw(format!(
+        "<span class=\"card-time\">{}</span>",
+        build_html::escape_html(&modified.to_rfc3339())
+    ));
+
+    if let Some(author) = author {
+        end_container.add_raw(format!(
+            "<span class=\"card-author\">{}</span>",
+            build_html::escape_html(author)
+        ));
+    }
+
+    // Continue after the summary.
+    let href = if summary.is_some() {
+        end_container.add_raw("<span class=\"card-more\">Read more</span>");
+        format!("{}#more", url)
+    } else {
+        url.clone()
+    };
+
+    container.add_container(end_container);
+
+    Some(format!(
+        "<a href=\"{}\" class=\"article-card\">{}</a>",
+        href,
+        container.to_html_string()
+    ))
 }
 
 impl Document {
     pub fn parse(content: &str, filename: &str, ctx: FileContext) -> Result<Self, String> {
+        let options = ExportOptions::scan(content);
+        let mut slf = Self::parse_nodes(content, filename, ctx.clone(), &options, 0)?;
+
+        slf.sections = Section::nest(std::mem::take(&mut slf.sections));
+        anchors::assign(&mut slf.sections);
+        anchors::number(&mut slf.sections, slf.options.num);
+        xref::resolve(&mut slf.sections, &xref::offsets(&ctx));
+        cite::resolve(&mut slf.sections, &slf.metadata, filename, &ctx);
+
+        Ok(slf)
+    }
+
+    /// `:KEY: value` lines; `:KEY+: value` appends to an earlier `KEY`.
+    fn parse_properties(lines: &[String]) -> HashMap<String, String> {
+        let mut properties: HashMap<String, String> = HashMap::new();
+
+        for line in lines {
+            let Some((key, value)) = line.trim().strip_prefix(':').and_then(|line| line.split_once(':')) else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.strip_suffix('+') {
+                Some(key) => {
+                    let existing = properties.entry(key.to_ascii_uppercase()).or_default();
+
+                    if !existing.is_empty() {
+                        existing.push(' ');
+                    }
+
+                    existing.push_str(value);
+                }
+                None => {
+                    properties.insert(key.to_ascii_uppercase(), value.to_owned());
+                }
+            }
+        }
+
+        properties
+    }
+
+    /// Wraps `node` if the affiliated keywords before it name or caption it.
+    fn labeled(node: Node, kind: &str, affiliated: &mut HashMap<String, String>) -> Node {
+        let name = affiliated.remove("name");
+        let caption = affiliated.remove("caption");
+
+        if name.is_none() && caption.is_none() {
+            return node;
+        }
+
+        Node::Labeled {
+            kind: kind.into(),
+            number: None,
+            name,
+            caption,
+            node: Box::new(node),
+        }
+    }
+
+    /// Parses arbitrary bytes without panicking, e.g. for fuzzing. Invalid UTF-8 is replaced.
+    pub fn parse_bytes(data: &[u8]) -> Result<Self, String> {
+        Self::parse(&String::from_utf8_lossy(data), "input.org", Default::default())
+    }
+
+    fn parse_nodes(
+        content: &str,
+        filename: &str,
+        ctx: FileContext,
+        options: &ExportOptions,
+        depth: usize,
+    ) -> Result<Self, String> {
+        if depth > MAX_NESTING {
+            return Err(format!("Blocks in {} are nested too deeply", filename));
+        }
+
         let mut slf = Self {
             metadata: HashMap::new(),
             sections: vec![Section {
                 nodes: vec![],
                 commented: false,
+                children: vec![],
             }],
+            published: None,
+            options: options.clone(),
         };
 
-        let lexed = Lexer::new(filename).lex(content)?;
+        let content = macros::expand(&include::expand(content, Path::new(filename))?);
+        let mut lexer = Lexer::new(filename);
+
+        if let Some(sequences) = ctx
+            .handler_config
+            .org
+            .as_ref()
+            .and_then(|org| org.todo_keywords.as_ref())
+        {
+            lexer = lexer.with_todo_keywords(&lex::parse_todo_keywords(sequences));
+        }
+
+        let lexed = lexer.lex(&content)?;
+        let link_types = inline::link_types(ctx.handler_config.org.as_ref());
+        let mut affiliated: HashMap<String, String> = HashMap::new();
+        // Level of the `:noexport:` or archived heading whose subtree is being skipped.
+        let mut excluded_level: Option<u8> = None;
 
         for token in lexed {
+            if let TokenKind::Heading {
+                level,
+                tags,
+                archived,
+                ..
+            } = &token.kind
+            {
+                if excluded_level.is_some_and(|excluded| *level <= excluded) {
+                    excluded_level = None;
+                }
+
+                if excluded_level.is_none() && (*archived || tags.iter().any(|tag| tag == "noexport")) {
+                    excluded_level = Some(*level);
+                }
+            }
+
+            if excluded_level.is_some() {
+                continue;
+            }
+
+            if slf.published.is_none() {
+                slf.published = match &token.kind {
+                    TokenKind::Keyword { content: text, .. }
+                    | TokenKind::Paragraph { content: text } => Timestamp::first_active(text),
+                    TokenKind::Planning { value, .. } => Timestamp::first_active(value),
+                    _ => None,
+                }
+                .map(|timestamp| timestamp.to_utc());
+            }
+
+            let is_affiliated = matches!(
+                &token.kind,
+                TokenKind::Keyword { name, .. } if AFFILIATED_KEYWORDS.contains(&name.as_str())
+            );
+
             match token.kind {
                 TokenKind::Heading {
                     level,
                     todo_state,
+                    priority,
                     title,
-                    tags,
+                    mut tags,
                     commented,
+                    completion_amount,
                     ..
-                } => slf.add_to_last(Node::Heading {
-                    level,
-                    title,
-                    todo_state,
-                    tags,
-                    commented,
-                }),
-                TokenKind::Paragraph { content } => slf.add_to_last(Node::Paragraph(content)),
+                } => {
+                    // `#+ATTR_HTML: :details t` before a heading is the same as its tag.
+                    if let Some(attrs) = affiliated.remove("attr_html") {
+                        if Self::parse_attributes(&attrs)
+                            .iter()
+                            .any(|(key, value)| key == html::DETAILS_TAG && value != "nil")
+                            && !tags.iter().any(|tag| tag == html::DETAILS_TAG)
+                        {
+                            tags.push(html::DETAILS_TAG.into());
+                        }
+                    }
+
+                    slf.add_to_last(Node::Heading {
+                        level,
+                        title,
+                        todo_state,
+                        priority,
+                        completion: completion_amount,
+                        tags,
+                        commented,
+                        properties: HashMap::new(),
+                        id: String::new(),
+                        number: None,
+                    })
+                }
+                TokenKind::Paragraph { content } => {
+                    // A `\\` ending the paragraph has nothing left to break.
+                    let content = match content.trim_end().strip_suffix("\\\\") {
+                        Some(before) => before.trim_end().to_owned(),
+                        None => content,
+                    };
+
+                    if let Some(path) = Self::image_link(&content) {
+                        let source = Path::new(filename)
+                            .parent()
+                            .unwrap_or(Path::new(""))
+                            .join(&path);
+
+                        if !source.exists() {
+                            ctx.warnings.lock().unwrap().add(
+                                "Referenced images do not exist",
+                                format!("{:?} at {}:{}", path, filename, token.location.line),
+                            );
+                        }
+
+                        let mut attributes = affiliated
+                            .remove("attr_html")
+                            .map(|attrs| Self::parse_attributes(&attrs))
+                            .unwrap_or_default();
+
+                        if let Some(max_width) = ctx
+                            .handler_config
+                            .images
+                            .as_ref()
+                            .and_then(|images| images.max_width)
+                        {
+                            if !attributes
+                                .iter()
+                                .any(|(key, _)| key == "width" || key == "style")
+                            {
+                                attributes.push((
+                                    "style".into(),
+                                    format!("max-width: {}px", max_width),
+                                ));
+                            }
+                        }
+
+                        // Commented sections aren't published, so they don't need it.
+                        if !affiliated.contains_key("caption")
+                            && !attributes.iter().any(|(key, _)| key == "alt")
+                            && !slf.sections.last().is_some_and(|section| section.commented)
+                        {
+                            ctx.warnings.lock().unwrap().add(
+                                "Images without alt text or a caption",
+                                format!("{:?} at {}:{}", path, filename, token.location.line),
+                            );
+                        }
+
+                        // Captioned or named images are figures, with the caption outside.
+                        let node = Node::Image {
+                            path,
+                            caption: None,
+                            attributes,
+                        };
+
+                        slf.add_to_last(Self::labeled(node, "Figure", &mut affiliated))
+                    } else if let Some(math) = &ctx.config.math {
+                        if let Some(tex) = math::display_fragment(&content) {
+                            slf.add_to_last(Node::LesserBlock {
+                                type_: "export".into(),
+                                args: vec!["html".into()],
+                                contents: math::render_display(&tex, math),
+                            })
+                        } else {
+                            slf.add_to_last(Node::Paragraph(inline::render(
+                                &content,
+                                Some(math),
+                                options,
+                                &link_types,
+                            )))
+                        }
+                    } else {
+                        slf.add_to_last(Node::Paragraph(inline::render(
+                            &content,
+                            None,
+                            options,
+                            &link_types,
+                        )))
+                    }
+                }
                 TokenKind::LesserBlock {
                     _type,
                     contents,
                     args,
+                } => match &ctx.config.math {
+                    Some(math) if _type == "export" && args.trim() == "latex" => {
+                        slf.add_to_last(Node::LesserBlock {
+                            type_: "export".into(),
+                            args: vec!["html".into()],
+                            contents: math::render_display(&contents.join("\n"), math),
+                        })
+                    }
+                    _ if _type == "src" && diagram::renders(&ctx.config, &args) => {
+                        let args = babel::split_args(&args);
+                        let source = contents.join("\n");
+                        let code = Node::LesserBlock {
+                            type_: _type,
+                            args: args.clone(),
+                            contents: source.clone(),
+                        };
+
+                        match diagram::render(&source, &args, &ctx.config, ctx.root()) {
+                            Ok(svg) => {
+                                if diagram::shows_code(&args) {
+                                    slf.add_to_last(code);
+                                }
+
+                                slf.add_to_last(Self::labeled(
+                                    Node::LesserBlock {
+                                        type_: "export".into(),
+                                        args: vec!["html".into()],
+                                        contents: svg,
+                                    },
+                                    "Figure",
+                                    &mut affiliated,
+                                ))
+                            }
+                            Err(err) => {
+                                ctx.warnings.lock().unwrap().add(
+                                    "Diagrams not rendered",
+                                    format!("{}: {}", ctx.relative_path.display(), err),
+                                );
+                                slf.add_to_last(Self::labeled(code, "Listing", &mut affiliated))
+                            }
+                        }
+                    }
+                    _ => {
+                        let listing = _type == "src" || _type == "example";
+                        let node = Node::LesserBlock {
+                            args: babel::split_args(&args),
+                            contents: contents.join("\n"),
+                            type_: _type,
+                        };
+
+                        slf.add_to_last(if listing {
+                            Self::labeled(node, "Listing", &mut affiliated)
+                        } else {
+                            node
+                        })
+                    }
+                },
+                TokenKind::GreaterBlock {
+                    _type,
+                    contents,
+                    args,
                 } => {
-                    slf.add_to_last(Node::LesserBlock {
-                        args: args
-                            .split(" ")
-                            .map(|x| x.to_owned())
-                            .collect::<Vec<String>>(),
-                        contents: contents.join("\n"),
+                    let inner =
+                        Self::parse_nodes(
+                        &contents.join("\n"),
+                        filename,
+                        ctx.clone(),
+                        options,
+                        depth + 1,
+                    )?;
+
+                    slf.published = slf.published.or(inner.published);
+
+                    slf.add_to_last(Node::GreaterBlock {
                         type_: _type,
-                    });
+                        args: args.trim().to_owned(),
+                        contents: inner
+                            .sections
+                            .into_iter()
+                            .flat_map(|section| section.nodes)
+                            .collect(),
+                    })
+                }
+                TokenKind::Table { rows, header_rows } => {
+                    let node = Node::Table {
+                        // A rule after the last row doesn't make the whole table a header.
+                        header_rows: if header_rows < rows.len() { header_rows } else { 0 },
+                        rows,
+                    };
+
+                    slf.add_to_last(Self::labeled(node, "Table", &mut affiliated))
                 }
-                TokenKind::Table { rows } => slf.add_to_last(Node::Table { rows }),
                 TokenKind::Keyword { name, content } => {
-                    slf.metadata.insert(name, content);
+                    if name == "print_bibliography" {
+                        slf.add_to_last(Node::Bibliography(String::new()))
+                    } else if name == "excerpt_separator" {
+                        slf.add_to_last(Node::ExcerptSeparator)
+                    } else if name == "index" {
+                        if !content.trim().is_empty() {
+                            slf.add_to_last(Node::IndexTerm {
+                                term: content.trim().to_owned(),
+                                id: String::new(),
+                            })
+                        }
+                    } else if is_affiliated {
+                        affiliated.insert(name, content);
+                    } else if !content.trim().is_empty() {
+                        // Empty keywords don't override defaults, like the title's.
+                        slf.metadata.insert(name, content);
+                    }
+                }
+                TokenKind::Comment { content } if content.trim() == "more" => {
+                    slf.add_to_last(Node::ExcerptSeparator)
                 }
                 TokenKind::Comment { .. } => {}
+                TokenKind::HorizontalRule => slf.add_to_last(Node::HorizontalRule),
+                TokenKind::FixedWidth { lines } => {
+                    slf.add_to_last(Node::FixedWidth(lines.join("\n")))
+                }
+                TokenKind::Planning { _type, value } => {
+                    let entries = timestamp::planning(&format!("{}: {}", _type, value));
+
+                    if entries.is_empty() {
+                        ctx.warnings.lock().unwrap().add(
+                            "Org constructs not supported, skipped",
+                            format!(
+                                "planning line `{}` without a timestamp at {}:{}",
+                                _type, filename, token.location.line
+                            ),
+                        );
+                    } else {
+                        slf.add_to_last(Node::Planning(entries));
+                    }
+                }
+                TokenKind::Drawer { name, contents } if name.eq_ignore_ascii_case("properties") => {
+                    match slf.sections.last_mut().and_then(|section| section.nodes.first_mut()) {
+                        Some(Node::Heading { properties, .. }) => {
+                            *properties = Self::parse_properties(&contents)
+                        }
+                        _ => ctx.warnings.lock().unwrap().add(
+                            "Org constructs not supported, skipped",
+                            format!(
+                                "property drawer outside a heading at {}:{}",
+                                filename, token.location.line
+                            ),
+                        ),
+                    }
+                }
+                // Other drawers, such as `:LOGBOOK:`, aren't exported.
+                TokenKind::Drawer { .. } => {}
                 TokenKind::Macro { name, args } => match name.as_str() {
                     "listing" => slf.sections.push(Section {
                         nodes: vec![
@@ -100,8 +693,13 @@ impl Document {
                                 level: 1,
                                 title: "Articles".into(),
                                 todo_state: None,
+                                priority: None,
+                                completion: None,
                                 tags: vec![],
                                 commented: false,
+                                properties: HashMap::new(),
+                                id: String::new(),
+                                number: None,
                             },
                             Node::LesserBlock {
                                 type_: "export".into(),
@@ -109,95 +707,16 @@ impl Document {
                                 contents: Container::new(ContainerType::Div)
                                     .with_attributes([("class", "articles")])
                                     .with_raw(
-                                        ctx.metadata
-                                            .lock()
-                                            .unwrap()
+                                        Self::listed_articles(&ctx)
                                             .iter()
-                                            .filter_map(|meta| match meta {
-                                                Metadata::Article {
-                                                    title,
-                                                    description,
-                                                    author,
-                                                    tags,
-                                                    modified,
-                                                    url,
-                                                } => {
-                                                    if url.starts_with(
-                                                        &(ctx.site_url.clone() + &args[0]),
-                                                    ) {
-                                                        let mut attributes = vec![
-                                                            (
-                                                                "data-title".into(),
-                                                                title.to_string(),
-                                                            ),
-                                                            (
-                                                                "data-last-modified".into(),
-                                                                modified.to_rfc3339(),
-                                                            ),
-                                                        ];
-
-                                                        if let Some(description) = description {
-                                                            attributes.push((
-                                                                "data-description".into(),
-                                                                description.to_string(),
-                                                            ));
-                                                        }
-
-                                                        if let Some(author) = author {
-                                                            attributes.push((
-                                                                "data-author".into(),
-                                                                author.to_string(),
-                                                            ));
-                                                        }
-
-                                                        if tags.len() > 0 {
-                                                            attributes.push((
-                                                                "data-tags".into(),
-                                                                tags.join(", "),
-                                                            ));
-                                                        }
-
-                                                        let mut container: Container =
-                                                            Container::new(ContainerType::Div)
-                                                                .with_attributes(attributes);
-
-                                                        container.add_paragraph_attr(
-                                                            title,
-                                                            [("class", "card-title")],
-                                                        );
-
-                                                        if let Some(description) = description {
-                                                            container.add_paragraph(description);
-                                                        }
-
-                                                        let mut end_container =
-                                                            Container::new(ContainerType::Div)
-                                                                .with_raw(format!(
-                                                    "<span class=\"card-time\">{}</span>",
-                                                    build_html::escape_html(&modified.to_rfc3339())
-                                                ));
-
-                                                        if let Some(author) = author {
-                                                            end_container.add_raw(format!(
-                                                        "<span class=\"card-author\">{}</span>",
-                                                        build_html::escape_html(author)
-                                                    ));
-                                                        }
-
-                                                        container.add_container(end_container);
-
-                                                        Some(format!(
-                                                    "<a href=\"{}\" class=\"article-card\">{}</a>",
-                                                    url,
-                                                    container.to_html_string()
-                                                ))
-                                                    } else {
-                                                        None
-                                                    }
-                                                }
-                                                _ => None,
+                                            .filter(|meta| match meta {
+                                                Metadata::Article { url, .. } => url.starts_with(
+                                                    &(ctx.site_url.clone()
+                                                        + args.first().map_or("", String::as_str)),
+                                                ),
+                                                _ => false,
                                             })
-                                            .map(|a| a.to_html_string())
+                                            .filter_map(article_card)
                                             .collect::<Vec<String>>()
                                             .join(""),
                                     )
@@ -205,22 +724,135 @@ impl Document {
                             },
                         ],
                         commented: false,
+                        children: vec![],
+                    }),
+                    "gallery" => slf.add_to_last(Node::LesserBlock {
+                        type_: "export".into(),
+                        args: vec!["html".into()],
+                        contents: crate::gallery::grid(&crate::gallery::photos(
+                            &ctx.metadata.lock().unwrap(),
+                            ctx.url().rsplit_once('/').map_or("", |(dir, _)| dir),
+                        )),
+                    }),
+                    "index" => slf.sections.push(Section {
+                        nodes: vec![
+                            Node::Heading {
+                                level: 1,
+                                title: "Index".into(),
+                                todo_state: None,
+                                priority: None,
+                                completion: None,
+                                tags: vec![],
+                                commented: false,
+                                properties: HashMap::new(),
+                                id: String::new(),
+                                number: None,
+                            },
+                            Node::LesserBlock {
+                                type_: "export".into(),
+                                args: vec!["html".into()],
+                                contents: index::render(
+                                    &ctx.metadata.lock().unwrap(),
+                                    ctx.config.language(),
+                                ),
+                            },
+                        ],
+                        commented: false,
+                        children: vec![],
                     }),
-                    _ => todo!("Macro `{}` not defined.", name),
+                    name if ctx.macros.contains_key(&name.to_ascii_lowercase()) => {
+                        match ctx.macros[&name.to_ascii_lowercase()].expand_macro(name, &args, &ctx)
+                        {
+                            Ok(html) => slf.add_to_last(Node::LesserBlock {
+                                type_: "export".into(),
+                                args: vec!["html".into()],
+                                contents: html,
+                            }),
+                            Err(err) => ctx.warnings.lock().unwrap().add(
+                                "Plugin macros failed",
+                                format!(
+                                    "{:?} at {}:{}: {}",
+                                    name, filename, token.location.line, err
+                                ),
+                            ),
+                        }
+                    }
+                    _ => ctx.warnings.lock().unwrap().add(
+                        "Macros not defined",
+                        format!("{:?} at {}:{}", name, filename, token.location.line),
+                    ),
                 },
-                _ => todo!(),
+                kind => ctx.warnings.lock().unwrap().add(
+                    "Org constructs not supported, skipped",
+                    format!(
+                        "{} at {}:{}",
+                        kind.describe(),
+                        filename,
+                        token.location.line
+                    ),
+                ),
+            }
+
+            if !is_affiliated {
+                affiliated.clear();
             }
         }
 
         Ok(slf)
     }
 
+    /// The target of a paragraph consisting only of a link to an image.
+    fn image_link(content: &str) -> Option<String> {
+        let caps = IMAGE_LINK.captures(content.trim()).ok()??;
+        let path = caps["path"].to_owned();
+        let ext = Path::new(&path).extension()?.to_str()?.to_ascii_lowercase();
+
+        IMAGE_EXTENSIONS.contains(&ext.as_str()).then_some(path)
+    }
+
+    /// Parses `:key value :other value` into (key, value) pairs, in order.
+    fn parse_attributes(attributes: &str) -> Vec<(String, String)> {
+        let mut parsed: Vec<(String, String)> = vec![];
+
+        for word in attributes.split_whitespace() {
+            if let Some(key) = word.strip_prefix(':') {
+                parsed.push((key.to_owned(), String::new()));
+            } else if let Some((_, value)) = parsed.last_mut() {
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+
+                value.push_str(word);
+            }
+        }
+
+        parsed
+    }
+
     fn add_to_last(&mut self, node: Node) {
         match node {
-            Node::Heading { commented, .. } => {
+            Node::Heading {
+                level, commented, ..
+            } => {
+                // Sections under a `COMMENT` heading are commented too, up to the next heading
+                // at the same level or above.
+                let parent_commented = self
+                    .sections
+                    .iter()
+                    .rev()
+                    .find_map(|section| match section.nodes.first() {
+                        Some(Node::Heading {
+                            level: parent_level,
+                            ..
+                        }) if *parent_level < level => Some(section.commented),
+                        _ => None,
+                    })
+                    .unwrap_or(false);
+
                 self.sections.push(Section {
                     nodes: vec![node],
-                    commented,
+                    commented: commented || parent_commented,
+                    children: vec![],
                 });
             }
             _ => {
@@ -230,6 +862,25 @@ impl Document {
         }
     }
 
+    /// Every article, sorted by title in the site's language.
+    fn listed_articles(ctx: &FileContext) -> Vec<Metadata> {
+        let mut articles: Vec<Metadata> = ctx
+            .metadata
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|meta| matches!(meta, Metadata::Article { .. }))
+            .cloned()
+            .collect();
+
+        collation::sort_by_key(&mut articles, ctx.config.language(), |meta| match meta {
+            Metadata::Article { title, .. } => title,
+            _ => "",
+        });
+
+        articles
+    }
+
     pub fn parse_file(filename: &str, ctx: FileContext) -> Result<Self, String> {
         Self::parse(
             &std::fs::read_to_string(filename).map_err(|_| "IO error of some kind".to_owned())?,
@@ -238,16 +889,251 @@ impl Document {
         )
     }
 
-    pub fn to_html(&self) -> String {
-        super::org::html::HtmlBuilder::new().from_document(self)
+    /// Whether the document is marked `#+DRAFT: t`.
+    pub fn is_draft(&self) -> bool {
+        self.metadata
+            .get("draft")
+            .is_some_and(|draft| !matches!(draft.trim(), "" | "nil"))
+    }
+
+    /// The text of the paragraphs before `# more` or `#+EXCERPT_SEPARATOR:`, if there is one.
+    pub fn summary(&self) -> Option<String> {
+        fn in_sections(sections: &[Section], paragraphs: &mut Vec<String>) -> bool {
+            for section in sections.iter().filter(|section| !section.commented) {
+                for node in &section.nodes {
+                    match node {
+                        Node::ExcerptSeparator => return true,
+                        Node::Paragraph(text) => paragraphs.push(html::plain_text(text)),
+                        _ => {}
+                    }
+                }
+
+                if in_sections(&section.children, paragraphs) {
+                    return true;
+                }
+            }
+
+            false
+        }
+
+        let mut paragraphs = vec![];
+
+        in_sections(&self.sections, &mut paragraphs)
+            .then(|| paragraphs.join(" "))
+            .filter(|summary| !summary.is_empty())
+    }
+
+    /// The path and alt text of every exported image, in document order.
+    pub fn images(&self) -> Vec<(String, String)> {
+        fn in_nodes(nodes: &[Node], images: &mut Vec<(String, String)>) {
+            for node in nodes {
+                match node {
+                    Node::Image {
+                        path,
+                        caption,
+                        attributes,
+                    } => images.push((
+                        path.clone(),
+                        html::alt_text(path, caption.as_deref(), attributes),
+                    )),
+                    Node::GreaterBlock { contents, .. } => in_nodes(contents, images),
+                    Node::Labeled { caption, node, .. } => match node.as_ref() {
+                        Node::Image {
+                            path, attributes, ..
+                        } => images.push((
+                            path.clone(),
+                            html::alt_text(path, caption.as_deref(), attributes),
+                        )),
+                        node => in_nodes(std::slice::from_ref(node), images),
+                    },
+                    _ => {}
+                }
+            }
+        }
+        fn in_sections(sections: &[Section], images: &mut Vec<(String, String)>) {
+            for section in sections.iter().filter(|section| !section.commented) {
+                in_nodes(&section.nodes, images);
+                in_sections(&section.children, images);
+            }
+        }
+
+        let mut images = vec![];
+
+        in_sections(&self.sections, &mut images);
+
+        images
+    }
+
+    /// The numbered figures, tables and listings by kind, e.g. for a list of figures.
+    pub fn numbering(&self) -> HashMap<String, Vec<xref::Numbered>> {
+        xref::numbering(&self.sections)
+    }
+
+    /// How many figures, tables and listings of each kind are numbered.
+    pub fn numbered(&self) -> HashMap<String, usize> {
+        self.numbering()
+            .into_iter()
+            .map(|(kind, numbered)| (kind, numbered.len()))
+            .collect()
+    }
+
+    /// Everything on the page that can be linked to with a fragment.
+    pub fn anchors(&self) -> Vec<crate::metadata::Anchor> {
+        anchors::list(&self.sections)
+    }
+
+    /// The rendered table of contents, if `#+OPTIONS` or the configuration ask for one.
+    pub fn table_of_contents(&self, config: &Config) -> Option<String> {
+        let org = config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref());
+        let depth = toc::depth(&self.options, org.and_then(|org| org.toc))?;
+
+        toc::render(&self.sections, depth)
+    }
+
+    /// The top-level headings' sections, in document order.
+    pub fn entries(&self, config: &Config) -> Vec<Entry> {
+        let mut builder = self.html_builder(config);
+
+        self.sections
+            .iter()
+            .filter(|section| !section.commented)
+            .filter_map(|section| {
+                let Some(Node::Heading { title, id, .. }) = section.nodes.first() else {
+                    return None;
+                };
+                let timestamps = Timestamp::find_all(title);
+                let mut text = title.clone();
+
+                for (range, _) in timestamps.iter().rev() {
+                    text.replace_range(range.clone(), "");
+                }
+
+                let date = timestamps
+                    .first()
+                    .map(|(_, timestamp)| timestamp.to_utc())
+                    .or_else(|| {
+                        section.nodes.iter().find_map(|node| match node {
+                            Node::Planning(entries) => {
+                                entries.first().map(|(_, timestamp)| timestamp.to_utc())
+                            }
+                            Node::Paragraph(text) => {
+                                Timestamp::first_active(text).map(|timestamp| timestamp.to_utc())
+                            }
+                            _ => None,
+                        })
+                    });
+
+                Some(Entry {
+                    title: html::plain_text(&text),
+                    id: id.clone(),
+                    date,
+                    html: builder.from_section(self, section),
+                })
+            })
+            .collect()
+    }
+
+    /// A builder rendering the way `config` asks, except for the table of contents.
+    fn html_builder(&self, config: &Config) -> html::HtmlBuilder {
+        let mut builder = html::HtmlBuilder::new();
+
+        if let Some(highlight) = &config.highlight {
+            builder = builder
+                .with_highlighting(highlight.theme.as_deref().unwrap_or("InspiredGitHub"));
+        }
+
+        if config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref())
+            .and_then(|org| org.heading_anchors)
+            .unwrap_or(false)
+        {
+            builder = builder.with_heading_anchors();
+        }
+
+        if let Some(every) = config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref())
+            .and_then(|org| org.paragraph_anchors)
+        {
+            builder = builder.with_paragraph_anchors(every);
+        }
+
+        if let Some(admonitions) = config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref())
+            .and_then(|org| org.admonitions.clone())
+        {
+            builder = builder.with_admonitions(admonitions);
+        }
+
+        builder
+    }
+
+    pub fn to_html(&self, config: &Config) -> String {
+        let mut builder = self.html_builder(config);
+        let toc_placement = config
+            .handlers
+            .as_ref()
+            .and_then(|handlers| handlers.org.as_ref())
+            .and_then(|org| org.toc_placement)
+            .unwrap_or(TocPlacement::Top);
+
+        if toc_placement == TocPlacement::Top {
+            if let Some(toc) = self.table_of_contents(config) {
+                builder = builder.with_table_of_contents(toc);
+            }
+        }
+
+        builder.from_document(self)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::config::{HandlerConfig, ImagesHandlerConfig};
+    use crate::handler::FileContext;
     use crate::org::{Document, Node, Section};
     use std::collections::HashMap;
 
+    #[test]
+    fn summary() {
+        let summary = |source: &str| {
+            Document::parse(source, "summary.org", Default::default())
+                .unwrap()
+                .summary()
+        };
+
+        assert_eq!(
+            summary("First =code= &\nline.\n\nSecond.\n# more\nRest."),
+            Some("First code & line. Second.".into())
+        );
+        assert_eq!(
+            summary("* Intro\nShort.\n#+EXCERPT_SEPARATOR:\n* Body\nLong."),
+            Some("Short.".into())
+        );
+        assert_eq!(summary("No marker."), None);
+    }
+
+    #[test]
+    fn drafts() {
+        let draft = |source: &str| {
+            Document::parse(source, "draft.org", Default::default())
+                .unwrap()
+                .is_draft()
+        };
+
+        assert!(draft("#+DRAFT: t"));
+        assert!(!draft("#+DRAFT: nil"));
+        assert!(!draft("#+TITLE: Published"));
+    }
+
     #[test]
     fn title() {
         assert_eq!(
@@ -257,9 +1143,12 @@ mod test {
                     "title".into(),
                     "hello".into()
                 )]),
+                published: None,
+                options: Default::default(),
                 sections: vec![Section {
                     nodes: vec![],
-                    commented: false
+                    commented: false,
+                    children: vec![]
                 }]
             })
         );
@@ -271,20 +1160,29 @@ mod test {
             Document::parse("* test", "heading.org", Default::default()),
             Ok(Document {
                 metadata: HashMap::new(),
+                published: None,
+                options: Default::default(),
                 sections: vec![
                     Section {
                         nodes: vec![],
-                        commented: false
+                        commented: false,
+                        children: vec![]
                     },
                     Section {
                         nodes: vec![Node::Heading {
                             level: 1,
                             title: "test".into(),
                             todo_state: None,
+                            priority: None,
+                            completion: None,
                             tags: vec![],
-                            commented: false
+                            commented: false,
+                            properties: HashMap::new(),
+                            id: "test".into(),
+                            number: None
                         }],
-                        commented: false
+                        commented: false,
+                        children: vec![]
                     }
                 ]
             })
@@ -301,18 +1199,87 @@ mod test {
             ),
             Ok(Document {
                 metadata: HashMap::new(),
+                published: None,
+                options: Default::default(),
                 sections: vec![Section {
                     nodes: vec![Node::LesserBlock {
                         type_: "src".into(),
                         args: vec!["python".into()],
                         contents: "print('Hello, world!')".into()
                     }],
-                    commented: false
+                    commented: false,
+                    children: vec![]
                 }]
             })
         );
     }
 
+    #[test]
+    fn image() {
+        assert_eq!(
+            Document::parse(
+                "#+CAPTION: A cat.\n#+ATTR_HTML: :alt A very good cat :width 300\n[[file:cat.png]]",
+                "image.org",
+                Default::default()
+            ),
+            Ok(Document {
+                metadata: HashMap::new(),
+                published: None,
+                options: Default::default(),
+                sections: vec![Section {
+                    nodes: vec![Node::Labeled {
+                        kind: "Figure".into(),
+                        number: Some(1),
+                        name: None,
+                        caption: Some("A cat.".into()),
+                        node: Box::new(Node::Image {
+                            path: "cat.png".into(),
+                            caption: None,
+                            attributes: vec![
+                                ("alt".into(), "A very good cat".into()),
+                                ("width".into(), "300".into())
+                            ]
+                        })
+                    }],
+                    commented: false,
+                    children: vec![]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn image_max_width() {
+        let ctx = FileContext {
+            handler_config: HandlerConfig {
+                images: Some(ImagesHandlerConfig {
+                    max_width: Some(640),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Document::parse("[[file:a.png]]\n\n#+ATTR_HTML: :width 100\n[[file:b.png]]", "image.org", ctx)
+                .unwrap()
+                .sections[0]
+                .nodes,
+            vec![
+                Node::Image {
+                    path: "a.png".into(),
+                    caption: None,
+                    attributes: vec![("style".into(), "max-width: 640px".into())]
+                },
+                Node::Image {
+                    path: "b.png".into(),
+                    caption: None,
+                    attributes: vec![("width".into(), "100".into())]
+                }
+            ]
+        );
+    }
+
     #[test]
     fn comment_heading() {
         assert_eq!(
@@ -323,8 +1290,248 @@ mod test {
             ),
             Ok(Document {
                 metadata: HashMap::new(),
-                sections: vec![]
+                published: None,
+                options: Default::default(),
+                sections: vec![
+                    Section {
+                        nodes: vec![],
+                        commented: false,
+                        children: vec![]
+                    },
+                    Section {
+                        nodes: vec![
+                            Node::Heading {
+                                level: 1,
+                                title: "COMMENT something".into(),
+                                todo_state: Some("TODO".into()),
+                                priority: None,
+                                completion: None,
+                                tags: vec![],
+                                commented: true,
+                                properties: HashMap::new(),
+                                id: String::new(),
+                                number: None
+                            },
+                            Node::Paragraph("some text".into())
+                        ],
+                        commented: true,
+                        children: vec![]
+                    }
+                ]
+            })
+        )
+    }
+
+    #[test]
+    fn comment_subtree() {
+        let doc = Document::parse(
+            "* COMMENT Hidden
+** Child
+*** Grandchild
+** Sibling
+* Shown
+** Shown child",
+            "comment_subtree.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        let hidden = &doc.sections[1];
+
+        assert!(hidden.commented);
+        assert!(hidden.children[0].commented && hidden.children[0].children[0].commented);
+        assert!(hidden.children[1].commented);
+        assert!(!doc.sections[2].commented && !doc.sections[2].children[0].commented);
+    }
+
+    #[test]
+    fn unsupported_constructs() {
+        let ctx: FileContext = Default::default();
+        let doc = Document::parse(
+            "* Heading\n#+BEGIN: clocktable\n#+END:\n{{{undefined}}}\n#+BEGIN_QUOTE\n#+END_SRC\n#+END_QUOTE\nText",
+            "unsupported.org",
+            ctx.clone(),
+        )
+        .unwrap();
+
+        let warnings = ctx.warnings.lock().unwrap();
+
+        assert_eq!(doc.sections.len(), 2);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings.details("Macros not defined"),
+            ["\"undefined\" at unsupported.org:4"]
+        );
+        assert_eq!(
+            warnings.details("Org constructs not supported, skipped"),
+            ["dynamic block at unsupported.org:2"]
+        );
+    }
+
+    #[test]
+    fn image_alt_text() {
+        let ctx: FileContext = Default::default();
+        let doc = Document::parse(
+            "[[a.png]]\n\n#+CAPTION: A cat\n[[b.png]]\n\n#+ATTR_HTML: :alt A dog\n#+CAPTION: Dogs\n[[c.png]]\n* COMMENT Hidden\n[[d.png]]\n** Under it\n[[e.png]]\n* Draft :noexport:\n[[f.png]]",
+            "alt.org",
+            ctx.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.images(),
+            [
+                ("a.png".to_owned(), "a.png".to_owned()),
+                ("b.png".to_owned(), "A cat".to_owned()),
+                ("c.png".to_owned(), "A dog".to_owned()),
+            ]
+        );
+        assert_eq!(
+            ctx.warnings
+                .lock()
+                .unwrap()
+                .details("Images without alt text or a caption"),
+            ["\"a.png\" at alt.org:1"]
+        );
+    }
+
+    #[test]
+    fn property_drawer() {
+        let doc = Document::parse(
+            r#"* Installing
+:PROPERTIES:
+:CUSTOM_ID: install
+:export_file_name: setup
+:TAGS: a
+:TAGS+: b
+:END:
+Text"#,
+            "properties.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        match &doc.sections[1].nodes[0] {
+            Node::Heading { properties, .. } => assert_eq!(
+                properties,
+                &HashMap::from_iter(vec![
+                    ("CUSTOM_ID".into(), "install".into()),
+                    ("EXPORT_FILE_NAME".into(), "setup".into()),
+                    ("TAGS".into(), "a b".into()),
+                ])
+            ),
+            node => panic!("Expected a heading, got {:?}", node),
+        }
+        assert_eq!(doc.sections[1].nodes[1], Node::Paragraph("Text".into()));
+    }
+
+    #[test]
+    fn excluded_subtrees() {
+        let doc = Document::parse(
+            r#"* Kept
+** Draft :noexport:
+Hidden
+*** Child
+Also hidden
+** Old :ARCHIVE:
+Archived
+* Also kept
+Shown"#,
+            "excluded.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        let headings: Vec<&str> = doc
+            .sections
+            .iter()
+            .filter_map(|section| match section.nodes.first() {
+                Some(Node::Heading { title, .. }) => Some(title.as_str()),
+                _ => None,
             })
+            .collect();
+
+        assert_eq!(headings, vec!["Kept", "Also kept"]);
+        assert_eq!(doc.sections[2].nodes[1], Node::Paragraph("Shown".into()));
+    }
+
+    #[test]
+    fn section_tree() {
+        let doc = Document::parse(
+            "Intro
+* A
+** A.1
+*** A.1.a
+** A.2
+* B
+*** B.?.a",
+            "tree.org",
+            Default::default(),
         )
+        .unwrap();
+
+        fn outline(sections: &[Section]) -> Vec<String> {
+            sections
+                .iter()
+                .map(|section| {
+                    let title = match section.nodes.first() {
+                        Some(Node::Heading { title, .. }) => title.clone(),
+                        _ => "-".into(),
+                    };
+
+                    if section.children.is_empty() {
+                        title
+                    } else {
+                        format!("{}({})", title, outline(&section.children).join(" "))
+                    }
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            outline(&doc.sections),
+            vec!["-", "A(A.1(A.1.a) A.2)", "B(B.?.a)"]
+        );
+    }
+
+    #[test]
+    fn hostile_input() {
+        let deep = "#+BEGIN_QUOTE\n".repeat(100) + &"#+END_QUOTE\n".repeat(100);
+        let inputs: Vec<Vec<u8>> = vec![
+            b"\xff\xfe* \xc3".to_vec(),
+            format!("{} too deep", "*".repeat(300)).into_bytes(),
+            deep.into_bytes(),
+            "#+BEGIN_SRC\n\u{3000}a\n\t\u{3000}b\n#+END_SRC".into(),
+            b"{{{listing}}}".to_vec(),
+            "$".repeat(5000).into_bytes(),
+        ];
+
+        for input in inputs {
+            let _ = Document::parse_bytes(&input);
+        }
+
+        assert_eq!(
+            Document::parse_bytes(b"Text\n#+BEGIN_SRC rust\nfn main() {}"),
+            Err("Block `src` at input.org:2 is never closed".into())
+        );
+    }
+
+    #[test]
+    fn planning_and_published() {
+        let doc = Document::parse(
+            "Drafted [2024-04-01].\n* Launch\nDEADLINE: <2024-05-03 Fri> SCHEDULED: <2024-05-01 Wed 09:30>",
+            "planning.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            &doc.sections[1].nodes[1],
+            Node::Planning(entries) if entries.len() == 2 && entries[1].0 == "SCHEDULED"
+        ));
+        assert_eq!(
+            doc.published.map(|published| published.to_rfc3339()),
+            Some("2024-05-03T00:00:00+00:00".into())
+        );
     }
 }
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref OPTIONS: Regex =
+        Regex::new(r"(?im)^[ \t]*#\+OPTIONS:[ \t]*(?<options>.*)$").unwrap();
+}
+
+/// How `^:` treats `a_b` and `a^b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubSuperscripts {
+    /// `^:nil`, left as text.
+    Off,
+    /// `^:t`, as `<sub>`/`<sup>`.
+    On,
+    /// `^:{}`, only when braced, like `a_{b}`.
+    Braced,
+}
+
+/// The `#+OPTIONS` export settings of a document. Where Emacs's defaults would change how
+/// existing pages render, headings aren't numbered, tags are hidden, `-:` is off and only
+/// braced sub- and superscripts are converted unless the file says otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// `toc:`, with `toc:t` as the headline levels and `toc:nil` as 0. `None` when unset.
+    pub toc: Option<usize>,
+    /// `H:`, the levels counted as headlines. Defaults to 3.
+    pub headline_levels: usize,
+    /// `num:`, the levels to number, with `num:t` as the headline levels. Defaults to 0.
+    pub num: usize,
+    /// `^:`. Defaults to `Braced`.
+    pub sub_superscripts: SubSuperscripts,
+    /// `e:`, converting entities like `\alpha`. Defaults to `true`.
+    pub entities: bool,
+    /// `-:`, converting `--`, `---`, `...` and `\-`.
+    pub special_strings: bool,
+    /// `tags:`, showing heading tags.
+    pub tags: bool,
+    /// `todo:`, showing TODO keywords. Defaults to `true`.
+    pub todo: bool,
+    /// `pri:`, showing priority cookies. Defaults to `true`.
+    pub priority: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            toc: None,
+            headline_levels: 3,
+            num: 0,
+            sub_superscripts: SubSuperscripts::Braced,
+            entities: true,
+            special_strings: false,
+            tags: false,
+            todo: true,
+            priority: true,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Options like `toc:2 num:nil ^:{}`. Later settings win; unknown ones are ignored.
+    pub fn parse(options: &str) -> Self {
+        let settings: HashMap<&str, &str> = options
+            .split_whitespace()
+            .filter_map(|option| option.split_once(':'))
+            .collect();
+        let mut parsed = Self::default();
+        let flag = |name: &str, default: bool| match settings.get(name) {
+            Some(&"nil") => false,
+            Some(_) => true,
+            None => default,
+        };
+
+        let headline_levels = settings
+            .get("H")
+            .and_then(|levels| levels.parse().ok())
+            .unwrap_or(parsed.headline_levels);
+        let levels = |value: &str| match value {
+            "nil" => Some(0),
+            "t" => Some(headline_levels),
+            levels => levels.parse().ok(),
+        };
+
+        parsed.headline_levels = headline_levels;
+        parsed.toc = settings.get("toc").and_then(|value| levels(value));
+        parsed.num = settings
+            .get("num")
+            .and_then(|value| levels(value))
+            .unwrap_or(0);
+        parsed.sub_superscripts = match settings.get("^") {
+            Some(&"nil") => SubSuperscripts::Off,
+            Some(&"{}") | None => SubSuperscripts::Braced,
+            Some(_) => SubSuperscripts::On,
+        };
+        parsed.entities = flag("e", true);
+        parsed.special_strings = flag("-", false);
+        parsed.tags = flag("tags", false);
+        parsed.todo = flag("todo", true);
+        parsed.priority = flag("pri", true);
+
+        parsed
+    }
+
+    /// The options of every `#+OPTIONS` line in `content`, which apply to the whole file.
+    pub fn scan(content: &str) -> Self {
+        let options: Vec<String> = OPTIONS
+            .captures_iter(content)
+            .map_while(Result::ok)
+            .map(|caps| caps["options"].to_owned())
+            .collect();
+
+        Self::parse(&options.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::org::options::{ExportOptions, SubSuperscripts};
+
+    #[test]
+    fn parse() {
+        let options = ExportOptions::parse("toc:t H:2 num:t ^:{} e:nil -:t tags:t todo:nil pri:nil");
+
+        assert_eq!(
+            options,
+            ExportOptions {
+                toc: Some(2),
+                headline_levels: 2,
+                num: 2,
+                sub_superscripts: SubSuperscripts::Braced,
+                entities: false,
+                special_strings: true,
+                tags: true,
+                todo: false,
+                priority: false,
+            }
+        );
+        assert_eq!(ExportOptions::parse("toc:nil").toc, Some(0));
+        assert_eq!(ExportOptions::parse("toc:x").toc, None);
+        assert_eq!(ExportOptions::parse(""), ExportOptions::default());
+    }
+
+    #[test]
+    fn scan() {
+        let options =
+            ExportOptions::scan("#+TITLE: x\n#+options: toc:1\ntext\n#+OPTIONS: ^:t toc:2");
+
+        assert_eq!(options.toc, Some(2));
+        assert_eq!(options.sub_superscripts, SubSuperscripts::On);
+    }
+}
@@ -0,0 +1,413 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Writing a parsed document back out as Org text.
+
+use crate::org::{Document, Node, Section};
+
+/// Quotes an argument if `babel::split_args` would otherwise split or unescape it.
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        return arg.to_owned();
+    }
+
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn heading_line(node: &Node) -> Option<String> {
+    let Node::Heading {
+        level,
+        title,
+        todo_state,
+        priority,
+        completion,
+        tags,
+        ..
+    } = node
+    else {
+        return None;
+    };
+
+    let mut line = "*".repeat(usize::from(*level));
+
+    if let Some(state) = todo_state {
+        line += &format!(" {}", state);
+    }
+
+    if let Some(priority) = priority {
+        line += &format!(" [#{}]", priority);
+    }
+
+    line += &format!(" {}", title);
+
+    if let Some(completion) = completion {
+        line += &format!(" [{}]", completion);
+    }
+
+    if !tags.is_empty() {
+        line += &format!(" :{}:", tags.join(":"));
+    }
+
+    Some(line)
+}
+
+fn node(this: &Node) -> String {
+    match this {
+        Node::Heading { .. } => heading_line(this).unwrap_or_default(),
+        Node::Paragraph(content) => content.clone(),
+        Node::HorizontalRule => "-----".into(),
+        Node::ExcerptSeparator => "# more".into(),
+        Node::IndexTerm { term, .. } => format!("#+INDEX: {}", term),
+        Node::Bibliography(_) => "#+PRINT_BIBLIOGRAPHY:".into(),
+        Node::FixedWidth(contents) => contents
+            .split('\n')
+            .map(|line| {
+                if line.is_empty() {
+                    ":".to_owned()
+                } else {
+                    format!(": {}", line)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Node::Planning(entries) => entries
+            .iter()
+            .map(|(kind, timestamp)| {
+                let (open, close) = if timestamp.active {
+                    ("<", ">")
+                } else {
+                    ("[", "]")
+                };
+
+                format!("{}: {}{}{}", kind, open, timestamp.text, close)
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Node::LesserBlock {
+            type_,
+            args,
+            contents,
+        } => {
+            let args: Vec<String> = args.iter().map(|arg| quote_arg(arg)).collect();
+
+            format!(
+                "#+BEGIN_{0}{1}\n{2}\n#+END_{0}",
+                type_.to_ascii_uppercase(),
+                if args.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", args.join(" "))
+                },
+                contents
+            )
+        }
+        Node::GreaterBlock {
+            type_,
+            args,
+            contents,
+        } => format!(
+            "#+BEGIN_{0}{1}\n{2}\n#+END_{0}",
+            type_.to_ascii_uppercase(),
+            if args.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", args)
+            },
+            nodes(contents)
+        ),
+        Node::Table { rows, header_rows } => {
+            let mut lines: Vec<Option<Vec<String>>> = rows.iter().cloned().map(Some).collect();
+
+            if *header_rows > 0 && *header_rows < lines.len() {
+                lines.insert(*header_rows, None);
+            }
+
+            table(&lines, "")
+        }
+        Node::Image {
+            path,
+            caption,
+            attributes,
+        } => {
+            let mut lines = vec![];
+
+            if let Some(caption) = caption {
+                lines.push(format!("#+CAPTION: {}", caption));
+            }
+
+            if !attributes.is_empty() {
+                lines.push(format!(
+                    "#+ATTR_HTML: {}",
+                    attributes
+                        .iter()
+                        .map(|(key, value)| format!(":{} {}", key, value))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ));
+            }
+
+            lines.push(format!("[[file:{}]]", path));
+            lines.join("\n")
+        }
+        Node::Labeled {
+            name,
+            caption,
+            node: labeled,
+            ..
+        } => {
+            let mut out = String::new();
+
+            if let Some(name) = name {
+                out += &format!("#+NAME: {}\n", name);
+            }
+
+            if let Some(caption) = caption {
+                out += &format!("#+CAPTION: {}\n", caption);
+            }
+
+            out + &node(labeled)
+        }
+    }
+}
+
+/// Rows with their columns aligned; `None` is a horizontal rule.
+pub fn table(rows: &[Option<Vec<String>>], indent: &str) -> String {
+    let mut widths: Vec<usize> = vec![];
+
+    for row in rows.iter().flatten() {
+        for (column, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    rows.iter()
+        .map(|row| match row {
+            Some(row) => {
+                let cells: Vec<String> = widths
+                    .iter()
+                    .enumerate()
+                    .map(|(column, width)| {
+                        let cell = row.get(column).map_or("", String::as_str);
+
+                        format!("{}{}", cell, " ".repeat(width - cell.chars().count()))
+                    })
+                    .collect();
+
+                format!("{}| {} |", indent, cells.join(" | "))
+            }
+            None => format!(
+                "{}|{}|",
+                indent,
+                widths
+                    .iter()
+                    .map(|width| "-".repeat(width + 2))
+                    .collect::<Vec<_>>()
+                    .join("+")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn nodes(nodes: &[Node]) -> String {
+    nodes.iter().map(node).collect::<Vec<_>>().join("\n\n")
+}
+
+fn section(section: &Section, out: &mut Vec<String>) {
+    let mut rest = section.nodes.as_slice();
+
+    if let Some(Node::Heading { properties, .. }) = rest.first() {
+        // Planning lines and the property drawer must directly follow the heading.
+        let mut head = vec![node(&rest[0])];
+
+        rest = &rest[1..];
+
+        if let Some(planning @ Node::Planning(_)) = rest.first() {
+            head.push(node(planning));
+            rest = &rest[1..];
+        }
+
+        if !properties.is_empty() {
+            let mut keys: Vec<&String> = properties.keys().collect();
+
+            keys.sort();
+            head.push(":PROPERTIES:".into());
+            head.extend(
+                keys.into_iter()
+                    .map(|key| format!(":{}: {}", key, properties[key])),
+            );
+            head.push(":END:".into());
+        }
+
+        out.push(head.join("\n"));
+    }
+
+    if !rest.is_empty() {
+        out.push(nodes(rest));
+    }
+
+    for child in &section.children {
+        self::section(child, out);
+    }
+}
+
+impl Document {
+    /// Writes the document as Org text, which parses back into the same document. Paragraphs
+    /// are written as they were rendered, so inline markup comes back as HTML.
+    pub fn to_org(&self) -> String {
+        let mut keywords: Vec<(&String, &String)> = self.metadata.iter().collect();
+        let mut out = vec![];
+
+        keywords.sort();
+
+        if !keywords.is_empty() {
+            out.push(
+                keywords
+                    .into_iter()
+                    .map(|(key, value)| format!("#+{}: {}", key.to_ascii_uppercase(), value))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+        }
+
+        for section in &self.sections {
+            self::section(section, &mut out);
+        }
+
+        out.join("\n\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+
+    use crate::org::{Document, Node, Section};
+
+    #[test]
+    fn round_trip() {
+        let source = r#"#+TITLE: Round trip
+
+Intro
+line two.
+
+* TODO [#A] Tasks [1/2] :work:
+DEADLINE: <2024-05-01 Wed>
+:PROPERTIES:
+:CUSTOM_ID: tasks
+:END:
+
+#+NAME: squares
+#+CAPTION: Squares
+| n | n² |
+|---+----|
+| 2 | 4  |
+
+** Code
+
+#+BEGIN_SRC rust :exports "both code"
+fn main() {}
+#+END_SRC
+
+#+BEGIN_QUOTE
+Quoted.
+#+END_QUOTE
+"#;
+        let doc = Document::parse(source, "round_trip.org", Default::default()).unwrap();
+
+        assert_eq!(doc.to_org(), source);
+        assert_eq!(
+            Document::parse(&doc.to_org(), "round_trip.org", Default::default()).unwrap(),
+            doc
+        );
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        prop::collection::vec("[a-z]{1,8}", 1..6).prop_map(|words| words.join(" "))
+    }
+
+    fn block() -> impl Strategy<Value = Node> {
+        prop_oneof![
+            prop::collection::vec(text(), 1..3).prop_map(|lines| Node::Paragraph(lines.join("\n"))),
+            ("[a-z]{1,6}", prop::collection::vec(text(), 1..4)).prop_map(|(language, lines)| {
+                Node::LesserBlock {
+                    type_: "src".into(),
+                    args: vec![language],
+                    contents: lines.join("\n"),
+                }
+            }),
+            (1..4usize, 2..4usize, 0..2usize).prop_flat_map(|(columns, rows, header_rows)| {
+                prop::collection::vec(prop::collection::vec("[a-z0-9]{1,5}", columns), rows)
+                    .prop_map(move |rows| Node::Table { rows, header_rows })
+            }),
+            Just(()).prop_map(|_| Node::HorizontalRule),
+            prop::collection::vec(text(), 1..3).prop_map(|lines| Node::FixedWidth(lines.join("\n"))),
+            prop::collection::vec(text(), 1..3).prop_map(|paragraphs| Node::GreaterBlock {
+                type_: "quote".into(),
+                args: String::new(),
+                contents: paragraphs.into_iter().map(Node::Paragraph).collect(),
+            }),
+            ("[a-z]{1,5}", text()).prop_map(|(name, paragraph)| Node::GreaterBlock {
+                type_: "tab".into(),
+                args: name,
+                contents: vec![Node::Paragraph(paragraph)],
+            }),
+        ]
+    }
+
+    fn section() -> impl Strategy<Value = Section> {
+        (
+            1..4u8,
+            text(),
+            prop::option::of(prop_oneof![Just("TODO"), Just("DONE")]),
+            prop::collection::vec("[a-z]{1,5}", 0..3),
+            prop::collection::vec(block(), 0..3),
+        )
+            .prop_map(|(level, title, todo_state, tags, blocks)| Section {
+                nodes: std::iter::once(Node::Heading {
+                    level,
+                    title,
+                    todo_state: todo_state.map(str::to_owned),
+                    priority: None,
+                    completion: None,
+                    tags,
+                    commented: false,
+                    properties: Default::default(),
+                    id: String::new(),
+                    number: None,
+                })
+                .chain(blocks)
+                .collect(),
+                commented: false,
+                children: vec![],
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn serialized_documents_parse_back(
+            intro in prop::collection::vec(block(), 0..3),
+            sections in prop::collection::vec(section(), 0..6),
+        ) {
+            let doc = Document {
+                metadata: Default::default(),
+                sections: Section::nest(
+                    std::iter::once(Section { nodes: intro, commented: false, children: vec![] })
+                        .chain(sections)
+                        .collect(),
+                ),
+                published: None,
+                options: Default::default(),
+            };
+            let org = doc.to_org();
+            let parsed = Document::parse(&org, "generated.org", Default::default()).unwrap();
+
+            prop_assert_eq!(parsed.to_org(), org);
+        }
+    }
+}
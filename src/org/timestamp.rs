@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use build_html::escape_html;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use fancy_regex::Regex;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref TIMESTAMP: Regex = Regex::new(
+        r"<(?<active>\d{4}-\d{2}-\d{2}(?:\s[^<>\n]*)?)>|\[(?<inactive>\d{4}-\d{2}-\d{2}(?:\s[^\[\]\n]*)?)\]"
+    )
+    .unwrap();
+    static ref TIME: Regex = Regex::new(r"\b(?<time>\d{1,2}:\d{2})\b").unwrap();
+    static ref PLANNING: Regex = Regex::new(
+        r"(?<kind>SCHEDULED|DEADLINE|CLOSED):\s*(?<timestamp><[^<>\n]+>|\[[^\[\]\n]+\])"
+    )
+    .unwrap();
+}
+
+/// `<2024-05-01 Wed 10:00>` (active) or `[2024-05-01 Wed]` (inactive).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    pub active: bool,
+    pub date: NaiveDate,
+    /// The start time; ranges, repeaters and warning delays are only kept in `text`.
+    pub time: Option<NaiveTime>,
+    /// As written, without the brackets.
+    pub text: String,
+}
+
+impl Timestamp {
+    fn from_inner(inner: &str, active: bool) -> Option<Self> {
+        Some(Self {
+            active,
+            date: NaiveDate::parse_from_str(inner.get(..10)?, "%Y-%m-%d").ok()?,
+            time: TIME
+                .captures(&inner[10..])
+                .ok()
+                .flatten()
+                .and_then(|caps| NaiveTime::parse_from_str(&caps["time"], "%H:%M").ok()),
+            text: inner.to_owned(),
+        })
+    }
+
+    /// Every timestamp in `text`, with its byte range.
+    pub fn find_all(text: &str) -> Vec<(std::ops::Range<usize>, Self)> {
+        TIMESTAMP
+            .captures_iter(text)
+            .map_while(Result::ok)
+            .filter_map(|caps| {
+                let (inner, active) = match caps.name("active") {
+                    Some(active) => (active.as_str(), true),
+                    None => (caps.name("inactive")?.as_str(), false),
+                };
+
+                Some((caps.get(0)?.range(), Self::from_inner(inner, active)?))
+            })
+            .collect()
+    }
+
+    /// The first active timestamp in `text`.
+    pub fn first_active(text: &str) -> Option<Self> {
+        Self::find_all(text)
+            .into_iter()
+            .map(|(_, timestamp)| timestamp)
+            .find(|timestamp| timestamp.active)
+    }
+
+    /// The `datetime` attribute: `2024-05-01` or `2024-05-01T10:00`.
+    pub fn datetime(&self) -> String {
+        match self.time {
+            Some(time) => format!("{}T{}", self.date, time.format("%H:%M")),
+            None => self.date.to_string(),
+        }
+    }
+
+    /// Times without a zone are taken as UTC.
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        self.date
+            .and_time(self.time.unwrap_or(NaiveTime::MIN))
+            .and_utc()
+    }
+
+    pub fn to_html(&self) -> String {
+        let (open, close) = if self.active { ("<", ">") } else { ("[", "]") };
+
+        format!(
+            "<time class=\"timestamp\" datetime=\"{}\">{}</time>",
+            self.datetime(),
+            escape_html(&format!("{}{}{}", open, self.text, close))
+        )
+    }
+}
+
+/// Replaces the timestamps in `text` with `<time>` elements.
+pub fn render(text: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+
+    for (range, timestamp) in Timestamp::find_all(text) {
+        out += &text[last..range.start];
+        out += &timestamp.to_html();
+        last = range.end;
+    }
+
+    out + &text[last..]
+}
+
+/// The `SCHEDULED`, `DEADLINE` and `CLOSED` entries of a planning line.
+pub fn planning(line: &str) -> Vec<(String, Timestamp)> {
+    PLANNING
+        .captures_iter(line)
+        .map_while(Result::ok)
+        .filter_map(|caps| {
+            let (_, timestamp) = Timestamp::find_all(&caps["timestamp"]).into_iter().next()?;
+
+            Some((caps["kind"].to_owned(), timestamp))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::org::timestamp::{planning, render, Timestamp};
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            Timestamp::first_active("[2024-04-30 Tue] then <2024-05-01 Wed 10:00 +1w>"),
+            Some(Timestamp {
+                active: true,
+                date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+                time: NaiveTime::from_hms_opt(10, 0, 0),
+                text: "2024-05-01 Wed 10:00 +1w".into()
+            })
+        );
+        assert_eq!(
+            render("Due [2024-05-01] or <not a date>."),
+            "Due <time class=\"timestamp\" datetime=\"2024-05-01\">[2024-05-01]</time> or <not a date>."
+        );
+    }
+
+    #[test]
+    fn planning_lines() {
+        let entries = planning("SCHEDULED: <2024-05-01 Wed> DEADLINE: <2024-05-03 Fri 17:00>");
+
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(kind, timestamp)| (kind.as_str(), timestamp.datetime()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("SCHEDULED", "2024-05-01".into()),
+                ("DEADLINE", "2024-05-03T17:00".into())
+            ]
+        );
+        assert!(planning("DEADLINE: tomorrow").is_empty());
+    }
+}
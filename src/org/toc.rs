@@ -0,0 +1,137 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use crate::org::{options::ExportOptions, Node, Section};
+
+/// How many heading levels the table of contents lists, from `#+OPTIONS` like `toc:2` or
+/// `toc:t H:4`, falling back to `default`.
+pub fn depth(options: &ExportOptions, default: Option<usize>) -> Option<usize> {
+    options.toc.or(default).filter(|depth| *depth > 0)
+}
+
+fn list(sections: &[Section], depth: usize, level: usize) -> String {
+    let items: String = sections
+        .iter()
+        .filter(|section| !section.commented)
+        .filter_map(|section| match section.nodes.first() {
+            Some(Node::Heading {
+                title, id, number, ..
+            }) => {
+                let children = if level < depth {
+                    list(&section.children, depth, level + 1)
+                } else {
+                    String::new()
+                };
+
+                let number = number
+                    .as_ref()
+                    .map(|number| format!("<span class=\"section-number\">{}</span> ", number))
+                    .unwrap_or_default();
+
+                Some(format!(
+                    "<li><a href=\"#{}\">{}{}</a>{}</li>",
+                    id, number, title, children
+                ))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if items.is_empty() {
+        items
+    } else {
+        format!("<ul>{}</ul>", items)
+    }
+}
+
+/// A nested list of links to the headings, down to `depth` levels, or `None` without any.
+pub fn render(sections: &[Section], depth: usize) -> Option<String> {
+    let list = list(sections, depth, 1);
+
+    (!list.is_empty()).then(|| {
+        format!(
+            "<nav class=\"table-of-contents\"><h2>Table of Contents</h2>{}</nav>",
+            list
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{Config, HandlerConfig, OrgHandlerConfig, TocPlacement},
+        org::{options::ExportOptions, toc, Document},
+    };
+
+    #[test]
+    fn depths() {
+        let depth = |options: &str, default| toc::depth(&ExportOptions::parse(options), default);
+
+        assert_eq!(depth("num:nil toc:2", None), Some(2));
+        assert_eq!(depth("toc:t H:4", None), Some(4));
+        assert_eq!(depth("toc:t", None), Some(3));
+        assert_eq!(depth("toc:nil", Some(2)), None);
+        assert_eq!(depth("H:2", Some(2)), Some(2));
+        assert_eq!(depth("", None), None);
+    }
+
+    #[test]
+    fn nested() {
+        let doc = Document::parse(
+            "* One\n** One.A\n*** Too deep\n* COMMENT Hidden\n* Two",
+            "toc.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            toc::render(&doc.sections, 2).unwrap(),
+            "<nav class=\"table-of-contents\"><h2>Table of Contents</h2><ul><li><a href=\"#one\">One</a><ul><li><a href=\"#one-a\">One.A</a></li></ul></li><li><a href=\"#two\">Two</a></li></ul></nav>"
+        );
+        assert_eq!(toc::render(&doc.sections[..1], 1), None);
+    }
+
+    #[test]
+    fn numbered() {
+        let doc = Document::parse(
+            "#+OPTIONS: num:1 toc:2\n* One\n** One.A\n* Two",
+            "toc.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.table_of_contents(&Config::default()).unwrap(),
+            "<nav class=\"table-of-contents\"><h2>Table of Contents</h2><ul><li><a href=\"#one\"><span class=\"section-number\">1</span> One</a><ul><li><a href=\"#one-a\">One.A</a></li></ul></li><li><a href=\"#two\"><span class=\"section-number\">2</span> Two</a></li></ul></nav>"
+        );
+    }
+
+    #[test]
+    fn placement() {
+        let doc =
+            Document::parse("#+OPTIONS: toc:1\n* One", "toc.org", Default::default()).unwrap();
+        let nav = "<nav class=\"table-of-contents\"><h2>Table of Contents</h2><ul><li><a href=\"#one\">One</a></li></ul></nav>";
+
+        assert_eq!(
+            doc.to_html(&Config::default()),
+            format!(
+                "<div class=\"article\">{}<section><h1 id=\"one\">One</h1></section></div>",
+                nav
+            )
+        );
+
+        let config = Config {
+            handlers: Some(HandlerConfig {
+                org: Some(OrgHandlerConfig {
+                    toc_placement: Some(TocPlacement::Template),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(!doc.to_html(&config).contains(nav));
+        assert_eq!(doc.table_of_contents(&config).as_deref(), Some(nav));
+    }
+}
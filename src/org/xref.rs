@@ -0,0 +1,336 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use build_html::escape_html;
+use fancy_regex::{Captures, Regex};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::{
+    config::NumberingScope,
+    handler::FileContext,
+    metadata::Metadata,
+    org::{Node, Section},
+};
+
+lazy_static! {
+    static ref INTERNAL_LINK: Regex =
+        Regex::new(r"\[\[(?<target>[^\]\[]+)\](?:\[(?<description>[^\]\[]+)\])?\]").unwrap();
+}
+
+pub(super) fn for_each_node(nodes: &mut [Node], f: &mut impl FnMut(&mut Node)) {
+    for node in nodes {
+        f(node);
+
+        match node {
+            Node::GreaterBlock { contents, .. } => for_each_node(contents, f),
+            Node::Labeled { node, .. } => for_each_node(std::slice::from_mut(node.as_mut()), f),
+            _ => {}
+        }
+    }
+}
+
+pub(super) fn for_each_section(sections: &mut [Section], f: &mut impl FnMut(&mut Section)) {
+    for section in sections {
+        f(section);
+        for_each_section(&mut section.children, f);
+    }
+}
+
+/// A numbered figure, table or listing, as templates see it.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Numbered {
+    pub number: usize,
+    /// E.g. "Figure 3".
+    pub label: String,
+    /// Its `#+NAME`, which is also its anchor.
+    pub name: Option<String>,
+    pub caption: Option<String>,
+}
+
+/// Where each kind's numbering starts: after the pages with earlier URLs if numbering is
+/// site-wide, and at zero otherwise.
+pub fn offsets(ctx: &FileContext) -> HashMap<String, usize> {
+    let mut offsets: HashMap<String, usize> = HashMap::new();
+    let site_wide = ctx
+        .handler_config
+        .org
+        .as_ref()
+        .and_then(|org| org.numbering)
+        == Some(NumberingScope::Site);
+
+    if !site_wide {
+        return offsets;
+    }
+
+    let url = ctx.url();
+
+    for meta in ctx.metadata.lock().unwrap().iter() {
+        if let Metadata::Article {
+            url: other,
+            numbered,
+            ..
+        } = meta
+        {
+            if *other < url {
+                for (kind, count) in numbered {
+                    *offsets.entry(kind.clone()).or_default() += count;
+                }
+            }
+        }
+    }
+
+    offsets
+}
+
+/// The numbered figures, tables and listings of exported sections by kind, in document order.
+pub fn numbering(sections: &[Section]) -> HashMap<String, Vec<Numbered>> {
+    fn in_nodes(nodes: &[Node], numbering: &mut HashMap<String, Vec<Numbered>>) {
+        for node in nodes {
+            match node {
+                Node::Labeled {
+                    kind,
+                    number: Some(number),
+                    name,
+                    caption,
+                    ..
+                } => numbering.entry(kind.clone()).or_default().push(Numbered {
+                    number: *number,
+                    label: format!("{} {}", kind, number),
+                    name: name.clone(),
+                    caption: caption.clone(),
+                }),
+                Node::GreaterBlock { contents, .. } => in_nodes(contents, numbering),
+                _ => {}
+            }
+        }
+    }
+
+    fn in_sections(sections: &[Section], numbering: &mut HashMap<String, Vec<Numbered>>) {
+        for section in sections.iter().filter(|section| !section.commented) {
+            in_nodes(&section.nodes, numbering);
+            in_sections(&section.children, numbering);
+        }
+    }
+
+    let mut numbering = HashMap::new();
+
+    in_sections(sections, &mut numbering);
+
+    numbering
+}
+
+/// Numbers captioned figures, tables and listings in document order, starting after `offsets`,
+/// then turns `[[name]]` links to named ones into links to their anchors, e.g. "Table 2". Links
+/// to headings, `[[*Title]]` or `[[#id]]`, read as the section's number if it has one and its
+/// title otherwise.
+pub fn resolve(sections: &mut [Section], offsets: &HashMap<String, usize>) {
+    let mut counts: HashMap<String, usize> = offsets.clone();
+    // Link targets, with the anchors they point to and their default text.
+    let mut targets: HashMap<String, (String, String)> = HashMap::new();
+
+    for_each_section(sections, &mut |section| {
+        if section.commented {
+            return;
+        }
+
+        if let Some(Node::Heading {
+            title, id, number, ..
+        }) = section.nodes.first()
+        {
+            let label = number.clone().unwrap_or_else(|| title.clone());
+
+            targets.insert(format!("#{}", id), (id.clone(), label.clone()));
+            targets
+                .entry(format!("*{}", title))
+                .or_insert((id.clone(), label));
+        }
+    });
+
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::Labeled {
+                kind,
+                number,
+                name,
+                caption,
+                ..
+            } = node
+            {
+                if caption.is_some() {
+                    let count = counts.entry(kind.clone()).or_default();
+                    *count += 1;
+                    *number = Some(*count);
+                }
+
+                if let Some(name) = name {
+                    targets.insert(
+                        name.clone(),
+                        (
+                            name.clone(),
+                            match number {
+                                Some(number) => format!("{} {}", kind, number),
+                                None => name.clone(),
+                            },
+                        ),
+                    );
+                }
+            }
+        });
+    });
+
+    if targets.is_empty() {
+        return;
+    }
+
+    for_each_section(sections, &mut |section| {
+        for_each_node(&mut section.nodes, &mut |node| {
+            if let Node::Paragraph(text) = node {
+                *text = INTERNAL_LINK
+                    .replace_all(text, |caps: &Captures| match targets.get(&caps["target"]) {
+                        Some((anchor, label)) => format!(
+                            "<a href=\"#{}\">{}</a>",
+                            escape_html(anchor),
+                            caps.name("description")
+                                .map_or(label.as_str(), |description| description.as_str())
+                        ),
+                        None => caps[0].to_owned(),
+                    })
+                    .into_owned();
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{
+        config::{HandlerConfig, NumberingScope, OrgHandlerConfig},
+        handler::FileContext,
+        metadata::Metadata,
+        org::{Document, Node},
+    };
+
+    #[test]
+    fn references() {
+        let doc = Document::parse(
+            r#"See [[squares]] and [[hello][the program]], but not [[elsewhere]].
+
+#+NAME: hello
+#+BEGIN_SRC rust
+fn main() {}
+#+END_SRC
+
+#+CAPTION: Unnamed
+| 0 |
+
+#+CAPTION: Squares
+#+NAME: squares
+| 1 | 1 |
+| 2 | 4 |"#,
+            "xref.org",
+            Default::default(),
+        )
+        .unwrap();
+        let nodes = &doc.sections[0].nodes;
+
+        assert_eq!(
+            nodes[0],
+            Node::Paragraph(
+                "See <a href=\"#squares\">Table 2</a> and <a href=\"#hello\">the program</a>, but not [[elsewhere]].".into()
+            )
+        );
+        assert!(matches!(
+            &nodes[1],
+            Node::Labeled { kind, number: None, name: Some(name), caption: None, .. }
+                if kind == "Listing" && name == "hello"
+        ));
+        assert!(matches!(
+            &nodes[3],
+            Node::Labeled {
+                number: Some(2),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn heading_references() {
+        let doc = Document::parse(
+            "#+OPTIONS: num:t\n* Setup\n** Install\nSee [[*Setup]], [[#install][installing]] and [[#nowhere]].",
+            "xref.org",
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            doc.sections[1].children[0].nodes[1],
+            Node::Paragraph(
+                "See <a href=\"#setup\">1</a>, <a href=\"#install\">installing</a> and [[#nowhere]].".into()
+            )
+        );
+
+        let doc = Document::parse("* Setup\nSee [[*Setup]].", "xref.org", Default::default()).unwrap();
+
+        assert_eq!(
+            doc.sections[1].nodes[1],
+            Node::Paragraph("See <a href=\"#setup\">Setup</a>.".into())
+        );
+    }
+
+    #[test]
+    fn site_wide_figures() {
+        let page = |url: &str, figures: usize| {
+            Metadata::article(url)
+                .with_title("Page")
+                .with_numbered("Figure", figures)
+        };
+        let source = "See [[cat]].\n\n#+CAPTION: A cat.\n#+NAME: cat\n[[file:cat.png]]\n\n#+CAPTION: A dog.\n[[file:dog.png]]";
+        let ctx = FileContext {
+            site_url: "https://example.com".into(),
+            relative_path: "b.org".into(),
+            handler_config: HandlerConfig {
+                org: Some(OrgHandlerConfig {
+                    numbering: Some(NumberingScope::Site),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            metadata: Arc::new(Mutex::new(vec![
+                page("https://example.com/a.html", 2),
+                page("https://example.com/c.html", 5),
+            ])),
+            ..Default::default()
+        };
+
+        let doc = Document::parse(source, "xref.org", Default::default()).unwrap();
+
+        assert_eq!(
+            doc.sections[0].nodes[0],
+            Node::Paragraph("See <a href=\"#cat\">Figure 1</a>.".into())
+        );
+        assert_eq!(doc.numbered(), HashMap::from([("Figure".into(), 2)]));
+
+        let doc = Document::parse(source, "xref.org", ctx).unwrap();
+        let numbering = doc.numbering();
+
+        assert_eq!(
+            doc.sections[0].nodes[0],
+            Node::Paragraph("See <a href=\"#cat\">Figure 3</a>.".into())
+        );
+        assert_eq!(
+            numbering["Figure"]
+                .iter()
+                .map(|figure| (figure.label.as_str(), figure.name.as_deref()))
+                .collect::<Vec<_>>(),
+            [("Figure 3", Some("cat")), ("Figure 4", None)]
+        );
+    }
+}
@@ -0,0 +1,626 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Handlers and Org macros provided as WebAssembly modules in the source's `plugins/`
+//! directory, or listed in `plugins` in the config.
+//!
+//! A plugin exports `memory` and `impertio_register`, `impertio_handle` if it claims
+//! extensions, `impertio_macro` if it claims macros, and optionally `impertio_metadata`. Each
+//! export returns `0` on success. The host provides, under the `impertio` module:
+//!
+//! - `abi_version() -> i32`: the version of this interface, [`ABI_VERSION`]
+//! - `register_extension(ptr, len)`: claim an extension, only during `impertio_register`
+//! - `register_macro(ptr, len)`: claim `{{{name(args)}}}` in Org pages, likewise
+//! - `source_len() -> i32` and `read_source(ptr)`: read the source file into memory, or in
+//!   `impertio_macro`, JSON with the macro's `name` and `args`, the `file` calling it, and the
+//!   site's `pages`, each with `title`, `url`, `description`, `author`, `tags` and `modified`
+//! - `write_output(ext_ptr, ext_len, ptr, len)`: write the output with the given extension,
+//!   letters and digits only, or in `impertio_macro`, HTML to expand the macro to, whatever
+//!   the extension
+//! - `emit_metadata(ptr, len)`: JSON with `title`, and optionally `description`, `author`,
+//!   `tags` and `extension` (of the page, defaulting to `html`, likewise letters and digits)
+//! - `log(level, ptr, len)`: 1 = error through 5 = trace
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+use crate::{
+    handler::{FileContext, FileHandler},
+    metadata::Metadata,
+};
+
+/// The version of the interface plugins are given. Changes to it only add to it.
+pub const ABI_VERSION: i32 = 1;
+
+#[derive(Deserialize, Debug, Clone)]
+struct PluginMetadata {
+    title: String,
+    description: Option<String>,
+    author: Option<String>,
+    tags: Option<Vec<String>>,
+    extension: Option<String>,
+}
+
+#[derive(Default)]
+struct HostState {
+    plugin: String,
+    registering: bool,
+    extensions: Vec<String>,
+    macros: Vec<String>,
+    source: Vec<u8>,
+    outputs: Vec<(String, Vec<u8>)>,
+    metadata: Option<PluginMetadata>,
+}
+
+fn read_memory(
+    caller: &Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+) -> Result<Vec<u8>, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("plugin does not export `memory`"))?;
+    let mut buf = vec![0; len as usize];
+
+    memory
+        .read(caller, ptr as usize, &mut buf)
+        .map_err(|err| wasmi::Error::new(err.to_string()))?;
+
+    Ok(buf)
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    String::from_utf8(read_memory(caller, ptr, len)?)
+        .map_err(|err| wasmi::Error::new(err.to_string()))
+}
+
+fn linker(engine: &Engine) -> anyhow::Result<Linker<HostState>> {
+    let mut linker: Linker<HostState> = Linker::new(engine);
+
+    linker.func_wrap(
+        "impertio",
+        "register_extension",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            if !caller.data().registering {
+                return Err(wasmi::Error::new(
+                    "extensions can only be registered in `impertio_register`",
+                ));
+            }
+
+            let extension = read_string(&caller, ptr, len)?;
+            caller.data_mut().extensions.push(extension);
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "impertio",
+        "register_macro",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            if !caller.data().registering {
+                return Err(wasmi::Error::new(
+                    "macros can only be registered in `impertio_register`",
+                ));
+            }
+
+            let name = read_string(&caller, ptr, len)?;
+            caller.data_mut().macros.push(name.to_ascii_lowercase());
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap("impertio", "abi_version", || -> i32 { ABI_VERSION })?;
+    linker.func_wrap(
+        "impertio",
+        "source_len",
+        |caller: Caller<'_, HostState>| -> i32 { caller.data().source.len() as i32 },
+    )?;
+    linker.func_wrap(
+        "impertio",
+        "read_source",
+        |mut caller: Caller<'_, HostState>, ptr: i32| -> Result<(), wasmi::Error> {
+            let memory = caller
+                .get_export("memory")
+                .and_then(Extern::into_memory)
+                .ok_or_else(|| wasmi::Error::new("plugin does not export `memory`"))?;
+            let source = std::mem::take(&mut caller.data_mut().source);
+
+            memory
+                .write(&mut caller, ptr as usize, &source)
+                .map_err(|err| wasmi::Error::new(err.to_string()))?;
+            caller.data_mut().source = source;
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "impertio",
+        "write_output",
+        |mut caller: Caller<'_, HostState>,
+         ext_ptr: i32,
+         ext_len: i32,
+         ptr: i32,
+         len: i32|
+         -> Result<(), wasmi::Error> {
+            let extension = read_string(&caller, ext_ptr, ext_len)?;
+            let output = read_memory(&caller, ptr, len)?;
+
+            caller.data_mut().outputs.push((extension, output));
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "impertio",
+        "emit_metadata",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let json = read_memory(&caller, ptr, len)?;
+            let metadata = serde_json::from_slice(&json)
+                .map_err(|err| wasmi::Error::new(format!("invalid metadata: {}", err)))?;
+
+            caller.data_mut().metadata = Some(metadata);
+
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "impertio",
+        "log",
+        |caller: Caller<'_, HostState>,
+         level: i32,
+         ptr: i32,
+         len: i32|
+         -> Result<(), wasmi::Error> {
+            let message = read_string(&caller, ptr, len)?;
+            let level = match level {
+                1 => log::Level::Error,
+                2 => log::Level::Warn,
+                3 => log::Level::Info,
+                4 => log::Level::Debug,
+                _ => log::Level::Trace,
+            };
+
+            log::log!(level, "[{}] {}", caller.data().plugin, message);
+
+            Ok(())
+        },
+    )?;
+
+    Ok(linker)
+}
+
+/// A page of the site, as macros see it.
+#[derive(Serialize)]
+struct MacroPage<'a> {
+    title: &'a str,
+    url: &'a str,
+    description: Option<&'a str>,
+    author: Option<&'a str>,
+    tags: &'a [String],
+    modified: String,
+}
+
+/// What `impertio_macro` reads as its source.
+#[derive(Serialize)]
+struct MacroCall<'a> {
+    name: &'a str,
+    args: &'a [String],
+    file: String,
+    pages: Vec<MacroPage<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WasmHandler {
+    name: String,
+    engine: Engine,
+    module: Module,
+    macros: Vec<String>,
+}
+
+impl WasmHandler {
+    /// Compiles a plugin and asks it which extensions it handles and which macros it expands.
+    pub fn load(path: &Path) -> anyhow::Result<(Self, Vec<String>)> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, std::fs::read(path)?)?;
+        let handler = Self {
+            name: path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            engine,
+            module,
+            macros: vec![],
+        };
+
+        let state = handler.call(
+            "impertio_register",
+            HostState {
+                registering: true,
+                ..Default::default()
+            },
+        )?;
+
+        Ok((
+            Self {
+                macros: state.macros,
+                ..handler
+            },
+            state.extensions,
+        ))
+    }
+
+    /// The names of the Org macros it expands, lowercased.
+    pub fn macros(&self) -> &[String] {
+        &self.macros
+    }
+
+    /// The HTML `{{{name(args)}}}` expands to on the page of `ctx`.
+    pub fn expand_macro(
+        &self,
+        name: &str,
+        args: &[String],
+        ctx: &FileContext,
+    ) -> anyhow::Result<String> {
+        let metadata = ctx.metadata.lock().unwrap();
+        let call = MacroCall {
+            name,
+            args,
+            file: ctx.relative_path.display().to_string(),
+            pages: metadata
+                .iter()
+                .filter_map(|meta| match meta {
+                    Metadata::Article {
+                        title,
+                        url,
+                        description,
+                        author,
+                        tags,
+                        modified,
+                        ..
+                    } => Some(MacroPage {
+                        title,
+                        url,
+                        description: description.as_deref(),
+                        author: author.as_deref(),
+                        tags,
+                        modified: modified.to_rfc3339(),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        };
+        let source = serde_json::to_vec(&call)?;
+
+        drop(metadata);
+
+        let state = self.call(
+            "impertio_macro",
+            HostState {
+                source,
+                ..Default::default()
+            },
+        )?;
+
+        Ok(state
+            .outputs
+            .iter()
+            .map(|(_, output)| String::from_utf8_lossy(output))
+            .collect())
+    }
+
+    /// Runs an export with a fresh instance, returning what the plugin handed back.
+    fn call(&self, export: &str, state: HostState) -> anyhow::Result<HostState> {
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                plugin: self.name.clone(),
+                ..state
+            },
+        );
+        let instance = linker(&self.engine)?.instantiate_and_start(&mut store, &self.module)?;
+        let status = instance
+            .get_typed_func::<(), i32>(&store, export)?
+            .call(&mut store, ())?;
+
+        if status != 0 {
+            return Err(anyhow::anyhow!(
+                "Plugin `{}` returned {} from `{}`.",
+                self.name,
+                status,
+                export
+            ));
+        }
+
+        Ok(store.into_data())
+    }
+
+    fn call_with_source(&self, export: &str, ctx: &FileContext) -> anyhow::Result<HostState> {
+        self.call(
+            export,
+            HostState {
+                source: std::fs::read(&ctx.source_path)?,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+/// Whether plugins may give outputs and pages `extension`: letters and digits only, so they
+/// stay next to their source.
+fn valid_extension(extension: &str) -> bool {
+    !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+impl FileHandler for WasmHandler {
+    fn handle_file(&mut self, ctx: FileContext) -> anyhow::Result<()> {
+        log::info!(
+            phase = "parse",
+            file:% = ctx.relative_path.display();
+            "Running plugin `{}` on {:?}",
+            self.name,
+            ctx.source_path
+        );
+
+        let state = self.call_with_source("impertio_handle", &ctx)?;
+
+        for (extension, output) in state.outputs {
+            let path = if extension.is_empty() {
+                ctx.output_path.clone()
+            } else if valid_extension(&extension) {
+                ctx.output_path.with_extension(extension)
+            } else {
+                anyhow::bail!(
+                    "Plugin `{}` wrote an output with the invalid extension {:?}.",
+                    self.name,
+                    extension
+                );
+            };
+
+            ctx.writeable(&path)?.write_all(&output)?;
+        }
+
+        Ok(())
+    }
+
+    fn extract_metadata(&mut self, ctx: FileContext) -> anyhow::Result<Metadata> {
+        if self.module.get_export("impertio_metadata").is_none() {
+            return Err(anyhow::anyhow!("Plugin `{}` has no metadata.", self.name));
+        }
+
+        let metadata = self
+            .call_with_source("impertio_metadata", &ctx)?
+            .metadata
+            .ok_or_else(|| anyhow::anyhow!("Plugin `{}` emitted no metadata.", self.name))?;
+        let extension = metadata.extension.as_deref().unwrap_or("html");
+
+        if !valid_extension(extension) {
+            anyhow::bail!(
+                "Plugin `{}` gave its page the invalid extension {:?}.",
+                self.name,
+                extension
+            );
+        }
+
+        Ok(Metadata::Article {
+            title: metadata.title,
+            description: metadata.description,
+            author: metadata.author,
+            tags: metadata.tags.unwrap_or_default(),
+            terms: Default::default(),
+            license: ctx.config.license.clone(),
+            modified: std::fs::metadata(&ctx.source_path)?.modified()?.into(),
+            published: None,
+            anchors: vec![],
+            robots: Default::default(),
+            summary: None,
+            numbered: HashMap::new(),
+            content: None,
+            url: format!(
+                "{}/{}",
+                ctx.site_url,
+                ctx.relative_path.with_extension(extension).display()
+            ),
+        })
+    }
+}
+
+/// Every loadable plugin in `dir`, then those at `declared`, with the extensions it claimed.
+pub fn load_plugins(dir: &Path, declared: &[PathBuf]) -> Vec<(WasmHandler, Vec<String>)> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "wasm" || ext == "wat")
+        })
+        .collect();
+    paths.sort();
+
+    for path in declared {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+
+    paths
+        .iter()
+        .filter_map(|path| match WasmHandler::load(path) {
+            Ok(plugin) => {
+                log::info!(
+                    "Loaded plugin {:?} for {:?} and macros {:?}",
+                    path,
+                    plugin.1,
+                    plugin.0.macros
+                );
+                Some(plugin)
+            }
+            Err(err) => {
+                log::warn!("Failed to load plugin {:?}: {}", path, err);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, path::Path, sync::Arc};
+
+    use crate::{
+        handler::{FileContext, FileHandler},
+        metadata::Metadata,
+        org::Document,
+        plugin::load_plugins,
+    };
+
+    const PLUGIN: &str = r#"(module
+  (import "impertio" "register_extension" (func $register_extension (param i32 i32)))
+  (import "impertio" "source_len" (func $source_len (result i32)))
+  (import "impertio" "read_source" (func $read_source (param i32)))
+  (import "impertio" "write_output" (func $write_output (param i32 i32 i32 i32)))
+  (import "impertio" "emit_metadata" (func $emit_metadata (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "txt")
+  (data (i32.const 8) "html")
+  (data (i32.const 16) "{\"title\": \"Shouting\"}")
+  (func (export "impertio_register") (result i32)
+    (call $register_extension (i32.const 0) (i32.const 3))
+    (i32.const 0))
+  (func (export "impertio_handle") (result i32)
+    (call $read_source (i32.const 1024))
+    (call $write_output (i32.const 8) (i32.const 4) (i32.const 1024) (call $source_len))
+    (i32.const 0))
+  (func (export "impertio_metadata") (result i32)
+    (call $emit_metadata (i32.const 16) (i32.const 21))
+    (i32.const 0)))"#;
+
+    #[test]
+    fn wat_plugin() {
+        let dir = std::env::temp_dir().join(format!("impertio-plugin-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("plugins")).unwrap();
+        std::fs::write(dir.join("plugins/shout.wat"), PLUGIN).unwrap();
+        std::fs::write(dir.join("page.txt"), "<p>hello</p>").unwrap();
+
+        let mut plugins = load_plugins(&dir.join("plugins"), &[]);
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].1, vec!["txt"]);
+
+        let ctx = FileContext {
+            relative_path: Path::new("page.txt").to_owned(),
+            source_path: dir.join("page.txt"),
+            output_path: dir.join("out/page.txt"),
+            ext: "txt".into(),
+            ..Default::default()
+        };
+        let handler = &mut plugins[0].0;
+
+        handler.handle_file(ctx.clone()).unwrap();
+        let written = std::fs::read_to_string(dir.join("out/page.html")).unwrap();
+        let metadata = handler.extract_metadata(ctx).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, "<p>hello</p>");
+
+        match metadata {
+            Metadata::Article { title, url, .. } => {
+                assert_eq!(title, "Shouting");
+                assert_eq!(url, "/page.html");
+            }
+            _ => panic!("Expected an article."),
+        }
+    }
+
+    #[test]
+    fn invalid_extensions() {
+        let dir = std::env::temp_dir().join(format!("impertio-extensions-{}", std::process::id()));
+        let plugin = PLUGIN
+            .replace(r#"(i32.const 8) "html""#, r#"(i32.const 8) "/../""#)
+            .replace(
+                r#"{\"title\": \"Shouting\"}""#,
+                r#"{\"title\": \"S\", \"extension\": \"a/b\"}""#,
+            )
+            .replace("(i32.const 21)", "(i32.const 34)");
+
+        std::fs::create_dir_all(dir.join("plugins")).unwrap();
+        std::fs::write(dir.join("plugins/escape.wat"), plugin).unwrap();
+        std::fs::write(dir.join("page.txt"), "hello").unwrap();
+
+        let mut plugins = load_plugins(&dir.join("plugins"), &[]);
+        let ctx = FileContext {
+            relative_path: Path::new("page.txt").to_owned(),
+            source_path: dir.join("page.txt"),
+            output_path: dir.join("out/page.txt"),
+            ext: "txt".into(),
+            ..Default::default()
+        };
+        let handled = plugins[0].0.handle_file(ctx.clone());
+        let metadata = plugins[0].0.extract_metadata(ctx);
+        let written = dir.join("out").exists();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            handled.unwrap_err().to_string(),
+            "Plugin `escape` wrote an output with the invalid extension \"/../\"."
+        );
+        assert_eq!(
+            metadata.unwrap_err().to_string(),
+            "Plugin `escape` gave its page the invalid extension \"a/b\"."
+        );
+        assert!(!written);
+    }
+
+    const MACRO_PLUGIN: &str = r#"(module
+  (import "impertio" "abi_version" (func $abi_version (result i32)))
+  (import "impertio" "register_macro" (func $register_macro (param i32 i32)))
+  (import "impertio" "source_len" (func $source_len (result i32)))
+  (import "impertio" "read_source" (func $read_source (param i32)))
+  (import "impertio" "write_output" (func $write_output (param i32 i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "Echo")
+  (func (export "impertio_register") (result i32)
+    (call $register_macro (i32.const 0) (i32.const 4))
+    (i32.sub (call $abi_version) (i32.const 1)))
+  (func (export "impertio_macro") (result i32)
+    (call $read_source (i32.const 1024))
+    (call $write_output (i32.const 0) (i32.const 0) (i32.const 1024) (call $source_len))
+    (i32.const 0)))"#;
+
+    #[test]
+    fn macro_plugin() {
+        let dir = std::env::temp_dir().join(format!("impertio-macro-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/echo.wat"), MACRO_PLUGIN).unwrap();
+
+        let plugins = load_plugins(&dir.join("plugins"), &[dir.join("lib/echo.wat")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].0.macros(), ["echo"]);
+        assert!(plugins[0].1.is_empty());
+
+        let ctx = FileContext {
+            relative_path: Path::new("page.org").to_owned(),
+            macros: Arc::new(HashMap::from([("echo".into(), plugins[0].0.clone())])),
+            ..Default::default()
+        };
+        let html = Document::parse("{{{echo(a, b)}}}", "page.org", ctx)
+            .unwrap()
+            .to_html(&Default::default());
+
+        assert!(html.contains(r#"{"name":"echo","args":["a","b"],"file":"page.org","pages":[]}"#));
+    }
+}
@@ -0,0 +1,164 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! What's left at the URLs of pages earlier builds published, so they don't break silently.
+
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+use build_html::escape_html;
+
+use crate::{config::RemovedConfig, manifest::Manifest};
+
+/// Where the server map of removed paths is written, relative to the destination.
+pub const FILE: &str = "removed.json";
+
+/// A page sending readers, and crawlers, on to `target`.
+fn redirect_page(target: &str) -> String {
+    let target = escape_html(target);
+
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\" /><title>Moved</title><link rel=\"canonical\" href=\"{0}\" /><meta name=\"robots\" content=\"noindex\" /><meta http-equiv=\"refresh\" content=\"0; url={0}\" /></head><body><p>This page has moved to <a href=\"{0}\">{0}</a>.</p></body></html>",
+        target
+    )
+}
+
+/// A page saying the one that was here is gone.
+fn gone_page() -> String {
+    "<!DOCTYPE html><html><head><meta charset=\"utf-8\" /><title>Gone</title><meta name=\"robots\" content=\"noindex\" /></head><body><p>This page has been removed.</p></body></html>".into()
+}
+
+/// The paths of removed pages, like `/posts/a.html`, and the absolute URLs they moved to: those
+/// `manifest` remembers, and redirected paths that aren't pages.
+pub fn removed(
+    config: &RemovedConfig,
+    site_url: &str,
+    manifest: &Manifest,
+) -> BTreeMap<String, Option<String>> {
+    let redirects = config.redirects.clone().unwrap_or_default();
+    let target = |path: &str| {
+        redirects.get(path).map(|target| {
+            crate::absolute::url(site_url, Path::new(path.trim_start_matches('/')), target)
+        })
+    };
+    let mut removed: BTreeMap<String, Option<String>> = manifest
+        .removed
+        .iter()
+        .filter_map(|url| url.strip_prefix(site_url))
+        .filter(|path| path.starts_with('/'))
+        .map(|path| (path.to_owned(), target(path)))
+        .collect();
+
+    for path in redirects.keys() {
+        if !manifest
+            .pages
+            .contains_key(&format!("{}{}", site_url, path))
+        {
+            removed.insert(path.clone(), target(path));
+        }
+    }
+
+    removed
+}
+
+/// Writes `removed.json` into `dir`, mapping each removed path to where it moved, or `null` if
+/// it's gone, for servers to redirect or answer 410 Gone. Unless disabled, a stub page is
+/// written at each, too.
+pub fn write(
+    config: &RemovedConfig,
+    site_url: &str,
+    manifest: &Manifest,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    let removed = removed(config, site_url, manifest);
+
+    std::fs::write(dir.join(FILE), serde_json::to_string_pretty(&removed)?)?;
+
+    if !config.stubs.unwrap_or(true) {
+        return Ok(());
+    }
+
+    for (path, target) in &removed {
+        let mut file = dir.join(path.trim_start_matches('/'));
+
+        if path.ends_with('/') {
+            file.push("index.html");
+        }
+
+        let page = match target {
+            Some(target) => redirect_page(target),
+            None => gone_page(),
+        };
+
+        let mut stub = crate::handler::writeable(&file)?;
+
+        stub.write_all(page.as_bytes())?;
+        // Dated to the epoch, so a page coming back here is rebuilt over it.
+        stub.set_modified(std::time::SystemTime::UNIX_EPOCH)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use crate::{
+        config::RemovedConfig,
+        manifest::Manifest,
+        removed::{removed, write},
+    };
+
+    #[test]
+    fn stubs() {
+        let config = RemovedConfig {
+            redirects: Some(HashMap::from_iter([
+                ("/blog/old.html".into(), "new.html".into()),
+                ("/a.html".into(), "/b.html".into()),
+                ("/live.html".into(), "/b.html".into()),
+            ])),
+            ..Default::default()
+        };
+        let manifest = Manifest {
+            pages: [("https://x.y/live.html".into(), Utc::now())].into(),
+            removed: [
+                "https://x.y/blog/old.html".into(),
+                "https://x.y/gone/".into(),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            removed(&config, "https://x.y", &manifest),
+            [
+                ("/a.html".into(), Some("https://x.y/b.html".into())),
+                (
+                    "/blog/old.html".into(),
+                    Some("https://x.y/blog/new.html".into())
+                ),
+                ("/gone/".into(), None),
+            ]
+            .into()
+        );
+
+        let dir = std::env::temp_dir().join(format!("impertio-removed-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        write(&config, "https://x.y", &manifest, &dir).unwrap();
+
+        let redirect = std::fs::read_to_string(dir.join("blog/old.html")).unwrap();
+        let gone = std::fs::read_to_string(dir.join("gone/index.html")).unwrap();
+        let map = std::fs::read_to_string(dir.join("removed.json")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(redirect.contains(
+            "<meta http-equiv=\"refresh\" content=\"0; url=https://x.y/blog/new.html\" />"
+        ));
+        assert!(gone.contains("<p>This page has been removed.</p>"));
+        assert!(map.contains("\"/gone/\": null"));
+    }
+}
@@ -0,0 +1,153 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+fn is_adornment(line: &str, min_len: usize) -> bool {
+    let line = line.trim_end();
+
+    match line.chars().next() {
+        Some(first) => {
+            first.is_ascii_punctuation()
+                && line.chars().all(|c| c == first)
+                && line.chars().count() >= min_len
+        }
+        None => false,
+    }
+}
+
+/// `:Name: value`, with the name mapped onto the keys Org keywords use, if it has one.
+fn field(line: &str) -> Option<(Option<&'static str>, String)> {
+    let (name, value) = line.trim().strip_prefix(':')?.split_once(':')?;
+
+    let key = match name.trim().to_ascii_lowercase().as_str() {
+        "author" | "authors" => Some("author"),
+        "description" | "abstract" | "summary" => Some("desc"),
+        "keywords" | "tags" => Some("tags"),
+        _ => None,
+    };
+
+    Some((key, value.trim().to_owned()))
+}
+
+/// Reads a field list starting at `start`, returning the index of the first line after it.
+fn fields(lines: &[&str], start: usize, metadata: &mut HashMap<String, String>) -> usize {
+    let mut i = start;
+
+    while let Some(line) = lines.get(i) {
+        if line.trim().is_empty() {
+            i += 1;
+            continue;
+        }
+
+        match field(line) {
+            Some((Some(key), value)) => {
+                metadata.insert(key.into(), value);
+            }
+            Some((None, _)) => {}
+            None => break,
+        }
+
+        i += 1;
+    }
+
+    i
+}
+
+/// The document title, with or without an overline, and the index of the line after it.
+fn title(lines: &[&str], i: usize) -> Option<(String, usize)> {
+    let line = lines.get(i)?;
+
+    if is_adornment(line, 1) {
+        let title = lines.get(i + 1)?.trim();
+        let underline = lines.get(i + 2)?;
+
+        (!title.is_empty() && underline.trim_end() == line.trim_end())
+            .then(|| (title.to_owned(), i + 3))
+    } else if !line.trim().is_empty() && !line.starts_with(' ') {
+        let title = line.trim();
+
+        is_adornment(lines.get(i + 1)?, title.chars().count()).then(|| (title.to_owned(), i + 2))
+    } else {
+        None
+    }
+}
+
+/// Reads the document title, docinfo field lists (before or after the title), and
+/// `.. meta::` directives into the same keys Org keywords use: `title`, `author`, `desc`
+/// and `tags`.
+pub fn parse_header(content: &str) -> HashMap<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut metadata: HashMap<String, String> = HashMap::new();
+
+    let start = fields(&lines, 0, &mut metadata);
+
+    if let Some((title, next)) = title(&lines, start) {
+        metadata.insert("title".into(), title);
+        fields(&lines, next, &mut metadata);
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim_end() != ".. meta::" {
+            continue;
+        }
+
+        for line in lines[i + 1..]
+            .iter()
+            .take_while(|line| line.trim().is_empty() || line.starts_with([' ', '\t']))
+        {
+            if let Some((Some(key), value)) = field(line) {
+                metadata.insert(key.into(), value);
+            }
+        }
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::rst::parse_header;
+
+    #[test]
+    fn header() {
+        assert_eq!(
+            parse_header(
+                r#":tags: docs migration
+
+==========
+ Sphinx Era
+==========
+
+:Author: Jane Doe
+:Version: 2
+
+.. meta::
+   :description: How it used to be.
+
+Body text.
+"#
+            ),
+            HashMap::from_iter(vec![
+                ("title".into(), "Sphinx Era".into()),
+                ("author".into(), "Jane Doe".into()),
+                ("desc".into(), "How it used to be.".into()),
+                ("tags".into(), "docs migration".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn underlined_title() {
+        assert_eq!(
+            parse_header("Installing\n----------\n\nFirst, ..."),
+            HashMap::from_iter(vec![("title".into(), "Installing".into())])
+        );
+        assert_eq!(
+            parse_header("Just a paragraph.\nStill one."),
+            HashMap::new()
+        );
+    }
+}
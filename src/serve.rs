@@ -0,0 +1,249 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! A static file server for previewing a built site locally. It isn't meant for production.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+};
+
+/// The `Content-Type` of a file, by its extension.
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+    {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "rss" => "application/rss+xml",
+        "txt" | "org" | "adoc" | "rst" | "csv" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decodes `%XX` escapes in a URL path.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// What a request is answered with.
+#[derive(Debug, PartialEq)]
+pub enum Resolved {
+    File(PathBuf),
+    /// A directory with an index page, requested without a trailing slash, is redirected to
+    /// its URL with one, which its page's relative links are relative to.
+    Redirect(String),
+}
+
+/// What a request for `target` in `dir` is answered with: the file itself, the `index.html`
+/// of a directory, or the `.html` page of an extensionless URL. `None` if there's no such
+/// file, or `target` tries to leave `dir`.
+pub fn resolve(dir: &Path, target: &str) -> Option<Resolved> {
+    let end = target.find(['?', '#']).unwrap_or(target.len());
+    let (url_path, rest) = target.split_at(end);
+    let path = PathBuf::from(percent_decode(url_path.trim_start_matches('/')));
+
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let file = dir.join(&path);
+
+    if file.is_dir() {
+        let index = Some(file.join("index.html")).filter(|index| index.is_file())?;
+
+        Some(if url_path.ends_with('/') {
+            Resolved::File(index)
+        } else {
+            Resolved::Redirect(format!("{}/{}", url_path, rest))
+        })
+    } else if file.is_file() {
+        Some(Resolved::File(file))
+    } else if path.extension().is_none() {
+        Some(file.with_extension("html"))
+            .filter(|page| page.is_file())
+            .map(Resolved::File)
+    } else {
+        None
+    }
+}
+
+fn respond(dir: &Path, mut stream: TcpStream) -> anyhow::Result<()> {
+    let mut request = String::new();
+
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("/"),
+    );
+
+    if method != "GET" && method != "HEAD" {
+        write!(
+            stream,
+            "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Ok(());
+    }
+
+    let (status, file) = match resolve(dir, target) {
+        Some(Resolved::File(file)) => ("200 OK", Some(file)),
+        Some(Resolved::Redirect(location)) => {
+            log::debug!(phase = "serve"; "{} {} 301 Moved Permanently", method, target);
+
+            write!(
+                stream,
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                location
+            )?;
+            return Ok(());
+        }
+        None => (
+            "404 Not Found",
+            match resolve(dir, "/404.html") {
+                Some(Resolved::File(file)) => Some(file),
+                _ => None,
+            },
+        ),
+    };
+    let body = match &file {
+        Some(file) => std::fs::read(file)?,
+        None => b"Not found".to_vec(),
+    };
+
+    log::debug!(phase = "serve"; "{} {} {}", method, target, status);
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        file.as_deref().map_or("text/plain; charset=utf-8", content_type),
+        body.len()
+    )?;
+
+    if method == "GET" {
+        stream.write_all(&body)?;
+    }
+
+    Ok(())
+}
+
+/// Serves the files in `dir` at `addr`, like `127.0.0.1:8000`, forever.
+pub fn serve(dir: &Path, addr: &str) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    log::info!(phase = "serve"; "Serving `{}` at http://{}", dir.display(), listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::warn!("{}", err);
+                continue;
+            }
+        };
+        let dir = dir.to_owned();
+
+        std::thread::spawn(move || {
+            if let Err(err) = respond(&dir, stream) {
+                log::debug!(phase = "serve"; "{}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::serve::{resolve, Resolved};
+
+    #[test]
+    fn paths() {
+        let dir = std::env::temp_dir().join(format!("impertio-serve-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("blog/my posts")).unwrap();
+        std::fs::write(dir.join("index.html"), "").unwrap();
+        std::fs::write(dir.join("blog/a.html"), "").unwrap();
+        std::fs::write(dir.join("blog/my posts/index.html"), "").unwrap();
+
+        let found = [
+            "/",
+            "/index.html?utm=x",
+            "/blog/a",
+            "/blog/my%20posts/",
+            "/blog/my%20posts?page=2",
+            "/blog/b.html",
+            "/blog/",
+            "/blog",
+            "/../etc/passwd",
+        ]
+        .map(|target| match resolve(&dir, target)? {
+            Resolved::File(file) => Some(file.strip_prefix(&dir).unwrap().display().to_string()),
+            Resolved::Redirect(location) => Some(format!("-> {}", location)),
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            found,
+            [
+                Some("index.html".into()),
+                Some("index.html".into()),
+                Some("blog/a.html".into()),
+                Some("blog/my posts/index.html".into()),
+                Some("-> /blog/my%20posts/?page=2".into()),
+                None,
+                None,
+                None,
+                None,
+            ]
+        );
+    }
+}
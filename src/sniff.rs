@@ -0,0 +1,121 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::{io::Read, path::Path};
+
+/// How many bytes from the start of a file are looked at.
+const SNIFF_LENGTH: u64 = 512;
+
+const MAGIC_BYTES: [(&[u8], &str); 6] = [
+    (b"\x89PNG\r\n\x1a\n", "png"),
+    (b"\xff\xd8\xff", "jpg"),
+    (b"GIF87a", "gif"),
+    (b"GIF89a", "gif"),
+    (b"%PDF-", "pdf"),
+    (b"\x00asm", "wasm"),
+];
+
+/// Guesses an extension for a file from its contents.
+pub fn sniff(path: &Path) -> Option<String> {
+    let mut head: Vec<u8> = vec![];
+
+    std::fs::File::open(path)
+        .ok()?
+        .take(SNIFF_LENGTH)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    sniff_bytes(&head)
+}
+
+/// Guesses an extension from the start of a file, checking magic bytes, then shebangs, then
+/// Org keywords and HTML doctypes.
+pub fn sniff_bytes(head: &[u8]) -> Option<String> {
+    if let Some((_, ext)) = MAGIC_BYTES
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+    {
+        return Some(ext.to_string());
+    }
+
+    if head.len() >= 12 && head.starts_with(b"RIFF") && &head[8..12] == b"WEBP" {
+        return Some("webp".into());
+    }
+
+    let text = String::from_utf8_lossy(head);
+
+    if let Some(shebang) = text.strip_prefix("#!") {
+        return shebang_extension(shebang.lines().next().unwrap_or(""));
+    }
+
+    let first_line = text.lines().find(|line| !line.trim().is_empty())?.trim();
+
+    if first_line.starts_with("#+") && first_line.contains(':') {
+        Some("org".into())
+    } else if first_line
+        .to_ascii_lowercase()
+        .starts_with("<!doctype html")
+        || first_line.to_ascii_lowercase().starts_with("<html")
+    {
+        Some("html".into())
+    } else {
+        None
+    }
+}
+
+/// `/usr/bin/env python3 -u` → `py`
+fn shebang_extension(shebang: &str) -> Option<String> {
+    let mut words = shebang.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    Some(
+        match interpreter {
+            "python" => "py",
+            "sh" | "bash" | "zsh" | "dash" => "sh",
+            "node" | "deno" => "js",
+            "ruby" => "rb",
+            "perl" => "pl",
+            other => other,
+        }
+        .into(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sniff::sniff_bytes;
+
+    #[test]
+    fn magic_bytes() {
+        assert_eq!(
+            sniff_bytes(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("png".into())
+        );
+        assert_eq!(sniff_bytes(b"RIFF\0\0\0\0WEBPVP8 "), Some("webp".into()));
+    }
+
+    #[test]
+    fn shebangs() {
+        assert_eq!(
+            sniff_bytes(b"#!/usr/bin/env python3\nprint()"),
+            Some("py".into())
+        );
+        assert_eq!(sniff_bytes(b"#!/bin/bash\necho"), Some("sh".into()));
+    }
+
+    #[test]
+    fn front_matter() {
+        assert_eq!(sniff_bytes(b"\n#+TITLE: Notes\n"), Some("org".into()));
+        assert_eq!(sniff_bytes(b"<!DOCTYPE html>\n<html>"), Some("html".into()));
+        assert_eq!(sniff_bytes(b"just some text"), None);
+        // CSS and YAML, which look like an Org heading and Markdown front matter.
+        assert_eq!(sniff_bytes(b"* {\n  margin: 0;\n}\n"), None);
+        assert_eq!(sniff_bytes(b"---\ntitle: Notes\n---\n"), None);
+    }
+}
@@ -0,0 +1,409 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Pages for the terms of taxonomies, like tags: one per term listing its articles, sorted and
+//! paginated as configured, with an optional feed, and an index of the terms.
+
+use std::collections::BTreeMap;
+
+use build_html::escape_html;
+use serde::Serialize;
+
+use crate::{
+    config::{TaxonomyConfig, TaxonomySort},
+    metadata::Metadata,
+};
+
+/// A term of a taxonomy and the articles that have it.
+#[derive(Debug, Clone)]
+pub struct Term {
+    /// As the first article with it, by URL, spells it.
+    pub name: String,
+    pub slug: String,
+    pub articles: Vec<Metadata>,
+}
+
+/// A page of a term's articles, as taxonomy pages get it as `taxonomy`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Page {
+    pub name: String,
+    pub term: String,
+    /// Counting from `1`.
+    pub page: usize,
+    pub pages: usize,
+    pub url: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    /// The term's RSS feed, if it has one.
+    pub feed: Option<String>,
+}
+
+/// The terms `article` has in the taxonomy `name`.
+fn article_terms<'a>(article: &'a Metadata, name: &str) -> &'a [String] {
+    match article {
+        Metadata::Article { tags, .. } if name == "tags" => tags,
+        Metadata::Article { terms, .. } => terms.get(name).map_or(&[], Vec::as_slice),
+        _ => &[],
+    }
+}
+
+/// When `article` was published, else when it was last modified.
+fn date(article: &Metadata) -> chrono::DateTime<chrono::Utc> {
+    match article {
+        Metadata::Article {
+            published,
+            modified,
+            ..
+        } => published.unwrap_or(*modified),
+        _ => Default::default(),
+    }
+}
+
+fn sort(articles: &mut [Metadata], by: TaxonomySort, language: Option<&str>) {
+    match by {
+        TaxonomySort::Date => articles.sort_by_key(|article| std::cmp::Reverse(date(article))),
+        TaxonomySort::Title => {
+            crate::collation::sort_by_key(articles, language, |article| match article {
+                Metadata::Article { title, .. } => title,
+                _ => "",
+            })
+        }
+    }
+}
+
+/// The terms of the taxonomy `config` with at least its `min_articles`, sorted by name, each
+/// with its articles sorted as `config` asks. Terms that only differ in case or punctuation are
+/// the same term.
+pub fn terms(
+    config: &TaxonomyConfig,
+    metadata: &[Metadata],
+    site_url: &str,
+    language: Option<&str>,
+) -> Vec<Term> {
+    let prefix = config
+        .dir
+        .as_ref()
+        .map(|dir| format!("{}/{}", site_url, dir.trim_matches('/')));
+    let mut by_slug: BTreeMap<String, Term> = BTreeMap::new();
+    // Pages are rendered in parallel, so their metadata comes in no particular order.
+    let mut articles: Vec<(&String, &Metadata)> = metadata
+        .iter()
+        .filter_map(|meta| match meta {
+            Metadata::Article { url, .. } => Some((url, meta)),
+            _ => None,
+        })
+        .collect();
+
+    articles.sort_by_key(|(url, _)| *url);
+
+    for (url, article) in articles {
+        if prefix
+            .as_ref()
+            .is_some_and(|prefix| !url.starts_with(prefix))
+        {
+            continue;
+        }
+
+        for name in article_terms(article, &config.name) {
+            let slug = crate::org::slugify(name);
+
+            if slug.is_empty() {
+                continue;
+            }
+
+            let term = by_slug.entry(slug.clone()).or_insert_with(|| Term {
+                name: name.clone(),
+                slug,
+                articles: vec![],
+            });
+
+            // Listing the same term twice doesn't list the article twice.
+            if !term.articles.last().is_some_and(
+                |last| matches!(last, Metadata::Article { url: last, .. } if last == url),
+            ) {
+                term.articles.push(article.clone());
+            }
+        }
+    }
+
+    let mut terms: Vec<Term> = by_slug
+        .into_values()
+        .filter(|term| term.articles.len() >= config.min_articles.unwrap_or(1))
+        .collect();
+
+    for term in &mut terms {
+        sort(
+            &mut term.articles,
+            config.sort.unwrap_or_default(),
+            language,
+        );
+    }
+
+    crate::collation::sort_by_key(&mut terms, language, |term| &term.name);
+
+    terms
+}
+
+/// The directory page `page` of `term` is written to, relative to the destination.
+pub fn page_dir(config: &TaxonomyConfig, term: &Term, page: usize) -> String {
+    match page {
+        1 => format!("{}/{}", config.name, term.slug),
+        page => format!("{}/{}/page/{}", config.name, term.slug, page),
+    }
+}
+
+/// The pages of `term`, with the articles on each.
+pub fn pages<'a>(
+    config: &TaxonomyConfig,
+    term: &'a Term,
+    site_url: &str,
+) -> Vec<(Page, &'a [Metadata])> {
+    let chunks: Vec<&[Metadata]> = match config.page_size {
+        Some(size) if size > 0 => term.articles.chunks(size).collect(),
+        _ => vec![&term.articles],
+    };
+    let url = |page: usize| format!("{}/{}/", site_url, page_dir(config, term, page));
+    let feed = config
+        .feeds
+        .unwrap_or(false)
+        .then(|| format!("{}/{}/{}/feed.xml", site_url, config.name, term.slug));
+    let pages = chunks.len();
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, articles)| {
+            let page = i + 1;
+
+            (
+                Page {
+                    name: config.name.clone(),
+                    term: term.name.clone(),
+                    page,
+                    pages,
+                    url: url(page),
+                    prev: (page > 1).then(|| url(page - 1)),
+                    next: (page < pages).then(|| url(page + 1)),
+                    feed: feed.clone(),
+                },
+                articles,
+            )
+        })
+        .collect()
+}
+
+/// The content of a term's page: its articles, a link to its feed, and links to the pages
+/// before and after it.
+pub fn content(page: &Page, articles: &[Metadata]) -> String {
+    let mut html = format!(
+        "<div class=\"articles\">{}</div>",
+        articles
+            .iter()
+            .filter_map(crate::org::article_card)
+            .collect::<String>()
+    );
+
+    if let Some(feed) = &page.feed {
+        html += &format!(
+            "<p class=\"term-feed\"><a href=\"{}\" type=\"application/rss+xml\">RSS feed</a></p>",
+            escape_html(feed)
+        );
+    }
+
+    if page.pages > 1 {
+        html += "<nav class=\"pagination\">";
+
+        if let Some(prev) = &page.prev {
+            html += &format!(
+                "<a rel=\"prev\" href=\"{}\">Previous</a>",
+                escape_html(prev)
+            );
+        }
+
+        html += &format!("<span>Page {} of {}</span>", page.page, page.pages);
+
+        if let Some(next) = &page.next {
+            html += &format!("<a rel=\"next\" href=\"{}\">Next</a>", escape_html(next));
+        }
+
+        html += "</nav>";
+    }
+
+    html
+}
+
+/// The content of a taxonomy's index: its terms, with how many articles have each.
+pub fn index(config: &TaxonomyConfig, terms: &[Term], site_url: &str) -> String {
+    format!(
+        "<ul class=\"terms\">{}</ul>",
+        terms
+            .iter()
+            .map(|term| format!(
+                "<li><a href=\"{}/{}/\">{}</a> <span class=\"term-count\">{}</span></li>",
+                site_url,
+                page_dir(config, term, 1),
+                escape_html(&term.name),
+                term.articles.len()
+            ))
+            .collect::<String>()
+    )
+}
+
+/// The feed of `term`'s articles, newest first.
+pub fn channel(
+    config: &TaxonomyConfig,
+    term: &Term,
+    site_url: &str,
+    language: Option<String>,
+) -> rss::Channel {
+    let mut articles = term.articles.clone();
+
+    sort(&mut articles, TaxonomySort::Date, None);
+
+    let mut channel = rss::Channel {
+        title: term.name.clone(),
+        link: format!("{}/{}/", site_url, page_dir(config, term, 1)),
+        description: format!("Articles in {} `{}`", config.name, term.name),
+        language,
+        generator: Some(format!(
+            "Impertio {} ({}), RSS Crate (https://crates.io/crates/rss)",
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_HOMEPAGE")
+        )),
+        docs: Some("https://www.rssboard.org/rss-specification".to_owned()),
+        items: articles
+            .iter()
+            .filter_map(|article| match article {
+                Metadata::Article {
+                    title,
+                    description,
+                    author,
+                    tags,
+                    url,
+                    license,
+                    ..
+                } => Some(rss::Item {
+                    title: Some(title.clone()),
+                    link: Some(url.clone()),
+                    guid: Some(rss::Guid {
+                        value: url.clone(),
+                        permalink: true,
+                    }),
+                    description: description.clone(),
+                    author: author.clone(),
+                    categories: tags
+                        .iter()
+                        .map(|tag| rss::Category {
+                            name: tag.clone(),
+                            domain: None,
+                        })
+                        .collect(),
+                    pub_date: Some(date(article).to_rfc2822()),
+                    extensions: crate::license::extension(license.as_deref()),
+                    ..Default::default()
+                }),
+                _ => None,
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    crate::license::add_namespace(&mut channel);
+
+    channel
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        config::{TaxonomyConfig, TaxonomySort},
+        metadata::Metadata,
+        taxonomy::{channel, content, pages, terms},
+    };
+
+    #[test]
+    fn taxonomy() {
+        let article = |url: &str, day: u32, tags: &[&str], categories: &[&str]| {
+            Metadata::article(url)
+                .with_title(url.rsplit('/').next().unwrap())
+                .with_tags(tags)
+                .with_terms("categories", categories)
+                .with_modified(Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap())
+        };
+        let metadata = [
+            article("https://x.y/blog/a.html", 1, &["Rust", "web"], &["Notes"]),
+            article("https://x.y/blog/c.html", 3, &["rust", "Rust"], &[]),
+            article("https://x.y/blog/b.html", 2, &["rust"], &["Notes"]),
+            article("https://x.y/about.html", 4, &["rust"], &[]),
+        ];
+        let tags = TaxonomyConfig {
+            name: "tags".into(),
+            page_size: Some(2),
+            dir: Some("/blog/".into()),
+            feeds: Some(true),
+            ..Default::default()
+        };
+        let found = terms(&tags, &metadata, "https://x.y", None);
+        let urls = |articles: &[Metadata]| {
+            articles
+                .iter()
+                .map(|article| match article {
+                    Metadata::Article { url, .. } => url.rsplit('/').next().unwrap().to_owned(),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            found
+                .iter()
+                .map(|term| (
+                    term.name.as_str(),
+                    term.slug.as_str(),
+                    urls(&term.articles).join(" ")
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("Rust", "rust", "c.html b.html a.html".into()),
+                ("web", "web", "a.html".into()),
+            ]
+        );
+
+        let rust = pages(&tags, &found[0], "https://x.y");
+
+        assert_eq!(rust.len(), 2);
+        assert_eq!(rust[1].0.url, "https://x.y/tags/rust/page/2/");
+        assert_eq!(rust[1].0.prev.as_deref(), Some("https://x.y/tags/rust/"));
+        assert_eq!(
+            rust[0].0.next.as_deref(),
+            Some("https://x.y/tags/rust/page/2/")
+        );
+        assert_eq!(urls(rust[1].1), ["a.html"]);
+
+        let html = content(&rust[0].0, rust[0].1);
+
+        assert!(html.contains("href=\"https://x.y/tags/rust/feed.xml\""));
+        assert!(html.contains("<a rel=\"next\" href=\"https://x.y/tags/rust/page/2/\">"));
+        assert!(html.contains("<span>Page 1 of 2</span>"));
+        assert!(!html.contains("rel=\"prev\""));
+
+        let feed = channel(&tags, &found[0], "https://x.y", None);
+
+        assert_eq!(feed.link, "https://x.y/tags/rust/");
+        assert_eq!(feed.items.len(), 3);
+
+        let categories = TaxonomyConfig {
+            name: "categories".into(),
+            sort: Some(TaxonomySort::Title),
+            min_articles: Some(2),
+            ..Default::default()
+        };
+        let found = terms(&categories, &metadata, "https://x.y", None);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(urls(&found[0].articles), ["a.html", "b.html"]);
+        assert_eq!(pages(&categories, &found[0], "https://x.y").len(), 1);
+    }
+}
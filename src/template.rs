@@ -3,31 +3,118 @@
 
 use std::{
     collections::HashMap,
+    io::Write,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
-use tera::{Context, Tera};
+use tera::{ast::Node, Context, Tera};
+
+use crate::config::{TemplateLimitsConfig, VersionedDocsConfig};
+
+/// The contexts pages were given, by file.
+type Captured = Arc<Mutex<Vec<(PathBuf, Context)>>>;
 
 #[derive(Clone, Debug)]
 pub struct Templates {
     dir: PathBuf,
+    /// Available to templates as `profile`.
+    profile: String,
+    minify: bool,
+    /// Used for pages without a `root.html` in their directory or above it.
+    fallback: Option<PathBuf>,
+    limits: TemplateLimitsConfig,
+    site_url: String,
+    /// Doc sets whose pages get a version switcher.
+    versions: Vec<VersionedDocsConfig>,
+    /// The site's data files, available to templates as `data`.
+    data: serde_json::Value,
+    /// The license of pages without their own, available to templates as `license`.
+    license: Option<String>,
+    /// If set, pages aren't rendered; their contexts are kept here instead, by file.
+    captured: Option<Captured>,
 }
 
 impl Templates {
     pub fn new(data_dir: &Path) -> Self {
         Self {
             dir: data_dir.to_owned(),
+            profile: String::new(),
+            minify: false,
+            fallback: None,
+            limits: Default::default(),
+            site_url: String::new(),
+            versions: vec![],
+            data: serde_json::Value::Object(Default::default()),
+            license: None,
+            captured: None,
         }
     }
 
-    /// Creates a Tera instance with the files and dirs
+    /// Expose the build profile to templates, and minify pages if asked to.
+    pub fn with_profile(mut self, profile: &str, minify: bool) -> Self {
+        self.profile = profile.to_owned();
+        self.minify = minify;
+        self
+    }
+
+    /// Render pages without a `root.html` of their own with `fallback` instead of failing.
+    pub fn with_fallback(mut self, fallback: Option<PathBuf>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Bound the include depth, output size and duration of renders.
+    pub fn with_limits(mut self, limits: TemplateLimitsConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Give pages in versioned doc sets a version switcher, linking to pages of `site_url`.
+    pub fn with_versions(mut self, site_url: &str, versions: Vec<VersionedDocsConfig>) -> Self {
+        self.site_url = site_url.to_owned();
+        self.versions = versions;
+        self
+    }
+
+    /// Give templates the site's data files as `data`.
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Give templates `license` as the license of pages without their own.
+    pub fn with_license(mut self, license: Option<String>) -> Self {
+        self.license = license;
+        self
+    }
+
+    /// Keep the context of each page instead of rendering it, for [`Self::captured`].
+    pub fn with_capture(mut self) -> Self {
+        self.captured = Some(Default::default());
+        self
+    }
+
+    /// The context the page built from `file` was last given, as JSON, if captured.
+    pub fn captured(&self, file: &Path) -> Option<serde_json::Value> {
+        self.captured
+            .as_ref()?
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(path, _)| path == file)
+            .map(|(_, context)| context.clone().into_json())
+    }
+
+    /// Creates a Tera instance with the files, by name, and dirs
     /// Also disables autoescape
-    fn create_tera(files: Vec<&Path>, dirs: Vec<&Path>) -> Result<Tera, tera::Error> {
+    fn create_tera(files: Vec<(&Path, &str)>, dirs: Vec<&Path>) -> Result<Tera, tera::Error> {
         let mut tera: Tera = Tera::default();
 
         tera.add_template_files(
             files
                 .iter()
-                .map(|path| (*path, path.file_name().unwrap().to_str()))
+                .map(|(path, name)| (*path, Some(*name)))
                 .collect::<Vec<(&Path, Option<&str>)>>(),
         )?;
         for dir in dirs {
@@ -51,8 +138,28 @@ impl Templates {
         contents: &str,
         ctx: Option<HashMap<&str, String>>,
     ) -> Result<String, tera::Error> {
-        let mut context: Context = Context::new();
+        self.render_with(template, file, contents, ctx, Context::new())
+    }
+
+    /// Render a page, with `values` that aren't strings, like lists or maps.
+    pub fn render_with(
+        &self,
+        template: &str,
+        file: &Path,
+        contents: &str,
+        ctx: Option<HashMap<&str, String>>,
+        values: Context,
+    ) -> Result<String, tera::Error> {
+        let mut context: Context = values;
         context.insert("content", contents);
+        context.insert("profile", &self.profile);
+        context.insert("data", &self.data);
+
+        if let Some(versions) =
+            crate::versions::switcher(&self.versions, &self.site_url, &self.dir, file)
+        {
+            context.insert("versions", &versions);
+        }
 
         if let Some(ctx) = ctx {
             for (key, value) in ctx.iter() {
@@ -60,50 +167,290 @@ impl Templates {
             }
         }
 
+        // The site's license for pages without their own, and its URL for `rel="license"`.
+        if let Some(license) = self
+            .license
+            .as_ref()
+            .filter(|_| !context.contains_key("license"))
+        {
+            context.insert("license", license);
+        }
+
+        if let Some(url) = context
+            .get("license")
+            .and_then(|license| license.as_str())
+            .and_then(crate::license::url)
+        {
+            context.insert("license_url", &url);
+        }
+
+        if let Some(captured) = &self.captured {
+            captured.lock().unwrap().push((file.to_owned(), context));
+
+            return Ok(String::new());
+        }
+
+        let page = self.render_context(template, file, &context)?;
+
+        Ok(if self.minify {
+            crate::minify::html(&page)
+        } else {
+            page
+        })
+    }
+
+    /// Render a page with a prepared context, e.g. from test fixtures.
+    pub fn render_context(
+        &self,
+        template: &str,
+        file: &Path,
+        context: &Context,
+    ) -> Result<String, tera::Error> {
+        let dir = file.parent().expect("Somehow the parent doesn't exist.");
+        let chain = self.sources(file);
+
+        let explain = |err: tera::Error| Self::explain(err, template, file, &chain, context);
+        if chain.is_empty() {
+            let searched: Vec<String> = Self::search_path(dir, &self.dir)
+                .iter()
+                .map(|dir| format!("{:?}", dir))
+                .collect();
+
+            return Err(explain(tera::Error::msg(format!(
+                "No `root.html` in {}, and no `fallback_template` configured",
+                searched.join(", ")
+            ))));
+        }
+
+        if let Some(missing) = chain.iter().find(|path| !path.exists()) {
+            return Err(explain(tera::Error::msg(format!(
+                "The fallback template {:?} doesn't exist",
+                missing
+            ))));
+        }
+
         let tera = Self::create_tera(
-            Self::find_upwards(
-                file.parent().expect("Somehow the parent doesn't exist."),
-                "root.html",
-                Some(&self.dir),
-            )
-            .iter()
-            .map(|path| path.as_path())
-            .collect(),
+            chain
+                .iter()
+                .map(|path| (path.as_path(), "root.html"))
+                .collect(),
             vec![],
-        )?;
+        )
+        .map_err(explain)?;
 
-        tera.render(template, &context)
+        self.check_includes(&tera, template).map_err(explain)?;
+        self.render_limited(tera, template, context).map_err(explain)
     }
 
-    /// Find every instance of a file or directory upwards in the directory tree.
-    fn find_upwards(dir: &Path, entry_name: &str, until: Option<&Path>) -> Vec<PathBuf> {
-        let mut found: Vec<PathBuf> = vec![];
-        let mut dir: PathBuf = dir.to_owned();
+    /// Fails if `template`, or a template it extends, nests includes deeper than allowed.
+    /// Tera would recurse until the stack overflows instead.
+    fn check_includes(&self, tera: &Tera, template: &str) -> Result<(), tera::Error> {
+        let template = tera.get_template(template)?;
 
-        loop {
-            if Self::concat_pathbuf(&dir, entry_name).exists() {
-                found.push(Self::concat_pathbuf(&dir, entry_name));
-            }
+        for name in std::iter::once(&template.name).chain(&template.parents) {
+            Self::include_depth(
+                tera,
+                &tera.get_template(name)?.ast,
+                &mut vec![name.clone()],
+                self.limits.max_include_depth(),
+            )?;
+        }
 
-            if let Some(parent) = dir.parent() {
-                dir = parent.to_owned();
+        Ok(())
+    }
 
-                if dir.parent() == until {
-                    break;
+    /// Walks `nodes` into every template they include, with the including templates in
+    /// `stack`, failing once it's more than `max` includes deep.
+    fn include_depth(
+        tera: &Tera,
+        nodes: &[Node],
+        stack: &mut Vec<String>,
+        max: usize,
+    ) -> Result<(), tera::Error> {
+        for node in nodes {
+            let bodies: Vec<&[Node]> = match node {
+                Node::Include(_, names, _) => {
+                    let Some(name) = names.iter().find(|name| tera.get_template(name).is_ok())
+                    else {
+                        continue;
+                    };
+
+                    stack.push(name.clone());
+
+                    if stack.len() > max + 1 {
+                        return Err(tera::Error::msg(format!(
+                            "Includes nest more than {} deep: {}",
+                            max,
+                            stack.join(" > ")
+                        )));
+                    }
+
+                    Self::include_depth(tera, &tera.get_template(name)?.ast, stack, max)?;
+                    stack.pop();
+
+                    continue;
                 }
-            } else {
+                Node::MacroDefinition(_, definition, _) => vec![&definition.body],
+                Node::FilterSection(_, section, _) => vec![&section.body],
+                Node::Block(_, block, _) => vec![&block.body],
+                Node::Forloop(_, forloop, _) => std::iter::once(&forloop.body)
+                    .chain(&forloop.empty_body)
+                    .map(Vec::as_slice)
+                    .collect(),
+                Node::If(branches, _) => branches
+                    .conditions
+                    .iter()
+                    .map(|(_, _, body)| body)
+                    .chain(branches.otherwise.iter().map(|(_, body)| body))
+                    .map(Vec::as_slice)
+                    .collect(),
+                _ => continue,
+            };
+
+            for body in bodies {
+                Self::include_depth(tera, body, stack, max)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Renders on a thread of its own, giving up on it once it overruns the time or size
+    /// limit. A render that times out is abandoned rather than stopped, since Tera can't be
+    /// interrupted, but the build fails either way.
+    fn render_limited(
+        &self,
+        tera: Tera,
+        template: &str,
+        context: &Context,
+    ) -> Result<String, tera::Error> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (template, context) = (template.to_owned(), context.clone());
+        let max_size = self.limits.max_output_size();
+
+        std::thread::spawn(move || {
+            let mut out = LimitedWriter {
+                buffer: vec![],
+                limit: max_size,
+            };
+            let result = match tera.render_to(&template, &context, &mut out) {
+                Err(_) if out.buffer.len() > max_size => Err(tera::Error::msg(format!(
+                    "The rendered page is larger than {} bytes",
+                    max_size
+                ))),
+                result => result.map(|()| String::from_utf8_lossy(&out.buffer).into_owned()),
+            };
+
+            let _ = sender.send(result);
+        });
+
+        let timeout = self.limits.render_timeout();
+
+        receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+            Err(tera::Error::msg(format!(
+                "Rendering took longer than {} seconds",
+                timeout.as_secs()
+            )))
+        })
+    }
+
+    /// The templates a page at `file` is rendered with, outermost first: the `root.html`s in
+    /// its directory and above it, or else the fallback.
+    pub fn sources(&self, file: &Path) -> Vec<PathBuf> {
+        let dir = file.parent().expect("Somehow the parent doesn't exist.");
+        let chain = Self::find_upwards(dir, "root.html", &self.dir);
+
+        if chain.is_empty() {
+            self.fallback.iter().cloned().collect()
+        } else {
+            chain
+        }
+    }
+
+    /// Wraps a Tera error with the page being rendered, the templates it was rendered with,
+    /// nearest last, and the context's keys.
+    fn explain(
+        err: tera::Error,
+        template: &str,
+        file: &Path,
+        chain: &[PathBuf],
+        context: &Context,
+    ) -> tera::Error {
+        let chain = if chain.is_empty() {
+            "no `root.html` found".to_owned()
+        } else {
+            chain
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" < ")
+        };
+        let keys = match context.clone().into_json() {
+            serde_json::Value::Object(values) => {
+                let mut keys: Vec<String> = values.keys().cloned().collect();
+
+                keys.sort();
+                keys.join(", ")
+            }
+            _ => String::new(),
+        };
+
+        tera::Error::chain(
+            format!(
+                "Failed to render {:?} with `{}` ({}); context has {}",
+                file, template, chain, keys
+            ),
+            err,
+        )
+    }
+
+    /// The directories searched for a page in `dir`: it and its ancestors, nearest first, up to
+    /// and including `until`.
+    fn search_path(dir: &Path, until: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![];
+
+        for dir in dir.ancestors() {
+            dirs.push(dir.to_owned());
+
+            if dir == until {
                 break;
             }
         }
 
-        found.iter().rev().map(|path| path.to_owned()).collect()
+        dirs
     }
 
-    /// Helper method for concatenating a str to a path
-    fn concat_pathbuf(pb: &Path, s: &str) -> PathBuf {
-        let mut new_pb: PathBuf = pb.to_owned();
-        new_pb.push(s);
-        new_pb
+    /// Find every instance of a file or directory in `dir` and upwards until `until`,
+    /// outermost first.
+    fn find_upwards(dir: &Path, entry_name: &str, until: &Path) -> Vec<PathBuf> {
+        Self::search_path(dir, until)
+            .iter()
+            .rev()
+            .map(|dir| dir.join(entry_name))
+            .filter(|path| path.exists())
+            .collect()
+    }
+}
+
+/// Collects output until it's past `limit`, then fails the write that went over.
+struct LimitedWriter {
+    buffer: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        if self.buffer.len() > self.limit {
+            return Err(std::io::Error::other("output limit exceeded"));
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
 }
 
@@ -114,17 +461,17 @@ mod test {
         path::Path,
     };
 
-    use crate::template::Templates;
+    use crate::{config::TemplateLimitsConfig, template::Templates};
 
     #[test]
     fn test() {
-        let templates = Templates::new(&Path::new("data"));
+        let templates = Templates::new(Path::new("data"));
 
         assert_eq!(
             templates
                 .render(
                     "root.html",
-                    &Path::new("data/index.org"),
+                    Path::new("data/index.org"),
                     "<h1>This is a test!</h1>",
                     Some(HashMap::from_iter(vec![("title", "yes".into())]))
                 )
@@ -133,4 +480,167 @@ mod test {
                 .to_owned()
         )
     }
+
+    #[test]
+    fn profile() {
+        let templates = Templates::new(Path::new("data")).with_profile("dev", true);
+
+        assert_eq!(
+            templates
+                .render(
+                    "root.html",
+                    Path::new("data/index.org"),
+                    "<p>Hi</p>",
+                    Some(HashMap::from_iter(vec![("title", "Hi".into())]))
+                )
+                .unwrap(),
+            "<html>\n<head><title>Hi</title></head>\n<body class=\"dev\"><p>Hi</p></body>\n</html>"
+        );
+    }
+
+    #[test]
+    fn error_context() {
+        let dir = std::env::temp_dir().join(format!("impertio-template-{}", std::process::id()));
+
+        std::fs::create_dir_all(dir.join("blog")).unwrap();
+        std::fs::write(dir.join("root.html"), "{{ content }}").unwrap();
+        std::fs::write(dir.join("blog/root.html"), "{{ missing }}").unwrap();
+
+        let err = Templates::new(&dir)
+            .render("root.html", &dir.join("blog/post.org"), "", None)
+            .unwrap_err();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to render {:?} with `root.html` ({} < {}); context has content, data, profile",
+                dir.join("blog/post.org"),
+                dir.join("root.html").display(),
+                dir.join("blog/root.html").display()
+            )
+        );
+        assert!(std::error::Error::source(&err)
+            .unwrap()
+            .to_string()
+            .contains("root.html"));
+    }
+
+    #[test]
+    fn missing_template() {
+        let dir = std::env::temp_dir().join(format!("impertio-fallback-{}", std::process::id()));
+        let page = dir.join("blog/post.org");
+
+        std::fs::create_dir_all(dir.join("blog")).unwrap();
+        std::fs::write(dir.join("page.html"), "<main>{{ content }}</main>").unwrap();
+
+        let missing = Templates::new(&dir)
+            .render("root.html", &page, "Hi", None)
+            .unwrap_err();
+        let fallback = Templates::new(&dir)
+            .with_fallback(Some(dir.join("page.html")))
+            .render("root.html", &page, "Hi", None)
+            .unwrap();
+
+        std::fs::write(dir.join("root.html"), "<body>{{ content }}</body>").unwrap();
+
+        let found = Templates::new(&dir)
+            .with_fallback(Some(dir.join("page.html")))
+            .render("root.html", &page, "Hi", None)
+            .unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            std::error::Error::source(&missing).unwrap().to_string(),
+            format!(
+                "No `root.html` in {:?}, {:?}, and no `fallback_template` configured",
+                dir.join("blog"),
+                dir
+            )
+        );
+        assert_eq!(fallback, "<main>Hi</main>");
+        assert_eq!(found, "<body>Hi</body>");
+    }
+
+    #[test]
+    fn license() {
+        let dir = std::env::temp_dir().join(format!("impertio-license-{}", std::process::id()));
+        let page = dir.join("data.csv");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("root.html"),
+            "{{ license | default(value='') }} {{ license_url | default(value='') }}",
+        )
+        .unwrap();
+
+        let templates = Templates::new(&dir).with_license(Some("CC-BY-4.0".into()));
+        let site = templates.render("root.html", &page, "", None).unwrap();
+        let own = templates
+            .render(
+                "root.html",
+                &page,
+                "",
+                Some(HashMap::from([("license", "CC0".into())])),
+            )
+            .unwrap();
+        let unlicensed = Templates::new(&dir)
+            .render("root.html", &page, "", None)
+            .unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            site,
+            "CC-BY-4.0 https://creativecommons.org/licenses/by/4.0/"
+        );
+        assert_eq!(
+            own,
+            "CC0 https://creativecommons.org/publicdomain/zero/1.0/"
+        );
+        assert_eq!(unlicensed, " ");
+    }
+
+    #[test]
+    fn limits() {
+        let dir = std::env::temp_dir().join(format!("impertio-limits-{}", std::process::id()));
+        let page = dir.join("index.org");
+        let limits = TemplateLimitsConfig {
+            max_include_depth: Some(4),
+            max_output_size: Some(64),
+            render_timeout: Some(1),
+        };
+        let render = |template: &str| {
+            std::fs::write(dir.join("root.html"), template).unwrap();
+
+            Templates::new(&dir)
+                .with_limits(limits.clone())
+                .render("root.html", &page, "Hi", None)
+                .map_err(|err| std::error::Error::source(&err).unwrap().to_string())
+        };
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let recursive = render("{% if content %}{% include \"root.html\" %}{% endif %}");
+        let large = render("{% for i in range(end=100) %}{{ content }}{% endfor %}");
+        let slow = render(
+            "{% for i in range(end=100000) %}{% for j in range(end=100000) %}{% endfor %}{% endfor %}",
+        );
+        let fine = render("<p>{{ content }}</p>");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            recursive.unwrap_err(),
+            "Includes nest more than 4 deep: root.html > root.html > root.html > root.html > root.html > root.html"
+        );
+        assert_eq!(
+            large.unwrap_err(),
+            "The rendered page is larger than 64 bytes"
+        );
+        assert_eq!(slow.unwrap_err(), "Rendering took longer than 1 seconds");
+        assert_eq!(fine.unwrap(), "<p>Hi</p>");
+    }
 }
@@ -0,0 +1,242 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Documentation published for several releases at once, like `docs/v1` and `docs/v2`.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sitemap_rs::{url::Url, url_set::UrlSet};
+
+use crate::config::VersionedDocsConfig;
+
+/// Where the newest version is copied to, in each doc set.
+pub const LATEST: &str = "latest";
+
+/// An entry of the version switcher, available to templates of versioned pages as `versions`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Version {
+    pub name: String,
+    /// The same page in this version, or the version's index if it has no such page.
+    pub url: String,
+    /// Whether the page being rendered is in this version.
+    pub current: bool,
+    pub latest: bool,
+}
+
+/// The versions of the doc set, oldest first.
+pub fn names(config: &VersionedDocsConfig, root: &Path) -> Vec<String> {
+    if let Some(versions) = &config.versions {
+        return versions.clone();
+    }
+
+    let mut names: Vec<String> = std::fs::read_dir(root.join(&config.dir))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name != LATEST && !name.starts_with('.'))
+        .collect();
+
+    names.sort();
+    names
+}
+
+pub fn latest(config: &VersionedDocsConfig, root: &Path) -> Option<String> {
+    config.latest.clone().or_else(|| names(config, root).pop())
+}
+
+/// The version switcher of the page whose source is `file`, if it's in a doc set.
+pub fn switcher(
+    configs: &[VersionedDocsConfig],
+    site_url: &str,
+    root: &Path,
+    file: &Path,
+) -> Option<Vec<Version>> {
+    let root = root.canonicalize().unwrap_or(root.to_owned());
+    let relative = file.strip_prefix(&root).ok()?;
+
+    configs.iter().find_map(|config| {
+        let dir = relative.strip_prefix(config.dir.trim_matches('/')).ok()?;
+        let mut components = dir.components();
+        let current = components
+            .next()?
+            .as_os_str()
+            .to_string_lossy()
+            .into_owned();
+        let page = components.as_path();
+        let names = names(config, &root);
+
+        if !names.contains(&current) {
+            return None;
+        }
+
+        let latest = latest(config, &root);
+
+        Some(
+            names
+                .into_iter()
+                .map(|name| {
+                    let version = PathBuf::from(config.dir.trim_matches('/')).join(&name);
+                    let url = if root.join(&version).join(page).exists() {
+                        format!(
+                            "{}/{}",
+                            site_url,
+                            version.join(page).with_extension("html").display()
+                        )
+                    } else {
+                        format!("{}/{}/", site_url, version.display())
+                    };
+
+                    Version {
+                        current: name == current,
+                        latest: latest.as_ref() == Some(&name),
+                        url,
+                        name,
+                    }
+                })
+                .collect(),
+        )
+    })
+}
+
+/// Writes a `sitemap.xml` into each version's directory in `dir`, of its pages among `urls`.
+pub fn write_sitemaps(
+    configs: &[VersionedDocsConfig],
+    site_url: &str,
+    root: &Path,
+    dir: &Path,
+    urls: &[Url],
+) -> anyhow::Result<()> {
+    for config in configs {
+        for name in names(config, root) {
+            let version = Path::new(config.dir.trim_matches('/')).join(&name);
+            let prefix = format!("{}/{}/", site_url, version.display());
+            let urls: Vec<Url> = urls
+                .iter()
+                .filter(|url| url.location.starts_with(&prefix))
+                .cloned()
+                .collect();
+
+            if urls.is_empty() || !dir.join(&version).is_dir() {
+                continue;
+            }
+
+            log::info!(phase = "sitemap"; "Generating a sitemap for `{}`", version.display());
+
+            UrlSet::new(urls)?.write(std::fs::File::create(
+                dir.join(&version).join("sitemap.xml"),
+            )?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the built latest version of each doc set to `latest/` next to it in `dir`, replacing
+/// what was there.
+pub fn alias_latest(
+    configs: &[VersionedDocsConfig],
+    root: &Path,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    for config in configs {
+        let Some(latest) = latest(config, root) else {
+            continue;
+        };
+        let docs = dir.join(config.dir.trim_matches('/'));
+        let (from, to) = (docs.join(&latest), docs.join(LATEST));
+
+        if !from.is_dir() {
+            continue;
+        }
+
+        if to.exists() {
+            std::fs::remove_dir_all(&to)?;
+        }
+
+        for entry in walkdir::WalkDir::new(&from) {
+            let entry = entry?;
+            let target = to.join(entry.path().strip_prefix(&from)?);
+
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(target)?;
+            } else {
+                std::fs::copy(entry.path(), target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::VersionedDocsConfig,
+        versions::{alias_latest, switcher, Version},
+    };
+
+    #[test]
+    fn versions() {
+        let dir = std::env::temp_dir().join(format!("impertio-versions-{}", std::process::id()));
+        let (source, dest) = (dir.join("src"), dir.join("dest"));
+        let config = VersionedDocsConfig {
+            dir: "docs".into(),
+            ..Default::default()
+        };
+
+        for file in [
+            "docs/v1/index.org",
+            "docs/v1/setup.org",
+            "docs/v2/index.org",
+            "docs/v2/guide/setup.org",
+        ] {
+            std::fs::create_dir_all(source.join(file).parent().unwrap()).unwrap();
+            std::fs::write(source.join(file), "").unwrap();
+        }
+
+        std::fs::create_dir_all(dest.join("docs/v2")).unwrap();
+        std::fs::write(dest.join("docs/v2/index.html"), "v2").unwrap();
+
+        let page = |file: &str| {
+            switcher(
+                std::slice::from_ref(&config),
+                "https://x.y",
+                &source,
+                &source.canonicalize().unwrap().join(file),
+            )
+        };
+        let setup = page("docs/v1/setup.org");
+        let index = page("docs/v2/index.org");
+        let other = page("blog/index.org");
+
+        alias_latest(std::slice::from_ref(&config), &source, &dest).unwrap();
+
+        let latest = std::fs::read_to_string(dest.join("docs/latest/index.html")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            setup.unwrap(),
+            vec![
+                Version {
+                    name: "v1".into(),
+                    url: "https://x.y/docs/v1/setup.html".into(),
+                    current: true,
+                    latest: false,
+                },
+                Version {
+                    name: "v2".into(),
+                    url: "https://x.y/docs/v2/".into(),
+                    current: false,
+                    latest: true,
+                },
+            ]
+        );
+        assert_eq!(index.unwrap()[0].url, "https://x.y/docs/v1/index.html");
+        assert_eq!(other, None);
+        assert_eq!(latest, "v2");
+    }
+}
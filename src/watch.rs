@@ -0,0 +1,241 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+//! Rebuilding a site whenever its sources, templates or config change. The build cache in the
+//! destination keeps rebuilds to the pages those changes affect.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::config::Config;
+
+/// How often the source is checked for changes.
+const POLL: Duration = Duration::from_millis(100);
+/// How long the source has to stay unchanged before rebuilding, so saving several files at
+/// once, or an editor's save-by-rename, triggers one rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// The modification time and size of every file in a directory.
+#[derive(Debug, Default, PartialEq)]
+pub struct Snapshot(BTreeMap<PathBuf, (SystemTime, u64)>);
+
+impl Snapshot {
+    /// The files in `dir` a build with `config` would see, those its `ignore`,
+    /// `.impertioignore` and, if `gitignore` is set, `.gitignore` files ignore left out, as
+    /// well as `.git` and `skip`, like a destination inside the source.
+    pub fn take(dir: &Path, skip: Option<&Path>, config: &Config) -> Self {
+        let mut ignore = config.ignore.clone().unwrap_or_default();
+
+        ignore.push(".git/".into());
+
+        let walk = crate::files::walk(
+            &dir.to_string_lossy(),
+            &ignore,
+            config.gitignore.unwrap_or(false),
+            skip.map(Path::to_path_buf),
+        );
+        let Ok(walk) = walk else {
+            return Self::default();
+        };
+
+        let files = walk
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+
+                Some((
+                    entry.into_path(),
+                    (metadata.modified().ok()?, metadata.len()),
+                ))
+            })
+            .collect();
+
+        Self(files)
+    }
+
+    /// The files added, changed or removed since `before`.
+    pub fn changes(&self, before: &Self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self
+            .0
+            .iter()
+            .filter(|(path, stat)| before.0.get(*path) != Some(stat))
+            .map(|(path, _)| path.clone())
+            .chain(
+                before
+                    .0
+                    .keys()
+                    .filter(|path| !self.0.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+
+        changed.sort();
+        changed
+    }
+}
+
+/// Calls `build`, logging how long it took, or why it failed. Returns whether it succeeded.
+fn rebuild(build: &mut impl FnMut() -> anyhow::Result<()>) -> bool {
+    let start = std::time::Instant::now();
+
+    match build() {
+        Ok(()) => {
+            log::info!(phase = "watch", duration_ms = start.elapsed().as_millis() as u64; "Rebuilt.");
+            true
+        }
+        Err(err) => {
+            log::error!(phase = "watch"; "Rebuilding failed: {:#}", err);
+            false
+        }
+    }
+}
+
+/// The source's config under `profile`, for what it ignores, or the default one if it can't
+/// be read, like while it's being edited.
+fn config(source: &Path, profile: &str) -> Config {
+    std::fs::read_to_string(source.join("impertio.yaml"))
+        .ok()
+        .and_then(|yaml| Config::load(&yaml, profile).ok())
+        .unwrap_or_default()
+}
+
+/// Calls `build` every time files in `source` that a build with the `profile` config would
+/// see change, forever. Failed builds are logged and retried on the next change. The
+/// destination `dest` is ignored if it's inside the source.
+pub fn watch(
+    source: &Path,
+    dest: &Path,
+    profile: &str,
+    mut build: impl FnMut() -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let dest = dest.canonicalize()?;
+    let source = source.canonicalize()?;
+    let skip = dest.starts_with(&source).then_some(dest.as_path());
+
+    log::info!(phase = "watch"; "Watching `{}` for changes", source.display());
+
+    // Taken after each build, so files builds write into the source, like the diagram cache,
+    // don't trigger another.
+    let mut config = self::config(&source, profile);
+    let mut snapshot = Snapshot::take(&source, skip, &config);
+
+    loop {
+        std::thread::sleep(POLL);
+
+        let mut current = Snapshot::take(&source, skip, &config);
+
+        if current == snapshot {
+            continue;
+        }
+
+        loop {
+            std::thread::sleep(DEBOUNCE);
+
+            let settled = Snapshot::take(&source, skip, &config);
+
+            if settled == current {
+                break;
+            }
+
+            current = settled;
+        }
+
+        for path in current.changes(&snapshot) {
+            log::info!(phase = "watch"; "Changed: `{}`", path.display());
+        }
+
+        rebuild(&mut build);
+
+        // The config may have changed what's ignored.
+        config = self::config(&source, profile);
+        snapshot = Snapshot::take(&source, skip, &config);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::Config,
+        files::FileDispatcher,
+        watch::{rebuild, Snapshot},
+    };
+
+    #[test]
+    fn changes() {
+        let dir = std::env::temp_dir().join(format!("impertio-watch-{}", std::process::id()));
+        let (page, template, out) = (
+            dir.join("index.org"),
+            dir.join("root.html"),
+            dir.join("out"),
+        );
+
+        let config = Config {
+            ignore: Some(vec!["*.draft.org".into()]),
+            ..Default::default()
+        };
+
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::create_dir_all(dir.join("node_modules")).unwrap();
+        std::fs::write(&page, "* A").unwrap();
+        std::fs::write(&template, "{{ content }}").unwrap();
+        std::fs::write(dir.join(".impertioignore"), "node_modules/").unwrap();
+
+        let before = Snapshot::take(&dir, Some(&out), &config);
+
+        std::fs::write(&page, "* AB").unwrap();
+        std::fs::remove_file(&template).unwrap();
+        std::fs::write(dir.join("new.org"), "").unwrap();
+        std::fs::write(out.join("index.html"), "").unwrap();
+        std::fs::write(dir.join(".git/index"), "").unwrap();
+        std::fs::write(dir.join("new.draft.org"), "").unwrap();
+        std::fs::write(dir.join("node_modules/x.js"), "").unwrap();
+
+        let after = Snapshot::take(&dir, Some(&out), &config);
+        let unchanged = Snapshot::take(&dir, Some(&out), &config);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            after.changes(&before),
+            vec![page, dir.join("new.org"), template]
+        );
+        assert!(unchanged.changes(&after).is_empty());
+    }
+
+    #[test]
+    fn failed_build() {
+        let dir = std::env::temp_dir().join(format!("impertio-rebuild-{}", std::process::id()));
+        let (source, dest) = (dir.join("site"), dir.join("public"));
+
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("index.org"), "#+TITLE: A\n\nText.").unwrap();
+        std::fs::write(source.join("root.html"), "{{ content }}{{ missing }}").unwrap();
+
+        let mut build = || {
+            FileDispatcher::new(source.to_str().unwrap(), Default::default())
+                .handle_files(
+                    dest.to_string_lossy().into_owned(),
+                    source.to_string_lossy().into_owned(),
+                )
+                .map(|_| ())
+        };
+
+        let failed = rebuild(&mut build);
+
+        std::fs::write(source.join("root.html"), "{{ content }}").unwrap();
+
+        let rebuilt = rebuild(&mut build);
+        let page = std::fs::read_to_string(dest.join("index.html")).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!failed);
+        assert!(rebuilt);
+        assert!(page.contains("Text."));
+    }
+}
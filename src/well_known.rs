@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Ohin "Kazani" Taylor <kazani@kazani.dev>
+// SPDX-License-Identifier: MIT
+
+use std::path::Path;
+
+use anyhow::bail;
+use chrono::{DateTime, Months, SecondsFormat, Utc};
+
+use crate::config::{SecurityTxtConfig, WellKnownConfig};
+
+/// Renders `security.txt`, canonically served from `site_url`.
+fn security_txt(
+    config: &SecurityTxtConfig,
+    site_url: &str,
+    now: DateTime<Utc>,
+) -> anyhow::Result<String> {
+    if config.contact.is_empty() {
+        bail!("security.txt needs at least one contact");
+    }
+
+    let expires = match &config.expires {
+        Some(expires) => DateTime::parse_from_rfc3339(expires)
+            .map_err(|err| anyhow::anyhow!("Invalid security.txt expiry `{}`: {}", expires, err))?
+            .with_timezone(&Utc),
+        None => now + Months::new(12),
+    };
+
+    if expires <= now {
+        log::warn!("security.txt expired on {}", expires);
+    }
+
+    let mut fields: Vec<(&str, String)> = config
+        .contact
+        .iter()
+        .map(|contact| ("Contact", contact.clone()))
+        .collect();
+
+    fields.push((
+        "Expires",
+        expires.to_rfc3339_opts(SecondsFormat::Secs, true),
+    ));
+
+    for (name, values) in [
+        ("Encryption", &config.encryption),
+        ("Acknowledgments", &config.acknowledgments),
+        ("Policy", &config.policy),
+        ("Hiring", &config.hiring),
+    ] {
+        fields.extend(values.iter().flatten().map(|value| (name, value.clone())));
+    }
+
+    if let Some(languages) = &config.preferred_languages {
+        fields.push(("Preferred-Languages", languages.join(", ")));
+    }
+
+    fields.push((
+        "Canonical",
+        format!("{}/.well-known/security.txt", site_url),
+    ));
+
+    Ok(fields
+        .into_iter()
+        .map(|(name, value)| format!("{}: {}\n", name, value))
+        .collect())
+}
+
+/// Writes the configured `.well-known` files into `data_path`.
+pub fn write(config: &WellKnownConfig, site_url: &str, data_path: &Path) -> anyhow::Result<()> {
+    let dir = data_path.join(".well-known");
+
+    std::fs::create_dir_all(&dir)?;
+
+    if let Some(security) = &config.security {
+        log::info!(phase = "well-known"; "Generating `security.txt`");
+
+        std::fs::write(
+            dir.join("security.txt"),
+            security_txt(security, site_url, Utc::now())?,
+        )?;
+    }
+
+    for (name, contents) in config.files.iter().flatten() {
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            bail!("Invalid .well-known file name `{}`", name);
+        }
+
+        log::info!(phase = "well-known"; "Generating `{}`", name);
+
+        std::fs::write(dir.join(name), contents)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{config::SecurityTxtConfig, well_known::security_txt};
+
+    #[test]
+    fn security() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let config = SecurityTxtConfig {
+            contact: vec!["mailto:security@example.com".into()],
+            preferred_languages: Some(vec!["en".into(), "fr".into()]),
+            policy: Some(vec!["https://example.com/policy".into()]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            security_txt(&config, "https://example.com", now).unwrap(),
+            "Contact: mailto:security@example.com
+Expires: 2025-03-01T00:00:00Z
+Policy: https://example.com/policy
+Preferred-Languages: en, fr
+Canonical: https://example.com/.well-known/security.txt
+"
+        );
+
+        assert!(security_txt(&Default::default(), "https://example.com", now).is_err());
+    }
+}